@@ -1,8 +1,102 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error;
+use crate::frame::frame_response::ResponseBody;
+use crate::frame::Frame;
 use crate::types::CBytes;
 
+const PASSWORD_AUTHENTICATOR: &str = "org.apache.cassandra.auth.PasswordAuthenticator";
+const DSE_AUTHENTICATOR: &str = "com.datastax.bdp.cassandra.auth.DseAuthenticator";
+
 pub trait Authenticator: Clone + Send + Sync {
     fn get_auth_token(&self) -> CBytes;
     fn get_cassandra_name(&self) -> Option<&str>;
+
+    /// Checks if the authenticator is able to authenticate with a given server-side
+    /// authenticator class. By default only `get_cassandra_name` is accepted.
+    fn accepts_authenticator(&self, server_authenticator: &str) -> bool {
+        self.get_cassandra_name() == Some(server_authenticator)
+    }
+
+    /// Creates a SASL authenticator which authenticates a single connection.
+    /// By default `get_auth_token` is sent as an initial response and is sent again
+    /// in reply to every challenge, which suits mechanisms without challenges.
+    fn new_sasl_authenticator(&self) -> Box<dyn SaslAuthenticator> {
+        Box::new(StaticTokenAuthenticator {
+            token: self.get_auth_token(),
+        })
+    }
+}
+
+/// Challenge/response capable authenticator of a single connection. Once a server
+/// requests authentication, `initial_response` is sent to it. Every `AUTH_CHALLENGE`
+/// the server replies with is passed to `evaluate_challenge` which produces the next
+/// response, until the server either accepts credentials with `AUTH_SUCCESS` or fails
+/// authentication with an error.
+pub trait SaslAuthenticator: Send {
+    /// Returns a token sent in the first `AUTH_RESPONSE` request.
+    fn initial_response(&mut self) -> CBytes;
+
+    /// Returns a token sent in reply to a challenge of a server.
+    fn evaluate_challenge(&mut self, challenge: &[u8]) -> CBytes;
+
+    /// Is called with a final token of a server once authentication has succeeded.
+    /// An error returned from here fails a connection, e.g. if the server could
+    /// not prove its identity.
+    fn on_auth_success(&mut self, _token: &[u8]) -> error::Result<()> {
+        Ok(())
+    }
+}
+
+/// Checks if a client authenticator supports an authenticator class which has been
+/// sent by a server in `AUTHENTICATE` response.
+pub(crate) fn check_authenticator<A: Authenticator>(
+    authenticator: &A,
+    server_authenticator: &str,
+) -> error::Result<()> {
+    if authenticator.accepts_authenticator(server_authenticator) {
+        Ok(())
+    } else {
+        Err(error::Error::UnsupportedAuthenticator(
+            server_authenticator.to_string(),
+        ))
+    }
+}
+
+/// Handles a response to `AUTH_RESPONSE` request. Returns a token of the next
+/// `AUTH_RESPONSE` if a server has sent a challenge or `None` once authentication
+/// has succeeded.
+pub(crate) fn evaluate_auth_response(
+    sasl_authenticator: &mut dyn SaslAuthenticator,
+    response: &Frame,
+) -> error::Result<Option<CBytes>> {
+    match response.get_body()? {
+        ResponseBody::AuthChallenge(challenge) => Ok(Some(
+            sasl_authenticator.evaluate_challenge(challenge.data.as_slice().unwrap_or(&[])),
+        )),
+        ResponseBody::AuthSuccess(success) => sasl_authenticator
+            .on_auth_success(success.data.as_slice().unwrap_or(&[]))
+            .map(|_| None),
+        _ => Err(error::Error::InvalidFrame(format!(
+            "Unexpected response to authentication {:?}",
+            response.opcode
+        ))),
+    }
+}
+
+struct StaticTokenAuthenticator {
+    token: CBytes,
+}
+
+impl SaslAuthenticator for StaticTokenAuthenticator {
+    fn initial_response(&mut self) -> CBytes {
+        self.token.clone()
+    }
+
+    fn evaluate_challenge(&mut self, _challenge: &[u8]) -> CBytes {
+        self.token.clone()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -37,14 +131,22 @@ impl<'a> Authenticator for PasswordAuthenticator<'a> {
     }
 
     fn get_cassandra_name(&self) -> Option<&str> {
-        Some("org.apache.cassandra.auth.PasswordAuthenticator")
+        Some(PASSWORD_AUTHENTICATOR)
+    }
+
+    fn accepts_authenticator(&self, server_authenticator: &str) -> bool {
+        server_authenticator == PASSWORD_AUTHENTICATOR || server_authenticator == DSE_AUTHENTICATOR
     }
 }
 
+/// Authenticator which sends a username and a password via plain SASL mechanism.
+/// Besides `PasswordAuthenticator` it is accepted by `DseAuthenticator` and by
+/// server-side authenticators added with `accept_authenticator`.
 #[derive(Debug, Clone)]
 pub struct StaticPasswordAuthenticator {
     username: String,
     password: String,
+    accepted_authenticators: Vec<String>,
 }
 
 impl StaticPasswordAuthenticator {
@@ -52,25 +154,132 @@ impl StaticPasswordAuthenticator {
         StaticPasswordAuthenticator {
             username: username.to_string(),
             password: password.to_string(),
+            accepted_authenticators: vec![
+                PASSWORD_AUTHENTICATOR.to_string(),
+                DSE_AUTHENTICATOR.to_string(),
+            ],
         }
     }
+
+    /// Accepts one more server-side authenticator class, e.g. a custom subclass
+    /// of `PasswordAuthenticator` which speaks the same plain SASL mechanism.
+    pub fn accept_authenticator<S: ToString>(
+        mut self,
+        server_authenticator: S,
+    ) -> StaticPasswordAuthenticator {
+        self.accepted_authenticators
+            .push(server_authenticator.to_string());
+        self
+    }
 }
 
 impl Authenticator for StaticPasswordAuthenticator {
     fn get_auth_token(&self) -> CBytes {
-        let mut token = vec![0];
-        token.extend_from_slice(self.username.as_bytes());
-        token.push(0);
-        token.extend_from_slice(self.password.as_bytes());
+        plain_token(&self.username, &self.password)
+    }
 
-        CBytes::new(token)
+    fn get_cassandra_name(&self) -> Option<&str> {
+        Some(PASSWORD_AUTHENTICATOR)
+    }
+
+    fn accepts_authenticator(&self, server_authenticator: &str) -> bool {
+        self.accepted_authenticators
+            .iter()
+            .any(|accepted| accepted == server_authenticator)
+    }
+}
+
+/// Source of credentials for `ProvidedPasswordAuthenticator`. It is asked for
+/// a username and a password every time a new connection is established.
+/// Closures which return `(username, password)` are providers as well.
+pub trait CredentialsProvider: Send + Sync {
+    fn get_credentials(&self) -> (String, String);
+}
+
+impl<F> CredentialsProvider for F
+where
+    F: Fn() -> (String, String) + Send + Sync,
+{
+    fn get_credentials(&self) -> (String, String) {
+        self()
+    }
+}
+
+/// Authenticator which sends credentials of a `CredentialsProvider` via plain SASL
+/// mechanism, so rotated credentials are picked up by new connections while
+/// established ones keep working. Server-side authenticators are accepted
+/// in the same way as by `StaticPasswordAuthenticator`.
+pub struct ProvidedPasswordAuthenticator<P: CredentialsProvider> {
+    provider: Arc<P>,
+    accepted_authenticators: Vec<String>,
+}
+
+impl<P: CredentialsProvider> ProvidedPasswordAuthenticator<P> {
+    pub fn new(provider: P) -> ProvidedPasswordAuthenticator<P> {
+        ProvidedPasswordAuthenticator {
+            provider: Arc::new(provider),
+            accepted_authenticators: vec![
+                PASSWORD_AUTHENTICATOR.to_string(),
+                DSE_AUTHENTICATOR.to_string(),
+            ],
+        }
+    }
+
+    /// Accepts one more server-side authenticator class, e.g. a custom subclass
+    /// of `PasswordAuthenticator` which speaks the same plain SASL mechanism.
+    pub fn accept_authenticator<S: ToString>(
+        mut self,
+        server_authenticator: S,
+    ) -> ProvidedPasswordAuthenticator<P> {
+        self.accepted_authenticators
+            .push(server_authenticator.to_string());
+        self
+    }
+}
+
+impl<P: CredentialsProvider> Clone for ProvidedPasswordAuthenticator<P> {
+    fn clone(&self) -> Self {
+        ProvidedPasswordAuthenticator {
+            provider: Arc::clone(&self.provider),
+            accepted_authenticators: self.accepted_authenticators.clone(),
+        }
+    }
+}
+
+impl<P: CredentialsProvider> fmt::Debug for ProvidedPasswordAuthenticator<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProvidedPasswordAuthenticator")
+            .field("accepted_authenticators", &self.accepted_authenticators)
+            .finish()
+    }
+}
+
+impl<P: CredentialsProvider> Authenticator for ProvidedPasswordAuthenticator<P> {
+    fn get_auth_token(&self) -> CBytes {
+        let (username, password) = self.provider.get_credentials();
+        plain_token(&username, &password)
     }
 
     fn get_cassandra_name(&self) -> Option<&str> {
-        Some("org.apache.cassandra.auth.PasswordAuthenticator")
+        Some(PASSWORD_AUTHENTICATOR)
+    }
+
+    fn accepts_authenticator(&self, server_authenticator: &str) -> bool {
+        self.accepted_authenticators
+            .iter()
+            .any(|accepted| accepted == server_authenticator)
     }
 }
 
+fn plain_token(username: &str, password: &str) -> CBytes {
+    let mut token = vec![0];
+    token.extend_from_slice(username.as_bytes());
+    token.push(0);
+    token.extend_from_slice(password.as_bytes());
+
+    CBytes::new(token)
+}
+
 #[derive(Debug, Clone)]
 pub struct NoneAuthenticator;
 
@@ -158,5 +367,63 @@ mod tests {
         assert_eq!(auth.get_auth_token().into_plain().unwrap(), vec![0]);
     }
 
+    #[test]
+    fn test_static_password_authenticator_sasl_authenticator() {
+        let auth = StaticPasswordAuthenticator::new("foo", "bar");
+        let mut sasl = auth.new_sasl_authenticator();
+
+        assert_eq!(sasl.initial_response(), auth.get_auth_token());
+        assert_eq!(sasl.evaluate_challenge(b"challenge"), auth.get_auth_token());
+        assert!(sasl.on_auth_success(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_static_password_authenticator_accepts_authenticator() {
+        let auth = StaticPasswordAuthenticator::new("foo", "bar")
+            .accept_authenticator("com.example.CustomPasswordAuthenticator");
+
+        assert!(auth.accepts_authenticator("org.apache.cassandra.auth.PasswordAuthenticator"));
+        assert!(auth.accepts_authenticator("com.datastax.bdp.cassandra.auth.DseAuthenticator"));
+        assert!(auth.accepts_authenticator("com.example.CustomPasswordAuthenticator"));
+        assert!(!auth.accepts_authenticator("com.example.KerberosAuthenticator"));
+    }
+
+    #[test]
+    fn test_check_authenticator() {
+        let server_authenticator = "org.apache.cassandra.auth.PasswordAuthenticator";
+        let auth = PasswordAuthenticator::new("foo", "bar");
+        assert!(check_authenticator(&auth, server_authenticator).is_ok());
+
+        match check_authenticator(&NoneAuthenticator, server_authenticator) {
+            Err(error::Error::UnsupportedAuthenticator(name)) => {
+                assert_eq!(name, server_authenticator)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_provided_password_authenticator_get_auth_token() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let rotations = AtomicUsize::new(0);
+        let auth = ProvidedPasswordAuthenticator::new(move || {
+            let rotation = rotations.fetch_add(1, Ordering::SeqCst);
+            ("foo".to_string(), format!("bar{}", rotation))
+        });
+        let pooled_auth = auth.clone();
+
+        let mut sasl = auth.new_sasl_authenticator();
+        assert_eq!(
+            sasl.initial_response().into_plain().unwrap(),
+            b"\0foo\0bar0".to_vec()
+        );
+        assert_eq!(
+            pooled_auth.get_auth_token().into_plain().unwrap(),
+            b"\0foo\0bar1".to_vec()
+        );
+        assert!(auth.accepts_authenticator("org.apache.cassandra.auth.PasswordAuthenticator"));
+    }
+
     fn authenticator_tester<A: Authenticator>(_authenticator: Box<A>) {}
 }
@@ -0,0 +1,700 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Cursor};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tokio::task::JoinHandle;
+
+use crate::authenticators::{check_authenticator, evaluate_auth_response, Authenticator};
+use crate::cluster::stream_ids::{StreamIds, MAX_STREAM_ID};
+use crate::compression::Compression;
+use crate::error;
+use crate::frame::parser::{parse_frame, parse_header, HEADER_LEN};
+use crate::frame::segment::SegmentDecoder;
+use crate::frame::{Frame, Opcode, ProtocolVersion};
+use crate::load_balancing::GetAddr;
+
+type Responder = oneshot::Sender<error::Result<Frame>>;
+
+/// Size of a chunk which is read from a socket at once in protocol v5.
+const READ_CHUNK_LEN: usize = 64 * 1024;
+
+/// Requests which were written to a connection and wait for their responses.
+struct InFlight {
+    stream_ids: StreamIds,
+    responders: HashMap<u16, Responder>,
+    /// Contains a reason if a connection cannot be used anymore.
+    closed: Option<String>,
+}
+
+impl InFlight {
+    fn finish(&mut self, stream: u16, permits: &Semaphore) -> Option<Responder> {
+        let responder = self.responders.remove(&stream)?;
+        self.stream_ids.release(stream);
+        permits.add_permits(1);
+        Some(responder)
+    }
+}
+
+/// Marks a connection closed and fails all requests which wait for their responses.
+fn close(in_flight: &Mutex<InFlight>, permits: &Semaphore, reason: String) {
+    if let Ok(mut in_flight) = in_flight.lock() {
+        if in_flight.closed.is_some() {
+            return;
+        }
+        for (_, responder) in in_flight.responders.drain() {
+            let _ = responder.send(Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                format!("Connection is closed: {}", reason),
+            )
+            .into()));
+        }
+        in_flight.closed = Some(reason);
+    }
+    permits.close();
+}
+
+/// Asynchronous connection to a single Cassandra node. Unlike connections
+/// from `r2d2` pools it does not wait for a response before sending next request,
+/// instead every request gets its own stream id and a background task routes
+/// received frames to waiting callers. Requests are written by another background
+/// task, so a request which is cancelled never leaves a partially written frame.
+pub struct AsyncConnection {
+    addr: SocketAddr,
+    compression: Compression,
+    protocol_version: ProtocolVersion,
+    requests: mpsc::UnboundedSender<Vec<u8>>,
+    in_flight: Arc<Mutex<InFlight>>,
+    permits: Arc<Semaphore>,
+    reader: JoinHandle<()>,
+    writer: JoinHandle<()>,
+}
+
+impl AsyncConnection {
+    /// Establishes new connection to a node and makes it ready for querying,
+    /// i.e. performs startup with given compression and authenticates if
+    /// a server requires that. The highest protocol version is attempted first,
+    /// if a server does not support it the connection is established again
+    /// with a lower version the server has suggested.
+    pub async fn connect<A: Authenticator>(
+        addr: &str,
+        authenticator: &A,
+        compression: Compression,
+    ) -> error::Result<AsyncConnection> {
+        let mut protocol_version = ProtocolVersion::HIGHEST;
+
+        loop {
+            let result = AsyncConnection::connect_with_version(
+                addr,
+                authenticator,
+                compression,
+                protocol_version,
+            )
+            .await;
+            let err = match result {
+                Ok(connection) => return Ok(connection),
+                Err(err) => err,
+            };
+            match err
+                .server_error()
+                .and_then(|err| protocol_version.downgrade(err))
+            {
+                Some(lower_version) => {
+                    debug!(
+                        "Protocol version {:?} is not supported, trying {:?}",
+                        protocol_version, lower_version
+                    );
+                    protocol_version = lower_version;
+                }
+                None => return Err(err),
+            }
+        }
+    }
+
+    async fn connect_with_version<A: Authenticator>(
+        addr: &str,
+        authenticator: &A,
+        compression: Compression,
+        protocol_version: ProtocolVersion,
+    ) -> error::Result<AsyncConnection> {
+        let stream = TcpStream::connect(addr).await?;
+        let addr = stream.peer_addr()?;
+        let (read_half, write_half) = stream.into_split();
+
+        let in_flight = Arc::new(Mutex::new(InFlight {
+            stream_ids: StreamIds::default(),
+            responders: HashMap::new(),
+            closed: None,
+        }));
+        let permits = Arc::new(Semaphore::new(MAX_STREAM_ID as usize + 1));
+        let reader = tokio::spawn(read_frames(
+            read_half,
+            compression,
+            protocol_version,
+            in_flight.clone(),
+            permits.clone(),
+        ));
+        let (requests, requests_receiver) = mpsc::unbounded_channel();
+        let writer = tokio::spawn(write_frames(
+            write_half,
+            requests_receiver,
+            in_flight.clone(),
+            permits.clone(),
+        ));
+
+        let connection = AsyncConnection {
+            addr,
+            compression,
+            protocol_version,
+            requests,
+            in_flight,
+            permits,
+            reader,
+            writer,
+        };
+
+        connection.startup(authenticator).await?;
+
+        Ok(connection)
+    }
+
+    /// Returns an address of a node.
+    pub fn get_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns compression which was negotiated with a node.
+    pub fn get_compressor(&self) -> Compression {
+        self.compression
+    }
+
+    /// Returns a protocol version which was negotiated with a node. Request frames
+    /// sent over the connection should be built for this version.
+    pub fn get_protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Checks if connection was closed, e.g. because of IO error.
+    pub fn is_closed(&self) -> bool {
+        self.in_flight
+            .lock()
+            .map(|in_flight| in_flight.closed.is_some())
+            .unwrap_or(true)
+    }
+
+    /// Sends request frame and waits for a response to it. Stream id of
+    /// the frame gets overridden by a free stream id of this connection.
+    /// Returned errors carry an address of a node the connection is established with.
+    pub async fn send(&self, frame: Frame) -> error::Result<Frame> {
+        self.send_frame(frame)
+            .await
+            .map_err(|err| err.with_node(self.addr))
+    }
+
+    /// Does the same as `send` but stops waiting for a response once `timeout` expires.
+    /// Unlike blocking connections the connection is kept: a request which is being
+    /// written when the timeout expires is still written as a whole by a writer task,
+    /// and a late response is routed by its stream id and dropped.
+    pub async fn send_with_timeout(
+        &self,
+        frame: Frame,
+        timeout: Option<Duration>,
+    ) -> error::Result<Frame> {
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.send(frame))
+                .await
+                .unwrap_or_else(|_| Err(error::Error::Timeout(timeout).with_node(self.addr))),
+            None => self.send(frame).await,
+        }
+    }
+
+    async fn send_frame(&self, mut frame: Frame) -> error::Result<Frame> {
+        self.permits
+            .acquire()
+            .await
+            .map_err(|_| closed_error(self.addr, "no more requests are accepted"))?
+            .forget();
+
+        let (stream, receiver) = {
+            let mut in_flight = self.lock_in_flight()?;
+            if let Some(ref reason) = in_flight.closed {
+                self.permits.add_permits(1);
+                return Err(closed_error(self.addr, reason));
+            }
+            let stream = match in_flight.stream_ids.acquire() {
+                Some(stream) => stream,
+                None => {
+                    self.permits.add_permits(1);
+                    return Err(error::Error::Pool("No free stream ids".to_string()));
+                }
+            };
+            let (sender, receiver) = oneshot::channel();
+            in_flight.responders.insert(stream, sender);
+            (stream, receiver)
+        };
+
+        let mut pending = PendingRequest {
+            connection: self,
+            stream,
+            written: false,
+        };
+
+        frame.stream = stream;
+        // framing of protocol v5 starts right after startup
+        let bytes = if frame.opcode == Opcode::Startup {
+            frame.encode_with(self.compression)?
+        } else {
+            frame.encode_framed(self.compression)?
+        };
+        // the frame is written as a whole even if this future gets cancelled
+        self.requests
+            .send(bytes)
+            .map_err(|_| closed_error(self.addr, "no more requests are accepted"))?;
+        pending.written = true;
+
+        receiver
+            .await
+            .map_err(|_| closed_error(self.addr, "response is not received"))?
+    }
+
+    async fn startup<A: Authenticator>(&self, authenticator: &A) -> error::Result<()> {
+        let startup_frame =
+            Frame::new_req_startup(self.compression.as_str(), self.protocol_version);
+        let start_response = self.send(startup_frame).await?;
+
+        match start_response.opcode {
+            Opcode::Ready => Ok(()),
+            Opcode::Authenticate => {
+                let body = start_response.get_body()?;
+                let server_authenticator = body.get_authenticator().ok_or_else(|| {
+                    error::Error::from("Authentication is required but no authenticator is specified")
+                })?;
+                check_authenticator(authenticator, server_authenticator)?;
+
+                let mut sasl_authenticator = authenticator.new_sasl_authenticator();
+                let mut token = sasl_authenticator.initial_response();
+                loop {
+                    let auth_frame = Frame::new_req_auth_response(token, self.protocol_version);
+                    let auth_response = self.send(auth_frame).await?;
+
+                    match evaluate_auth_response(sasl_authenticator.as_mut(), &auth_response)? {
+                        Some(next_token) => token = next_token,
+                        None => return Ok(()),
+                    }
+                }
+            }
+            opcode => Err(error::Error::InvalidFrame(format!(
+                "Unexpected response to startup {:?}",
+                opcode
+            ))),
+        }
+    }
+
+    fn lock_in_flight(&self) -> error::Result<std::sync::MutexGuard<'_, InFlight>> {
+        self.in_flight
+            .lock()
+            .map_err(|_| error::Error::from("Connection state is poisoned"))
+    }
+}
+
+impl GetAddr for AsyncConnection {
+    fn get_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for AsyncConnection {
+    fn drop(&mut self) {
+        self.reader.abort();
+        self.writer.abort();
+    }
+}
+
+/// Releases a stream id if a request was dropped before it was queued for writing.
+struct PendingRequest<'a> {
+    connection: &'a AsyncConnection,
+    stream: u16,
+    written: bool,
+}
+
+impl<'a> Drop for PendingRequest<'a> {
+    fn drop(&mut self) {
+        if self.written {
+            // stream id will be released once a response is received
+            return;
+        }
+
+        if let Ok(mut in_flight) = self.connection.in_flight.lock() {
+            in_flight.finish(self.stream, &self.connection.permits);
+        }
+    }
+}
+
+fn closed_error(addr: SocketAddr, reason: &str) -> error::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        format!("Connection to {} is closed: {}", addr, reason),
+    )
+    .into()
+}
+
+pub(crate) async fn read_raw_frame<R: AsyncRead + Unpin>(reader: &mut R) -> error::Result<Vec<u8>> {
+    let mut bytes = vec![0; HEADER_LEN];
+    reader.read_exact(&mut bytes).await?;
+
+    // a body is read gradually, so a corrupted length does not allocate
+    // more memory than a server has actually sent
+    let (_, length) = parse_header(&bytes);
+    let read = reader.take(length as u64).read_to_end(&mut bytes).await?;
+    if read != length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Connection was closed by a server",
+        )
+        .into());
+    }
+
+    Ok(bytes)
+}
+
+/// Reads a next frame which was wrapped into protocol v5 segments. Bytes of segments
+/// which were received but not decoded yet are kept in `buffer`.
+pub(crate) async fn read_segmented_frame<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    segments: &mut SegmentDecoder,
+    buffer: &mut Vec<u8>,
+) -> error::Result<Vec<u8>> {
+    loop {
+        if let Some(frame) = segments.next_frame() {
+            return Ok(frame);
+        }
+
+        buffer.reserve(READ_CHUNK_LEN);
+        if reader.read_buf(buffer).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection was closed by a server",
+            )
+            .into());
+        }
+        segments.decode(buffer)?;
+    }
+}
+
+async fn read_frames(
+    mut reader: OwnedReadHalf,
+    compression: Compression,
+    protocol_version: ProtocolVersion,
+    in_flight: Arc<Mutex<InFlight>>,
+    permits: Arc<Semaphore>,
+) {
+    // the first frame is a response to startup, then frames are wrapped
+    // into segments in protocol v5
+    let mut segments: Option<SegmentDecoder> = None;
+    let mut buffer = vec![];
+
+    let reason = loop {
+        let read = match segments {
+            Some(ref mut segments) => {
+                read_segmented_frame(&mut reader, segments, &mut buffer).await
+            }
+            None => read_raw_frame(&mut reader).await,
+        };
+        let bytes = match read {
+            Ok(bytes) => bytes,
+            Err(err) => break err.to_string(),
+        };
+
+        let (stream, _) = parse_header(&bytes);
+        let response = if segments.is_some() {
+            parse_frame(&RefCell::new(Cursor::new(bytes)), &Compression::None)
+        } else {
+            parse_frame(&RefCell::new(Cursor::new(bytes)), &compression)
+        };
+        if protocol_version.is_segmented() && segments.is_none() {
+            segments = Some(SegmentDecoder::new(compression));
+        }
+
+        let responder = match in_flight.lock() {
+            Ok(mut in_flight) => in_flight.finish(stream, &permits),
+            Err(_) => break "connection state is poisoned".to_string(),
+        };
+
+        // frames which nobody waits for (e.g. events or responses to
+        // cancelled requests) are dropped
+        if let Some(responder) = responder {
+            let _ = responder.send(response);
+        }
+    };
+
+    close(&in_flight, &permits, reason);
+}
+
+async fn write_frames(
+    mut writer: OwnedWriteHalf,
+    mut requests: mpsc::UnboundedReceiver<Vec<u8>>,
+    in_flight: Arc<Mutex<InFlight>>,
+    permits: Arc<Semaphore>,
+) {
+    while let Some(bytes) = requests.recv().await {
+        if let Err(err) = writer.write_all(bytes.as_slice()).await {
+            close(&in_flight, &permits, err.to_string());
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authenticators::NoneAuthenticator;
+    use crate::consistency::Consistency;
+    use crate::test::{
+        unsupported_version_error, versioned_response_frame, ChallengedAuthenticator, TestServer,
+    };
+    use tokio::net::TcpListener;
+
+    fn set_keyspace_body(keyspace: &str) -> Vec<u8> {
+        let mut body = vec![0, 0, 0, 3, 0, keyspace.len() as u8];
+        body.extend_from_slice(keyspace.as_bytes());
+        body
+    }
+
+    #[tokio::test]
+    async fn routes_responses_by_stream_id() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let mut server = TestServer::accept(&listener).await;
+            let (stream, opcode) = server.read_request().await;
+            assert_eq!(opcode, Opcode::Startup);
+            server.respond(stream, Opcode::Ready, vec![]).await;
+
+            let (first, _) = server.read_request().await;
+            let (second, _) = server.read_request().await;
+            assert_ne!(first, second);
+
+            // responses are sent in reversed order
+            server
+                .respond(second, Opcode::Result, set_keyspace_body("second"))
+                .await;
+            server
+                .respond(first, Opcode::Result, set_keyspace_body("first"))
+                .await;
+        });
+
+        let connection = AsyncConnection::connect(&addr, &NoneAuthenticator, Compression::None)
+            .await
+            .unwrap();
+
+        let first = connection.send(Frame::new_req_options(connection.get_protocol_version()));
+        let (first, second) = {
+            let second = async {
+                // make sure the first request is written before the second one
+                tokio::task::yield_now().await;
+                connection
+                    .send(Frame::new_req_options(connection.get_protocol_version()))
+                    .await
+            };
+            tokio::join!(first, second)
+        };
+
+        assert_eq!(first.unwrap().body, set_keyspace_body("first"));
+        assert_eq!(second.unwrap().body, set_keyspace_body("second"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn fails_pending_requests_once_closed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let mut server = TestServer::accept(&listener).await;
+            let (stream, _) = server.read_request().await;
+            server.respond(stream, Opcode::Ready, vec![]).await;
+            server.read_request().await;
+        });
+
+        let connection = AsyncConnection::connect(&addr, &NoneAuthenticator, Compression::None)
+            .await
+            .unwrap();
+
+        assert!(connection
+            .send(Frame::new_req_options(connection.get_protocol_version()))
+            .await
+            .is_err());
+        assert!(connection.is_closed());
+        assert!(connection
+            .send(Frame::new_req_options(connection.get_protocol_version()))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn keeps_connection_after_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let mut server = TestServer::accept(&listener).await;
+            let (stream, _) = server.read_request().await;
+            server.respond(stream, Opcode::Ready, vec![]).await;
+
+            let (stalled, _) = server.read_request().await;
+            let (next, _) = server.read_request().await;
+            server
+                .respond(stalled, Opcode::Result, set_keyspace_body("stalled"))
+                .await;
+            server
+                .respond(next, Opcode::Result, set_keyspace_body("next"))
+                .await;
+        });
+
+        let connection = AsyncConnection::connect(&addr, &NoneAuthenticator, Compression::None)
+            .await
+            .unwrap();
+        let options = || Frame::new_req_options(connection.get_protocol_version());
+        let timeout = Duration::from_millis(10);
+
+        let err = connection
+            .send_with_timeout(options(), Some(timeout))
+            .await
+            .unwrap_err();
+        assert!(matches!(err.inner(), error::Error::Timeout(t) if *t == timeout));
+        assert_eq!(err.node_addr(), Some(connection.get_addr()));
+
+        // a late response to the stalled request is dropped
+        let response = connection.send_with_timeout(options(), None).await;
+        assert_eq!(response.unwrap().body, set_keyspace_body("next"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn writes_whole_frames_of_cancelled_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let mut server = TestServer::accept(&listener).await;
+            let (stream, _) = server.read_request().await;
+            server.respond(stream, Opcode::Ready, vec![]).await;
+
+            // the client gives up while the large frame does not fit into socket buffers
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let (_, opcode) = server.read_request().await;
+            assert_eq!(opcode, Opcode::Query);
+            let (next, opcode) = server.read_request().await;
+            assert_eq!(opcode, Opcode::Options);
+            server
+                .respond(next, Opcode::Result, set_keyspace_body("next"))
+                .await;
+        });
+
+        let connection = AsyncConnection::connect(&addr, &NoneAuthenticator, Compression::None)
+            .await
+            .unwrap();
+        let query = Frame::new_req_query(
+            "x".repeat(16 * 1024 * 1024),
+            Consistency::One,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            vec![],
+            connection.get_protocol_version(),
+        );
+
+        let timeout = Duration::from_millis(1);
+        assert!(connection
+            .send_with_timeout(query, Some(timeout))
+            .await
+            .is_err());
+
+        let response = connection
+            .send(Frame::new_req_options(connection.get_protocol_version()))
+            .await;
+        assert_eq!(response.unwrap().body, set_keyspace_body("next"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn does_not_trust_body_length_of_truncated_frames() {
+        let mut bytes = versioned_response_frame(Opcode::Ready, vec![], ProtocolVersion::V4);
+        bytes[HEADER_LEN - 4..HEADER_LEN].copy_from_slice(&[0x7F, 0xFF, 0xFF, 0xFF]);
+        bytes.extend_from_slice(&[1, 2, 3]);
+
+        let err = read_raw_frame(&mut Cursor::new(bytes)).await.unwrap_err();
+        assert!(
+            matches!(err, error::Error::Io(ref err) if err.kind() == io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_auth_challenges() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let mut server = TestServer::accept(&listener).await;
+            let responses =
+                ChallengedAuthenticator::server_responses(ChallengedAuthenticator::SERVER_FINAL);
+            for (opcode, body) in responses {
+                let (stream, _) = server.read_request().await;
+                server.respond(stream, opcode, body).await;
+            }
+        });
+
+        AsyncConnection::connect(&addr, &ChallengedAuthenticator, Compression::None)
+            .await
+            .unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn negotiates_lower_protocol_version() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let highest_version = ProtocolVersion::HIGHEST;
+        let supported_version = highest_version.lower().unwrap();
+
+        let server = tokio::spawn(async move {
+            // the first connection is closed once startup is rejected
+            let mut server = TestServer::accept_with_version(&listener, supported_version).await;
+            let (stream, _) = server.read_request().await;
+            assert_eq!(server.request_version, Some(highest_version));
+            let error = unsupported_version_error(highest_version, supported_version);
+            server.respond(stream, Opcode::Error, error).await;
+
+            let mut server = TestServer::accept_with_version(&listener, supported_version).await;
+            let (stream, _) = server.read_request().await;
+            assert_eq!(server.request_version, Some(supported_version));
+            server.respond(stream, Opcode::Ready, vec![]).await;
+
+            let (stream, _) = server.read_request().await;
+            assert_eq!(server.request_version, Some(supported_version));
+            server.respond(stream, Opcode::Supported, vec![0, 0]).await;
+        });
+
+        let connection = AsyncConnection::connect(&addr, &NoneAuthenticator, Compression::None)
+            .await
+            .unwrap();
+        assert_eq!(connection.get_protocol_version(), supported_version);
+
+        let response = connection
+            .send(Frame::new_req_options(connection.get_protocol_version()))
+            .await
+            .unwrap();
+        assert_eq!(response.protocol_version, supported_version);
+        server.await.unwrap();
+    }
+}
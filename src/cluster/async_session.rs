@@ -0,0 +1,1388 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+use crate::authenticators::Authenticator;
+use crate::cluster::metadata::{
+    into_rows, query_frame, ClusterMetadata, PeersTable, SELECT_LOCAL, SELECT_PEERS,
+    SELECT_PEERS_V2,
+};
+use crate::cluster::{AsyncConnection, ClusterTcpConfig};
+use crate::compression::Compression;
+use crate::consistency::Consistency;
+use crate::error;
+use crate::error::{Error, ErrorKind};
+use crate::frame::frame_result::BodyResResultPrepared;
+use crate::frame::{Frame, ProtocolVersion};
+use crate::load_balancing::{LoadBalancingStrategy, RoutingInfo};
+use crate::query::utils::prepare_flags;
+use crate::query::{
+    PreparedQuery, Query, QueryBatch, QueryParams, QueryParamsBuilder, QueryResponse, QueryValues,
+};
+use crate::retry::{DefaultRetryPolicy, RetryDecision, RetryInfo, RetryPolicy};
+use crate::speculative_execution::SpeculativeExecutionPolicy;
+use crate::types::custom::CustomTypeRegistry;
+
+/// Asynchronous CDRS session that holds one multiplexed connection per node.
+/// It provides the same querying functionality as `QueryExecutor`, `PrepareExecutor`,
+/// `ExecExecutor` and `BatchExecutor` do for a blocking `Session`, but methods
+/// return futures that should be run on tokio runtime.
+///
+/// A connection which has been closed, e.g. because its node has restarted, is
+/// established again once it is picked for a request. Nodes which cannot be
+/// connected to are skipped and tried again in background.
+pub struct AsyncSession<LB> {
+    load_balancing: Mutex<LB>,
+    node_connector: Option<NodeConnector>,
+    retry_policy: Box<dyn RetryPolicy>,
+    speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy>>,
+    request_timeout: Option<Duration>,
+    custom_type_registry: CustomTypeRegistry,
+    compression: Compression,
+}
+
+/// Establishes a connection to a node with a given address.
+type ConnectNode = dyn Fn(String) -> Pin<Box<dyn Future<Output = error::Result<AsyncConnection>> + Send>>
+    + Send
+    + Sync;
+
+/// Address of a node together with a result of connecting to it.
+type Connected = (String, error::Result<AsyncConnection>);
+
+/// Delay after which a node which could not be connected to is tried again.
+const NODE_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Connects to nodes again once their connections are closed and to nodes which
+/// could not be connected to before. The latter are connected to in background tasks,
+/// so requests do not wait for nodes which are likely down.
+struct NodeConnector {
+    connect: Arc<ConnectNode>,
+    /// Nodes which could not be connected to, together with times of next attempts.
+    unreachable: Mutex<HashMap<String, Instant>>,
+    connected_tx: mpsc::UnboundedSender<Connected>,
+    connected_rx: Mutex<mpsc::UnboundedReceiver<Connected>>,
+}
+
+impl NodeConnector {
+    fn new(connect: Arc<ConnectNode>) -> Self {
+        let (connected_tx, connected_rx) = mpsc::unbounded_channel();
+        NodeConnector {
+            connect,
+            unreachable: Mutex::new(HashMap::new()),
+            connected_tx,
+            connected_rx: Mutex::new(connected_rx),
+        }
+    }
+
+    fn failed(&self, addr: String, err: &Error) {
+        warn!(
+            "Unable to connect to node {}, it will be tried again in {:?}: {}",
+            addr, NODE_RETRY_DELAY, err
+        );
+        if let Ok(mut unreachable) = self.unreachable.lock() {
+            unreachable.insert(addr, Instant::now() + NODE_RETRY_DELAY);
+        }
+    }
+
+    /// Starts connecting to unreachable nodes which should be tried again.
+    /// Results are received with `connected_rx`.
+    fn retry_unreachable(&self) {
+        let now = Instant::now();
+        let to_retry: Vec<String> = match self.unreachable.lock() {
+            Ok(mut unreachable) => {
+                let to_retry = unreachable
+                    .iter()
+                    .filter(|(_, retry_at)| **retry_at <= now)
+                    .map(|(addr, _)| addr.clone())
+                    .collect();
+                for addr in &to_retry {
+                    unreachable.remove(addr);
+                }
+                to_retry
+            }
+            Err(_) => return,
+        };
+
+        for addr in to_retry {
+            let connect = self.connect.clone();
+            let connected_tx = self.connected_tx.clone();
+            tokio::spawn(async move {
+                let result = connect(addr.clone()).await;
+                let _ = connected_tx.send((addr, result));
+            });
+        }
+    }
+}
+
+impl<LB> AsyncSession<LB> {
+    /// Returns compression that current session has.
+    pub fn get_compressor(&self) -> Compression {
+        self.compression
+    }
+
+    /// Returns retry policy that current session has.
+    pub fn get_retry_policy(&self) -> &dyn RetryPolicy {
+        self.retry_policy.as_ref()
+    }
+
+    /// Replaces retry policy of a session, by default `DefaultRetryPolicy` is used.
+    pub fn with_retry_policy<P: RetryPolicy + 'static>(mut self, retry_policy: P) -> Self {
+        self.retry_policy = Box::new(retry_policy);
+        self
+    }
+
+    /// Returns speculative execution policy that current session has.
+    pub fn get_speculative_execution_policy(&self) -> Option<&dyn SpeculativeExecutionPolicy> {
+        self.speculative_execution_policy.as_deref()
+    }
+
+    /// Sets speculative execution policy of a session, by default idempotent requests
+    /// are not executed speculatively.
+    pub fn with_speculative_execution_policy<P: SpeculativeExecutionPolicy + 'static>(
+        mut self,
+        speculative_execution_policy: P,
+    ) -> Self {
+        self.speculative_execution_policy = Some(Box::new(speculative_execution_policy));
+        self
+    }
+
+    /// Returns request timeout that current session has.
+    pub fn get_request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// Sets a timeout of requests which do not have their own one in `QueryParams`,
+    /// by default the driver waits for responses indefinitely.
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Returns registry of custom types that current session has.
+    pub fn get_custom_type_registry(&self) -> &CustomTypeRegistry {
+        &self.custom_type_registry
+    }
+
+    /// Replaces registry of custom types of a session, by default it is empty,
+    /// so values of custom types are returned as raw bytes.
+    pub fn with_custom_type_registry(mut self, custom_type_registry: CustomTypeRegistry) -> Self {
+        self.custom_type_registry = custom_type_registry;
+        self
+    }
+}
+
+impl<LB: LoadBalancingStrategy<Arc<AsyncConnection>> + Sized> AsyncSession<LB> {
+    /// Returns connection from a load balancer.
+    pub fn get_connection(&self) -> error::Result<Arc<AsyncConnection>> {
+        self.get_connection_for(&RoutingInfo::default())
+    }
+
+    /// Returns connection which is the most suitable for a request with given
+    /// routing information.
+    pub fn get_connection_for(&self, routing: &RoutingInfo) -> error::Result<Arc<AsyncConnection>> {
+        self.load_balancing
+            .lock()
+            .map_err(|_| Error::from("Load balancer is poisoned"))?
+            .next_for(routing)
+            .cloned()
+            .ok_or_else(|| Error::Pool("Unable to get connection".to_string()))
+    }
+
+    /// Returns an open connection which is the most suitable for a request with given
+    /// routing information. A closed connection is established again, if its node
+    /// cannot be connected to the node is skipped until it is tried again later.
+    async fn open_connection_for(
+        &self,
+        routing: &RoutingInfo,
+    ) -> error::Result<Arc<AsyncConnection>> {
+        let node_connector = match self.node_connector {
+            Some(ref node_connector) => node_connector,
+            None => return self.get_connection_for(routing),
+        };
+        self.add_connected_nodes(node_connector)?;
+
+        let mut closed = HashSet::new();
+        loop {
+            let connection = self.get_connection_for(routing)?;
+            if !connection.is_closed() {
+                return Ok(connection);
+            }
+
+            let addr = connection.get_addr();
+            if !closed.insert(addr) {
+                // a load balancer is not able to remove nodes
+                return Err(Error::Pool("Unable to get connection".to_string()));
+            }
+            self.lock_load_balancing()?
+                .remove_node(|connection| connection.get_addr() == addr);
+
+            match (node_connector.connect)(addr.to_string()).await {
+                Ok(connection) => {
+                    let connection = Arc::new(connection);
+                    self.replace_node(connection.clone())?;
+                    return Ok(connection);
+                }
+                Err(err) => node_connector.failed(addr.to_string(), &err),
+            }
+        }
+    }
+
+    /// Adds nodes which have been connected to in background to the load balancer and
+    /// starts connecting to unreachable nodes which should be tried again.
+    fn add_connected_nodes(&self, node_connector: &NodeConnector) -> error::Result<()> {
+        let mut connected = vec![];
+        if let Ok(mut connected_rx) = node_connector.connected_rx.lock() {
+            while let Ok(result) = connected_rx.try_recv() {
+                connected.push(result);
+            }
+        }
+
+        for (addr, result) in connected {
+            match result {
+                Ok(connection) => self.replace_node(Arc::new(connection))?,
+                Err(err) => node_connector.failed(addr, &err),
+            }
+        }
+        node_connector.retry_unreachable();
+
+        Ok(())
+    }
+
+    fn replace_node(&self, connection: Arc<AsyncConnection>) -> error::Result<()> {
+        let addr = connection.get_addr();
+        let mut load_balancing = self.lock_load_balancing()?;
+        load_balancing.remove_node(|connection| connection.get_addr() == addr);
+        load_balancing.add_node(connection);
+
+        Ok(())
+    }
+
+    fn lock_load_balancing(&self) -> error::Result<std::sync::MutexGuard<'_, LB>> {
+        self.load_balancing
+            .lock()
+            .map_err(|_| Error::from("Load balancer is poisoned"))
+    }
+
+    /// Fetches cluster topology from one of nodes and passes it to the load balancer,
+    /// so strategies like `TokenAware` could route requests basing on it.
+    pub async fn refresh_metadata(&self) -> error::Result<()> {
+        let connection = self.open_connection_for(&RoutingInfo::default()).await?;
+        let protocol_version = connection.get_protocol_version();
+        let local = connection
+            .send(query_frame(SELECT_LOCAL, protocol_version))
+            .await?;
+        let (peers_table, peers) = match connection
+            .send(query_frame(SELECT_PEERS_V2, protocol_version))
+            .await
+        {
+            // system.peers_v2 does not exist prior Cassandra 4.0
+            Err(ref err) if err.server_error().is_some() => (
+                PeersTable::Peers,
+                connection
+                    .send(query_frame(SELECT_PEERS, protocol_version))
+                    .await?,
+            ),
+            result => (PeersTable::PeersV2, result?),
+        };
+        let metadata = ClusterMetadata::from_rows(
+            connection.get_addr(),
+            &into_rows(local)?,
+            peers_table,
+            &into_rows(peers)?,
+        )?;
+
+        self.load_balancing
+            .lock()
+            .map_err(|_| Error::from("Load balancer is poisoned"))?
+            .update_metadata(&metadata);
+
+        Ok(())
+    }
+
+    /// Sends a request frame built for a protocol version of a picked connection.
+    async fn send_frame<F>(&self, build_frame: F) -> error::Result<Frame>
+    where
+        F: FnOnce(ProtocolVersion) -> Frame,
+    {
+        self.send_routed_frame(build_frame, &RoutingInfo::default())
+            .await
+    }
+
+    async fn send_routed_frame<F>(
+        &self,
+        build_frame: F,
+        routing: &RoutingInfo,
+    ) -> error::Result<Frame>
+    where
+        F: FnOnce(ProtocolVersion) -> Frame,
+    {
+        let connection = self.open_connection_for(routing).await?;
+        connection
+            .send_with_timeout(
+                build_frame(connection.get_protocol_version()),
+                self.request_timeout,
+            )
+            .await
+    }
+
+    /// Sends a request frame built for a consistency level from routing information
+    /// and a protocol version of a picked connection and resends it as long as retry
+    /// policy decides to retry server errors.
+    /// Idempotent requests are executed speculatively if a session has such policy.
+    /// Every attempt is given `timeout`, or request timeout of a session if it is not
+    /// specified.
+    async fn send_retried_frame<F>(
+        &self,
+        routing: &RoutingInfo,
+        is_idempotent: bool,
+        timeout: Option<Duration>,
+        build_frame: F,
+    ) -> error::Result<Frame>
+    where
+        F: Fn(Consistency, ProtocolVersion) -> Frame,
+    {
+        let speculative_execution_policy = self
+            .get_speculative_execution_policy()
+            .filter(|_| is_idempotent);
+        let timeout = timeout.or(self.request_timeout);
+        let mut consistency = routing.consistency.unwrap_or_default();
+        let mut retry_count = 0;
+        let mut connection = self.open_connection_for(routing).await?;
+
+        loop {
+            let result = match speculative_execution_policy {
+                Some(policy) => {
+                    let (winner, result) = self
+                        .send_speculative_frame(
+                            connection,
+                            consistency,
+                            policy,
+                            timeout,
+                            |version| build_frame(consistency, version),
+                        )
+                        .await?;
+                    connection = winner;
+                    result
+                }
+                None => {
+                    let frame = build_frame(consistency, connection.get_protocol_version());
+                    connection.send_with_timeout(frame, timeout).await
+                }
+            };
+            let decision = match result.as_ref().map_err(Error::server_error) {
+                Err(Some(error)) => self.retry_policy.decide(&RetryInfo {
+                    error,
+                    consistency,
+                    retry_count,
+                    is_idempotent,
+                }),
+                _ => RetryDecision::DontRetry,
+            };
+
+            match decision {
+                RetryDecision::RetrySameNode(new_consistency) => {
+                    consistency = new_consistency.unwrap_or(consistency);
+                }
+                RetryDecision::RetryNextNode(new_consistency) => {
+                    consistency = new_consistency.unwrap_or(consistency);
+                    // routing key is omitted, otherwise the same replica could be picked again
+                    connection = self
+                        .open_connection_for(&RoutingInfo {
+                            routing_key: None,
+                            consistency: Some(consistency),
+                        })
+                        .await?;
+                }
+                RetryDecision::DontRetry => return result,
+            }
+
+            retry_count += 1;
+        }
+    }
+
+    /// Sends a request frame over given connection and then over connections to next nodes
+    /// every time speculative execution policy decides so until a first successful response
+    /// is received. Responses to other executions are ignored. Returns the first successful
+    /// response or the last error if all executions failed, together with a connection
+    /// it was received with. Executions which are still running are cancelled.
+    async fn send_speculative_frame<F>(
+        &self,
+        connection: Arc<AsyncConnection>,
+        consistency: Consistency,
+        policy: &dyn SpeculativeExecutionPolicy,
+        request_timeout: Option<Duration>,
+        build_frame: F,
+    ) -> error::Result<(Arc<AsyncConnection>, error::Result<Frame>)>
+    where
+        F: Fn(ProtocolVersion) -> Frame,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut executions = vec![];
+        let mut execute = |connection: Arc<AsyncConnection>| {
+            let tx = tx.clone();
+            let frame = build_frame(connection.get_protocol_version());
+            executions.push(tokio::spawn(async move {
+                let result = connection.send_with_timeout(frame, request_timeout).await;
+                let _ = tx.send((connection, result));
+            }));
+        };
+        // routing key is omitted, otherwise the same replica could be picked again
+        let next_routing = RoutingInfo {
+            routing_key: None,
+            consistency: Some(consistency),
+        };
+
+        execute(connection);
+        let mut running_executions = 1;
+        let mut pending_executions = 1;
+        // a delay is counted from a start of the last execution, so failed responses
+        // which are received in the meantime do not postpone a next one
+        let mut next_execution_at = policy
+            .next_execution(running_executions)
+            .map(|delay| Instant::now() + delay);
+
+        let winner = loop {
+            let received = match next_execution_at {
+                Some(at) => {
+                    match timeout(at.saturating_duration_since(Instant::now()), rx.recv()).await {
+                        Ok(received) => received,
+                        Err(_) => {
+                            next_execution_at = match self.open_connection_for(&next_routing).await
+                            {
+                                Ok(connection) => {
+                                    execute(connection);
+                                    running_executions += 1;
+                                    pending_executions += 1;
+                                    policy
+                                        .next_execution(running_executions)
+                                        .map(|delay| Instant::now() + delay)
+                                }
+                                Err(_) => None,
+                            };
+                            continue;
+                        }
+                    }
+                }
+                None => rx.recv().await,
+            };
+
+            match received {
+                Some((connection, result)) => {
+                    pending_executions -= 1;
+                    if result.is_ok() || pending_executions == 0 {
+                        break Ok((connection, result));
+                    }
+                }
+                None => {
+                    break Err(Error::General(
+                        "Speculative executions finished without a response".to_string(),
+                    ))
+                }
+            }
+        };
+
+        // responses of other executions are not needed anymore
+        for execution in executions {
+            execution.abort();
+        }
+
+        winner
+    }
+
+    pub async fn query_with_params_tw<Q: ToString>(
+        &self,
+        query: Q,
+        query_params: QueryParams,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<Frame> {
+        let routing = RoutingInfo {
+            consistency: Some(query_params.consistency),
+            ..Default::default()
+        };
+        let query = query.to_string();
+        let is_idempotent = query_params.is_idempotent;
+        let timeout = query_params.timeout;
+        self.send_retried_frame(
+            &routing,
+            is_idempotent,
+            timeout,
+            |consistency, protocol_version| {
+                let query = Query {
+                    query: query.clone(),
+                    params: QueryParams {
+                        consistency,
+                        ..query_params.clone()
+                    },
+                };
+                Frame::new_query(
+                    query,
+                    prepare_flags(with_tracing, with_warnings),
+                    protocol_version,
+                )
+            },
+        )
+        .await
+    }
+
+    /// Executes a query with default parameters.
+    pub async fn query<Q: ToString>(&self, query: Q) -> error::Result<Frame> {
+        self.query_tw(query, false, false).await
+    }
+
+    /// Executes a query with ability to trace it and see warnings, and default parameters.
+    pub async fn query_tw<Q: ToString>(
+        &self,
+        query: Q,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<Frame> {
+        let query_params = QueryParamsBuilder::new().finalize();
+        self.query_with_params_tw(query, query_params, with_tracing, with_warnings)
+            .await
+    }
+
+    /// Executes a query with bounded values (either with or without names).
+    pub async fn query_with_values<Q: ToString, V: Into<QueryValues>>(
+        &self,
+        query: Q,
+        values: V,
+    ) -> error::Result<Frame> {
+        self.query_with_values_tw(query, values, false, false).await
+    }
+
+    /// Executes a query with bounded values (either with or without names)
+    /// and ability to see warnings, trace a request and default parameters.
+    pub async fn query_with_values_tw<Q: ToString, V: Into<QueryValues>>(
+        &self,
+        query: Q,
+        values: V,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<Frame> {
+        let query_params = QueryParamsBuilder::new().values(values.into()).finalize();
+        self.query_with_params_tw(query, query_params, with_tracing, with_warnings)
+            .await
+    }
+
+    /// Executes a query with query params without warnings and tracing.
+    pub async fn query_with_params<Q: ToString>(
+        &self,
+        query: Q,
+        query_params: QueryParams,
+    ) -> error::Result<Frame> {
+        self.query_with_params_tw(query, query_params, false, false)
+            .await
+    }
+
+    /// Executes a query with query params and ability to trace it and see warnings.
+    /// Unlike `query_with_params_tw` it returns a decoded response together with
+    /// a tracing id, warnings and a custom payload sent by a server.
+    pub async fn query_response_tw<Q: ToString>(
+        &self,
+        query: Q,
+        query_params: QueryParams,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<QueryResponse> {
+        self.query_with_params_tw(query, query_params, with_tracing, with_warnings)
+            .await
+            .and_then(QueryResponse::try_from)
+    }
+
+    /// Executes a query with query params without warnings and tracing
+    /// and returns a decoded response.
+    pub async fn query_response<Q: ToString>(
+        &self,
+        query: Q,
+        query_params: QueryParams,
+    ) -> error::Result<QueryResponse> {
+        self.query_response_tw(query, query_params, false, false)
+            .await
+    }
+
+    /// It prepares a query for execution, along with query itself the
+    /// method takes `with_tracing` and `with_warnings` flags to get
+    /// tracing information and warnings. Return the raw prepared
+    /// query result.
+    pub async fn prepare_raw_tw<Q: ToString>(
+        &self,
+        query: Q,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<BodyResResultPrepared> {
+        let flags = prepare_flags(with_tracing, with_warnings);
+        let response = self
+            .send_frame(|protocol_version| {
+                Frame::new_req_prepare(query.to_string(), flags, protocol_version)
+            })
+            .await?;
+
+        response
+            .get_body()?
+            .into_prepared()
+            .ok_or_else(|| Error::InvalidFrame("Expected prepared result in response".to_string()))
+    }
+
+    /// It prepares query without additional tracing information and warnings.
+    /// Return the raw prepared query result.
+    pub async fn prepare_raw<Q: ToString>(&self, query: Q) -> error::Result<BodyResResultPrepared> {
+        self.prepare_raw_tw(query, false, false).await
+    }
+
+    /// It prepares a query for execution, along with query itself
+    /// the method takes `with_tracing` and `with_warnings` flags
+    /// to get tracing information and warnings. Return the prepared
+    /// query ID.
+    pub async fn prepare_tw<Q: ToString>(
+        &self,
+        query: Q,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<PreparedQuery> {
+        let query = query.to_string();
+        let prepared = self
+            .prepare_raw_tw(query.clone(), with_tracing, with_warnings)
+            .await?;
+
+        Ok(PreparedQuery::from_prepared(prepared, query))
+    }
+
+    /// It prepares query without additional tracing information and warnings.
+    /// Return the prepared query ID.
+    pub async fn prepare<Q: ToString>(&self, query: Q) -> error::Result<PreparedQuery> {
+        self.prepare_tw(query, false, false).await
+    }
+
+    pub async fn exec_with_params_tw(
+        &self,
+        prepared: &PreparedQuery,
+        query_parameters: QueryParams,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<Frame> {
+        let routing = RoutingInfo {
+            routing_key: query_parameters
+                .values
+                .as_ref()
+                .and_then(|values| prepared.routing_key(values)),
+            consistency: Some(query_parameters.consistency),
+        };
+        let build_frame = |consistency, protocol_version| {
+            let query_parameters = QueryParams {
+                consistency,
+                ..query_parameters.clone()
+            };
+            let flags = prepare_flags(with_tracing, with_warnings);
+            Frame::new_req_execute_with_result_metadata_id(
+                &prepared.get_id(),
+                prepared.get_result_metadata_id().as_ref(),
+                &query_parameters,
+                flags,
+                protocol_version,
+            )
+        };
+        let is_idempotent = prepared.is_idempotent() || query_parameters.is_idempotent;
+        let timeout = query_parameters.timeout;
+        let result = self
+            .send_retried_frame(&routing, is_idempotent, timeout, build_frame)
+            .await;
+
+        match result {
+            // if query is unprepared
+            Err(ref error) if error.kind() == Some(ErrorKind::Unprepared) => {
+                let new = self.prepare_raw(&prepared.query).await?;
+                prepared.set_id(new.id);
+                prepared.set_result_metadata_id(new.result_metadata_id);
+                self.send_retried_frame(&routing, is_idempotent, timeout, build_frame)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    pub async fn exec_with_params(
+        &self,
+        prepared: &PreparedQuery,
+        query_parameters: QueryParams,
+    ) -> error::Result<Frame> {
+        self.exec_with_params_tw(prepared, query_parameters, false, false)
+            .await
+    }
+
+    pub async fn exec_with_values_tw<V: Into<QueryValues>>(
+        &self,
+        prepared: &PreparedQuery,
+        values: V,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<Frame> {
+        let query_params = QueryParamsBuilder::new().values(values.into()).finalize();
+        self.exec_with_params_tw(prepared, query_params, with_tracing, with_warnings)
+            .await
+    }
+
+    pub async fn exec_with_values<V: Into<QueryValues>>(
+        &self,
+        prepared: &PreparedQuery,
+        values: V,
+    ) -> error::Result<Frame> {
+        self.exec_with_values_tw(prepared, values, false, false)
+            .await
+    }
+
+    pub async fn exec_tw(
+        &self,
+        prepared: &PreparedQuery,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<Frame> {
+        let query_params = QueryParamsBuilder::new().finalize();
+        self.exec_with_params_tw(prepared, query_params, with_tracing, with_warnings)
+            .await
+    }
+
+    pub async fn exec(&self, prepared: &PreparedQuery) -> error::Result<Frame> {
+        self.exec_tw(prepared, false, false).await
+    }
+
+    /// Executes a prepared query with query params and ability to trace it and see
+    /// warnings. Unlike `exec_with_params_tw` it returns a decoded response together
+    /// with a tracing id, warnings and a custom payload sent by a server.
+    pub async fn exec_response_tw(
+        &self,
+        prepared: &PreparedQuery,
+        query_parameters: QueryParams,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<QueryResponse> {
+        self.exec_with_params_tw(prepared, query_parameters, with_tracing, with_warnings)
+            .await
+            .and_then(QueryResponse::try_from)
+    }
+
+    /// Executes a prepared query with query params without warnings and tracing
+    /// and returns a decoded response.
+    pub async fn exec_response(
+        &self,
+        prepared: &PreparedQuery,
+        query_parameters: QueryParams,
+    ) -> error::Result<QueryResponse> {
+        self.exec_response_tw(prepared, query_parameters, false, false)
+            .await
+    }
+
+    pub async fn batch_with_params_tw(
+        &self,
+        batch: QueryBatch,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<Frame> {
+        let routing = RoutingInfo {
+            consistency: Some(batch.consistency),
+            ..Default::default()
+        };
+        let is_idempotent = batch.is_idempotent;
+        self.send_retried_frame(
+            &routing,
+            is_idempotent,
+            None,
+            |consistency, protocol_version| {
+                let batch = QueryBatch {
+                    consistency,
+                    ..batch.clone()
+                };
+                Frame::new_req_batch(
+                    batch,
+                    prepare_flags(with_tracing, with_warnings),
+                    protocol_version,
+                )
+            },
+        )
+        .await
+    }
+
+    pub async fn batch_with_params(&self, batch: QueryBatch) -> error::Result<Frame> {
+        self.batch_with_params_tw(batch, false, false).await
+    }
+
+    /// Executes a batch with ability to trace it and see warnings. Unlike
+    /// `batch_with_params_tw` it returns a decoded response together with
+    /// a tracing id, warnings and a custom payload sent by a server.
+    pub async fn batch_response_tw(
+        &self,
+        batch: QueryBatch,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<QueryResponse> {
+        self.batch_with_params_tw(batch, with_tracing, with_warnings)
+            .await
+            .and_then(QueryResponse::try_from)
+    }
+
+    /// Executes a batch without warnings and tracing and returns a decoded response.
+    pub async fn batch_response(&self, batch: QueryBatch) -> error::Result<QueryResponse> {
+        self.batch_response_tw(batch, false, false).await
+    }
+}
+
+async fn connect<'a, A, LB>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    load_balancing: LB,
+    compression: Compression,
+) -> error::Result<AsyncSession<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<Arc<AsyncConnection>> + Sized,
+{
+    let addrs: Vec<String> = node_configs
+        .0
+        .iter()
+        .map(|node_config| node_config.addr.to_string())
+        .collect();
+    let authenticators: HashMap<String, A> = node_configs
+        .0
+        .iter()
+        .map(|node_config| {
+            (
+                node_config.addr.to_string(),
+                node_config.authenticator.clone(),
+            )
+        })
+        .collect();
+    let default_authenticator = node_configs
+        .0
+        .first()
+        .map(|node_config| node_config.authenticator.clone())
+        .ok_or_else(|| Error::from("Cluster config does not contain any nodes"))?;
+
+    // nodes which are connected to again are identified by IP addresses, so they
+    // are authenticated like the first node if their configs cannot be found
+    let connect_node = move |addr: String| {
+        let authenticator = authenticators
+            .get(&addr)
+            .unwrap_or(&default_authenticator)
+            .clone();
+        let connect: Pin<Box<dyn Future<Output = _> + Send>> =
+            Box::pin(
+                async move { AsyncConnection::connect(&addr, &authenticator, compression).await },
+            );
+        connect
+    };
+
+    let session = connect_nodes(addrs, Arc::new(connect_node), load_balancing, compression).await?;
+    if let Err(err) = session.refresh_metadata().await {
+        warn!("Unable to fetch cluster metadata: {}", err);
+    }
+
+    Ok(session)
+}
+
+/// Connects to nodes with given addresses. Nodes which cannot be connected to are
+/// tried again later, an error is returned only if none of them is reachable.
+async fn connect_nodes<LB>(
+    addrs: Vec<String>,
+    connect: Arc<ConnectNode>,
+    mut load_balancing: LB,
+    compression: Compression,
+) -> error::Result<AsyncSession<LB>>
+where
+    LB: LoadBalancingStrategy<Arc<AsyncConnection>> + Sized,
+{
+    let node_connector = NodeConnector::new(connect);
+    let mut nodes: Vec<Arc<AsyncConnection>> = Vec::with_capacity(addrs.len());
+    let mut last_error = None;
+
+    for addr in addrs {
+        match (node_connector.connect)(addr.clone()).await {
+            Ok(connection) => nodes.push(Arc::new(connection)),
+            Err(err) => {
+                node_connector.failed(addr, &err);
+                last_error = Some(err);
+            }
+        }
+    }
+
+    if nodes.is_empty() {
+        return Err(last_error.unwrap_or_else(|| Error::from("No nodes to connect to")));
+    }
+
+    load_balancing.init(nodes);
+
+    Ok(AsyncSession {
+        load_balancing: Mutex::new(load_balancing),
+        node_connector: Some(node_connector),
+        retry_policy: Box::new(DefaultRetryPolicy::new()),
+        speculative_execution_policy: None,
+        request_timeout: None,
+        custom_type_registry: CustomTypeRegistry::new(),
+        compression,
+    })
+}
+
+/// Creates new asynchronous session that will perform queries without any compression.
+/// Only node addresses and authenticators are taken from the cluster config as
+/// every node is served by a single multiplexed connection.
+/// As a parameter it takes:
+/// * cluster config
+/// * load balancing strategy (cannot be changed during `AsyncSession` life time).
+pub async fn new<'a, A, LB>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    load_balancing: LB,
+) -> error::Result<AsyncSession<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<Arc<AsyncConnection>> + Sized,
+{
+    connect(node_configs, load_balancing, Compression::None).await
+}
+
+/// Creates new asynchronous session that will perform queries with Snappy compression.
+/// As a parameter it takes:
+/// * cluster config
+/// * load balancing strategy (cannot be changed during `AsyncSession` life time).
+pub async fn new_snappy<'a, A, LB>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    load_balancing: LB,
+) -> error::Result<AsyncSession<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<Arc<AsyncConnection>> + Sized,
+{
+    connect(node_configs, load_balancing, Compression::Snappy).await
+}
+
+/// Creates new asynchronous session that will perform queries with LZ4 compression.
+/// As a parameter it takes:
+/// * cluster config
+/// * load balancing strategy (cannot be changed during `AsyncSession` life time).
+pub async fn new_lz4<'a, A, LB>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    load_balancing: LB,
+) -> error::Result<AsyncSession<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<Arc<AsyncConnection>> + Sized,
+{
+    connect(node_configs, load_balancing, Compression::Lz4).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authenticators::NoneAuthenticator;
+    use crate::frame::Opcode;
+    use crate::load_balancing::RoundRobinSync;
+    use crate::query::QueryParamsBuilder;
+    use crate::speculative_execution::ConstantSpeculativeExecutionPolicy;
+    use crate::test::TestServer;
+    use tokio::net::TcpListener;
+    use tokio::sync::oneshot;
+    use tokio::task::JoinHandle;
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    fn empty_session() -> AsyncSession<RoundRobinSync<Arc<AsyncConnection>>> {
+        AsyncSession {
+            load_balancing: Mutex::new(RoundRobinSync::new()),
+            node_connector: None,
+            retry_policy: Box::new(DefaultRetryPolicy::new()),
+            speculative_execution_policy: None,
+            request_timeout: None,
+            custom_type_registry: CustomTypeRegistry::new(),
+            compression: Compression::None,
+        }
+    }
+
+    #[test]
+    fn futures_are_send() {
+        let session = empty_session();
+        let prepared = PreparedQuery::new(crate::types::CBytesShort::new(vec![1]), "".into());
+
+        assert_send(&session.query("SELECT * FROM system.local"));
+        assert_send(&session.prepare("SELECT * FROM system.local"));
+        assert_send(&session.exec(&prepared));
+    }
+
+    /// Connects to nodes over TCP, first `failures` attempts to connect to a node
+    /// with a given address fail.
+    fn connector(failures: Vec<(String, usize)>) -> Arc<ConnectNode> {
+        let failures = Mutex::new(failures.into_iter().collect::<HashMap<_, _>>());
+        Arc::new(move |addr: String| {
+            let fail = match failures.lock().unwrap().get_mut(&addr) {
+                Some(failures) if *failures > 0 => {
+                    *failures -= 1;
+                    true
+                }
+                _ => false,
+            };
+            Box::pin(async move {
+                if fail {
+                    Err(Error::from("Connection refused"))
+                } else {
+                    AsyncConnection::connect(&addr, &NoneAuthenticator, Compression::None).await
+                }
+            })
+        })
+    }
+
+    async fn session_of(
+        addrs: Vec<String>,
+        connector: Arc<ConnectNode>,
+    ) -> error::Result<AsyncSession<RoundRobinSync<Arc<AsyncConnection>>>> {
+        connect_nodes(addrs, connector, RoundRobinSync::new(), Compression::None).await
+    }
+
+    async fn listen() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        (listener, addr)
+    }
+
+    /// Returns an address nobody listens on.
+    fn unused_addr() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    async fn accept_node(listener: &TcpListener) -> TestServer {
+        let mut server = TestServer::accept(listener).await;
+        let (stream, _) = server.read_request().await;
+        server.respond(stream, Opcode::Ready, vec![]).await;
+        server
+    }
+
+    async fn serve_query(server: &mut TestServer) {
+        let (stream, opcode) = server.read_request().await;
+        assert_eq!(opcode, Opcode::Query);
+        // void result
+        server
+            .respond(stream, Opcode::Result, vec![0, 0, 0, 1])
+            .await;
+    }
+
+    async fn wait_until_closed(connection: &AsyncConnection) {
+        while !connection.is_closed() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn is_unreachable<LB>(session: &AsyncSession<LB>, addr: &str) -> bool {
+        let node_connector = session.node_connector.as_ref().unwrap();
+        node_connector
+            .unreachable
+            .lock()
+            .unwrap()
+            .contains_key(addr)
+    }
+
+    #[tokio::test]
+    async fn skips_unreachable_nodes_at_connect() {
+        let (listener, addr) = listen().await;
+        let unreachable_addr = unused_addr();
+        let server = tokio::spawn(async move {
+            let mut server = accept_node(&listener).await;
+            serve_query(&mut server).await;
+            server
+        });
+
+        let session = session_of(vec![unreachable_addr.clone(), addr], connector(vec![]))
+            .await
+            .unwrap();
+        assert!(is_unreachable(&session, &unreachable_addr));
+        session.query("SELECT * FROM ks.t").await.unwrap();
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn fails_if_no_node_is_reachable() {
+        let result = session_of(vec![unused_addr(), unused_addr()], connector(vec![])).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn reconnects_closed_connections() {
+        let (listener, addr) = listen().await;
+        let server = tokio::spawn(async move {
+            // a node restarts
+            drop(accept_node(&listener).await);
+            let mut server = accept_node(&listener).await;
+            serve_query(&mut server).await;
+            server
+        });
+
+        let session = session_of(vec![addr], connector(vec![])).await.unwrap();
+        wait_until_closed(&session.get_connection().unwrap()).await;
+
+        session.query("SELECT * FROM ks.t").await.unwrap();
+        assert!(!session.get_connection().unwrap().is_closed());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_nodes_which_cannot_be_reconnected() {
+        let (listener, addr) = listen().await;
+        let (down_listener, down_addr) = listen().await;
+        let server = tokio::spawn(async move {
+            let mut server = accept_node(&listener).await;
+            serve_query(&mut server).await;
+            serve_query(&mut server).await;
+            server
+        });
+        let down_server = tokio::spawn(async move {
+            // a node goes down and does not accept connections anymore
+            drop(accept_node(&down_listener).await);
+        });
+
+        let session = session_of(vec![addr, down_addr.clone()], connector(vec![]))
+            .await
+            .unwrap();
+        down_server.await.unwrap();
+        for _ in 0..2 {
+            let connection = session.get_connection().unwrap();
+            if connection.get_addr().to_string() == down_addr {
+                wait_until_closed(&connection).await;
+            }
+        }
+
+        // every node is picked by round robin
+        session.query("SELECT * FROM ks.t").await.unwrap();
+        session.query("SELECT * FROM ks.t").await.unwrap();
+        assert!(is_unreachable(&session, &down_addr));
+        server.await.unwrap();
+    }
+
+    /// Returns a session which nodes are picked in the order: a node served
+    /// by `first_server` and then a node served by `second_server`.
+    async fn two_node_session<F, S>(
+        first_server: F,
+        second_server: S,
+    ) -> AsyncSession<RoundRobinSync<Arc<AsyncConnection>>>
+    where
+        F: FnOnce(TcpListener) -> JoinHandle<()>,
+        S: FnOnce(TcpListener) -> JoinHandle<()>,
+    {
+        let (first_listener, first_addr) = listen().await;
+        let (second_listener, second_addr) = listen().await;
+        first_server(first_listener);
+        second_server(second_listener);
+
+        session_of(vec![second_addr, first_addr], connector(vec![]))
+            .await
+            .unwrap()
+    }
+
+    /// Returns a session which nodes are picked in the order: a node served
+    /// by `slow_server` and then a node served by `fast_server`.
+    async fn speculative_session<S, F>(
+        slow_server: S,
+        fast_server: F,
+    ) -> AsyncSession<RoundRobinSync<Arc<AsyncConnection>>>
+    where
+        S: FnOnce(TcpListener) -> JoinHandle<()>,
+        F: FnOnce(TcpListener) -> JoinHandle<()>,
+    {
+        two_node_session(slow_server, fast_server)
+            .await
+            .with_speculative_execution_policy(ConstantSpeculativeExecutionPolicy::new(
+                1,
+                Duration::from_millis(10),
+            ))
+    }
+
+    fn query_params(idempotent: bool) -> QueryParams {
+        QueryParamsBuilder::new().idempotent(idempotent).finalize()
+    }
+
+    #[tokio::test]
+    async fn speculative_execution_cancels_losing_executions() {
+        let (request_received, slow_requested) = oneshot::channel();
+        let session = speculative_session(
+            |listener| {
+                tokio::spawn(async move {
+                    let mut server = accept_node(&listener).await;
+                    server.read_request().await;
+                    let _ = request_received.send(());
+                    // the node never responds
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                })
+            },
+            |listener| {
+                tokio::spawn(async move {
+                    let mut server = accept_node(&listener).await;
+                    serve_query(&mut server).await;
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                })
+            },
+        )
+        .await;
+        let slow_connection = session.get_connection().unwrap();
+        session.get_connection().unwrap();
+
+        session
+            .query_with_params("SELECT * FROM ks.t", query_params(true))
+            .await
+            .unwrap();
+        slow_requested.await.unwrap();
+
+        // an execution which waits for the slow node does not hold its connection anymore
+        let cancelled = async {
+            while Arc::strong_count(&slow_connection) > 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(1), cancelled)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn non_idempotent_requests_are_not_executed_speculatively() {
+        let (fast_idle, fast_requested) = oneshot::channel();
+        let session = speculative_session(
+            |listener| {
+                tokio::spawn(async move {
+                    let mut server = accept_node(&listener).await;
+                    let (stream, _) = server.read_request().await;
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    server
+                        .respond(stream, Opcode::Result, vec![0, 0, 0, 1])
+                        .await;
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                })
+            },
+            |listener| {
+                tokio::spawn(async move {
+                    let mut server = accept_node(&listener).await;
+                    let request =
+                        tokio::time::timeout(Duration::from_millis(200), server.read_request())
+                            .await;
+                    let _ = fast_idle.send(request.is_err());
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                })
+            },
+        )
+        .await;
+
+        session
+            .query_with_params("INSERT INTO ks.t (id) VALUES (now())", query_params(false))
+            .await
+            .unwrap();
+        assert!(fast_requested.await.unwrap());
+    }
+
+    /// Serves a node which responds to the first request that it is overloaded
+    /// and tells whether a second request was received.
+    fn overloaded_node(
+        listener: TcpListener,
+        second_requested: oneshot::Sender<bool>,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut server = accept_node(&listener).await;
+            let (stream, _) = server.read_request().await;
+            server
+                .respond(stream, Opcode::Error, vec![0, 0, 0x10, 0x01, 0, 0])
+                .await;
+            let request =
+                tokio::time::timeout(Duration::from_millis(200), server.read_request()).await;
+            let _ = second_requested.send(request.is_ok());
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        })
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_requests_with_next_node() {
+        let (first_idle, first_requested) = oneshot::channel();
+        let session = two_node_session(
+            |listener| overloaded_node(listener, first_idle),
+            |listener| {
+                tokio::spawn(async move {
+                    let mut server = accept_node(&listener).await;
+                    serve_query(&mut server).await;
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                })
+            },
+        )
+        .await;
+
+        session
+            .query_with_params("SELECT * FROM ks.t", query_params(true))
+            .await
+            .unwrap();
+        // the request is not retried with the overloaded node
+        assert!(!first_requested.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_non_idempotent_requests_with_next_node() {
+        let (first_idle, _) = oneshot::channel();
+        let (second_idle, second_requested) = oneshot::channel();
+        let session = two_node_session(
+            |listener| overloaded_node(listener, first_idle),
+            |listener| {
+                tokio::spawn(async move {
+                    let mut server = accept_node(&listener).await;
+                    let request =
+                        tokio::time::timeout(Duration::from_millis(200), server.read_request())
+                            .await;
+                    let _ = second_idle.send(request.is_ok());
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                })
+            },
+        )
+        .await;
+
+        let error = session
+            .query_with_params("INSERT INTO ks.t (id) VALUES (now())", query_params(false))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), Some(ErrorKind::Overloaded));
+        assert!(!second_requested.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn connects_to_unreachable_nodes_in_background() {
+        let (listener, addr) = listen().await;
+        let (late_listener, late_addr) = listen().await;
+        let server = tokio::spawn(async move { accept_node(&listener).await });
+        let late_server = tokio::spawn(async move { accept_node(&late_listener).await });
+
+        let session = session_of(
+            vec![addr, late_addr.clone()],
+            connector(vec![(late_addr.clone(), 1)]),
+        )
+        .await
+        .unwrap();
+        assert!(is_unreachable(&session, &late_addr));
+
+        // the node is due to be tried again
+        let node_connector = session.node_connector.as_ref().unwrap();
+        node_connector
+            .unreachable
+            .lock()
+            .unwrap()
+            .insert(late_addr.clone(), Instant::now());
+
+        let mut connected = false;
+        while !connected {
+            session
+                .open_connection_for(&RoutingInfo::default())
+                .await
+                .unwrap();
+            connected = (0..2).any(|_| {
+                let connection = session.get_connection().unwrap();
+                connection.get_addr().to_string() == late_addr
+            });
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!is_unreachable(&session, &late_addr));
+        late_server.await.unwrap();
+        server.await.unwrap();
+    }
+}
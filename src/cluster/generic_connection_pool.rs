@@ -3,6 +3,8 @@ use std::sync::Arc;
 
 use r2d2;
 
+use crate::load_balancing::GetAddr;
+
 /// Generic pool connection that is able to return an
 /// `r2r2::Pool` as well as an IP address of a node.
 pub struct ConnectionPool<M: r2d2::ManageConnection> {
@@ -28,3 +30,18 @@ impl<M: r2d2::ManageConnection> ConnectionPool<M> {
     self.addr
   }
 }
+
+impl<M: r2d2::ManageConnection> Clone for ConnectionPool<M> {
+  fn clone(&self) -> Self {
+    ConnectionPool {
+      pool: self.pool.clone(),
+      addr: self.addr,
+    }
+  }
+}
+
+impl<M: r2d2::ManageConnection> GetAddr for ConnectionPool<M> {
+  fn get_addr(&self) -> SocketAddr {
+    self.addr
+  }
+}
@@ -0,0 +1,303 @@
+use std::cell::RefCell;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::compression::Compression;
+use crate::error;
+use crate::frame::{Frame, ProtocolVersion};
+use crate::query::utils::send_frame_over;
+use crate::query::{Query, QueryParamsBuilder};
+use crate::transport::CDRSTransport;
+use crate::types::list::List;
+use crate::types::rows::Row;
+use crate::types::{AsRustType, ByName};
+
+/// Query which returns information about a node that executes it.
+pub const SELECT_LOCAL: &str =
+    "SELECT data_center, rack, tokens, partitioner FROM system.local WHERE key = 'local'";
+/// Query which returns information about other nodes of a cluster.
+pub const SELECT_PEERS: &str =
+    "SELECT peer, rpc_address, data_center, rack, tokens FROM system.peers";
+/// Query which returns information about other nodes of a cluster including their
+/// native transport ports. `system.peers_v2` is available since Cassandra 4.0.
+pub const SELECT_PEERS_V2: &str =
+    "SELECT peer, native_address, native_port, data_center, rack, tokens FROM system.peers_v2";
+
+/// System table which information about peers was selected from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeersTable {
+    /// `system.peers` selected with `SELECT_PEERS`.
+    Peers,
+    /// `system.peers_v2` selected with `SELECT_PEERS_V2`.
+    PeersV2,
+}
+
+/// Information about a single node of a cluster.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeMetadata {
+    /// Address which clients use to connect to the node.
+    pub addr: IpAddr,
+    /// Native transport port of the node if it is known.
+    pub port: Option<u16>,
+    pub datacenter: Option<String>,
+    pub rack: Option<String>,
+    /// Tokens owned by the node as they are stored in system tables.
+    pub tokens: Vec<String>,
+}
+
+/// Cluster topology as it is seen by one of its nodes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClusterMetadata {
+    /// Full class name of a partitioner used by the cluster.
+    pub partitioner: Option<String>,
+    pub nodes: Vec<NodeMetadata>,
+}
+
+impl ClusterMetadata {
+    /// Fetches cluster metadata over given connection. `local_addr` is an address
+    /// of a node the connection is established with.
+    pub fn fetch<T: CDRSTransport + 'static>(
+        transport: &RefCell<T>,
+        local_addr: SocketAddr,
+        compression: Compression,
+    ) -> error::Result<ClusterMetadata> {
+        let protocol_version = transport.borrow().protocol_version();
+        let send_query = |query| {
+            send_frame_over(
+                transport,
+                query_frame(query, protocol_version),
+                compression,
+                None,
+            )
+        };
+
+        let local = send_query(SELECT_LOCAL)?;
+        let (peers_table, peers) = match send_query(SELECT_PEERS_V2) {
+            // system.peers_v2 does not exist prior Cassandra 4.0
+            Err(ref err) if err.server_error().is_some() => {
+                (PeersTable::Peers, send_query(SELECT_PEERS)?)
+            }
+            result => (PeersTable::PeersV2, result?),
+        };
+
+        ClusterMetadata::from_rows(
+            local_addr,
+            &into_rows(local)?,
+            peers_table,
+            &into_rows(peers)?,
+        )
+    }
+
+    /// Builds cluster metadata from rows of `SELECT_LOCAL` and either `SELECT_PEERS`
+    /// or `SELECT_PEERS_V2` queries. Both queries should be executed on the same node,
+    /// `local_addr` is an address of that node.
+    pub fn from_rows(
+        local_addr: SocketAddr,
+        local: &[Row],
+        peers_table: PeersTable,
+        peers: &[Row],
+    ) -> error::Result<ClusterMetadata> {
+        let mut metadata = ClusterMetadata::default();
+
+        if let Some(row) = local.first() {
+            metadata.partitioner = row.by_name("partitioner")?;
+            metadata.nodes.push(node_metadata(
+                local_addr.ip(),
+                Some(local_addr.port()),
+                row,
+            )?);
+        }
+
+        for row in peers {
+            let (address, port) = match peers_table {
+                PeersTable::Peers => (row.by_name("rpc_address")?, None),
+                PeersTable::PeersV2 => {
+                    let port: Option<i32> = row.by_name("native_port")?;
+                    (row.by_name("native_address")?, port.map(|port| port as u16))
+                }
+            };
+            let addr = match address {
+                Some(addr) if !IpAddr::is_unspecified(&addr) => addr,
+                _ => row.r_by_name("peer")?,
+            };
+            metadata.nodes.push(node_metadata(addr, port, row)?);
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Returns a request frame of a query which selects cluster metadata.
+pub(crate) fn query_frame(query: &str, protocol_version: ProtocolVersion) -> Frame {
+    let query = Query {
+        query: query.to_string(),
+        params: QueryParamsBuilder::new().finalize(),
+    };
+    Frame::new_query(query, vec![], protocol_version)
+}
+
+/// Extracts rows from a response to a metadata query.
+pub(crate) fn into_rows(response: Frame) -> error::Result<Vec<Row>> {
+    response
+        .get_body()?
+        .into_rows()
+        .ok_or_else(|| error::Error::from("Metadata query did not return rows"))
+}
+
+fn node_metadata(addr: IpAddr, port: Option<u16>, row: &Row) -> error::Result<NodeMetadata> {
+    let tokens: Option<List> = row.by_name("tokens")?;
+    let tokens = match tokens {
+        Some(tokens) => tokens.as_rust_type()?.unwrap_or_default(),
+        None => vec![],
+    };
+
+    Ok(NodeMetadata {
+        addr,
+        port,
+        datacenter: row.by_name("data_center")?,
+        rack: row.by_name("rack")?,
+        tokens,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::ColType;
+    use crate::test::rows;
+
+    fn tokens(tokens: &[&str]) -> Vec<u8> {
+        let mut bytes = (tokens.len() as i32).to_be_bytes().to_vec();
+        for token in tokens {
+            bytes.extend_from_slice(&(token.len() as i32).to_be_bytes());
+            bytes.extend_from_slice(token.as_bytes());
+        }
+        bytes
+    }
+
+    fn local() -> Vec<Row> {
+        rows(
+            &[
+                ("data_center", ColType::Varchar),
+                ("rack", ColType::Varchar),
+                ("tokens", ColType::Set),
+                ("partitioner", ColType::Varchar),
+            ],
+            vec![vec![
+                Some(b"dc1".to_vec()),
+                Some(b"rack1".to_vec()),
+                Some(tokens(&["-100", "100"])),
+                Some(b"org.apache.cassandra.dht.Murmur3Partitioner".to_vec()),
+            ]],
+        )
+    }
+
+    #[test]
+    fn metadata_from_peers() {
+        let peers = rows(
+            &[
+                ("peer", ColType::Inet),
+                ("rpc_address", ColType::Inet),
+                ("data_center", ColType::Varchar),
+                ("rack", ColType::Varchar),
+                ("tokens", ColType::Set),
+            ],
+            vec![
+                vec![
+                    Some(vec![10, 0, 0, 2]),
+                    Some(vec![127, 0, 0, 2]),
+                    Some(b"dc2".to_vec()),
+                    None,
+                    Some(tokens(&["0"])),
+                ],
+                vec![
+                    Some(vec![10, 0, 0, 3]),
+                    Some(vec![0, 0, 0, 0]),
+                    Some(b"dc2".to_vec()),
+                    None,
+                    None,
+                ],
+            ],
+        );
+
+        let metadata = ClusterMetadata::from_rows(
+            "127.0.0.1:9042".parse().unwrap(),
+            &local(),
+            PeersTable::Peers,
+            &peers,
+        )
+        .unwrap();
+
+        assert_eq!(
+            metadata.partitioner,
+            Some("org.apache.cassandra.dht.Murmur3Partitioner".to_string())
+        );
+        assert_eq!(
+            metadata.nodes,
+            vec![
+                NodeMetadata {
+                    addr: "127.0.0.1".parse().unwrap(),
+                    port: Some(9042),
+                    datacenter: Some("dc1".to_string()),
+                    rack: Some("rack1".to_string()),
+                    tokens: vec!["-100".to_string(), "100".to_string()],
+                },
+                NodeMetadata {
+                    addr: "127.0.0.2".parse().unwrap(),
+                    port: None,
+                    datacenter: Some("dc2".to_string()),
+                    rack: None,
+                    tokens: vec!["0".to_string()],
+                },
+                // unspecified rpc address is replaced with peer's one
+                NodeMetadata {
+                    addr: "10.0.0.3".parse().unwrap(),
+                    port: None,
+                    datacenter: Some("dc2".to_string()),
+                    rack: None,
+                    tokens: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn metadata_from_peers_v2() {
+        let peers = rows(
+            &[
+                ("peer", ColType::Inet),
+                ("native_address", ColType::Inet),
+                ("native_port", ColType::Int),
+                ("data_center", ColType::Varchar),
+                ("rack", ColType::Varchar),
+                ("tokens", ColType::Set),
+            ],
+            vec![vec![
+                Some(vec![10, 0, 0, 2]),
+                Some(vec![127, 0, 0, 2]),
+                Some(9043i32.to_be_bytes().to_vec()),
+                Some(b"dc1".to_vec()),
+                Some(b"rack2".to_vec()),
+                Some(tokens(&["0"])),
+            ]],
+        );
+
+        let metadata = ClusterMetadata::from_rows(
+            "127.0.0.1:9042".parse().unwrap(),
+            &local(),
+            PeersTable::PeersV2,
+            &peers,
+        )
+        .unwrap();
+
+        assert_eq!(
+            metadata.nodes[1],
+            NodeMetadata {
+                addr: "127.0.0.2".parse().unwrap(),
+                port: Some(9043),
+                datacenter: Some("dc1".to_string()),
+                rack: Some("rack2".to_string()),
+                tokens: vec!["0".to_string()],
+            }
+        );
+    }
+}
@@ -1,26 +1,44 @@
 use r2d2;
 use std::cell;
+use std::time::Duration;
 
+#[cfg(feature = "async")]
+pub(crate) mod async_connection;
+#[cfg(feature = "async")]
+pub mod async_session;
 #[cfg(feature = "ssl")]
 mod config_ssl;
 #[cfg(feature = "rust-tls")]
 mod config_rustls;
 mod config_tcp;
 mod generic_connection_pool;
+pub mod metadata;
+mod multiplexed_connection;
 mod pager;
+mod query_trace;
 pub mod session;
 #[cfg(feature = "ssl")]
 mod ssl_connection_pool;
 #[cfg(feature = "rust-tls")]
 mod rustls_connection_pool;
+mod stream_ids;
 mod tcp_connection_pool;
 
+#[cfg(feature = "async")]
+pub use crate::cluster::async_connection::AsyncConnection;
 #[cfg(feature = "ssl")]
 pub use crate::cluster::config_ssl::{ClusterSslConfig, NodeSslConfig, NodeSslConfigBuilder};
 #[cfg(feature = "rust-tls")]
 pub use crate::cluster::config_rustls::{ClusterRustlsConfig, NodeRustlsConfig, NodeRustlsConfigBuilder};
 pub use crate::cluster::config_tcp::{ClusterTcpConfig, NodeTcpConfig, NodeTcpConfigBuilder};
+pub use crate::cluster::multiplexed_connection::{
+    MultiplexedConnection, MultiplexedConnectionsManager, MultiplexedTransport,
+};
 pub use crate::cluster::pager::{PagerState, QueryPager, SessionPager};
+pub use crate::cluster::query_trace::{
+    QueryTrace, TraceEvent, DEFAULT_TRACE_ATTEMPTS, DEFAULT_TRACE_INTERVAL, SELECT_TRACE_EVENTS,
+    SELECT_TRACE_SESSION,
+};
 #[cfg(feature = "ssl")]
 pub use crate::cluster::ssl_connection_pool::{
     new_ssl_pool, SslConnectionPool, SslConnectionsManager,
@@ -30,14 +48,19 @@ pub use crate::cluster::rustls_connection_pool::{
     new_rustls_pool, RustlsConnectionPool, RustlsConnectionsManager,
 };
 pub use crate::cluster::tcp_connection_pool::{
-    new_tcp_pool, startup, TcpConnectionPool, TcpConnectionsManager,
+    connect_and_startup, new_multiplexed_tcp_pool, new_tcp_pool, startup,
+    MultiplexedTcpConnectionPool, TcpConnectionPool, TcpConnectionsManager,
 };
 pub(crate) use generic_connection_pool::ConnectionPool;
 
 use crate::compression::Compression;
 use crate::error;
+use crate::load_balancing::RoutingInfo;
 use crate::query::{BatchExecutor, ExecExecutor, PrepareExecutor, QueryExecutor};
+use crate::retry::RetryPolicy;
+use crate::speculative_execution::SpeculativeExecutionPolicy;
 use crate::transport::CDRSTransport;
+use crate::types::custom::CustomTypeRegistry;
 
 /// `GetConnection` trait provides a unified interface for Session to get a connection
 /// from a load balancer
@@ -48,6 +71,12 @@ pub trait GetConnection<
 {
     /// Returns connection from a load balancer.
     fn get_connection(&self) -> Option<r2d2::PooledConnection<M>>;
+
+    /// Returns connection which is the most suitable for a request with given
+    /// routing information. By default routing information is ignored.
+    fn get_connection_for(&self, _routing: &RoutingInfo) -> Option<r2d2::PooledConnection<M>> {
+        self.get_connection()
+    }
 }
 
 /// `GetCompressor` trait provides a unified interface for Session to get a compressor
@@ -57,6 +86,34 @@ pub trait GetCompressor<'a> {
     fn get_compressor(&self) -> Compression;
 }
 
+/// `GetRetryPolicy` trait provides a unified interface for Session to get a policy
+/// which decides whether failed requests should be retried.
+pub trait GetRetryPolicy {
+    /// Returns actual retry policy.
+    fn get_retry_policy(&self) -> &dyn RetryPolicy;
+}
+
+/// `GetSpeculativeExecutionPolicy` trait provides a unified interface for Session to get
+/// a policy which decides when idempotent requests should be executed speculatively.
+pub trait GetSpeculativeExecutionPolicy {
+    /// Returns actual speculative execution policy if there is any.
+    fn get_speculative_execution_policy(&self) -> Option<&dyn SpeculativeExecutionPolicy>;
+}
+
+/// `GetRequestTimeout` trait provides a unified interface for Session to get a timeout
+/// of requests which do not specify their own one.
+pub trait GetRequestTimeout {
+    /// Returns actual request timeout if there is any.
+    fn get_request_timeout(&self) -> Option<Duration>;
+}
+
+/// `GetCustomTypeRegistry` trait provides a unified interface for Session to get
+/// codecs of custom types which are used to decode columns of such types.
+pub trait GetCustomTypeRegistry {
+    /// Returns actual registry of custom types.
+    fn get_custom_type_registry(&self) -> &CustomTypeRegistry;
+}
+
 /// `CDRSSession` trait wrap ups whole query functionality. Use it only if whole query
 /// machinery is needed and direct sub traits otherwise.
 pub trait CDRSSession<
@@ -66,6 +123,10 @@ pub trait CDRSSession<
 >:
     GetCompressor<'static>
     + GetConnection<T, M>
+    + GetRetryPolicy
+    + GetSpeculativeExecutionPolicy
+    + GetRequestTimeout
+    + GetCustomTypeRegistry
     + QueryExecutor<T, M>
     + PrepareExecutor<T, M>
     + ExecExecutor<T, M>
@@ -0,0 +1,934 @@
+use r2d2::ManageConnection;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, Cursor, Read, Write};
+use std::mem;
+use std::net::{self, SocketAddr};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::cluster::stream_ids::StreamIds;
+use crate::compression::Compression;
+use crate::error;
+use crate::frame::parser::{
+    parse_frame, parse_framed, parse_header, set_header_stream, HEADER_LEN,
+};
+use crate::frame::segment::{encode_segments, SegmentDecoder};
+use crate::frame::{Frame, ProtocolVersion};
+use crate::transport::CDRSTransport;
+
+/// Size of a chunk which is read from a transport at once.
+const READ_CHUNK_LEN: usize = 64 * 1024;
+
+/// State shared by all threads which use a connection.
+struct Dispatch {
+    stream_ids: StreamIds,
+    /// Responses by stream ids of requests which are in flight. `None`
+    /// means a response was not received yet.
+    responses: HashMap<u16, Option<error::Result<Vec<u8>>>>,
+    /// Stream ids of requests which nobody waits a response for anymore.
+    /// They are released once responses are received.
+    abandoned: HashSet<u16>,
+    /// Received bytes which do not form a complete frame yet.
+    buffer: Vec<u8>,
+    /// Frames received in protocol v5 segments.
+    segments: SegmentDecoder,
+    /// Whether some thread is reading from a transport at the moment.
+    reading: bool,
+    /// Contains a reason if a connection cannot be used anymore.
+    broken: Option<String>,
+}
+
+/// Blocking connection that allows many threads to have requests in flight
+/// simultaneously. Each request gets a free stream id and each received frame
+/// is routed to a thread that waits for a response with the same stream id.
+///
+/// There is no dedicated reader thread. Instead one of waiting threads reads
+/// frames from a transport and parks responses which belong to other threads.
+/// To let other threads write requests meanwhile, a reading thread holds
+/// a transport only while a read with `poll_interval` timeout lasts.
+///
+/// A transport should be already started up, e.g. via `cluster::startup`.
+/// Sessions use it through `MultiplexedConnectionsManager`.
+pub struct MultiplexedConnection<T: CDRSTransport> {
+    transport: Mutex<T>,
+    addr: SocketAddr,
+    compression: Compression,
+    protocol_version: ProtocolVersion,
+    poll_interval: Duration,
+    dispatch: Mutex<Dispatch>,
+    dispatched: Condvar,
+}
+
+impl<T: CDRSTransport> MultiplexedConnection<T> {
+    /// Default duration of a single read from a transport.
+    pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+    /// Creates new multiplexed connection on top of a ready to use transport.
+    /// `compression` should be the same as one negotiated during startup.
+    pub fn new(transport: T, compression: Compression) -> Self {
+        Self::with_poll_interval(transport, compression, Self::DEFAULT_POLL_INTERVAL)
+    }
+
+    /// Creates new multiplexed connection that holds a transport for reading not longer
+    /// than `poll_interval` at once. It is an error to pass the zero Duration.
+    pub fn with_poll_interval(
+        transport: T,
+        compression: Compression,
+        poll_interval: Duration,
+    ) -> Self {
+        MultiplexedConnection {
+            protocol_version: transport.protocol_version(),
+            addr: transport.addr(),
+            transport: Mutex::new(transport),
+            compression,
+            poll_interval,
+            dispatch: Mutex::new(Dispatch {
+                stream_ids: StreamIds::default(),
+                responses: HashMap::new(),
+                abandoned: HashSet::new(),
+                buffer: vec![],
+                segments: SegmentDecoder::new(compression),
+                reading: false,
+                broken: None,
+            }),
+            dispatched: Condvar::new(),
+        }
+    }
+
+    /// Returns a protocol version negotiated for a transport. Request frames sent
+    /// over the connection should be built for this version.
+    pub fn get_protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Returns an address of a node the connection is established with.
+    pub fn get_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Checks if connection cannot be used anymore, e.g. because of IO error.
+    pub fn is_broken(&self) -> bool {
+        self.lock_dispatch()
+            .map(|dispatch| dispatch.broken.is_some())
+            .unwrap_or(true)
+    }
+
+    /// Sends a request and waits for a response to it.
+    pub fn send(&self, frame: Frame) -> error::Result<Frame> {
+        let stream = self.write(frame)?;
+        self.read(stream)
+    }
+
+    /// Writes a request frame and returns a stream id assigned to it. The stream
+    /// id should be passed to `read` in order to get a response and release the id.
+    /// If all stream ids are in use it blocks until one gets released.
+    pub fn write(&self, frame: Frame) -> error::Result<u16> {
+        frame
+            .encode_with(self.frame_compression())
+            .and_then(|frame_bytes| self.write_frame(frame_bytes))
+            .map_err(|err| err.with_node(self.addr))
+    }
+
+    /// Waits for a response to a request with a given stream id.
+    pub fn read(&self, stream: u16) -> error::Result<Frame> {
+        self.read_response(stream, None)
+            .and_then(|frame_bytes| {
+                parse_frame(
+                    &RefCell::new(Cursor::new(frame_bytes)),
+                    &self.frame_compression(),
+                )
+            })
+            .map_err(|err| err.with_node(self.addr))
+    }
+
+    /// Compression of frames themselves. In protocol v5 segments are compressed instead.
+    fn frame_compression(&self) -> Compression {
+        if self.protocol_version.is_segmented() {
+            Compression::None
+        } else {
+            self.compression
+        }
+    }
+
+    /// Writes an encoded request frame which stream id is replaced with a free one.
+    fn write_frame(&self, mut frame_bytes: Vec<u8>) -> error::Result<u16> {
+        let stream = {
+            let mut dispatch = self.lock_dispatch()?;
+            loop {
+                if let Some(ref reason) = dispatch.broken {
+                    return Err(broken_error(reason));
+                }
+                if let Some(stream) = dispatch.stream_ids.acquire() {
+                    // a response may be received before this thread finishes writing
+                    dispatch.responses.insert(stream, None);
+                    break stream;
+                }
+                dispatch = self.wait(dispatch, None)?;
+            }
+        };
+
+        set_header_stream(&mut frame_bytes, stream);
+        let written = if self.protocol_version.is_segmented() {
+            encode_segments(frame_bytes.as_slice(), self.compression)
+        } else {
+            Ok(frame_bytes)
+        }
+        .and_then(|bytes| self.write_bytes(bytes.as_slice()));
+
+        let mut dispatch = self.lock_dispatch()?;
+        match written {
+            Ok(_) => Ok(stream),
+            Err(err) => {
+                dispatch.responses.remove(&stream);
+                dispatch.stream_ids.release(stream);
+                if let error::Error::Io(ref io_err) = err {
+                    dispatch.broken = Some(io_err.to_string());
+                }
+                self.dispatched.notify_all();
+                Err(err)
+            }
+        }
+    }
+
+    /// Waits for an encoded response frame until a deadline if there is any.
+    /// If the deadline passes the request is abandoned and `TimedOut` IO error
+    /// is returned.
+    fn read_response(&self, stream: u16, deadline: Option<Instant>) -> error::Result<Vec<u8>> {
+        let mut dispatch = self.lock_dispatch()?;
+
+        loop {
+            match dispatch.responses.get_mut(&stream) {
+                None => {
+                    return Err(error::Error::General(format!(
+                        "No request in flight with stream id {}",
+                        stream
+                    )));
+                }
+                Some(response) => {
+                    if let Some(response) = response.take() {
+                        dispatch.responses.remove(&stream);
+                        dispatch.stream_ids.release(stream);
+                        self.dispatched.notify_all();
+                        return response;
+                    }
+                }
+            }
+
+            if let Some(ref reason) = dispatch.broken {
+                return Err(broken_error(reason));
+            }
+
+            if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                abandon(&mut dispatch, stream);
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Response timed out").into());
+            }
+
+            if dispatch.reading {
+                dispatch = self.wait(dispatch, deadline)?;
+                continue;
+            }
+
+            dispatch.reading = true;
+            let mut buffer = mem::take(&mut dispatch.buffer);
+            drop(dispatch);
+
+            let received = self.read_chunk(&mut buffer);
+
+            dispatch = self.lock_dispatch()?;
+            dispatch.reading = false;
+            match received {
+                Ok(_) => dispatch_frames(&mut dispatch, &mut buffer, self.protocol_version),
+                Err(err) => dispatch.broken = Some(err.to_string()),
+            }
+            dispatch.buffer = buffer;
+            self.dispatched.notify_all();
+        }
+    }
+
+    /// Lets a connection drop a response to a request with a given stream id
+    /// and reuse the stream id afterwards.
+    fn abandon(&self, stream: u16) {
+        if let Ok(mut dispatch) = self.lock_dispatch() {
+            abandon(&mut dispatch, stream);
+            self.dispatched.notify_all();
+        }
+    }
+
+    fn write_bytes(&self, bytes: &[u8]) -> error::Result<()> {
+        let mut transport = self.lock_transport()?;
+        transport.set_timeout(None)?;
+        transport.write_all(bytes)?;
+        Ok(())
+    }
+
+    fn read_chunk(&self, buffer: &mut Vec<u8>) -> io::Result<()> {
+        let mut transport = self
+            .transport
+            .lock()
+            .map_err(|_| io::Error::other("Transport is poisoned"))?;
+        transport.set_timeout(Some(self.poll_interval))?;
+
+        let len = buffer.len();
+        buffer.resize(len + READ_CHUNK_LEN, 0);
+        let read = transport.read(&mut buffer[len..]);
+        buffer.truncate(len + *read.as_ref().unwrap_or(&0));
+
+        match read {
+            Ok(0) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection was closed by a server",
+            )),
+            Ok(_) => Ok(()),
+            Err(ref err)
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::TimedOut
+                    || err.kind() == io::ErrorKind::Interrupted =>
+            {
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn lock_dispatch(&self) -> error::Result<MutexGuard<'_, Dispatch>> {
+        self.dispatch
+            .lock()
+            .map_err(|_| error::Error::from("Connection state is poisoned"))
+    }
+
+    fn lock_transport(&self) -> error::Result<MutexGuard<'_, T>> {
+        self.transport
+            .lock()
+            .map_err(|_| error::Error::from("Transport is poisoned"))
+    }
+
+    fn wait<'a>(
+        &self,
+        dispatch: MutexGuard<'a, Dispatch>,
+        deadline: Option<Instant>,
+    ) -> error::Result<MutexGuard<'a, Dispatch>> {
+        match deadline {
+            Some(deadline) => self
+                .dispatched
+                .wait_timeout(dispatch, deadline.saturating_duration_since(Instant::now()))
+                .map(|(dispatch, _)| dispatch)
+                .map_err(|_| error::Error::from("Connection state is poisoned")),
+            None => self
+                .dispatched
+                .wait(dispatch)
+                .map_err(|_| error::Error::from("Connection state is poisoned")),
+        }
+    }
+}
+
+/// Parses all complete frames from a buffer and stores them as responses.
+/// Frames which nobody waits for (e.g. server events) are dropped.
+fn dispatch_frames(
+    dispatch: &mut Dispatch,
+    buffer: &mut Vec<u8>,
+    protocol_version: ProtocolVersion,
+) {
+    if protocol_version.is_segmented() {
+        if let Err(err) = dispatch.segments.decode(buffer) {
+            dispatch.broken = Some(err.to_string());
+            return;
+        }
+        while let Some(frame_bytes) = dispatch.segments.next_frame() {
+            dispatch_frame(dispatch, frame_bytes);
+        }
+        return;
+    }
+
+    while buffer.len() >= HEADER_LEN {
+        let (_, length) = parse_header(buffer.as_slice());
+        if buffer.len() < HEADER_LEN + length {
+            break;
+        }
+
+        let rest = buffer.split_off(HEADER_LEN + length);
+        let frame_bytes = mem::replace(buffer, rest);
+        dispatch_frame(dispatch, frame_bytes);
+    }
+}
+
+fn dispatch_frame(dispatch: &mut Dispatch, frame_bytes: Vec<u8>) {
+    let (stream, _) = parse_header(frame_bytes.as_slice());
+    if let Some(response) = dispatch.responses.get_mut(&stream) {
+        *response = Some(Ok(frame_bytes));
+    } else if dispatch.abandoned.remove(&stream) {
+        dispatch.stream_ids.release(stream);
+    }
+}
+
+fn abandon(dispatch: &mut Dispatch, stream: u16) {
+    match dispatch.responses.remove(&stream) {
+        Some(Some(_)) => dispatch.stream_ids.release(stream),
+        Some(None) => {
+            dispatch.abandoned.insert(stream);
+        }
+        None => {}
+    }
+}
+
+fn broken_error(reason: &str) -> error::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        format!("Connection is broken: {}", reason),
+    )
+    .into()
+}
+
+/// Transport which sends requests over a `MultiplexedConnection` shared with other
+/// transports. Stream ids of written frames are replaced with free ones of
+/// the connection and responses are read back with original stream ids.
+///
+/// Closing the transport, e.g. because a request has timed out, leaves the shared
+/// connection open. A late response is dropped once it is received.
+pub struct MultiplexedTransport<T: CDRSTransport> {
+    connection: Arc<MultiplexedConnection<T>>,
+    /// Written bytes which do not form a complete frame yet.
+    written: Vec<u8>,
+    /// Frames written in protocol v5 segments.
+    segments: SegmentDecoder,
+    /// Stream ids of the connection and original stream ids of requests
+    /// which responses were not read yet.
+    in_flight: VecDeque<(u16, u16)>,
+    to_read: Cursor<Vec<u8>>,
+    timeout: Option<Duration>,
+    closed: bool,
+}
+
+impl<T: CDRSTransport> MultiplexedTransport<T> {
+    /// Creates new transport on top of a connection.
+    pub fn new(connection: Arc<MultiplexedConnection<T>>) -> Self {
+        MultiplexedTransport {
+            segments: SegmentDecoder::new(connection.compression),
+            connection,
+            written: vec![],
+            in_flight: VecDeque::new(),
+            to_read: Cursor::new(vec![]),
+            timeout: None,
+            closed: false,
+        }
+    }
+
+    /// Returns a next complete frame which has been written.
+    fn next_request(&mut self) -> io::Result<Option<Vec<u8>>> {
+        if self.connection.protocol_version.is_segmented() {
+            self.segments
+                .decode(&mut self.written)
+                .map_err(into_io_error)?;
+            return Ok(self.segments.next_frame());
+        }
+
+        if self.written.len() < HEADER_LEN {
+            return Ok(None);
+        }
+        let (_, length) = parse_header(self.written.as_slice());
+        if self.written.len() < HEADER_LEN + length {
+            return Ok(None);
+        }
+        let rest = self.written.split_off(HEADER_LEN + length);
+        Ok(Some(mem::replace(&mut self.written, rest)))
+    }
+
+    /// Waits for a response to the oldest request which is in flight.
+    fn read_next_response(&mut self) -> io::Result<Vec<u8>> {
+        let (stream, request_stream) = self
+            .in_flight
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::WouldBlock, "No request in flight"))?;
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut frame_bytes = self
+            .connection
+            .read_response(stream, deadline)
+            .map_err(into_io_error)?;
+        set_header_stream(&mut frame_bytes, request_stream);
+
+        if self.connection.protocol_version.is_segmented() {
+            encode_segments(frame_bytes.as_slice(), self.connection.compression)
+                .map_err(into_io_error)
+        } else {
+            Ok(frame_bytes)
+        }
+    }
+}
+
+impl<T: CDRSTransport> Read for MultiplexedTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.to_read.position() as usize == self.to_read.get_ref().len() {
+            self.to_read = Cursor::new(self.read_next_response()?);
+        }
+        self.to_read.read(buf)
+    }
+}
+
+impl<T: CDRSTransport> Write for MultiplexedTransport<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.closed {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Transport is closed",
+            ));
+        }
+
+        self.written.extend_from_slice(buf);
+        while let Some(request) = self.next_request()? {
+            let (request_stream, _) = parse_header(request.as_slice());
+            let stream = self
+                .connection
+                .write_frame(request)
+                .map_err(into_io_error)?;
+            self.in_flight.push_back((stream, request_stream));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: CDRSTransport> CDRSTransport for MultiplexedTransport<T> {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(MultiplexedTransport::new(self.connection.clone()))
+    }
+
+    fn close(&mut self, _close: net::Shutdown) -> io::Result<()> {
+        self.closed = true;
+        for (stream, _) in self.in_flight.drain(..) {
+            self.connection.abandon(stream);
+        }
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, dur: Option<Duration>) -> io::Result<()> {
+        self.timeout = dur;
+        Ok(())
+    }
+
+    fn is_alive(&self) -> bool {
+        !self.closed && !self.connection.is_broken()
+    }
+
+    fn protocol_version(&self) -> ProtocolVersion {
+        self.connection.protocol_version
+    }
+
+    fn addr(&self) -> SocketAddr {
+        self.connection.addr
+    }
+}
+
+impl<T: CDRSTransport> Drop for MultiplexedTransport<T> {
+    fn drop(&mut self) {
+        for (stream, _) in self.in_flight.drain(..) {
+            self.connection.abandon(stream);
+        }
+    }
+}
+
+fn into_io_error(err: error::Error) -> io::Error {
+    match err {
+        error::Error::Io(err) => err,
+        err => io::Error::other(err.to_string()),
+    }
+}
+
+/// `r2d2` connection manager which connections are `MultiplexedTransport`s sharing
+/// a single connection created by an inner manager, e.g. `TcpConnectionsManager`.
+/// The shared connection is created again once it is broken.
+///
+/// As connections of a pool are cheap, pool's `max_size` limits a number of requests
+/// which are in flight rather than a number of sockets.
+pub struct MultiplexedConnectionsManager<T: CDRSTransport, M> {
+    manager: M,
+    compression: Compression,
+    connection: Mutex<Option<Arc<MultiplexedConnection<T>>>>,
+}
+
+impl<T: CDRSTransport, M> MultiplexedConnectionsManager<T, M> {
+    /// Creates new manager. `compression` should be the same as one which is
+    /// negotiated by the inner manager.
+    pub fn new(manager: M, compression: Compression) -> Self {
+        MultiplexedConnectionsManager {
+            manager,
+            compression,
+            connection: Mutex::new(None),
+        }
+    }
+}
+
+impl<T, M> ManageConnection for MultiplexedConnectionsManager<T, M>
+where
+    T: CDRSTransport + 'static,
+    M: ManageConnection<Connection = RefCell<T>, Error = error::Error>,
+{
+    type Connection = RefCell<MultiplexedTransport<T>>;
+    type Error = error::Error;
+
+    fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| error::Error::from("Connection manager is poisoned"))?;
+
+        let connection = match *connection {
+            Some(ref connection) if !connection.is_broken() => connection.clone(),
+            _ => {
+                let transport = self.manager.connect()?.into_inner();
+                let new_connection =
+                    Arc::new(MultiplexedConnection::new(transport, self.compression));
+                *connection = Some(new_connection.clone());
+                new_connection
+            }
+        };
+
+        Ok(RefCell::new(MultiplexedTransport::new(connection)))
+    }
+
+    fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        let protocol_version = conn.borrow().protocol_version();
+        let options_frame =
+            Frame::new_req_options(protocol_version).encode_framed(self.compression)?;
+        conn.borrow_mut().write_all(options_frame.as_slice())?;
+
+        parse_framed(conn, &self.compression, protocol_version).map(|_| ())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        !conn.borrow().is_alive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{CustomPayload, IntoBytes, Opcode, Version};
+    use crate::query::utils::send_frame_over;
+    use crate::query::{Query, QueryParams};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    /// Transport that responds to every request with a `RESULT` frame which body
+    /// is the same as a body of a request. Queries which contain `silent` are
+    /// not responded to.
+    struct EchoTransport {
+        written: Vec<u8>,
+        to_read: Vec<u8>,
+        segments: SegmentDecoder,
+        protocol_version: ProtocolVersion,
+    }
+
+    impl Default for EchoTransport {
+        fn default() -> Self {
+            EchoTransport {
+                written: vec![],
+                to_read: vec![],
+                segments: SegmentDecoder::new(Compression::None),
+                protocol_version: ProtocolVersion::default(),
+            }
+        }
+    }
+
+    impl EchoTransport {
+        fn next_request(&mut self) -> Option<Vec<u8>> {
+            if self.protocol_version.is_segmented() {
+                self.segments.decode(&mut self.written).unwrap();
+                return self.segments.next_frame();
+            }
+
+            if self.written.len() < HEADER_LEN {
+                return None;
+            }
+            let (_, length) = parse_header(self.written.as_slice());
+            if self.written.len() < HEADER_LEN + length {
+                return None;
+            }
+            let rest = self.written.split_off(HEADER_LEN + length);
+            Some(mem::replace(&mut self.written, rest))
+        }
+    }
+
+    impl Read for EchoTransport {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.to_read.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "no data"));
+            }
+            let len = buf.len().min(self.to_read.len());
+            buf[..len].copy_from_slice(&self.to_read[..len]);
+            self.to_read.drain(..len);
+            Ok(len)
+        }
+    }
+
+    impl Write for EchoTransport {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            while let Some(request) = self.next_request() {
+                let (stream, _) = parse_header(request.as_slice());
+                let body = request[HEADER_LEN..].to_vec();
+                if body.windows(6).any(|bytes| bytes == b"silent") {
+                    continue;
+                }
+                let response = Frame {
+                    version: Version::Response,
+                    protocol_version: self.protocol_version,
+                    flags: vec![],
+                    opcode: Opcode::Result,
+                    stream,
+                    body,
+                    tracing_id: None,
+                    warnings: vec![],
+                    custom_payload: CustomPayload::new(),
+                };
+                let response = response.into_cbytes();
+                if self.protocol_version.is_segmented() {
+                    self.to_read
+                        .extend(encode_segments(&response, Compression::None).unwrap());
+                } else {
+                    self.to_read.extend(response);
+                }
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CDRSTransport for EchoTransport {
+        fn try_clone(&self) -> io::Result<Self> {
+            Err(io::Error::other("not supported"))
+        }
+
+        fn close(&mut self, _close: net::Shutdown) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn set_timeout(&mut self, _dur: Option<Duration>) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn is_alive(&self) -> bool {
+            true
+        }
+
+        fn protocol_version(&self) -> ProtocolVersion {
+            self.protocol_version
+        }
+
+        fn set_protocol_version(&mut self, protocol_version: ProtocolVersion) {
+            self.protocol_version = protocol_version;
+        }
+
+        fn addr(&self) -> net::SocketAddr {
+            "127.0.0.1:9042".parse().unwrap()
+        }
+    }
+
+    /// Creates echo transports and counts them.
+    #[derive(Default)]
+    struct EchoConnectionsManager {
+        connected: Arc<AtomicUsize>,
+    }
+
+    impl ManageConnection for EchoConnectionsManager {
+        type Connection = RefCell<EchoTransport>;
+        type Error = error::Error;
+
+        fn connect(&self) -> Result<Self::Connection, Self::Error> {
+            self.connected.fetch_add(1, Ordering::SeqCst);
+            Ok(RefCell::new(EchoTransport::default()))
+        }
+
+        fn is_valid(&self, _conn: &mut Self::Connection) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn has_broken(&self, _conn: &mut Self::Connection) -> bool {
+            false
+        }
+    }
+
+    fn query_frame(query: &str, protocol_version: ProtocolVersion) -> Frame {
+        let query = Query {
+            query: query.to_string(),
+            params: QueryParams::default(),
+        };
+        Frame::new_query(query, vec![], protocol_version)
+    }
+
+    #[test]
+    fn routes_responses_by_stream_id() {
+        for protocol_version in vec![ProtocolVersion::V4, ProtocolVersion::V5] {
+            let mut transport = EchoTransport::default();
+            transport.set_protocol_version(protocol_version);
+            let connection = MultiplexedConnection::new(transport, Compression::None);
+            let first_request = query_frame("first", protocol_version);
+            let second_request = query_frame("second", protocol_version);
+            let first_body = first_request.body.clone();
+            let second_body = second_request.body.clone();
+
+            let first = connection.write(first_request).unwrap();
+            let second = connection.write(second_request).unwrap();
+            assert_ne!(first, second);
+
+            // response to the first request is received first and gets parked
+            let second_response = connection.read(second).unwrap();
+            assert_eq!(second_response.stream, second);
+            assert_eq!(second_response.body, second_body);
+            assert_eq!(second_response.protocol_version, protocol_version);
+
+            let first_response = connection.read(first).unwrap();
+            assert_eq!(first_response.stream, first);
+            assert_eq!(first_response.body, first_body);
+
+            assert!(connection.read(first).is_err());
+        }
+    }
+
+    #[test]
+    fn concurrent_requests() {
+        let connection = Arc::new(MultiplexedConnection::new(
+            EchoTransport::default(),
+            Compression::None,
+        ));
+
+        let handles: Vec<_> = (0..8)
+            .map(|thread_idx| {
+                let connection = connection.clone();
+                thread::spawn(move || {
+                    for request_idx in 0..50 {
+                        let request = query_frame(
+                            &format!("{} {}", thread_idx, request_idx),
+                            connection.get_protocol_version(),
+                        );
+                        let body = request.body.clone();
+                        let response = connection.send(request).unwrap();
+                        assert_eq!(response.body, body);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(!connection.is_broken());
+    }
+
+    #[test]
+    fn transports_keep_request_stream_ids() {
+        for protocol_version in vec![ProtocolVersion::V4, ProtocolVersion::V5] {
+            let mut echo = EchoTransport::default();
+            echo.set_protocol_version(protocol_version);
+            let connection = Arc::new(MultiplexedConnection::new(echo, Compression::None));
+            let first = RefCell::new(MultiplexedTransport::new(connection.clone()));
+            let second = RefCell::new(MultiplexedTransport::new(connection));
+
+            // both requests have the same stream id, which a single connection cannot have
+            let first_request = query_frame("first", protocol_version);
+            let mut second_request = query_frame("second", protocol_version);
+            second_request.stream = first_request.stream;
+            let stream = first_request.stream;
+            let first_body = first_request.body.clone();
+            let second_body = second_request.body.clone();
+
+            first
+                .borrow_mut()
+                .write_all(&first_request.encode_framed(Compression::None).unwrap())
+                .unwrap();
+            second
+                .borrow_mut()
+                .write_all(&second_request.encode_framed(Compression::None).unwrap())
+                .unwrap();
+
+            let second_response =
+                parse_framed(&second, &Compression::None, protocol_version).unwrap();
+            assert_eq!(second_response.stream, stream);
+            assert_eq!(second_response.body, second_body);
+
+            let first_response =
+                parse_framed(&first, &Compression::None, protocol_version).unwrap();
+            assert_eq!(first_response.stream, stream);
+            assert_eq!(first_response.body, first_body);
+        }
+    }
+
+    #[test]
+    fn timed_out_request_leaves_connection_open() {
+        let connection = Arc::new(MultiplexedConnection::new(
+            EchoTransport::default(),
+            Compression::None,
+        ));
+        let transport = RefCell::new(MultiplexedTransport::new(connection.clone()));
+        let timeout = Duration::from_millis(20);
+
+        let result = send_frame_over(
+            &transport,
+            query_frame("silent", ProtocolVersion::default()),
+            Compression::None,
+            Some(timeout),
+        );
+
+        assert!(matches!(
+            result.as_ref().map_err(error::Error::inner),
+            Err(error::Error::Timeout(t)) if *t == timeout
+        ));
+        assert!(!transport.borrow().is_alive());
+        assert!(!connection.is_broken());
+        assert_eq!(connection.lock_dispatch().unwrap().abandoned.len(), 1);
+
+        let other = RefCell::new(MultiplexedTransport::new(connection.clone()));
+        let response = send_frame_over(
+            &other,
+            query_frame("other", ProtocolVersion::default()),
+            Compression::None,
+            Some(timeout),
+        );
+        assert!(response.is_ok());
+    }
+
+    #[test]
+    fn pool_shares_single_connection() {
+        let echo_manager = EchoConnectionsManager::default();
+        let connected = echo_manager.connected.clone();
+        let manager = MultiplexedConnectionsManager::new(echo_manager, Compression::None);
+        let pool = Arc::new(
+            r2d2::Pool::builder()
+                .max_size(4)
+                .min_idle(Some(4))
+                .build(manager)
+                .unwrap(),
+        );
+
+        let handles: Vec<_> = (0..8)
+            .map(|thread_idx| {
+                let pool = pool.clone();
+                thread::spawn(move || {
+                    for request_idx in 0..20 {
+                        let transport = pool.get().unwrap();
+                        let request = query_frame(
+                            &format!("{} {}", thread_idx, request_idx),
+                            transport.borrow().protocol_version(),
+                        );
+                        let body = request.body.clone();
+                        let response =
+                            send_frame_over(&transport, request, Compression::None, None).unwrap();
+                        assert_eq!(response.body, body);
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(pool.state().connections, 4);
+        assert_eq!(connected.load(Ordering::SeqCst), 1);
+    }
+}
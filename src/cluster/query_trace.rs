@@ -0,0 +1,345 @@
+use r2d2;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::thread;
+use std::time::Duration;
+
+use time::PrimitiveDateTime;
+use uuid::Uuid;
+
+use crate::consistency::Consistency;
+use crate::error;
+use crate::frame::Frame;
+use crate::query::{QueryExecutor, QueryParams, QueryParamsBuilder, QueryValues};
+use crate::transport::CDRSTransport;
+use crate::types::map::Map;
+use crate::types::rows::Row;
+use crate::types::value::Value;
+use crate::types::{AsRustType, ByName};
+
+#[cfg(feature = "async")]
+use crate::cluster::async_session::AsyncSession;
+#[cfg(feature = "async")]
+use crate::cluster::AsyncConnection;
+#[cfg(feature = "async")]
+use crate::load_balancing::LoadBalancingStrategy;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+/// Query which selects a tracing session by its id.
+pub const SELECT_TRACE_SESSION: &str = "SELECT coordinator, client, command, request, \
+     parameters, started_at, duration FROM system_traces.sessions WHERE session_id = ?";
+/// Query which selects events of a tracing session by its id.
+pub const SELECT_TRACE_EVENTS: &str = "SELECT event_id, activity, source, source_elapsed, \
+     thread FROM system_traces.events WHERE session_id = ?";
+
+/// Number of times a tracing session is selected by `QueryTrace::fetch` before
+/// giving up if the session is not complete.
+pub const DEFAULT_TRACE_ATTEMPTS: usize = 5;
+/// Interval between selects of a tracing session made by `QueryTrace::fetch`.
+pub const DEFAULT_TRACE_INTERVAL: Duration = Duration::from_millis(3);
+
+/// Trace of a request which has been sent with tracing enabled. Its id is returned
+/// in `Frame::tracing_id` of a response. Traces are written to `system_traces`
+/// keyspace asynchronously, so `fetch` polls it until the trace is complete.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTrace {
+    pub session_id: Uuid,
+    /// Node which has coordinated the request.
+    pub coordinator: IpAddr,
+    /// Address of a client which has sent the request.
+    pub client: Option<IpAddr>,
+    /// Type of the request, e.g. `QUERY` or `EXECUTE`.
+    pub command: Option<String>,
+    /// Short description of the request.
+    pub request: Option<String>,
+    /// Parameters of the request like a query string and a consistency level.
+    pub parameters: HashMap<String, String>,
+    pub started_at: Option<PrimitiveDateTime>,
+    /// Time the coordinator has spent on the request.
+    pub duration: Duration,
+    /// Events recorded by nodes involved in the request in the order of their ids.
+    pub events: Vec<TraceEvent>,
+}
+
+/// Single step of a traced request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub event_id: Uuid,
+    pub activity: Option<String>,
+    /// Node which has recorded the event.
+    pub source: Option<IpAddr>,
+    /// Time elapsed on the source node since it has started to process the request.
+    pub source_elapsed: Option<Duration>,
+    pub thread: Option<String>,
+}
+
+impl QueryTrace {
+    /// Fetches a trace of a request with a given tracing id. The trace is polled
+    /// `DEFAULT_TRACE_ATTEMPTS` times with `DEFAULT_TRACE_INTERVAL` between attempts.
+    pub fn fetch<T, M, S>(session: &S, tracing_id: Uuid) -> error::Result<QueryTrace>
+    where
+        T: CDRSTransport + 'static,
+        M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
+        S: QueryExecutor<T, M>,
+    {
+        QueryTrace::fetch_with(
+            session,
+            tracing_id,
+            DEFAULT_TRACE_ATTEMPTS,
+            DEFAULT_TRACE_INTERVAL,
+        )
+    }
+
+    /// Fetches a trace of a request with a given tracing id. The tracing session
+    /// is selected up to `attempts` times, sleeping for `interval` between attempts,
+    /// until it is complete. Events are selected once the session is complete.
+    pub fn fetch_with<T, M, S>(
+        session: &S,
+        tracing_id: Uuid,
+        attempts: usize,
+        interval: Duration,
+    ) -> error::Result<QueryTrace>
+    where
+        T: CDRSTransport + 'static,
+        M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
+        S: QueryExecutor<T, M>,
+    {
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                thread::sleep(interval);
+            }
+
+            let sessions = into_rows(
+                session.query_with_params(SELECT_TRACE_SESSION, trace_params(tracing_id))?,
+            )?;
+            if !is_complete(&sessions)? {
+                continue;
+            }
+
+            let events =
+                session.query_with_params(SELECT_TRACE_EVENTS, trace_params(tracing_id))?;
+            return QueryTrace::from_rows(tracing_id, &sessions, &into_rows(events)?);
+        }
+
+        Err(incomplete_error(tracing_id, attempts))
+    }
+
+    /// Asynchronous version of `fetch_with`.
+    #[cfg(feature = "async")]
+    pub async fn fetch_async<LB>(
+        session: &AsyncSession<LB>,
+        tracing_id: Uuid,
+        attempts: usize,
+        interval: Duration,
+    ) -> error::Result<QueryTrace>
+    where
+        LB: LoadBalancingStrategy<Arc<AsyncConnection>> + Sized,
+    {
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                tokio::time::sleep(interval).await;
+            }
+
+            let sessions = into_rows(
+                session
+                    .query_with_params(SELECT_TRACE_SESSION, trace_params(tracing_id))
+                    .await?,
+            )?;
+            if !is_complete(&sessions)? {
+                continue;
+            }
+
+            let events = session
+                .query_with_params(SELECT_TRACE_EVENTS, trace_params(tracing_id))
+                .await?;
+            return QueryTrace::from_rows(tracing_id, &sessions, &into_rows(events)?);
+        }
+
+        Err(incomplete_error(tracing_id, attempts))
+    }
+
+    /// Builds a trace from rows of `SELECT_TRACE_SESSION` and `SELECT_TRACE_EVENTS`
+    /// queries. An error is returned if the tracing session is not complete yet.
+    pub fn from_rows(
+        session_id: Uuid,
+        sessions: &[Row],
+        events: &[Row],
+    ) -> error::Result<QueryTrace> {
+        let row = match sessions.first() {
+            Some(row) => row,
+            None => return Err(incomplete_error(session_id, 1)),
+        };
+        let duration: i32 = match row.by_name("duration")? {
+            Some(duration) => duration,
+            None => return Err(incomplete_error(session_id, 1)),
+        };
+        let parameters: Option<Map> = row.by_name("parameters")?;
+        let parameters = match parameters {
+            Some(parameters) => parameters.as_rust_type()?.unwrap_or_default(),
+            None => HashMap::new(),
+        };
+
+        Ok(QueryTrace {
+            session_id,
+            coordinator: row.r_by_name("coordinator")?,
+            client: row.by_name("client")?,
+            command: row.by_name("command")?,
+            request: row.by_name("request")?,
+            parameters,
+            started_at: row.by_name("started_at")?,
+            duration: micros(duration),
+            events: events
+                .iter()
+                .map(trace_event)
+                .collect::<error::Result<Vec<TraceEvent>>>()?,
+        })
+    }
+}
+
+fn trace_event(row: &Row) -> error::Result<TraceEvent> {
+    let source_elapsed: Option<i32> = row.by_name("source_elapsed")?;
+
+    Ok(TraceEvent {
+        event_id: row.r_by_name("event_id")?,
+        activity: row.by_name("activity")?,
+        source: row.by_name("source")?,
+        source_elapsed: source_elapsed.map(micros),
+        thread: row.by_name("thread")?,
+    })
+}
+
+/// A tracing session is complete once its duration is written.
+fn is_complete(sessions: &[Row]) -> error::Result<bool> {
+    match sessions.first() {
+        Some(row) => {
+            let duration: Option<i32> = row.by_name("duration")?;
+            Ok(duration.is_some())
+        }
+        None => Ok(false),
+    }
+}
+
+fn trace_params(tracing_id: Uuid) -> QueryParams {
+    QueryParamsBuilder::new()
+        .consistency(Consistency::One)
+        .values(QueryValues::SimpleValues(vec![Value::from(tracing_id)]))
+        .finalize()
+}
+
+fn into_rows(response: Frame) -> error::Result<Vec<Row>> {
+    response
+        .get_body()?
+        .into_rows()
+        .ok_or_else(|| error::Error::from("Trace query did not return rows"))
+}
+
+fn incomplete_error(tracing_id: Uuid, attempts: usize) -> error::Error {
+    error::Error::General(format!(
+        "Trace {} is not complete after {} attempts",
+        tracing_id, attempts
+    ))
+}
+
+/// Durations are stored in `system_traces` as microseconds.
+fn micros(value: i32) -> Duration {
+    Duration::from_micros(value.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::ColType;
+    use crate::test::rows;
+
+    fn tracing_id() -> Uuid {
+        Uuid::parse_str("5e0c6a40-1d3b-11eb-8b6f-0242ac110002").unwrap()
+    }
+
+    fn map(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = (entries.len() as i32).to_be_bytes().to_vec();
+        for (key, value) in entries {
+            for item in &[key, value] {
+                bytes.extend_from_slice(&(item.len() as i32).to_be_bytes());
+                bytes.extend_from_slice(item.as_bytes());
+            }
+        }
+        bytes
+    }
+
+    fn sessions(duration: Option<i32>) -> Vec<Row> {
+        rows(
+            &[
+                ("coordinator", ColType::Inet),
+                ("client", ColType::Inet),
+                ("command", ColType::Varchar),
+                ("request", ColType::Varchar),
+                ("parameters", ColType::Map),
+                ("started_at", ColType::Timestamp),
+                ("duration", ColType::Int),
+            ],
+            vec![vec![
+                Some(vec![127, 0, 0, 1]),
+                Some(vec![127, 0, 0, 100]),
+                Some(b"QUERY".to_vec()),
+                Some(b"Execute CQL3 query".to_vec()),
+                Some(map(&[("consistency_level", "ONE"), ("query", "SELECT 1")])),
+                None,
+                duration.map(|duration| duration.to_be_bytes().to_vec()),
+            ]],
+        )
+    }
+
+    fn events() -> Vec<Row> {
+        rows(
+            &[
+                ("event_id", ColType::Timeuuid),
+                ("activity", ColType::Varchar),
+                ("source", ColType::Inet),
+                ("source_elapsed", ColType::Int),
+                ("thread", ColType::Varchar),
+            ],
+            vec![vec![
+                Some(tracing_id().as_bytes().to_vec()),
+                Some(b"Parsing SELECT 1".to_vec()),
+                Some(vec![127, 0, 0, 2]),
+                Some(250i32.to_be_bytes().to_vec()),
+                None,
+            ]],
+        )
+    }
+
+    #[test]
+    fn trace_from_rows() {
+        let trace = QueryTrace::from_rows(tracing_id(), &sessions(Some(1500)), &events()).unwrap();
+
+        assert_eq!(trace.coordinator, "127.0.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(trace.client, Some("127.0.0.100".parse().unwrap()));
+        assert_eq!(trace.command, Some("QUERY".to_string()));
+        assert_eq!(trace.duration, Duration::from_micros(1500));
+        assert_eq!(trace.started_at, None);
+        assert_eq!(
+            trace.parameters.get("query").map(String::as_str),
+            Some("SELECT 1")
+        );
+        assert_eq!(
+            trace.events,
+            vec![TraceEvent {
+                event_id: tracing_id(),
+                activity: Some("Parsing SELECT 1".to_string()),
+                source: Some("127.0.0.2".parse().unwrap()),
+                source_elapsed: Some(Duration::from_micros(250)),
+                thread: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn incomplete_trace() {
+        assert!(!is_complete(&[]).unwrap());
+        assert!(!is_complete(&sessions(None)).unwrap());
+        assert!(is_complete(&sessions(Some(1))).unwrap());
+        assert!(QueryTrace::from_rows(tracing_id(), &sessions(None), &[]).is_err());
+    }
+}
@@ -6,12 +6,12 @@ use std::io::Write;
 use std::error::Error;
 use core::cell::RefCell;
 
-use crate::cluster::{startup, NodeRustlsConfig};
+use crate::cluster::{connect_and_startup, NodeRustlsConfig};
 use crate::authenticators::Authenticator;
 use crate::cluster::ConnectionPool;
 use crate::compression::Compression;
-use crate::frame::parser::parse_frame;
-use crate::frame::{Frame, IntoBytes};
+use crate::frame::parser::parse_framed;
+use crate::frame::Frame;
 use crate::transport::{CDRSTransport, TransportRustls};
 use crate::error;
 
@@ -20,12 +20,14 @@ pub type RustlsConnectionPool<A> = ConnectionPool<RustlsConnectionsManager<A>>;
 /// `r2d2::Pool` of SSL-based CDRS connections.
 ///
 /// Used internally for SSL Session for holding connections to a specific Cassandra node.
-pub fn new_rustls_pool<A: Authenticator + Send + Sync + 'static>(node_config: NodeRustlsConfig<A>) -> error::Result<RustlsConnectionPool<A>> {
+/// Every connection negotiates given `compression` during startup.
+pub fn new_rustls_pool<A: Authenticator + Send + Sync + 'static>(node_config: NodeRustlsConfig<A>, compression: Compression) -> error::Result<RustlsConnectionPool<A>> {
     let manager = RustlsConnectionsManager::new(
         node_config.addr,
         node_config.dns_name,
         node_config.config,
         node_config.authenticator,
+        compression,
     );
 
     let pool = Builder::new()
@@ -46,16 +48,18 @@ pub struct RustlsConnectionsManager<A> {
     dns_name: webpki::DNSName,
     config: Arc<rustls::ClientConfig>,
     auth: A,
+    compression: Compression,
 }
 
 impl<A> RustlsConnectionsManager<A> {
     #[inline]
-    pub fn new(addr: net::SocketAddr, dns_name: webpki::DNSName, config: Arc<rustls::ClientConfig>, auth: A) -> Self {
+    pub fn new(addr: net::SocketAddr, dns_name: webpki::DNSName, config: Arc<rustls::ClientConfig>, auth: A, compression: Compression) -> Self {
         Self {
             addr,
             dns_name,
             config,
             auth,
+            compression,
         }
     }
 }
@@ -65,17 +69,23 @@ impl<A: Authenticator + 'static + Send + Sync> ManageConnection for RustlsConnec
     type Error = error::Error;
 
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        let transport = RefCell::new(TransportRustls::new(self.addr, self.dns_name.clone(), self.config.clone())?);
-        startup(&transport, &self.auth)?;
-
-        Ok(transport)
+        connect_and_startup(
+            || {
+                TransportRustls::new(self.addr, self.dns_name.clone(), self.config.clone())
+                    .map_err(Into::into)
+            },
+            &self.auth,
+            self.compression,
+        )
     }
 
     fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        let options_frame = Frame::new_req_options().into_cbytes();
-        conn.borrow_mut().write(options_frame.as_slice())?;
+        let protocol_version = conn.borrow().protocol_version();
+        let options_frame =
+            Frame::new_req_options(protocol_version).encode_framed(self.compression)?;
+        conn.borrow_mut().write_all(options_frame.as_slice())?;
 
-        parse_frame(conn, &Compression::None {}).map(|_| ())
+        parse_framed(conn, &self.compression, protocol_version).map(|_| ())
     }
 
     fn has_broken(&self, conn: &mut Self::Connection) -> bool {
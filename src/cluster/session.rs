@@ -1,30 +1,42 @@
 use r2d2;
-use std::borrow::{Borrow, BorrowMut};
+use std::borrow::Borrow;
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io::Write;
 use std::iter::Iterator;
-use std::sync::Mutex;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-#[cfg(feature = "unstable-dynamic-cluster")]
-use crate::cluster::NodeTcpConfig;
-#[cfg(feature = "ssl")]
-use crate::cluster::{new_ssl_pool, ClusterSslConfig, NodeSslConfig, SslConnectionPool};
 use crate::cluster::{
-    new_tcp_pool, startup, CDRSSession, ClusterTcpConfig, ConnectionPool, GetCompressor,
-    GetConnection, TcpConnectionPool,
+    connect_and_startup, new_multiplexed_tcp_pool, new_tcp_pool, CDRSSession, ClusterTcpConfig,
+    ConnectionPool, GetCompressor, GetConnection, GetCustomTypeRegistry, GetRequestTimeout,
+    GetRetryPolicy, GetSpeculativeExecutionPolicy, MultiplexedTcpConnectionPool, NodeTcpConfig,
+    TcpConnectionPool,
 };
+#[cfg(feature = "ssl")]
+use crate::cluster::{new_ssl_pool, ClusterSslConfig, NodeSslConfig, SslConnectionPool};
 use crate::error;
-use crate::load_balancing::LoadBalancingStrategy;
+use crate::load_balancing::{GetAddr, LoadBalancingStrategy, RoutingInfo};
 use crate::transport::{CDRSTransport, TransportTcp};
 
 use crate::authenticators::Authenticator;
+use crate::cluster::metadata::ClusterMetadata;
 use crate::cluster::SessionPager;
 use crate::compression::Compression;
 use crate::events::{new_listener, EventStream, EventStreamNonBlocking, Listener};
-use crate::frame::events::{ServerEvent, SimpleServerEvent, StatusChange, StatusChangeType};
-use crate::frame::parser::parse_frame;
-use crate::frame::{Frame, IntoBytes};
+use crate::frame::events::{
+    ServerEvent, SimpleServerEvent, StatusChange, StatusChangeType, TopologyChange,
+    TopologyChangeType,
+};
+use crate::frame::parser::parse_framed;
+use crate::frame::Frame;
 use crate::query::{BatchExecutor, ExecExecutor, PrepareExecutor, QueryExecutor};
+use crate::retry::{DefaultRetryPolicy, RetryPolicy};
+use crate::speculative_execution::SpeculativeExecutionPolicy;
+use crate::types::custom::CustomTypeRegistry;
 
 #[cfg(feature = "ssl")]
 use crate::transport::TransportTls;
@@ -33,15 +45,158 @@ use openssl::ssl::SslConnector;
 
 /// CDRS session that holds one pool of authorized connecitons per node.
 /// `compression` field contains data compressor that will be used
-/// for decompressing data received from Cassandra server.
+/// for compressing requests and decompressing data received from Cassandra server.
+/// It is negotiated with every connection during startup, so it should not be
+/// changed during `Session` life time.
 #[derive(Debug)]
 pub struct Session<LB> {
     load_balancing: Mutex<LB>,
     event_stream: Option<Mutex<EventStreamNonBlocking>>,
+    node_factory: Option<NodeFactory<LB>>,
+    retry_policy: Box<dyn RetryPolicy>,
+    speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy>>,
+    request_timeout: Option<Duration>,
+    custom_type_registry: CustomTypeRegistry,
     #[allow(dead_code)]
     pub compression: Compression,
 }
 
+/// Delay after which a node which has come up but could not be connected to is tried again.
+const NODE_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Creates connection pools to nodes that join a cluster or come up after a session
+/// was created. Pools are created in background threads, so requests are not blocked
+/// while nodes are connected to, and they are added to a load balancer afterwards.
+struct NodeFactory<LB> {
+    connect: Box<Connect>,
+    add_connected: Box<AddConnected<LB>>,
+    nodes: Mutex<Nodes>,
+}
+
+/// Starts connecting to a node in a background thread.
+type Connect = dyn Fn(SocketAddr) + Send + Sync;
+
+/// Adds nodes which have been connected to to a load balancer.
+type AddConnected<LB> = dyn Fn(&mut LB, &mut Nodes) + Send + Sync;
+
+impl<LB> NodeFactory<LB> {
+    fn new<N, F>(known: Vec<SocketAddr>, new_node: F) -> Self
+    where
+        N: GetAddr + Send + 'static,
+        F: Fn(SocketAddr) -> error::Result<N> + Send + Sync + 'static,
+        LB: LoadBalancingStrategy<N>,
+    {
+        let new_node = Arc::new(new_node);
+        let connected: Arc<Mutex<Vec<(SocketAddr, error::Result<N>)>>> = Arc::default();
+
+        let connect = {
+            let connected = connected.clone();
+            move |addr: SocketAddr| {
+                let new_node = new_node.clone();
+                let connected = connected.clone();
+                thread::spawn(move || {
+                    let result = new_node(addr);
+                    if let Ok(mut connected) = connected.lock() {
+                        connected.push((addr, result));
+                    }
+                });
+            }
+        };
+
+        let add_connected = move |load_balancing: &mut LB, nodes: &mut Nodes| {
+            let connected = match connected.lock() {
+                Ok(mut connected) => connected.drain(..).collect::<Vec<_>>(),
+                Err(_) => return,
+            };
+            for (addr, result) in connected {
+                // a node has been removed while it was being connected to
+                if !nodes.connecting.contains(&addr) {
+                    continue;
+                }
+
+                match result {
+                    Ok(node) => {
+                        let node_addr = node.get_addr();
+                        load_balancing.remove_node(|n| n.get_addr() == node_addr);
+                        load_balancing.add_node(node);
+                        nodes.added(addr);
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Unable to add node {}, it will be tried again in {:?}: {}",
+                            addr, NODE_RETRY_DELAY, err
+                        );
+                        nodes.failed(addr, Instant::now());
+                    }
+                }
+            }
+        };
+
+        NodeFactory {
+            connect: Box::new(connect),
+            add_connected: Box::new(add_connected),
+            nodes: Mutex::new(Nodes {
+                known: known.into_iter().collect(),
+                ..Nodes::default()
+            }),
+        }
+    }
+}
+
+impl<LB> fmt::Debug for NodeFactory<LB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NodeFactory")
+    }
+}
+
+/// Nodes which a load balancer has connection pools for, nodes which are being connected
+/// to and nodes which have come up but could not be connected to, together with a time
+/// of a next attempt.
+#[derive(Debug, Default)]
+struct Nodes {
+    known: HashSet<SocketAddr>,
+    connecting: HashSet<SocketAddr>,
+    unreachable: HashMap<SocketAddr, Instant>,
+}
+
+impl Nodes {
+    /// Marks a node as being connected to. Returns `false` if the node is already known
+    /// or is being connected to.
+    fn connecting(&mut self, addr: SocketAddr) -> bool {
+        if self.known.contains(&addr) || !self.connecting.insert(addr) {
+            return false;
+        }
+        self.unreachable.remove(&addr);
+        true
+    }
+
+    fn added(&mut self, addr: SocketAddr) {
+        self.known.insert(addr);
+        self.connecting.remove(&addr);
+        self.unreachable.remove(&addr);
+    }
+
+    fn failed(&mut self, addr: SocketAddr, now: Instant) {
+        self.connecting.remove(&addr);
+        self.unreachable.insert(addr, now + NODE_RETRY_DELAY);
+    }
+
+    fn removed(&mut self, addr: SocketAddr) {
+        self.known.remove(&addr);
+        self.connecting.remove(&addr);
+        self.unreachable.remove(&addr);
+    }
+
+    /// Returns unreachable nodes which should be tried again.
+    fn to_retry(&self, now: Instant) -> Vec<SocketAddr> {
+        self.unreachable
+            .iter()
+            .filter(|(_, retry_at)| **retry_at <= now)
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+}
+
 impl<'a, LB> GetCompressor<'a> for Session<LB> {
     /// Returns compression that current session has.
     fn get_compressor(&self) -> Compression {
@@ -49,7 +204,68 @@ impl<'a, LB> GetCompressor<'a> for Session<LB> {
     }
 }
 
+impl<LB> GetRetryPolicy for Session<LB> {
+    /// Returns retry policy that current session has.
+    fn get_retry_policy(&self) -> &dyn RetryPolicy {
+        self.retry_policy.as_ref()
+    }
+}
+
+impl<LB> GetSpeculativeExecutionPolicy for Session<LB> {
+    /// Returns speculative execution policy that current session has.
+    fn get_speculative_execution_policy(&self) -> Option<&dyn SpeculativeExecutionPolicy> {
+        self.speculative_execution_policy.as_deref()
+    }
+}
+
+impl<LB> GetRequestTimeout for Session<LB> {
+    /// Returns request timeout that current session has.
+    fn get_request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+}
+
+impl<LB> GetCustomTypeRegistry for Session<LB> {
+    /// Returns registry of custom types that current session has.
+    fn get_custom_type_registry(&self) -> &CustomTypeRegistry {
+        &self.custom_type_registry
+    }
+}
+
 impl<'a, LB: Sized> Session<LB> {
+    /// Replaces retry policy of a session, by default `DefaultRetryPolicy` is used.
+    pub fn with_retry_policy<P: RetryPolicy + 'static>(mut self, retry_policy: P) -> Self {
+        self.retry_policy = Box::new(retry_policy);
+        self
+    }
+
+    /// Sets speculative execution policy of a session, by default idempotent requests
+    /// are not executed speculatively. Executions which have lost keep their connections
+    /// until they are finished, so they are limited by a request timeout, or by
+    /// `DEFAULT_SPECULATIVE_EXECUTION_TIMEOUT` if a session does not have one.
+    pub fn with_speculative_execution_policy<P: SpeculativeExecutionPolicy + 'static>(
+        mut self,
+        speculative_execution_policy: P,
+    ) -> Self {
+        self.speculative_execution_policy = Some(Box::new(speculative_execution_policy));
+        self
+    }
+
+    /// Sets a timeout of requests which do not have their own one in `QueryParams`,
+    /// by default the driver waits for responses indefinitely. A connection which
+    /// has timed out is discarded, as a late response would be read by a next request.
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Replaces registry of custom types of a session, by default it is empty,
+    /// so values of custom types are returned as raw bytes.
+    pub fn with_custom_type_registry(mut self, custom_type_registry: CustomTypeRegistry) -> Self {
+        self.custom_type_registry = custom_type_registry;
+        self
+    }
+
     /// Basing on current session returns new `SessionPager` that can be used
     /// for performing paged queries.
     pub fn paged<
@@ -73,37 +289,151 @@ impl<
     > GetConnection<T, M> for Session<LB>
 {
     fn get_connection(&self) -> Option<r2d2::PooledConnection<M>> {
-        if cfg!(feature = "unstable-dynamic-cluster") {
-            if let Some(ref event_stream_mx) = self.event_stream {
-                if let Ok(ref mut event_stream) = event_stream_mx.try_lock() {
-                    loop {
-                        let next_event = event_stream.borrow_mut().next();
-
-                        match next_event {
-                            None => break,
-                            Some(ServerEvent::StatusChange(StatusChange {
-                                addr,
-                                change_type: StatusChangeType::Down,
-                            })) => {
-                                self.load_balancing
-                                    .lock()
-                                    .ok()?
-                                    .borrow_mut()
-                                    .remove_node(|pool| pool.get_addr() == addr.addr);
+        self.get_connection_for(&RoutingInfo::default())
+    }
+
+    fn get_connection_for(&self, routing: &RoutingInfo) -> Option<r2d2::PooledConnection<M>> {
+        self.process_events()?;
+
+        self.load_balancing
+            .lock()
+            .ok()?
+            .borrow()
+            .next_for(routing)
+            .and_then(|pool| pool.get_pool().get().ok())
+    }
+}
+
+impl<LB> Session<LB> {
+    /// Applies received topology and status changes to the load balancer.
+    fn process_events<T, M>(&self) -> Option<()>
+    where
+        T: CDRSTransport + 'static,
+        M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error>,
+        LB: LoadBalancingStrategy<ConnectionPool<M>>,
+    {
+        let mut topology_changed = false;
+
+        if let Some(ref event_stream_mx) = self.event_stream {
+            if let Ok(mut event_stream) = event_stream_mx.try_lock() {
+                for event in event_stream.by_ref() {
+                    match event {
+                        ServerEvent::StatusChange(StatusChange {
+                            addr,
+                            change_type: StatusChangeType::Down,
+                        }) => self.remove_node(addr.addr)?,
+                        ServerEvent::StatusChange(StatusChange {
+                            addr,
+                            change_type: StatusChangeType::Up,
+                        }) => self.add_node(addr.addr)?,
+                        ServerEvent::TopologyChange(TopologyChange { addr, change_type }) => {
+                            match change_type {
+                                TopologyChangeType::NewNode => self.add_node(addr.addr)?,
+                                TopologyChangeType::RemovedNode => self.remove_node(addr.addr)?,
+                                TopologyChangeType::MovedNode => {}
                             }
-                            Some(_) => continue,
+                            topology_changed = true;
                         }
+                        ServerEvent::SchemaChange(_) => continue,
+                    }
+                }
+
+                // nodes which have come up but were not connected to yet
+                if let Some(ref node_factory) = self.node_factory {
+                    let to_retry = node_factory.nodes.lock().ok()?.to_retry(Instant::now());
+                    for addr in to_retry {
+                        self.add_node(addr)?;
                     }
                 }
             }
         }
 
+        self.add_connected_nodes()?;
+
+        // tokens of nodes have changed
+        if topology_changed {
+            if let Err(err) = self.refresh_metadata() {
+                warn!("Unable to refresh cluster metadata: {}", err);
+            }
+        }
+
+        Some(())
+    }
+
+    /// Starts connecting to a node which has joined a cluster or come up.
+    /// It is added to the load balancer once connected.
+    fn add_node(&self, addr: SocketAddr) -> Option<()> {
+        if let Some(ref node_factory) = self.node_factory {
+            if node_factory.nodes.lock().ok()?.connecting(addr) {
+                (node_factory.connect)(addr);
+            }
+        }
+
+        Some(())
+    }
+
+    /// Adds nodes which have been connected to in background to the load balancer.
+    fn add_connected_nodes(&self) -> Option<()> {
+        if let Some(ref node_factory) = self.node_factory {
+            let mut load_balancing = self.load_balancing.lock().ok()?;
+            let mut nodes = node_factory.nodes.lock().ok()?;
+            (node_factory.add_connected)(&mut load_balancing, &mut nodes);
+        }
+
+        Some(())
+    }
+
+    fn remove_node<M>(&self, addr: SocketAddr) -> Option<()>
+    where
+        M: r2d2::ManageConnection,
+        LB: LoadBalancingStrategy<ConnectionPool<M>>,
+    {
         self.load_balancing
             .lock()
             .ok()?
-            .borrow()
+            .remove_node(|pool| pool.get_addr() == addr);
+        if let Some(ref node_factory) = self.node_factory {
+            node_factory.nodes.lock().ok()?.removed(addr);
+        }
+
+        Some(())
+    }
+
+    /// Fetches cluster topology from one of nodes and passes it to the load balancer,
+    /// so strategies like `TokenAware` could route requests basing on it.
+    pub fn refresh_metadata<T, M>(&self) -> error::Result<()>
+    where
+        T: CDRSTransport + 'static,
+        M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error>,
+        LB: LoadBalancingStrategy<ConnectionPool<M>>,
+    {
+        let node = self
+            .load_balancing
+            .lock()
+            .map_err(|_| error::Error::from("Load balancer is poisoned"))?
             .next()
-            .and_then(|pool| pool.get_pool().get().ok())
+            .cloned()
+            .ok_or_else(|| error::Error::Pool("Unable to get connection".to_string()))?;
+        let metadata = fetch_metadata(&node, self.compression)?;
+
+        self.load_balancing
+            .lock()
+            .map_err(|_| error::Error::from("Load balancer is poisoned"))?
+            .update_metadata(&metadata);
+
+        Ok(())
+    }
+
+    fn init_metadata<T, M>(self) -> Self
+    where
+        T: CDRSTransport + 'static,
+        M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error>,
+        LB: LoadBalancingStrategy<ConnectionPool<M>>,
+    {
+        if let Err(err) = self.refresh_metadata() {
+            warn!("Unable to fetch cluster metadata: {}", err);
+        }
+        self
     }
 }
 
@@ -152,32 +482,205 @@ impl<
 {
 }
 
+/// Fetches cluster metadata over a connection from given pool.
+fn fetch_metadata<T, M>(
+    node: &ConnectionPool<M>,
+    compression: Compression,
+) -> error::Result<ClusterMetadata>
+where
+    T: CDRSTransport + 'static,
+    M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error>,
+{
+    let transport = node
+        .get_pool()
+        .get()
+        .map_err(|err| error::Error::Pool(err.to_string()).with_node(node.get_addr()))?;
+
+    ClusterMetadata::fetch(&transport, node.get_addr(), compression)
+}
+
 fn connect_static<'a, A, LB>(
     node_configs: &ClusterTcpConfig<'a, A>,
-    mut load_balancing: LB,
+    load_balancing: LB,
     compression: Compression,
 ) -> error::Result<Session<LB>>
 where
     A: Authenticator + 'static + Sized,
     LB: LoadBalancingStrategy<TcpConnectionPool<A>> + Sized,
 {
-    let mut nodes: Vec<TcpConnectionPool<A>> = Vec::with_capacity(node_configs.0.len());
+    connect_pools(node_configs, load_balancing, compression, new_tcp_pool)
+}
+
+fn connect_multiplexed<'a, A, LB>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    load_balancing: LB,
+    compression: Compression,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<MultiplexedTcpConnectionPool<A>> + Sized,
+{
+    connect_pools(
+        node_configs,
+        load_balancing,
+        compression,
+        new_multiplexed_tcp_pool,
+    )
+}
+
+/// Creates a connection pool per node with `new_pool`.
+fn connect_pools<'a, A, T, M, LB, F>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    mut load_balancing: LB,
+    compression: Compression,
+    new_pool: F,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    T: CDRSTransport + 'static,
+    M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error>,
+    LB: LoadBalancingStrategy<ConnectionPool<M>> + Sized,
+    F: Fn(NodeTcpConfig<'a, A>, Compression) -> error::Result<ConnectionPool<M>>,
+{
+    let mut nodes: Vec<ConnectionPool<M>> = Vec::with_capacity(node_configs.0.len());
 
     for node_config in &node_configs.0 {
-        let node_connection_pool = new_tcp_pool(node_config.clone())?;
+        let node_connection_pool = new_pool(node_config.clone(), compression)?;
         nodes.push(node_connection_pool);
     }
 
     load_balancing.init(nodes);
 
+    let session = Session {
+        load_balancing: Mutex::new(load_balancing),
+        event_stream: None,
+        node_factory: None,
+        retry_policy: Box::new(DefaultRetryPolicy::new()),
+        speculative_execution_policy: None,
+        request_timeout: None,
+        custom_type_registry: CustomTypeRegistry::new(),
+        compression,
+    };
+
+    Ok(session.init_metadata())
+}
+
+/// Connects to every node which seeds know about besides the seeds themselves.
+/// `new_pool` creates a connection pool to a discovered node with given address.
+fn connect_with_discovery<T, M, LB, F>(
+    mut nodes: Vec<ConnectionPool<M>>,
+    new_pool: F,
+    mut load_balancing: LB,
+    compression: Compression,
+) -> error::Result<Session<LB>>
+where
+    T: CDRSTransport + 'static,
+    M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error>,
+    LB: LoadBalancingStrategy<ConnectionPool<M>> + Sized,
+    F: Fn(&str) -> error::Result<ConnectionPool<M>>,
+{
+    let mut metadata = None;
+    for seed in &nodes {
+        match fetch_metadata(seed, compression) {
+            Ok(seed_metadata) => {
+                metadata = Some(seed_metadata);
+                break;
+            }
+            Err(err) => warn!("Unable to discover peers of {}: {}", seed.get_addr(), err),
+        }
+    }
+
+    let metadata = metadata.unwrap_or_default();
+    let default_port = nodes.first().map(|seed| seed.get_addr().port());
+    for node in &metadata.nodes {
+        if nodes.iter().any(|pool| pool.get_addr().ip() == node.addr) {
+            continue;
+        }
+
+        let port = match node.port.or(default_port) {
+            Some(port) => port,
+            None => continue,
+        };
+        let addr = SocketAddr::new(node.addr, port).to_string();
+
+        match new_pool(&addr) {
+            Ok(node_connection_pool) => nodes.push(node_connection_pool),
+            Err(err) => warn!("Unable to connect to discovered node {}: {}", addr, err),
+        }
+    }
+
+    load_balancing.init(nodes);
+    load_balancing.update_metadata(&metadata);
+
     Ok(Session {
         load_balancing: Mutex::new(load_balancing),
         event_stream: None,
+        node_factory: None,
+        retry_policy: Box::new(DefaultRetryPolicy::new()),
+        speculative_execution_policy: None,
+        request_timeout: None,
+        custom_type_registry: CustomTypeRegistry::new(),
         compression,
     })
 }
 
-#[cfg(feature = "unstable-dynamic-cluster")]
+fn connect_tcp_with_discovery<'a, 'b, A, LB>(
+    seeds: &ClusterTcpConfig<'a, A>,
+    node_template: &NodeTcpConfig<'b, A>,
+    load_balancing: LB,
+    compression: Compression,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<TcpConnectionPool<A>> + Sized,
+{
+    let mut nodes: Vec<TcpConnectionPool<A>> = Vec::with_capacity(seeds.0.len());
+
+    for node_config in &seeds.0 {
+        let node_connection_pool = new_tcp_pool(node_config.clone(), compression)?;
+        nodes.push(node_connection_pool);
+    }
+
+    let new_pool = |addr: &str| {
+        let node_config = NodeTcpConfig {
+            addr,
+            ..node_template.clone()
+        };
+        new_tcp_pool(node_config, compression)
+    };
+
+    connect_with_discovery(nodes, new_pool, load_balancing, compression)
+}
+
+#[cfg(feature = "ssl")]
+fn connect_ssl_with_discovery<'a, 'b, A, LB>(
+    seeds: &ClusterSslConfig<'a, A>,
+    node_template: &NodeSslConfig<'b, A>,
+    load_balancing: LB,
+    compression: Compression,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<SslConnectionPool<A>> + Sized,
+{
+    let mut nodes: Vec<SslConnectionPool<A>> = Vec::with_capacity(seeds.0.len());
+
+    for node_config in &seeds.0 {
+        let node_connection_pool = new_ssl_pool(node_config.clone(), compression)?;
+        nodes.push(node_connection_pool);
+    }
+
+    let new_pool = |addr: &str| {
+        let node_config = NodeSslConfig {
+            addr,
+            ..node_template.clone()
+        };
+        new_ssl_pool(node_config, compression)
+    };
+
+    connect_with_discovery(nodes, new_pool, load_balancing, compression)
+}
+
 fn connect_dynamic<'a, A, LB>(
     node_configs: &ClusterTcpConfig<'a, A>,
     mut load_balancing: LB,
@@ -191,33 +694,60 @@ where
     let mut nodes: Vec<TcpConnectionPool<A>> = Vec::with_capacity(node_configs.0.len());
 
     for node_config in &node_configs.0 {
-        let node_connection_pool = new_tcp_pool(node_config.clone())?;
+        let node_connection_pool = new_tcp_pool(node_config.clone(), compression)?;
         nodes.push(node_connection_pool);
     }
 
+    let known = nodes.iter().map(GetAddr::get_addr).collect();
     load_balancing.init(nodes);
 
+    let node_template = NodeTcpConfig {
+        addr: "",
+        authenticator: event_src.authenticator.clone(),
+        max_size: event_src.max_size,
+        min_idle: event_src.min_idle,
+        max_lifetime: event_src.max_lifetime,
+        idle_timeout: event_src.idle_timeout,
+        connection_timeout: event_src.connection_timeout,
+    };
+    let node_factory = NodeFactory::new(known, move |addr: SocketAddr| {
+        let addr = addr.to_string();
+        let node_config = NodeTcpConfig {
+            addr: &addr,
+            ..node_template.clone()
+        };
+        new_tcp_pool(node_config, compression)
+    });
+
     let mut session = Session {
         load_balancing: Mutex::new(load_balancing),
         event_stream: None,
+        node_factory: Some(node_factory),
+        retry_policy: Box::new(DefaultRetryPolicy::new()),
+        speculative_execution_policy: None,
+        request_timeout: None,
+        custom_type_registry: CustomTypeRegistry::new(),
         compression,
     };
 
     let (listener, event_stream) = session.listen_non_blocking(
         event_src.addr,
         event_src.authenticator,
-        vec![SimpleServerEvent::StatusChange],
+        vec![
+            SimpleServerEvent::StatusChange,
+            SimpleServerEvent::TopologyChange,
+        ],
     )?;
 
-    ::std::thread::spawn(move || listener.start(&Compression::None));
+    ::std::thread::spawn(move || listener.start(&compression));
 
     session.event_stream = Some(Mutex::new(event_stream));
 
-    Ok(session)
+    Ok(session.init_metadata())
 }
 
-/// Creates new session that will perform queries without any compression. `Compression` type
-/// can be changed at any time.
+/// Creates new session that will perform queries without any compression. Compression is
+/// negotiated with every connection during its startup.
 /// As a parameter it takes:
 /// * cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
@@ -232,14 +762,13 @@ where
     connect_static(node_configs, load_balancing, Compression::None)
 }
 
-/// Creates new session that will perform queries without any compression. `Compression` type
-/// can be changed at any time. Once received topology change event, it will adjust an inner load
+/// Creates new session that will perform queries without any compression. Compression is
+/// negotiated with every connection during its startup. Once received topology change event, it will adjust an inner load
 /// balancer.
 /// As a parameter it takes:
 /// * cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
 /// * node address where to listen events
-#[cfg(feature = "unstable-dynamic-cluster")]
 pub fn new_dynamic<'a, A, LB>(
     node_configs: &ClusterTcpConfig<'a, A>,
     load_balancing: LB,
@@ -252,8 +781,8 @@ where
     connect_dynamic(node_configs, load_balancing, Compression::None, event_src)
 }
 
-/// Creates new session that will perform queries with Snappy compression. `Compression` type
-/// can be changed at any time.
+/// Creates new session that will perform queries with Snappy compression. Compression is
+/// negotiated with every connection during its startup.
 /// As a parameter it takes:
 /// * cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
@@ -268,14 +797,13 @@ where
     connect_static(node_configs, load_balancing, Compression::Snappy)
 }
 
-/// Creates new session that will perform queries with Snappy compression. `Compression` type
-/// can be changed at any time. Once received topology change event, it will adjust an inner load
+/// Creates new session that will perform queries with Snappy compression. Compression is
+/// negotiated with every connection during its startup. Once received topology change event, it will adjust an inner load
 /// balancer.
 /// As a parameter it takes:
 /// * cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
 /// * node address where to listen events
-#[cfg(feature = "unstable-dynamic-cluster")]
 pub fn new_snappy_dynamic<'a, A, LB>(
     node_configs: &ClusterTcpConfig<'a, A>,
     load_balancing: LB,
@@ -288,8 +816,8 @@ where
     connect_dynamic(node_configs, load_balancing, Compression::Snappy, event_src)
 }
 
-/// Creates new session that will perform queries with LZ4 compression. `Compression` type
-/// can be changed at any time.
+/// Creates new session that will perform queries with LZ4 compression. Compression is
+/// negotiated with every connection during its startup.
 /// As a parameter it takes:
 /// * cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
@@ -304,14 +832,13 @@ where
     connect_static(node_configs, load_balancing, Compression::Lz4)
 }
 
-/// Creates new session that will perform queries with LZ4 compression. `Compression` type
-/// can be changed at any time. Once received topology change event, it will adjust an inner load
+/// Creates new session that will perform queries with LZ4 compression. Compression is
+/// negotiated with every connection during its startup. Once received topology change event, it will adjust an inner load
 /// balancer.
 /// As a parameter it takes:
 /// * cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
 /// * node address where to listen events
-#[cfg(feature = "unstable-dynamic-cluster")]
 pub fn new_lz4_dynamic<'a, A, LB>(
     node_configs: &ClusterTcpConfig<'a, A>,
     load_balancing: LB,
@@ -324,8 +851,124 @@ where
     connect_dynamic(node_configs, load_balancing, Compression::Lz4, event_src)
 }
 
+/// Creates new session that will perform queries without any compression. Every node
+/// has a single connection which requests of all threads are multiplexed over, so
+/// `max_size` of a node config limits a number of concurrent requests to the node
+/// rather than a number of sockets. Compression is negotiated during connection startup.
+/// As a parameter it takes:
+/// * cluster config
+/// * load balancing strategy (cannot be changed during `Session` life time).
+pub fn new_multiplexed<'a, A, LB>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<MultiplexedTcpConnectionPool<A>> + Sized,
+{
+    connect_multiplexed(node_configs, load_balancing, Compression::None)
+}
+
+/// Creates new session that will perform queries with Snappy compression. Every node
+/// has a single connection which requests of all threads are multiplexed over, so
+/// `max_size` of a node config limits a number of concurrent requests to the node
+/// rather than a number of sockets. Compression is negotiated during connection startup.
+/// As a parameter it takes:
+/// * cluster config
+/// * load balancing strategy (cannot be changed during `Session` life time).
+pub fn new_snappy_multiplexed<'a, A, LB>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<MultiplexedTcpConnectionPool<A>> + Sized,
+{
+    connect_multiplexed(node_configs, load_balancing, Compression::Snappy)
+}
+
+/// Creates new session that will perform queries with LZ4 compression. Every node
+/// has a single connection which requests of all threads are multiplexed over, so
+/// `max_size` of a node config limits a number of concurrent requests to the node
+/// rather than a number of sockets. Compression is negotiated during connection startup.
+/// As a parameter it takes:
+/// * cluster config
+/// * load balancing strategy (cannot be changed during `Session` life time).
+pub fn new_lz4_multiplexed<'a, A, LB>(
+    node_configs: &ClusterTcpConfig<'a, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<MultiplexedTcpConnectionPool<A>> + Sized,
+{
+    connect_multiplexed(node_configs, load_balancing, Compression::Lz4)
+}
+
+/// Creates new session that will perform queries without any compression. Besides seed
+/// nodes from the cluster config it connects to every node which seeds know about
+/// from `system.peers_v2` or `system.peers`. Connection pools of discovered nodes
+/// are configured with a node template, its address is ignored.
+/// As a parameter it takes:
+/// * cluster config of seed nodes
+/// * config template of discovered nodes
+/// * load balancing strategy (cannot be changed during `Session` life time).
+pub fn new_with_discovery<'a, 'b, A, LB>(
+    seeds: &ClusterTcpConfig<'a, A>,
+    node_template: &NodeTcpConfig<'b, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<TcpConnectionPool<A>> + Sized,
+{
+    connect_tcp_with_discovery(seeds, node_template, load_balancing, Compression::None)
+}
+
+/// Creates new session that will perform queries with Snappy compression. Besides seed
+/// nodes from the cluster config it connects to every node which seeds know about
+/// from `system.peers_v2` or `system.peers`. Connection pools of discovered nodes
+/// are configured with a node template, its address is ignored.
+/// As a parameter it takes:
+/// * cluster config of seed nodes
+/// * config template of discovered nodes
+/// * load balancing strategy (cannot be changed during `Session` life time).
+pub fn new_snappy_with_discovery<'a, 'b, A, LB>(
+    seeds: &ClusterTcpConfig<'a, A>,
+    node_template: &NodeTcpConfig<'b, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<TcpConnectionPool<A>> + Sized,
+{
+    connect_tcp_with_discovery(seeds, node_template, load_balancing, Compression::Snappy)
+}
+
+/// Creates new session that will perform queries with LZ4 compression. Besides seed
+/// nodes from the cluster config it connects to every node which seeds know about
+/// from `system.peers_v2` or `system.peers`. Connection pools of discovered nodes
+/// are configured with a node template, its address is ignored.
+/// As a parameter it takes:
+/// * cluster config of seed nodes
+/// * config template of discovered nodes
+/// * load balancing strategy (cannot be changed during `Session` life time).
+pub fn new_lz4_with_discovery<'a, 'b, A, LB>(
+    seeds: &ClusterTcpConfig<'a, A>,
+    node_template: &NodeTcpConfig<'b, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<TcpConnectionPool<A>> + Sized,
+{
+    connect_tcp_with_discovery(seeds, node_template, load_balancing, Compression::Lz4)
+}
+
 impl<'a, L> Session<L> {
-    /// Returns new event listener.
+    /// Returns new event listener. Events are compressed in the same way as
+    /// responses to session queries, so session's compression should be passed
+    /// to `Listener::start`.
     pub fn listen<A: Authenticator + 'static + Sized>(
         &self,
         node: &str,
@@ -333,13 +976,17 @@ impl<'a, L> Session<L> {
         events: Vec<SimpleServerEvent>,
     ) -> error::Result<(Listener<RefCell<TransportTcp>>, EventStream)> {
         let compression = self.get_compressor();
-        let transport = TransportTcp::new(&node).map(RefCell::new)?;
-
-        startup(&transport, &authenticator)?;
+        let transport = connect_and_startup(
+            || TransportTcp::new(node).map_err(Into::into),
+            &authenticator,
+            compression,
+        )?;
 
-        let query_frame = Frame::new_req_register(events).into_cbytes();
-        transport.borrow_mut().write(query_frame.as_slice())?;
-        parse_frame(&transport, &compression)?;
+        let protocol_version = transport.borrow().protocol_version();
+        let query_frame =
+            Frame::new_req_register(events, protocol_version).encode_framed(compression)?;
+        transport.borrow_mut().write_all(query_frame.as_slice())?;
+        parse_framed(&transport, &compression, protocol_version)?;
 
         Ok(new_listener(transport))
     }
@@ -370,17 +1017,24 @@ where
     let mut nodes: Vec<SslConnectionPool<A>> = Vec::with_capacity(node_configs.0.len());
 
     for node_config in &node_configs.0 {
-        let node_connection_pool = new_ssl_pool(node_config.clone())?;
+        let node_connection_pool = new_ssl_pool(node_config.clone(), compression)?;
         nodes.push(node_connection_pool);
     }
 
     load_balancing.init(nodes);
 
-    Ok(Session {
+    let session = Session {
         load_balancing: Mutex::new(load_balancing),
         event_stream: None,
+        node_factory: None,
+        retry_policy: Box::new(DefaultRetryPolicy::new()),
+        speculative_execution_policy: None,
+        request_timeout: None,
+        custom_type_registry: CustomTypeRegistry::new(),
         compression,
-    })
+    };
+
+    Ok(session.init_metadata())
 }
 
 #[cfg(feature = "ssl")]
@@ -397,33 +1051,61 @@ where
     let mut nodes: Vec<SslConnectionPool<A>> = Vec::with_capacity(node_configs.0.len());
 
     for node_config in &node_configs.0 {
-        let node_connection_pool = new_ssl_pool(node_config.clone())?;
+        let node_connection_pool = new_ssl_pool(node_config.clone(), compression)?;
         nodes.push(node_connection_pool);
     }
 
+    let known = nodes.iter().map(GetAddr::get_addr).collect();
     load_balancing.init(nodes);
 
+    let node_template = NodeSslConfig {
+        addr: "",
+        authenticator: event_src.authenticator.clone(),
+        ssl_connector: event_src.ssl_connector.clone(),
+        max_size: event_src.max_size,
+        min_idle: event_src.min_idle,
+        max_lifetime: event_src.max_lifetime,
+        idle_timeout: event_src.idle_timeout,
+        connection_timeout: event_src.connection_timeout,
+    };
+    let node_factory = NodeFactory::new(known, move |addr: SocketAddr| {
+        let addr = addr.to_string();
+        let node_config = NodeSslConfig {
+            addr: &addr,
+            ..node_template.clone()
+        };
+        new_ssl_pool(node_config, compression)
+    });
+
     let mut session = Session {
         load_balancing: Mutex::new(load_balancing),
         event_stream: None,
+        node_factory: Some(node_factory),
+        retry_policy: Box::new(DefaultRetryPolicy::new()),
+        speculative_execution_policy: None,
+        request_timeout: None,
+        custom_type_registry: CustomTypeRegistry::new(),
         compression,
     };
 
     let (listener, event_stream) = session.listen_non_blocking_ssl(
         (event_src.addr, &event_src.ssl_connector),
         event_src.authenticator,
-        vec![SimpleServerEvent::TopologyChange],
+        vec![
+            SimpleServerEvent::StatusChange,
+            SimpleServerEvent::TopologyChange,
+        ],
     )?;
 
-    ::std::thread::spawn(move || listener.start(&Compression::None));
+    ::std::thread::spawn(move || listener.start(&compression));
 
     session.event_stream = Some(Mutex::new(event_stream));
 
-    Ok(session)
+    Ok(session.init_metadata())
 }
 
-/// Creates new SSL-based session that will perform queries without any compression. `Compression` type
-/// can be changed at any time.
+/// Creates new SSL-based session that will perform queries without any compression. Compression is
+/// negotiated with every connection during its startup.
 /// As a parameter it takes:
 /// * SSL cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
@@ -439,9 +1121,9 @@ where
     connect_ssl_static(node_configs, load_balancing, Compression::None)
 }
 
-/// Creates new SSL-based session that will perform queries without any compression. `Compression` type
-/// can be changed at any time. Once received `TopologyChange` event from event source node it will adjust
-/// a cluster - remove dead nodes.
+/// Creates new SSL-based session that will perform queries without any compression. Compression is
+/// negotiated with every connection during its startup. Once received `TopologyChange` or `StatusChange` event from event source node it will adjust
+/// a cluster - add new and recovered nodes, remove dead ones.
 /// As a parameter it takes:
 /// * SSL cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
@@ -459,15 +1141,15 @@ where
     connect_ssl_dynamic(node_configs, load_balancing, Compression::None, event_src)
 }
 
-/// Creates new SSL-based session that will perform queries with Snappy compression. `Compression` type
-/// can be changed at any time.
+/// Creates new SSL-based session that will perform queries with Snappy compression. Compression is
+/// negotiated with every connection during its startup.
 /// As a parameter it takes:
 /// * SSL cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
 #[cfg(feature = "ssl")]
 pub fn new_snappy_ssl<'a, A, LB>(
     node_configs: &ClusterSslConfig<'a, A>,
-    mut load_balancing: LB,
+    load_balancing: LB,
 ) -> error::Result<Session<LB>>
 where
     A: Authenticator + 'static + Sized,
@@ -476,9 +1158,9 @@ where
     connect_ssl_static(node_configs, load_balancing, Compression::Snappy)
 }
 
-/// Creates new SSL-based session that will perform queries with Snappy compression. `Compression` type
-/// can be changed at any time. Once received `TopologyChange` event from event source node it will adjust
-/// a cluster - remove dead nodes.
+/// Creates new SSL-based session that will perform queries with Snappy compression. Compression is
+/// negotiated with every connection during its startup. Once received `TopologyChange` or `StatusChange` event from event source node it will adjust
+/// a cluster - add new and recovered nodes, remove dead ones.
 /// As a parameter it takes:
 /// * SSL cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
@@ -496,15 +1178,15 @@ where
     connect_ssl_dynamic(node_configs, load_balancing, Compression::Snappy, event_src)
 }
 
-/// Creates new SSL-based session that will perform queries with LZ4 compression. `Compression` type
-/// can be changed at any time.
+/// Creates new SSL-based session that will perform queries with LZ4 compression. Compression is
+/// negotiated with every connection during its startup.
 /// As a parameter it takes:
 /// * SSL cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
 #[cfg(feature = "ssl")]
 pub fn new_lz4_ssl<'a, A, LB>(
     node_configs: &ClusterSslConfig<'a, A>,
-    mut load_balancing: LB,
+    load_balancing: LB,
 ) -> error::Result<Session<LB>>
 where
     A: Authenticator + 'static + Sized,
@@ -513,9 +1195,9 @@ where
     connect_ssl_static(node_configs, load_balancing, Compression::Lz4)
 }
 
-/// Creates new SSL-based session that will perform queries with LZ4 compression. `Compression` type
-/// can be changed at any time. Once received `TopologyChange` event from event source node it will adjust
-/// a cluster - remove dead nodes.
+/// Creates new SSL-based session that will perform queries with LZ4 compression. Compression is
+/// negotiated with every connection during its startup. Once received `TopologyChange` or `StatusChange` event from event source node it will adjust
+/// a cluster - add new and recovered nodes, remove dead ones.
 /// As a parameter it takes:
 /// * SSL cluster config
 /// * load balancing strategy (cannot be changed during `Session` life time).
@@ -533,6 +1215,69 @@ where
     connect_ssl_dynamic(node_configs, load_balancing, Compression::Lz4, event_src)
 }
 
+/// Creates new SSL-based session that will perform queries without any compression. Besides seed
+/// nodes from the cluster config it connects to every node which seeds know about
+/// from `system.peers_v2` or `system.peers`. Connection pools of discovered nodes
+/// are configured with a node template, its address is ignored.
+/// As a parameter it takes:
+/// * SSL cluster config of seed nodes
+/// * SSL config template of discovered nodes
+/// * load balancing strategy (cannot be changed during `Session` life time).
+#[cfg(feature = "ssl")]
+pub fn new_ssl_with_discovery<'a, 'b, A, LB>(
+    seeds: &ClusterSslConfig<'a, A>,
+    node_template: &NodeSslConfig<'b, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<SslConnectionPool<A>> + Sized,
+{
+    connect_ssl_with_discovery(seeds, node_template, load_balancing, Compression::None)
+}
+
+/// Creates new SSL-based session that will perform queries with Snappy compression. Besides seed
+/// nodes from the cluster config it connects to every node which seeds know about
+/// from `system.peers_v2` or `system.peers`. Connection pools of discovered nodes
+/// are configured with a node template, its address is ignored.
+/// As a parameter it takes:
+/// * SSL cluster config of seed nodes
+/// * SSL config template of discovered nodes
+/// * load balancing strategy (cannot be changed during `Session` life time).
+#[cfg(feature = "ssl")]
+pub fn new_snappy_ssl_with_discovery<'a, 'b, A, LB>(
+    seeds: &ClusterSslConfig<'a, A>,
+    node_template: &NodeSslConfig<'b, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<SslConnectionPool<A>> + Sized,
+{
+    connect_ssl_with_discovery(seeds, node_template, load_balancing, Compression::Snappy)
+}
+
+/// Creates new SSL-based session that will perform queries with LZ4 compression. Besides seed
+/// nodes from the cluster config it connects to every node which seeds know about
+/// from `system.peers_v2` or `system.peers`. Connection pools of discovered nodes
+/// are configured with a node template, its address is ignored.
+/// As a parameter it takes:
+/// * SSL cluster config of seed nodes
+/// * SSL config template of discovered nodes
+/// * load balancing strategy (cannot be changed during `Session` life time).
+#[cfg(feature = "ssl")]
+pub fn new_lz4_ssl_with_discovery<'a, 'b, A, LB>(
+    seeds: &ClusterSslConfig<'a, A>,
+    node_template: &NodeSslConfig<'b, A>,
+    load_balancing: LB,
+) -> error::Result<Session<LB>>
+where
+    A: Authenticator + 'static + Sized,
+    LB: LoadBalancingStrategy<SslConnectionPool<A>> + Sized,
+{
+    connect_ssl_with_discovery(seeds, node_template, load_balancing, Compression::Lz4)
+}
+
 /// Returns new SSL-based event listener.
 #[cfg(feature = "ssl")]
 impl<'a, L> Session<L> {
@@ -544,13 +1289,17 @@ impl<'a, L> Session<L> {
     ) -> error::Result<(Listener<RefCell<TransportTls>>, EventStream)> {
         let (addr_ref, ssl_connector_ref) = node;
         let compression = self.get_compressor();
-        let transport = TransportTls::new(addr_ref, ssl_connector_ref).map(RefCell::new)?;
-
-        startup(&transport, &authenticator)?;
+        let transport = connect_and_startup(
+            || TransportTls::new(addr_ref, ssl_connector_ref).map_err(Into::into),
+            &authenticator,
+            compression,
+        )?;
 
-        let query_frame = Frame::new_req_register(events).into_cbytes();
-        transport.borrow_mut().write(query_frame.as_slice())?;
-        parse_frame(&transport, &compression)?;
+        let protocol_version = transport.borrow().protocol_version();
+        let query_frame =
+            Frame::new_req_register(events, protocol_version).encode_framed(compression)?;
+        transport.borrow_mut().write_all(query_frame.as_slice())?;
+        parse_framed(&transport, &compression, protocol_version)?;
 
         Ok(new_listener(transport))
     }
@@ -567,3 +1316,615 @@ impl<'a, L> Session<L> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_response::ResponseBody;
+    use crate::frame::frame_result::ColType;
+    use crate::frame::frame_result::ResResultBody;
+    use crate::frame::parser::{parse_header, HEADER_LEN};
+    use crate::frame::{Flag, IntoBytes, Opcode, ProtocolVersion};
+    use crate::load_balancing::{DcAwareRoundRobin, RoundRobinSync, SingleNode};
+    use crate::query::{
+        BatchQueryBuilder, PreparedQuery, QueryParamsBuilder, QueryResponse, QueryValues,
+    };
+    use crate::retry::{DefaultRetryPolicy, FallthroughRetryPolicy};
+    use crate::speculative_execution::ConstantSpeculativeExecutionPolicy;
+    use crate::test::{
+        rows_response, versioned_response_frame, ScriptedConnectionsManager, ScriptedTransport,
+        SCRIPTED_TRANSPORT_ADDR,
+    };
+    use crate::types::{CBytesShort, CString, CStringList};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    type ScriptedNode = ConnectionPool<ScriptedConnectionsManager>;
+
+    fn scripted_node(addr: &str, to_read: Vec<u8>) -> ScriptedNode {
+        slow_scripted_node(addr, to_read, Duration::from_secs(0))
+    }
+
+    fn slow_scripted_node(addr: &str, to_read: Vec<u8>, read_delay: Duration) -> ScriptedNode {
+        let manager = ScriptedConnectionsManager {
+            to_read,
+            protocol_version: ProtocolVersion::V4,
+            read_delay,
+        };
+        let pool = r2d2::Pool::builder().max_size(1).build(manager).unwrap();
+        ConnectionPool::new(pool, addr.parse().unwrap())
+    }
+
+    fn session_of<LB>(load_balancing: LB) -> Session<LB> {
+        Session {
+            load_balancing: Mutex::new(load_balancing),
+            event_stream: None,
+            node_factory: None,
+            retry_policy: Box::new(FallthroughRetryPolicy::new()),
+            speculative_execution_policy: None,
+            request_timeout: None,
+            custom_type_registry: CustomTypeRegistry::new(),
+            compression: Compression::None,
+        }
+    }
+
+    fn scripted_session(to_read: Vec<u8>) -> Session<SingleNode<ScriptedNode>> {
+        session_of(SingleNode::from(vec![scripted_node(
+            SCRIPTED_TRANSPORT_ADDR,
+            to_read,
+        )]))
+    }
+
+    /// Returns responses of a node of `local_dc` to metadata queries
+    /// when its peers are given together with their datacenters.
+    fn metadata_responses(local_dc: &str, peers: &[([u8; 4], &str)]) -> Vec<u8> {
+        let mut responses = rows_response(
+            &[
+                ("data_center", ColType::Varchar),
+                ("rack", ColType::Varchar),
+                ("tokens", ColType::Set),
+                ("partitioner", ColType::Varchar),
+            ],
+            vec![vec![Some(local_dc.as_bytes().to_vec()), None, None, None]],
+            ProtocolVersion::V4,
+        );
+        responses.extend(rows_response(
+            &[
+                ("peer", ColType::Inet),
+                ("native_address", ColType::Inet),
+                ("native_port", ColType::Int),
+                ("data_center", ColType::Varchar),
+                ("rack", ColType::Varchar),
+                ("tokens", ColType::Set),
+            ],
+            peers
+                .iter()
+                .map(|(peer, peer_dc)| {
+                    vec![
+                        Some(peer.to_vec()),
+                        Some(peer.to_vec()),
+                        Some(9042i32.to_be_bytes().to_vec()),
+                        Some(peer_dc.as_bytes().to_vec()),
+                        None,
+                        None,
+                    ]
+                })
+                .collect(),
+            ProtocolVersion::V4,
+        ));
+        responses
+    }
+
+    #[test]
+    fn executors_return_decoded_responses() {
+        let tracing_id = Uuid::parse_str("5e0c6a40-1d3b-11eb-8b6f-0242ac110002").unwrap();
+        let mut body = tracing_id.as_bytes().to_vec();
+        let warnings = CStringList {
+            list: vec![CString::new("Batch is of unusual size".to_string())],
+        };
+        body.extend(warnings.into_cbytes());
+        // void result
+        body.extend_from_slice(&[0, 0, 0, 1]);
+        let mut response = versioned_response_frame(Opcode::Result, body, ProtocolVersion::V4);
+        let flags = Flag::many_to_cbytes(&vec![Flag::Tracing, Flag::Warning]);
+        response[1] = flags;
+
+        let session = scripted_session(response.repeat(3));
+        let query_params = QueryParamsBuilder::new().finalize();
+        let prepared = PreparedQuery::new(CBytesShort::new(vec![1]), "INSERT".to_string());
+        let batch = BatchQueryBuilder::new()
+            .add_query("INSERT", QueryValues::SimpleValues(vec![]))
+            .finalize()
+            .unwrap();
+
+        let responses: Vec<QueryResponse> = vec![
+            session
+                .query_response_tw("SELECT 1", query_params.clone(), true, true)
+                .unwrap(),
+            session
+                .exec_response_tw(&prepared, query_params, true, true)
+                .unwrap(),
+            session.batch_response_tw(batch, true, true).unwrap(),
+        ];
+        for response in responses {
+            assert_eq!(response.tracing_id, Some(tracing_id));
+            assert_eq!(
+                response.warnings,
+                vec!["Batch is of unusual size".to_string()]
+            );
+            assert!(matches!(response.body, ResponseBody::Result(_)));
+        }
+
+        let transport: r2d2::PooledConnection<ScriptedConnectionsManager> =
+            session.get_connection().unwrap();
+        let transport: &RefCell<ScriptedTransport> = &transport;
+        // requests ask for tracing and warnings
+        assert_eq!(transport.borrow().written[1], flags);
+    }
+
+    /// Returns a response to `USE` query which tells a keyspace name.
+    fn set_keyspace_response(keyspace: &str) -> Vec<u8> {
+        let mut body = vec![0, 0, 0, 3];
+        body.extend(CString::new(keyspace.to_string()).into_cbytes());
+        versioned_response_frame(Opcode::Result, body, ProtocolVersion::V4)
+    }
+
+    fn keyspace_of(response: Frame) -> Option<String> {
+        match response.get_body().unwrap() {
+            ResponseBody::Result(ResResultBody::SetKeyspace(body)) => Some(body.body.as_plain()),
+            _ => None,
+        }
+    }
+
+    fn written(node: &ScriptedNode) -> Vec<u8> {
+        let transport = node.get_pool().get().unwrap();
+        let transport: &RefCell<ScriptedTransport> = &transport;
+        let written = transport.borrow().written.clone();
+        written
+    }
+
+    /// Returns a session which nodes are picked in the order: a node that responds
+    /// slowly and then a node that responds immediately.
+    fn speculative_session(
+        slow: &ScriptedNode,
+        fast: &ScriptedNode,
+    ) -> Session<RoundRobinSync<ScriptedNode>> {
+        session_of(RoundRobinSync::from(vec![fast.clone(), slow.clone()]))
+            .with_speculative_execution_policy(ConstantSpeculativeExecutionPolicy::new(
+                1,
+                Duration::from_millis(10),
+            ))
+    }
+
+    fn use_keyspace(
+        session: &Session<RoundRobinSync<ScriptedNode>>,
+        idempotent: bool,
+    ) -> error::Result<Frame> {
+        let query_params = QueryParamsBuilder::new().idempotent(idempotent).finalize();
+        session.query_with_params("USE ks", query_params)
+    }
+
+    #[test]
+    fn speculative_execution_returns_first_response() {
+        let slow = slow_scripted_node(
+            "127.0.0.1:9042",
+            set_keyspace_response("slow"),
+            Duration::from_millis(50),
+        );
+        let fast = scripted_node("127.0.0.2:9042", set_keyspace_response("fast"));
+        // a session does not have a request timeout, so a default one is used
+        let session = speculative_session(&slow, &fast);
+
+        let response = use_keyspace(&session, true).unwrap();
+
+        assert_eq!(keyspace_of(response), Some("fast".to_string()));
+        assert!(!written(&slow).is_empty());
+    }
+
+    #[test]
+    fn speculative_execution_releases_losing_connections() {
+        let slow = slow_scripted_node("127.0.0.1:9042", vec![], Duration::from_millis(20));
+        let fast = scripted_node("127.0.0.2:9042", set_keyspace_response("fast"));
+        let timeout = Duration::from_millis(100);
+        let session = speculative_session(&slow, &fast).with_request_timeout(timeout);
+
+        let started = Instant::now();
+        let response = use_keyspace(&session, true).unwrap();
+        assert_eq!(keyspace_of(response), Some("fast".to_string()));
+        assert!(started.elapsed() < timeout);
+
+        // the losing execution gives its connection back once it has timed out
+        let transport = slow.get_pool().get_timeout(Duration::from_secs(1)).unwrap();
+        let transport: &RefCell<ScriptedTransport> = &transport;
+        assert!(transport.borrow().is_alive());
+    }
+
+    #[test]
+    fn non_idempotent_requests_are_not_executed_speculatively() {
+        let slow = slow_scripted_node(
+            "127.0.0.1:9042",
+            set_keyspace_response("slow"),
+            Duration::from_millis(20),
+        );
+        let fast = scripted_node("127.0.0.2:9042", set_keyspace_response("fast"));
+        let session = speculative_session(&slow, &fast);
+
+        let response = use_keyspace(&session, false).unwrap();
+
+        assert_eq!(keyspace_of(response), Some("slow".to_string()));
+        assert!(written(&fast).is_empty());
+    }
+
+    fn error_response(error_code: u32, additional_info: &[u8]) -> Vec<u8> {
+        let mut body = error_code.to_be_bytes().to_vec();
+        body.extend(CString::new("".to_string()).into_cbytes());
+        body.extend_from_slice(additional_info);
+        versioned_response_frame(Opcode::Error, body, ProtocolVersion::V4)
+    }
+
+    fn overloaded_response() -> Vec<u8> {
+        error_response(0x1001, &[])
+    }
+
+    /// Returns a read timeout error after all replicas responded but data was not retrieved.
+    fn read_timeout_response() -> Vec<u8> {
+        error_response(0x1200, &[0, 4, 0, 0, 0, 3, 0, 0, 0, 3, 0])
+    }
+
+    /// Returns a session which nodes are picked in the order: `first` and then `second`.
+    fn retry_session(
+        first: &ScriptedNode,
+        second: &ScriptedNode,
+    ) -> Session<RoundRobinSync<ScriptedNode>> {
+        session_of(RoundRobinSync::from(vec![second.clone(), first.clone()]))
+            .with_retry_policy(DefaultRetryPolicy::new())
+    }
+
+    /// Sends a query, an execution of a prepared query and a batch with a given session
+    /// one by one. Every request is started with a first node of the session.
+    fn send_requests(
+        session: &Session<RoundRobinSync<ScriptedNode>>,
+        idempotent: bool,
+    ) -> Vec<error::Result<Frame>> {
+        let query_params = QueryParamsBuilder::new().idempotent(idempotent).finalize();
+        let prepared = PreparedQuery::new(CBytesShort::new(vec![1]), "USE ks".to_string());
+        let batch = BatchQueryBuilder::new()
+            .add_query("INSERT", QueryValues::SimpleValues(vec![]))
+            .idempotent(idempotent)
+            .finalize()
+            .unwrap();
+        let requests: Vec<Box<dyn Fn() -> error::Result<Frame>>> = vec![
+            Box::new(|| session.query_with_params("USE ks", query_params.clone())),
+            Box::new(|| session.exec_with_params(&prepared, query_params.clone())),
+            Box::new(|| session.batch_with_params(batch.clone())),
+        ];
+
+        requests
+            .iter()
+            .map(|send| {
+                let response = send();
+                // a failed request has not been retried with a second node, so it is
+                // skipped to send a next request to a first node again
+                if response.is_err() {
+                    session.load_balancing.lock().unwrap().next();
+                }
+                response
+            })
+            .collect()
+    }
+
+    /// Returns a number of request frames written to a node.
+    fn requests_sent(node: &ScriptedNode) -> usize {
+        let written = written(node);
+        let mut offset = 0;
+        let mut requests = 0;
+        while offset < written.len() {
+            let (_, length) = parse_header(&written[offset..]);
+            offset += HEADER_LEN + length;
+            requests += 1;
+        }
+        requests
+    }
+
+    fn is_overloaded(response: &error::Result<Frame>) -> bool {
+        match response {
+            Err(error) => error.kind() == Some(error::ErrorKind::Overloaded),
+            Ok(_) => false,
+        }
+    }
+
+    #[test]
+    fn retries_idempotent_requests_with_next_node() {
+        let first = scripted_node("127.0.0.1:9042", overloaded_response().repeat(3));
+        let second = scripted_node("127.0.0.2:9042", set_keyspace_response("second").repeat(3));
+        let session = retry_session(&first, &second);
+
+        for response in send_requests(&session, true) {
+            assert_eq!(keyspace_of(response.unwrap()), Some("second".to_string()));
+        }
+        assert_eq!(requests_sent(&first), 3);
+        assert_eq!(requests_sent(&second), 3);
+    }
+
+    #[test]
+    fn does_not_retry_non_idempotent_requests_with_next_node() {
+        let first = scripted_node("127.0.0.1:9042", overloaded_response().repeat(3));
+        let second = scripted_node("127.0.0.2:9042", set_keyspace_response("second").repeat(3));
+        let session = retry_session(&first, &second);
+
+        for response in send_requests(&session, false) {
+            assert!(is_overloaded(&response));
+        }
+        assert_eq!(requests_sent(&first), 3);
+        assert_eq!(requests_sent(&second), 0);
+    }
+
+    #[test]
+    fn retries_with_same_node_if_policy_decides_so() {
+        let mut responses = read_timeout_response();
+        responses.extend(set_keyspace_response("first"));
+        let first = scripted_node("127.0.0.1:9042", responses);
+        let second = scripted_node("127.0.0.2:9042", set_keyspace_response("second"));
+        let session = retry_session(&first, &second);
+
+        let response = use_keyspace(&session, false).unwrap();
+
+        assert_eq!(keyspace_of(response), Some("first".to_string()));
+        assert!(written(&second).is_empty());
+    }
+
+    #[test]
+    fn returns_errors_if_policy_does_not_retry() {
+        let first = scripted_node("127.0.0.1:9042", overloaded_response());
+        let second = scripted_node("127.0.0.2:9042", set_keyspace_response("second"));
+        let session = retry_session(&first, &second).with_retry_policy(FallthroughRetryPolicy);
+
+        let response = use_keyspace(&session, true);
+
+        assert!(is_overloaded(&response));
+        assert!(written(&second).is_empty());
+    }
+
+    #[test]
+    fn refreshes_metadata_with_dc_aware_load_balancer() {
+        let mut load_balancing = DcAwareRoundRobin::new("dc1");
+        load_balancing.init(vec![
+            scripted_node(
+                "127.0.0.1:9042",
+                metadata_responses("dc2", &[([127, 0, 0, 2], "dc1")]),
+            ),
+            scripted_node(
+                "127.0.0.2:9042",
+                metadata_responses("dc1", &[([127, 0, 0, 1], "dc2")]),
+            ),
+        ]);
+        let session = session_of(load_balancing);
+
+        session.refresh_metadata().unwrap();
+
+        let load_balancing = session.load_balancing.lock().unwrap();
+        let local_addr: SocketAddr = "127.0.0.2:9042".parse().unwrap();
+        for _ in 0..3 {
+            assert_eq!(
+                load_balancing.next().map(GetAddr::get_addr),
+                Some(local_addr)
+            );
+        }
+    }
+
+    #[test]
+    fn discovers_peers_of_seed_nodes() {
+        // metadata cannot be fetched from the first seed, so the second one is used
+        let seeds = vec![
+            scripted_node("127.0.0.1:9042", vec![]),
+            scripted_node(
+                "127.0.0.2:9042",
+                metadata_responses(
+                    "dc1",
+                    &[
+                        ([127, 0, 0, 1], "dc1"),
+                        ([127, 0, 0, 3], "dc1"),
+                        ([127, 0, 0, 4], "dc2"),
+                    ],
+                ),
+            ),
+        ];
+        let connected = RefCell::new(vec![]);
+        let new_pool = |addr: &str| {
+            connected.borrow_mut().push(addr.to_string());
+            if addr == "127.0.0.4:9042" {
+                return Err(error::Error::from("Connection refused"));
+            }
+            Ok(scripted_node(addr, vec![]))
+        };
+
+        let session =
+            connect_with_discovery(seeds, new_pool, RoundRobinSync::new(), Compression::None)
+                .unwrap();
+
+        // seeds are not connected to again and an unreachable peer is skipped
+        assert_eq!(
+            connected.into_inner(),
+            vec!["127.0.0.3:9042".to_string(), "127.0.0.4:9042".to_string()]
+        );
+        let addrs: HashSet<String> = vec!["127.0.0.1:9042", "127.0.0.2:9042", "127.0.0.3:9042"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(node_addrs(&session), addrs);
+    }
+
+    fn event_response(event_type: &str, change_type: &str, ip: [u8; 4]) -> Vec<u8> {
+        let mut body = CString::new(event_type.to_string()).into_cbytes();
+        body.extend(CString::new(change_type.to_string()).into_cbytes());
+        body.push(4);
+        body.extend_from_slice(&ip);
+        body.extend_from_slice(&9042i32.to_be_bytes());
+        versioned_response_frame(Opcode::Event, body, ProtocolVersion::V4)
+    }
+
+    fn event_stream(events: Vec<Vec<u8>>) -> Option<Mutex<EventStreamNonBlocking>> {
+        let mut transport = ScriptedTransport::new(events.concat());
+        transport.set_protocol_version(ProtocolVersion::V4);
+        let (listener, event_stream) = new_listener(RefCell::new(transport));
+        // a listener stops once all events are read
+        assert!(listener.start(&Compression::None).is_err());
+        Some(Mutex::new(event_stream.into()))
+    }
+
+    /// Returns a session of nodes `127.0.0.1` and `127.0.0.2` which connects to new
+    /// nodes after `connect_delay`. First `failures` attempts to connect fail.
+    fn dynamic_session(
+        connect_delay: Duration,
+        failures: usize,
+    ) -> Session<RoundRobinSync<ScriptedNode>> {
+        let nodes = vec![
+            scripted_node("127.0.0.1:9042", vec![]),
+            scripted_node("127.0.0.2:9042", vec![]),
+        ];
+        let known = nodes.iter().map(GetAddr::get_addr).collect();
+        let failures = AtomicUsize::new(failures);
+        let node_factory = NodeFactory::new(known, move |addr: SocketAddr| {
+            thread::sleep(connect_delay);
+            let fail = failures.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| {
+                failures.checked_sub(1)
+            });
+            match fail {
+                Ok(_) => Err(error::Error::from("Connection refused")),
+                Err(_) => Ok(scripted_node(&addr.to_string(), vec![])),
+            }
+        });
+
+        Session {
+            node_factory: Some(node_factory),
+            ..session_of(RoundRobinSync::from(nodes))
+        }
+    }
+
+    fn node_addrs(session: &Session<RoundRobinSync<ScriptedNode>>) -> HashSet<String> {
+        let load_balancing = session.load_balancing.lock().unwrap();
+        (0..4)
+            .filter_map(|_| load_balancing.next())
+            .map(|node| node.get_addr().to_string())
+            .collect()
+    }
+
+    /// Processes events of a session until a condition is met.
+    fn wait_until<F>(session: &Session<RoundRobinSync<ScriptedNode>>, condition: F)
+    where
+        F: Fn() -> bool,
+    {
+        let started = Instant::now();
+        while !condition() {
+            assert!(started.elapsed() < Duration::from_secs(1));
+            session.process_events().unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Processes events of a session until its load balancer has exactly given nodes.
+    fn wait_for_nodes(session: &Session<RoundRobinSync<ScriptedNode>>, addrs: &[&str]) {
+        let addrs: HashSet<String> = addrs.iter().map(|addr| addr.to_string()).collect();
+        wait_until(session, || node_addrs(session) == addrs);
+    }
+
+    #[test]
+    fn applies_topology_and_status_changes() {
+        let mut session = dynamic_session(Duration::from_secs(0), 0);
+
+        session.event_stream = event_stream(vec![
+            event_response("TOPOLOGY_CHANGE", "NEW_NODE", [127, 0, 0, 3]),
+            event_response("TOPOLOGY_CHANGE", "REMOVED_NODE", [127, 0, 0, 2]),
+            event_response("STATUS_CHANGE", "DOWN", [127, 0, 0, 1]),
+        ]);
+        wait_for_nodes(&session, &["127.0.0.3:9042"]);
+
+        session.event_stream =
+            event_stream(vec![event_response("STATUS_CHANGE", "UP", [127, 0, 0, 1])]);
+        wait_for_nodes(&session, &["127.0.0.1:9042", "127.0.0.3:9042"]);
+    }
+
+    #[test]
+    fn does_not_block_requests_while_connecting_to_nodes() {
+        let mut session = dynamic_session(Duration::from_millis(200), 0);
+        session.event_stream = event_stream(vec![event_response(
+            "TOPOLOGY_CHANGE",
+            "NEW_NODE",
+            [127, 0, 0, 3],
+        )]);
+
+        let started = Instant::now();
+        let _: r2d2::PooledConnection<ScriptedConnectionsManager> =
+            session.get_connection().unwrap();
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(node_addrs(&session).len(), 2);
+
+        wait_for_nodes(
+            &session,
+            &["127.0.0.1:9042", "127.0.0.2:9042", "127.0.0.3:9042"],
+        );
+    }
+
+    #[test]
+    fn retries_nodes_which_could_not_be_connected_to() {
+        let mut session = dynamic_session(Duration::from_secs(0), 1);
+        session.event_stream = event_stream(vec![event_response(
+            "TOPOLOGY_CHANGE",
+            "NEW_NODE",
+            [127, 0, 0, 3],
+        )]);
+        let addr: SocketAddr = "127.0.0.3:9042".parse().unwrap();
+        let node_factory = session.node_factory.as_ref().unwrap();
+
+        wait_until(&session, || {
+            let nodes = node_factory.nodes.lock().unwrap();
+            nodes.unreachable.contains_key(&addr)
+        });
+        assert_eq!(node_addrs(&session).len(), 2);
+
+        // the node is due to be tried again
+        node_factory
+            .nodes
+            .lock()
+            .unwrap()
+            .unreachable
+            .insert(addr, Instant::now());
+        wait_for_nodes(
+            &session,
+            &["127.0.0.1:9042", "127.0.0.2:9042", "127.0.0.3:9042"],
+        );
+    }
+
+    #[test]
+    fn does_not_add_nodes_removed_while_being_connected_to() {
+        let mut session = dynamic_session(Duration::from_millis(50), 0);
+        session.event_stream = event_stream(vec![
+            event_response("TOPOLOGY_CHANGE", "NEW_NODE", [127, 0, 0, 3]),
+            event_response("TOPOLOGY_CHANGE", "REMOVED_NODE", [127, 0, 0, 3]),
+        ]);
+
+        session.process_events().unwrap();
+        thread::sleep(Duration::from_millis(100));
+        wait_for_nodes(&session, &["127.0.0.1:9042", "127.0.0.2:9042"]);
+    }
+
+    #[test]
+    fn tracks_unreachable_nodes() {
+        let addr: SocketAddr = "127.0.0.1:9042".parse().unwrap();
+        let now = Instant::now();
+        let mut nodes = Nodes::default();
+
+        nodes.failed(addr, now);
+        assert!(nodes.to_retry(now).is_empty());
+        assert_eq!(nodes.to_retry(now + NODE_RETRY_DELAY), vec![addr]);
+
+        nodes.added(addr);
+        assert!(nodes.known.contains(&addr));
+        assert!(nodes.to_retry(now + NODE_RETRY_DELAY).is_empty());
+
+        nodes.failed(addr, now);
+        nodes.removed(addr);
+        assert!(!nodes.known.contains(&addr));
+        assert!(nodes.to_retry(now + NODE_RETRY_DELAY).is_empty());
+    }
+}
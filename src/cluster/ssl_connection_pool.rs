@@ -1,17 +1,17 @@
 use openssl::ssl::SslConnector;
-use r2d2::{Builder, ManageConnection, Pool};
+use r2d2::{Builder, ManageConnection};
 use std::cell::RefCell;
 use std::error::Error;
 use std::io::Write;
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::ToSocketAddrs;
 
 use crate::authenticators::Authenticator;
 use crate::cluster::ConnectionPool;
-use crate::cluster::{startup, NodeSslConfig};
+use crate::cluster::{connect_and_startup, NodeSslConfig};
 use crate::compression::Compression;
 use crate::error;
-use crate::frame::parser::parse_frame;
-use crate::frame::{Frame, IntoBytes};
+use crate::frame::parser::parse_framed;
+use crate::frame::Frame;
 use crate::transport::CDRSTransport;
 use crate::transport::TransportTls;
 
@@ -21,13 +21,16 @@ pub type SslConnectionPool<A> = ConnectionPool<SslConnectionsManager<A>>;
 /// `r2d2::Pool` of SSL-based CDRS connections.
 ///
 /// Used internally for SSL Session for holding connections to a specific Cassandra node.
+/// Every connection negotiates given `compression` during startup.
 pub fn new_ssl_pool<'a, A: Authenticator + Send + Sync + 'static>(
     node_config: NodeSslConfig<'a, A>,
+    compression: Compression,
 ) -> error::Result<SslConnectionPool<A>> {
     let manager = SslConnectionsManager::new(
         node_config.addr,
         node_config.authenticator,
         node_config.ssl_connector,
+        compression,
     );
 
     let pool = Builder::new()
@@ -53,14 +56,21 @@ pub struct SslConnectionsManager<A> {
     addr: String,
     ssl_connector: SslConnector,
     auth: A,
+    compression: Compression,
 }
 
 impl<A> SslConnectionsManager<A> {
-    pub fn new<S: ToString>(addr: S, auth: A, ssl_connector: SslConnector) -> Self {
+    pub fn new<S: ToString>(
+        addr: S,
+        auth: A,
+        ssl_connector: SslConnector,
+        compression: Compression,
+    ) -> Self {
         SslConnectionsManager {
             addr: addr.to_string(),
             auth,
             ssl_connector,
+            compression,
         }
     }
 }
@@ -70,17 +80,20 @@ impl<A: Authenticator + 'static + Send + Sync> ManageConnection for SslConnectio
     type Error = error::Error;
 
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        let transport = RefCell::new(TransportTls::new(&self.addr, &self.ssl_connector)?);
-        startup(&transport, &self.auth)?;
-
-        Ok(transport)
+        connect_and_startup(
+            || TransportTls::new(&self.addr, &self.ssl_connector).map_err(Into::into),
+            &self.auth,
+            self.compression,
+        )
     }
 
     fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        let options_frame = Frame::new_req_options().into_cbytes();
-        conn.borrow_mut().write(options_frame.as_slice())?;
+        let protocol_version = conn.borrow().protocol_version();
+        let options_frame =
+            Frame::new_req_options(protocol_version).encode_framed(self.compression)?;
+        conn.borrow_mut().write_all(options_frame.as_slice())?;
 
-        parse_frame(conn, &Compression::None {}).map(|_| ())
+        parse_framed(conn, &self.compression, protocol_version).map(|_| ())
     }
 
     fn has_broken(&self, conn: &mut Self::Connection) -> bool {
@@ -0,0 +1,51 @@
+/// The biggest stream id a client may use. Negative stream ids are reserved
+/// by the protocol for server initiated frames (events).
+pub const MAX_STREAM_ID: u16 = 0x7FFF;
+
+/// Allocator of stream ids for a single connection. Every request which is
+/// in flight on a connection should own a unique stream id so a response could be
+/// correlated with its request.
+#[derive(Debug)]
+pub(crate) struct StreamIds {
+    free: Vec<u16>,
+}
+
+impl StreamIds {
+    /// Creates new allocator that hands out ids from `0` to `max` inclusively.
+    pub fn new(max: u16) -> Self {
+        StreamIds {
+            free: (0..=max).rev().collect(),
+        }
+    }
+
+    /// Takes a free stream id if there is any.
+    pub fn acquire(&mut self) -> Option<u16> {
+        self.free.pop()
+    }
+
+    /// Returns stream id back so it could be reused by further requests.
+    pub fn release(&mut self, id: u16) {
+        self.free.push(id);
+    }
+}
+
+impl Default for StreamIds {
+    fn default() -> Self {
+        StreamIds::new(MAX_STREAM_ID)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquire_release() {
+        let mut ids = StreamIds::new(1);
+        assert_eq!(ids.acquire(), Some(0));
+        assert_eq!(ids.acquire(), Some(1));
+        assert_eq!(ids.acquire(), None);
+        ids.release(0);
+        assert_eq!(ids.acquire(), Some(0));
+    }
+}
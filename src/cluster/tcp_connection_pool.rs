@@ -1,30 +1,68 @@
 use r2d2::{Builder, ManageConnection};
 use std::cell::RefCell;
-use std::io;
 use std::io::Write;
 use std::net::ToSocketAddrs;
 
-use crate::authenticators::Authenticator;
-use crate::cluster::ConnectionPool;
+use crate::authenticators::{check_authenticator, evaluate_auth_response, Authenticator};
 use crate::cluster::NodeTcpConfig;
+use crate::cluster::{ConnectionPool, MultiplexedConnectionsManager};
 use crate::compression::Compression;
 use crate::error;
-use crate::frame::parser::parse_frame;
-use crate::frame::{Frame, IntoBytes, Opcode};
+use crate::frame::parser::{parse_frame, parse_framed};
+use crate::frame::{Frame, Opcode, ProtocolVersion};
 use crate::transport::{CDRSTransport, TransportTcp};
 
 /// Shortcut for `r2d2::Pool` type of TCP-based CDRS connections.
 pub type TcpConnectionPool<A> = ConnectionPool<TcpConnectionsManager<A>>;
 
+/// Shortcut for `r2d2::Pool` type of transports which share a single TCP-based
+/// CDRS connection.
+pub type MultiplexedTcpConnectionPool<A> =
+    ConnectionPool<MultiplexedConnectionsManager<TransportTcp, TcpConnectionsManager<A>>>;
+
 /// `r2d2::Pool` of TCP-based CDRS connections.
 ///
 /// Used internally for TCP Session for holding connections to a specific Cassandra node.
+/// Every connection negotiates given `compression` during startup.
 pub fn new_tcp_pool<'a, A: Authenticator + Send + Sync + 'static>(
     node_config: NodeTcpConfig<'a, A>,
+    compression: Compression,
 ) -> error::Result<TcpConnectionPool<A>> {
-    let manager =
-        TcpConnectionsManager::new(node_config.addr.to_string(), node_config.authenticator);
+    let manager = TcpConnectionsManager::new(
+        node_config.addr.to_string(),
+        node_config.authenticator.clone(),
+        compression,
+    );
+
+    build_pool(manager, node_config)
+}
+
+/// `r2d2::Pool` of transports which send requests over a single TCP-based CDRS
+/// connection to a specific Cassandra node, so `max_size` of a node config limits
+/// a number of concurrent requests rather than a number of sockets.
+///
+/// The connection negotiates given `compression` during startup and is created
+/// again once it is broken.
+pub fn new_multiplexed_tcp_pool<'a, A: Authenticator + Send + Sync + 'static>(
+    node_config: NodeTcpConfig<'a, A>,
+    compression: Compression,
+) -> error::Result<MultiplexedTcpConnectionPool<A>> {
+    let manager = TcpConnectionsManager::new(
+        node_config.addr.to_string(),
+        node_config.authenticator.clone(),
+        compression,
+    );
 
+    build_pool(
+        MultiplexedConnectionsManager::new(manager, compression),
+        node_config,
+    )
+}
+
+fn build_pool<'a, A, M: ManageConnection>(
+    manager: M,
+    node_config: NodeTcpConfig<'a, A>,
+) -> error::Result<ConnectionPool<M>> {
     let pool = Builder::new()
         .max_size(node_config.max_size)
         .min_idle(node_config.min_idle)
@@ -40,20 +78,22 @@ pub fn new_tcp_pool<'a, A: Authenticator + Send + Sync + 'static>(
         .next()
         .ok_or_else(|| error::Error::from("Cannot parse address"))?;
 
-    Ok(TcpConnectionPool::new(pool, addr))
+    Ok(ConnectionPool::new(pool, addr))
 }
 
 /// `r2d2` connection manager.
 pub struct TcpConnectionsManager<A> {
     addr: String,
     auth: A,
+    compression: Compression,
 }
 
 impl<A> TcpConnectionsManager<A> {
-    pub fn new<S: ToString>(addr: S, auth: A) -> Self {
+    pub fn new<S: ToString>(addr: S, auth: A, compression: Compression) -> Self {
         TcpConnectionsManager {
             addr: addr.to_string(),
             auth,
+            compression,
         }
     }
 }
@@ -63,17 +103,20 @@ impl<A: Authenticator + 'static + Send + Sync> ManageConnection for TcpConnectio
     type Error = error::Error;
 
     fn connect(&self) -> Result<Self::Connection, Self::Error> {
-        let transport = RefCell::new(TransportTcp::new(&self.addr)?);
-        startup(&transport, &self.auth)?;
-
-        Ok(transport)
+        connect_and_startup(
+            || TransportTcp::new(&self.addr).map_err(Into::into),
+            &self.auth,
+            self.compression,
+        )
     }
 
     fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
-        let options_frame = Frame::new_req_options().into_cbytes();
-        conn.borrow_mut().write(options_frame.as_slice())?;
+        let protocol_version = conn.borrow().protocol_version();
+        let options_frame =
+            Frame::new_req_options(protocol_version).encode_framed(self.compression)?;
+        conn.borrow_mut().write_all(options_frame.as_slice())?;
 
-        parse_frame(conn, &Compression::None {}).map(|_| ())
+        parse_framed(conn, &self.compression, protocol_version).map(|_| ())
     }
 
     fn has_broken(&self, conn: &mut Self::Connection) -> bool {
@@ -81,16 +124,62 @@ impl<A: Authenticator + 'static + Send + Sync> ManageConnection for TcpConnectio
     }
 }
 
+/// Creates a transport with `connect` and starts it up. The highest protocol version
+/// is attempted first. If a server does not support it, the transport is created
+/// again and started up with a lower version the server has suggested.
+pub fn connect_and_startup<T, A, F>(
+    connect: F,
+    session_authenticator: &A,
+    compression: Compression,
+) -> error::Result<RefCell<T>>
+where
+    T: CDRSTransport + 'static,
+    A: Authenticator + 'static + Sized,
+    F: Fn() -> error::Result<T>,
+{
+    let mut protocol_version = ProtocolVersion::HIGHEST;
+
+    loop {
+        let transport = RefCell::new(connect()?);
+        transport
+            .borrow_mut()
+            .set_protocol_version(protocol_version);
+
+        let err = match startup(&transport, session_authenticator, compression) {
+            Ok(()) => return Ok(transport),
+            Err(err) => err,
+        };
+        match err
+            .server_error()
+            .and_then(|err| protocol_version.downgrade(err))
+        {
+            Some(lower_version) => {
+                debug!(
+                    "Protocol version {:?} is not supported, trying {:?}",
+                    protocol_version, lower_version
+                );
+                protocol_version = lower_version;
+            }
+            None => return Err(err.with_node(transport.borrow().addr())),
+        }
+    }
+}
+
+/// Makes a transport ready for querying: sends `STARTUP` request which asks a server
+/// to use given `compression` and authenticates if the server requires that. The request
+/// is sent with a protocol version stored in the transport.
 pub fn startup<'b, T: CDRSTransport + 'static, A: Authenticator + 'static + Sized>(
     transport: &RefCell<T>,
     session_authenticator: &'b A,
+    compression: Compression,
 ) -> error::Result<()> {
-    let ref mut compression = Compression::None;
-    let startup_frame = Frame::new_req_startup(compression.as_str()).into_cbytes();
+    let protocol_version = transport.borrow().protocol_version();
+    let startup_frame =
+        Frame::new_req_startup(compression.as_str(), protocol_version).encode_with(compression)?;
 
-    transport.borrow_mut().write(startup_frame.as_slice())?;
+    transport.borrow_mut().write_all(startup_frame.as_slice())?;
 
-    let start_response = parse_frame(transport, compression)?;
+    let start_response = parse_frame(transport, &compression)?;
 
     if start_response.opcode == Opcode::Ready {
         return Ok(());
@@ -98,52 +187,171 @@ pub fn startup<'b, T: CDRSTransport + 'static, A: Authenticator + 'static + Size
 
     if start_response.opcode == Opcode::Authenticate {
         let body = start_response.get_body()?;
-        let authenticator = body.get_authenticator().expect(
-            "Cassandra Server did communicate that it neededs
-                authentication but the auth schema was missing in the body response",
-        );
+        let authenticator = body.get_authenticator().ok_or_else(|| {
+            error::Error::InvalidFrame(
+                "Cassandra Server did communicate that it neededs authentication \
+                 but the auth schema was missing in the body response"
+                    .to_string(),
+            )
+        })?;
+
+        check_authenticator(session_authenticator, authenticator)?;
 
-        // This creates a new scope; avoiding a clone
-        // and we check whether
-        // 1. any authenticators has been passed in by client and if not send error back
-        // 2. authenticator is provided by the client and `auth_scheme` presented by
-        //      the server and client are same if not send error back
-        // 3. if it falls through it means the preliminary conditions are true
-
-        let auth_check = session_authenticator
-            .get_cassandra_name()
-            .ok_or(error::Error::General(
-                "No authenticator was provided".to_string(),
-            ))
-            .map(|auth| {
-                if authenticator != auth {
-                    let io_err = io::Error::new(
-                        io::ErrorKind::NotFound,
-                        format!(
-                            "Unsupported type of authenticator. {:?} got,
-                             but {} is supported.",
-                            authenticator, auth
-                        ),
-                    );
-                    return Err(error::Error::Io(io_err));
-                }
-                Ok(())
-            });
-
-        if let Err(err) = auth_check {
-            return Err(err);
+        let mut sasl_authenticator = session_authenticator.new_sasl_authenticator();
+        let mut auth_token_bytes = sasl_authenticator.initial_response();
+        loop {
+            transport.borrow_mut().write_all(
+                Frame::new_req_auth_response(auth_token_bytes, protocol_version)
+                    .encode_framed(compression)?
+                    .as_slice(),
+            )?;
+            let auth_response = parse_framed(transport, &compression, protocol_version)?;
+
+            match evaluate_auth_response(sasl_authenticator.as_mut(), &auth_response)? {
+                Some(token) => auth_token_bytes = token,
+                None => return Ok(()),
+            }
         }
+    }
+
+    Err(error::Error::InvalidFrame(format!(
+        "Unexpected response to startup {:?}",
+        start_response.opcode
+    )))
+}
 
-        let auth_token_bytes = session_authenticator.get_auth_token();
-        transport.borrow_mut().write(
-            Frame::new_req_auth_response(auth_token_bytes)
-                .into_cbytes()
-                .as_slice(),
-        )?;
-        parse_frame(transport, compression)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authenticators::{NoneAuthenticator, StaticPasswordAuthenticator};
+    use crate::frame::AsByte;
+    use crate::test::{
+        reset_stream_ids, response_frame, unsupported_version_error, versioned_response_frame,
+        ChallengedAuthenticator, ScriptedTransport, SCRIPTED_TRANSPORT_ADDR,
+    };
+    use std::cell::Cell;
 
-        return Ok(());
+    #[test]
+    fn startup_negotiates_compression() {
+        let fixtures = vec![
+            (Compression::None, "frame/startup/none"),
+            (Compression::Lz4, "frame/startup/lz4"),
+            (Compression::Snappy, "frame/startup/snappy"),
+        ];
+
+        for (compression, fixture) in fixtures {
+            let ready = response_frame(Opcode::Ready, vec![]);
+            let transport = RefCell::new(ScriptedTransport::new(ready));
+            transport
+                .borrow_mut()
+                .set_protocol_version(ProtocolVersion::V4);
+
+            startup(&transport, &NoneAuthenticator, compression).unwrap();
+
+            let written = reset_stream_ids(transport.into_inner().written);
+            assert_eq!(written, fixture!(fixture).unwrap(), "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn startup_answers_auth_challenges() {
+        let server_finals: Vec<&[u8]> = vec![ChallengedAuthenticator::SERVER_FINAL, b"forged"];
+
+        for server_final in server_finals {
+            let responses = ChallengedAuthenticator::server_responses(server_final)
+                .into_iter()
+                .flat_map(|(opcode, body)| {
+                    versioned_response_frame(opcode, body, ProtocolVersion::V4)
+                })
+                .collect();
+            let transport = RefCell::new(ScriptedTransport::new(responses));
+            transport
+                .borrow_mut()
+                .set_protocol_version(ProtocolVersion::V4);
+
+            let result = startup(&transport, &ChallengedAuthenticator, Compression::None);
+
+            assert_eq!(
+                result.is_ok(),
+                server_final == ChallengedAuthenticator::SERVER_FINAL
+            );
+            let written = transport.into_inner().written;
+            assert!(written.windows(12).any(|bytes| bytes == b"client-final"));
+        }
+    }
+
+    #[test]
+    fn startup_rejects_unsupported_authenticator() {
+        let (opcode, body) = ChallengedAuthenticator::server_responses(&[]).remove(0);
+        let transport = RefCell::new(ScriptedTransport::new(response_frame(opcode, body)));
+
+        let result = startup(
+            &transport,
+            &StaticPasswordAuthenticator::new("foo", "bar"),
+            Compression::None,
+        );
+
+        assert!(matches!(
+            result,
+            Err(error::Error::UnsupportedAuthenticator(ref name))
+                if name == ChallengedAuthenticator::NAME
+        ));
+    }
+
+    #[test]
+    fn connect_and_startup_downgrades_protocol_version() {
+        let highest_version = ProtocolVersion::HIGHEST;
+        let supported_version = highest_version.lower().unwrap();
+        let attempts = Cell::new(0);
+
+        let transport = connect_and_startup(
+            || {
+                attempts.set(attempts.get() + 1);
+                let response = if attempts.get() == 1 {
+                    versioned_response_frame(
+                        Opcode::Error,
+                        unsupported_version_error(highest_version, supported_version),
+                        supported_version,
+                    )
+                } else {
+                    versioned_response_frame(Opcode::Ready, vec![], supported_version)
+                };
+                Ok(ScriptedTransport::new(response))
+            },
+            &NoneAuthenticator,
+            Compression::None,
+        )
+        .unwrap();
+
+        assert_eq!(attempts.get(), 2);
+        assert_eq!(transport.borrow().protocol_version(), supported_version);
+        assert_eq!(transport.borrow().written[0], supported_version.as_byte());
     }
 
-    unreachable!();
+    #[test]
+    fn connect_and_startup_returns_other_errors() {
+        let attempts = Cell::new(0);
+        let error = versioned_response_frame(
+            Opcode::Error,
+            vec![0, 0, 0, 0x0A, 0, 3, b'o', b'o', b'p'],
+            ProtocolVersion::default(),
+        );
+
+        let result = connect_and_startup(
+            || {
+                attempts.set(attempts.get() + 1);
+                Ok(ScriptedTransport::new(error.clone()))
+            },
+            &NoneAuthenticator,
+            Compression::None,
+        );
+
+        let err = result.err().unwrap();
+        assert_eq!(err.kind(), Some(error::ErrorKind::Protocol));
+        assert_eq!(
+            err.node_addr(),
+            Some(SCRIPTED_TRANSPORT_ADDR.parse().unwrap())
+        );
+        assert_eq!(attempts.get(), 1);
+    }
 }
@@ -108,10 +108,7 @@ impl Compression {
     ///     let lz4_compression = Compression::Lz4;
     ///     let bytes = String::from("Hello World").into_bytes().to_vec();
     ///     let encoded = lz4_compression.encode(bytes.clone()).unwrap();
-    ///     let len = encoded.len() as u8;
-    ///     let mut input = vec![0, 0, 0, len];
-    ///     input.extend_from_slice(encoded.as_slice());
-    ///     assert_eq!(lz4_compression.decode(input).unwrap(), bytes);
+    ///     assert_eq!(lz4_compression.decode(encoded).unwrap(), bytes);
     /// ```
     pub fn decode(&self, bytes: Vec<u8>) -> Result<Vec<u8>> {
         match *self {
@@ -145,12 +142,22 @@ impl Compression {
     }
 
     fn encode_lz4(bytes: Vec<u8>) -> Result<Vec<u8>> {
-        Ok(lz4::compress(bytes.as_slice()))
+        // prepend uncompressed length in accordance to
+        // https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L805
+        let mut encoded = (bytes.len() as u32).to_be_bytes().to_vec();
+        encoded.extend_from_slice(lz4::compress(bytes.as_slice()).as_slice());
+        Ok(encoded)
     }
 
     fn decode_lz4(bytes: Vec<u8>) -> Result<Vec<u8>> {
         // skip first 4 bytes in accordance to
         // https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L805
+        if bytes.len() < 4 {
+            return Err(CompressionError::Lz4(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Lz4 compressed body is too short",
+            )));
+        }
         lz4::decompress(&bytes[4..]).map_err(CompressionError::Lz4)
     }
 }
@@ -229,10 +236,8 @@ mod tests {
         let lz4_compression = Compression::Lz4;
         let bytes = String::from("Hello World").into_bytes().to_vec();
         let encoded = lz4_compression.encode(bytes.clone()).unwrap();
-        let len = encoded.len() as u8;
-        let mut input = vec![0, 0, 0, len];
-        input.extend_from_slice(encoded.as_slice());
-        assert_eq!(lz4_compression.decode(input).unwrap(), bytes);
+        assert_eq!(&encoded[..4], &[0, 0, 0, 11]);
+        assert_eq!(lz4_compression.decode(encoded).unwrap(), bytes);
     }
 
     #[test]
@@ -253,10 +258,11 @@ mod tests {
     }
 
     #[test]
-    fn test_compression_encode_lz4_with_invalid_input() {
+    fn test_compression_decode_lz4_with_invalid_input() {
         let lz4_compression = Compression::Lz4;
         let bytes: Vec<u8> = vec![0x7f, 0x7f, 0x7f, 0x7f, 0x7f];
-        let encoded = lz4_compression.encode(bytes.clone()).unwrap();
+        let encoded = lz4::compress(bytes.as_slice());
+        // uncompressed length is missing
         let decode = lz4_compression.decode(encoded);
         assert_eq!(decode.is_err(), true);
     }
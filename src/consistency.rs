@@ -71,6 +71,16 @@ pub enum Consistency {
     Unknown,
 }
 
+impl Consistency {
+    /// Checks if consistency level is satisfied by nodes of a coordinator's data center only.
+    pub fn is_dc_local(&self) -> bool {
+        matches!(
+            self,
+            Consistency::LocalQuorum | Consistency::LocalSerial | Consistency::LocalOne
+        )
+    }
+}
+
 impl Default for Consistency {
     fn default() -> Consistency {
         Consistency::One
@@ -164,6 +174,15 @@ mod tests {
         assert_eq!(Consistency::Unknown.into_cbytes(), &[0, 99]);
     }
 
+    #[test]
+    fn test_consistency_is_dc_local() {
+        assert!(Consistency::LocalQuorum.is_dc_local());
+        assert!(Consistency::LocalSerial.is_dc_local());
+        assert!(Consistency::LocalOne.is_dc_local());
+        assert!(!Consistency::Quorum.is_dc_local());
+        assert!(!Consistency::One.is_dc_local());
+    }
+
     #[test]
     fn test_consistency_from() {
         assert_eq!(Consistency::from(0), Consistency::Any);
@@ -2,23 +2,30 @@ use std::error;
 use std::fmt;
 use std::fmt::Display;
 use std::io;
+use std::net::SocketAddr;
 use std::result;
 use std::string::FromUtf8Error;
+use std::time::Duration;
 
 use crate::compression::CompressionError;
 use crate::frame::frame_error::CDRSError;
+pub use crate::frame::frame_error::ErrorKind;
 use uuid::Error as UUIDError;
 
 pub type Result<T> = result::Result<T, Error>;
 
 /// CDRS custom error type. CDRS expects two types of error - errors returned by Server
-/// and internal erros occured within the driver itself. Ocassionaly `io::Error`
-/// is a type that represent internal error because due to implementation IO errors only
-/// can be raised by CDRS driver. `Server` error is an error which are ones returned by
+/// and internal erros occured within the driver itself. `Io` errors are raised by
+/// connections to nodes, while malformed data received from them is reported as
+/// `InvalidFrame`. `Server` error is an error which are ones returned by
 /// a Server via result error frames.
+///
+/// Errors of requests sent by sessions are wrapped into `Node` which carries an address
+/// of a node that produced an error. Use methods like `kind` or `is_timeout` to classify
+/// an error regardless of whether it is wrapped.
 #[derive(Debug)]
 pub enum Error {
-    /// Internal IO error.
+    /// Internal IO error of a connection.
     Io(io::Error),
     /// Internal error that may be raised during `uuid::Uuid::from_bytes`
     UUIDParse(UUIDError),
@@ -30,12 +37,136 @@ pub enum Error {
     Compression(CompressionError),
     /// Server error.
     Server(CDRSError),
+    /// Internal error that is raised when a frame received from a server
+    /// is malformed or is not expected by the driver.
+    InvalidFrame(String),
+    /// No connection is available to send a request, e.g. all nodes are down.
+    Pool(String),
+    /// Error which has been produced by a node with a given address.
+    Node(SocketAddr, Box<Error>),
+    /// A server requires authentication with an authenticator class which is not
+    /// accepted by a client `Authenticator`.
+    UnsupportedAuthenticator(String),
+    /// A response to a request has not been received within a request timeout.
+    Timeout(Duration),
+}
+
+impl Error {
+    /// Attaches an address of a node that has produced the error.
+    /// An address which has been already attached is kept.
+    pub fn with_node(self, addr: SocketAddr) -> Error {
+        match self {
+            Error::Node(..) => self,
+            err => Error::Node(addr, Box::new(err)),
+        }
+    }
+
+    /// Returns an address of a node that has produced the error if it is known.
+    pub fn node_addr(&self) -> Option<SocketAddr> {
+        match *self {
+            Error::Node(addr, _) => Some(addr),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without an attached node address.
+    pub fn inner(&self) -> &Error {
+        match *self {
+            Error::Node(_, ref err) => err.inner(),
+            _ => self,
+        }
+    }
+
+    /// Returns an error returned by a server if it is the case.
+    pub fn server_error(&self) -> Option<&CDRSError> {
+        match *self.inner() {
+            Error::Server(ref err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Returns a kind of an error returned by a server if it is the case.
+    pub fn kind(&self) -> Option<ErrorKind> {
+        self.server_error().map(CDRSError::kind)
+    }
+
+    /// Indicates if a server or a connection has timed out.
+    pub fn is_timeout(&self) -> bool {
+        match *self.inner() {
+            Error::Io(ref err) => {
+                err.kind() == io::ErrorKind::TimedOut || err.kind() == io::ErrorKind::WouldBlock
+            }
+            Error::Server(ref err) => {
+                matches!(err.kind(), ErrorKind::ReadTimeout | ErrorKind::WriteTimeout)
+            }
+            Error::Timeout(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Indicates if not enough replicas are alive to achieve a requested consistency level.
+    pub fn is_unavailable(&self) -> bool {
+        self.kind() == Some(ErrorKind::Unavailable)
+    }
+
+    /// Indicates if the same request may succeed when it is sent again, possibly to
+    /// another node. Errors caused by a request itself, e.g. syntax errors, are not
+    /// retryable. Whether a request which is not idempotent can be safely sent again
+    /// is told by `is_idempotency_safe`.
+    pub fn is_retryable(&self) -> bool {
+        match *self.inner() {
+            Error::Io(_) | Error::Pool(_) | Error::Timeout(_) => true,
+            Error::Server(ref err) => matches!(
+                err.kind(),
+                ErrorKind::Server
+                    | ErrorKind::Unavailable
+                    | ErrorKind::Overloaded
+                    | ErrorKind::IsBootstrapping
+                    | ErrorKind::Truncate
+                    | ErrorKind::WriteTimeout
+                    | ErrorKind::ReadTimeout
+            ),
+            _ => false,
+        }
+    }
+
+    /// Indicates if a request has surely not been applied, so it can be sent again
+    /// even if it is not idempotent.
+    pub fn is_idempotency_safe(&self) -> bool {
+        match *self.inner() {
+            Error::Pool(_) => true,
+            Error::Server(ref err) => matches!(
+                err.kind(),
+                ErrorKind::Protocol
+                    | ErrorKind::Authentication
+                    | ErrorKind::Unavailable
+                    | ErrorKind::Overloaded
+                    | ErrorKind::IsBootstrapping
+                    | ErrorKind::Syntax
+                    | ErrorKind::Unauthorized
+                    | ErrorKind::Invalid
+                    | ErrorKind::Config
+                    | ErrorKind::AlreadyExists
+                    | ErrorKind::Unprepared
+            ),
+            _ => false,
+        }
+    }
 }
 
 pub fn column_is_empty_err<T: Display>(column_name: T) -> Error {
     Error::General(format!("Column or UDT property '{}' is empty", column_name))
 }
 
+/// Converts IO errors raised while decoding bytes which have been already received
+/// into `InvalidFrame` ones, so they are not confused with errors of a connection.
+pub(crate) fn decoding_error(err: Error) -> Error {
+    match err {
+        Error::Io(err) => Error::InvalidFrame(err.to_string()),
+        err => err,
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -45,6 +176,15 @@ impl fmt::Display for Error {
             Error::FromUtf8(ref err) => write!(f, "FromUtf8Error error: {:?}", err),
             Error::UUIDParse(ref err) => write!(f, "UUIDParse error: {:?}", err),
             Error::General(ref err) => write!(f, "GeneralParsing error: {:?}", err),
+            Error::InvalidFrame(ref err) => write!(f, "Invalid frame: {}", err),
+            Error::Pool(ref err) => write!(f, "Pool error: {}", err),
+            Error::Node(ref addr, ref err) => write!(f, "{} (node {})", err, addr),
+            Error::UnsupportedAuthenticator(ref server_authenticator) => write!(
+                f,
+                "Server authenticator {} is not supported by a client authenticator",
+                server_authenticator
+            ),
+            Error::Timeout(ref timeout) => write!(f, "Request timed out after {:?}", timeout),
         }
     }
 }
@@ -56,6 +196,7 @@ impl error::Error for Error {
             Error::UUIDParse(ref e) => Some(e),
             Error::FromUtf8(ref e) => Some(e),
             Error::Compression(ref e) => Some(e),
+            Error::Node(_, ref e) => Some(e.as_ref()),
             _ => None
         }
     }
@@ -102,3 +243,117 @@ impl<'a> From<&'a str> for Error {
         Error::General(err.to_string())
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::General(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Error {
+        Error::General(format!("Column or UDT property '{}' is missing", field))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_error::{AdditionalErrorInfo, SimpleError, WriteType};
+    use crate::retry::test_utils::{overloaded, read_timeout, unavailable, write_timeout};
+    use crate::types::CString;
+
+    fn node() -> SocketAddr {
+        "127.0.0.1:9042".parse().unwrap()
+    }
+
+    fn syntax_error() -> Error {
+        Error::Server(CDRSError {
+            error_code: 0x2000,
+            message: CString::new("line 1:0 no viable alternative".into()),
+            additional_info: AdditionalErrorInfo::Syntax(SimpleError {}),
+        })
+    }
+
+    #[test]
+    fn error_kind_codes() {
+        for code in &[
+            0x0000, 0x000A, 0x0100, 0x1000, 0x1001, 0x1002, 0x1003, 0x1100, 0x1200, 0x1300, 0x1400,
+            0x1500, 0x1600, 0x1700, 0x2000, 0x2100, 0x2200, 0x2300, 0x2400, 0x2500, 0x7777,
+        ] {
+            assert_eq!(ErrorKind::from_code(*code).code(), *code);
+        }
+        assert_eq!(ErrorKind::from_code(0x7777), ErrorKind::Unknown(0x7777));
+    }
+
+    #[test]
+    fn with_node() {
+        let other: SocketAddr = "127.0.0.2:9042".parse().unwrap();
+        let err = syntax_error().with_node(node()).with_node(other);
+
+        assert_eq!(err.node_addr(), Some(node()));
+        assert_eq!(err.kind(), Some(ErrorKind::Syntax));
+        assert!(matches!(err.inner(), Error::Server(_)));
+        assert!(err.to_string().ends_with("(node 127.0.0.1:9042)"));
+        assert!(error::Error::source(&err).is_some());
+        assert_eq!(syntax_error().node_addr(), None);
+    }
+
+    #[test]
+    fn classifies_server_errors() {
+        let timeouts: Vec<Error> = vec![
+            read_timeout(1, false).into(),
+            write_timeout(1, WriteType::Simple).into(),
+        ];
+        for err in timeouts {
+            let err = err.with_node(node());
+            assert!(err.is_timeout());
+            assert!(err.is_retryable());
+            assert!(!err.is_idempotency_safe());
+        }
+
+        let err = Error::from(unavailable(1)).with_node(node());
+        assert!(err.is_unavailable());
+        assert!(err.is_retryable());
+        assert!(err.is_idempotency_safe());
+        assert!(!err.is_timeout());
+
+        let err = Error::from(overloaded());
+        assert_eq!(err.kind(), Some(ErrorKind::Overloaded));
+        assert!(err.is_retryable());
+        assert!(err.is_idempotency_safe());
+
+        let err = syntax_error();
+        assert!(!err.is_retryable());
+        assert!(err.is_idempotency_safe());
+    }
+
+    #[test]
+    fn classifies_driver_errors() {
+        let err = Error::from(io::Error::from(io::ErrorKind::TimedOut)).with_node(node());
+        assert!(err.is_timeout());
+        assert!(err.is_retryable());
+        assert!(!err.is_idempotency_safe());
+        assert_eq!(err.kind(), None);
+
+        let err = Error::Timeout(Duration::from_secs(1)).with_node(node());
+        assert!(err.is_timeout());
+        assert!(err.is_retryable());
+        assert!(!err.is_idempotency_safe());
+
+        let err = Error::Pool("No connection available".into());
+        assert!(err.is_retryable());
+        assert!(err.is_idempotency_safe());
+
+        let err = Error::InvalidFrame("Unexpected opcode".into());
+        assert!(!err.is_retryable());
+        assert!(!err.is_timeout());
+        assert_eq!(err.server_error().map(|err| err.error_code), None);
+    }
+
+    #[test]
+    fn decoding_errors() {
+        let err = decoding_error(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        assert!(matches!(err, Error::InvalidFrame(_)));
+        assert!(matches!(decoding_error(syntax_error()), Error::Server(_)));
+    }
+}
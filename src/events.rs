@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::io::Cursor;
 use std::iter::Iterator;
 use std::sync::mpsc::{channel, Receiver, Sender};
 
@@ -9,6 +10,7 @@ use crate::frame::events::{
     SimpleServerEvent as FrameSimpleServerEvent,
 };
 use crate::frame::parser::parse_frame;
+use crate::frame::segment::SegmentDecoder;
 use crate::transport::CDRSTransport;
 
 /// Full Server Event which includes all details about occured change.
@@ -51,10 +53,17 @@ pub struct Listener<X> {
 impl<X: CDRSTransport + 'static> Listener<RefCell<X>> {
     /// It starts a process of listening to new events. Locks a frame.
     pub fn start(self, compressor: &Compression) -> error::Result<()> {
+        // in protocol v5 a single segment may contain several events
+        let mut segments = SegmentDecoder::new(*compressor);
+        let protocol_version = self.transport.borrow().protocol_version();
         loop {
-            let event_opt = parse_frame(&self.transport, compressor)?
-                .get_body()?
-                .into_server_event();
+            let frame = if protocol_version.is_segmented() {
+                let frame = segments.read_frame(&mut *self.transport.borrow_mut())?;
+                parse_frame(&RefCell::new(Cursor::new(frame)), &Compression::None)?
+            } else {
+                parse_frame(&self.transport, compressor)?
+            };
+            let event_opt = frame.get_body()?.into_server_event();
 
             let event = if event_opt.is_some() {
                 // unwrap is safe as we've checked that event_opt.is_some()
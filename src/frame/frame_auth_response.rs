@@ -24,7 +24,7 @@ impl IntoBytes for BodyReqAuthResponse {
 
 impl Frame {
     /// Creates new frame of type `AuthResponse`.
-    pub fn new_req_auth_response(token_bytes: CBytes) -> Frame {
+    pub fn new_req_auth_response(token_bytes: CBytes, protocol_version: ProtocolVersion) -> Frame {
         let version = Version::Request;
         let flag = Flag::Ignore;
         let stream = rand::random::<u16>();
@@ -33,6 +33,7 @@ impl Frame {
 
         Frame {
             version: version,
+            protocol_version,
             flags: vec![flag],
             stream: stream,
             opcode: opcode,
@@ -40,6 +41,7 @@ impl Frame {
             // for request frames it's always None
             tracing_id: None,
             warnings: vec![],
+            custom_payload: CustomPayload::new(),
         }
     }
 }
@@ -60,7 +62,7 @@ mod tests {
     #[test]
     fn frame_body_req_auth_response() {
         let bytes = vec![1, 2, 3];
-        let frame = Frame::new_req_auth_response(CBytes::new(bytes));
+        let frame = Frame::new_req_auth_response(CBytes::new(bytes), ProtocolVersion::V4);
 
         assert_eq!(frame.version, Version::Request);
         assert_eq!(frame.flags, vec![Flag::Ignore]);
@@ -2,14 +2,18 @@ use std::io::Cursor;
 
 use crate::error;
 use crate::frame::FromCursor;
+use crate::types::CBytes;
 
 /// `BodyReqAuthSuccess` is a frame that represents a successfull authentication response.
+/// It carries a final token of a SASL exchange which may be null.
 #[derive(Debug, PartialEq)]
-pub struct BodyReqAuthSuccess {}
+pub struct BodyReqAuthSuccess {
+    pub data: CBytes,
+}
 
 impl FromCursor for BodyReqAuthSuccess {
-    fn from_cursor(mut _cursor: &mut Cursor<&[u8]>) -> error::Result<BodyReqAuthSuccess> {
-        Ok(BodyReqAuthSuccess {})
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyReqAuthSuccess> {
+        CBytes::from_cursor(cursor).map(|data| BodyReqAuthSuccess { data })
     }
 }
 
@@ -21,9 +25,22 @@ mod tests {
 
     #[test]
     fn test_name() {
-        let rnd_bytes = [4, 5, 3, 8, 4, 6, 5, 0, 3, 7, 2];
+        let rnd_bytes = [0, 0, 0, 3, 4, 5, 3];
         let mut cursor: Cursor<&[u8]> = Cursor::new(&rnd_bytes);
         let body = BodyReqAuthSuccess::from_cursor(&mut cursor).unwrap();
-        assert_eq!(body, BodyReqAuthSuccess {});
+        assert_eq!(
+            body,
+            BodyReqAuthSuccess {
+                data: CBytes::new(vec![4, 5, 3])
+            }
+        );
+    }
+
+    #[test]
+    fn body_req_auth_success_null_token() {
+        let null_bytes = [0xFF, 0xFF, 0xFF, 0xFF];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(&null_bytes);
+        let body = BodyReqAuthSuccess::from_cursor(&mut cursor).unwrap();
+        assert_eq!(body.data.as_slice(), None);
     }
 }
@@ -17,10 +17,21 @@ pub struct BodyReqBatch {
     pub query_flags: Vec<QueryFlags>,
     pub serial_consistency: Option<Consistency>,
     pub timestamp: Option<i64>,
+    /// Keyspace queries should be executed against. Protocol v5 only.
+    pub keyspace: Option<String>,
+    /// Current time in seconds a server should use for queries. Protocol v5 only.
+    pub now_in_seconds: Option<i32>,
+    /// Is a batch idempotent, i.e. can it be applied multiple times without changing
+    /// the result. Only idempotent batches are executed speculatively or retried with
+    /// other nodes. It is not sent to a server.
+    pub is_idempotent: bool,
+    /// Custom payload sent along with a batch. It is not sent in protocol v3.
+    pub custom_payload: CustomPayload,
 }
 
-impl IntoBytes for BodyReqBatch {
-    fn into_cbytes(&self) -> Vec<u8> {
+impl BodyReqBatch {
+    /// Serializes the body in accordance to a given protocol version.
+    pub fn into_cbytes_with_version(&self, protocol_version: ProtocolVersion) -> Vec<u8> {
         let mut bytes = vec![];
 
         bytes.push(self.batch_type.as_byte());
@@ -34,11 +45,19 @@ impl IntoBytes for BodyReqBatch {
 
         bytes.extend_from_slice(self.consistency.into_cbytes().as_slice());
 
-        let flag_byte = self
-            .query_flags
-            .iter()
-            .fold(0, |mut _bytes, f| _bytes | f.as_byte());
-        bytes.push(flag_byte);
+        if protocol_version >= ProtocolVersion::V5 {
+            let flags = self
+                .query_flags
+                .iter()
+                .fold(0, |flags, f| flags | f.as_int());
+            bytes.extend_from_slice(to_int(flags).as_slice());
+        } else {
+            let flag_byte = self
+                .query_flags
+                .iter()
+                .fold(0, |mut _bytes, f| _bytes | f.as_byte());
+            bytes.push(flag_byte);
+        }
 
         if let Some(ref serial_consistency) = self.serial_consistency {
             bytes.extend_from_slice(serial_consistency.into_cbytes().as_slice());
@@ -49,10 +68,26 @@ impl IntoBytes for BodyReqBatch {
             bytes.extend_from_slice(to_bigint(*timestamp).as_slice());
         }
 
+        if protocol_version >= ProtocolVersion::V5 {
+            if let Some(ref keyspace) = self.keyspace {
+                bytes.extend_from_slice(CString::new(keyspace.clone()).into_cbytes().as_slice());
+            }
+
+            if let Some(now_in_seconds) = self.now_in_seconds {
+                bytes.extend_from_slice(to_int(now_in_seconds).as_slice());
+            }
+        }
+
         bytes
     }
 }
 
+impl IntoBytes for BodyReqBatch {
+    fn into_cbytes(&self) -> Vec<u8> {
+        self.into_cbytes_with_version(ProtocolVersion::default())
+    }
+}
+
 /// Batch type
 #[derive(Debug, Clone, PartialEq)]
 pub enum BatchType {
@@ -124,7 +159,7 @@ impl IntoBytes for BatchQuery {
 
         match self.subject {
             BatchQuerySubj::PreparedId(ref s) => {
-                bytes.extend_from_slice(s.id.borrow().into_cbytes().as_slice());
+                bytes.extend_from_slice(s.get_id().into_cbytes().as_slice());
             }
             BatchQuerySubj::QueryString(ref s) => {
                 bytes.extend_from_slice(s.into_cbytes().as_slice());
@@ -141,20 +176,27 @@ impl IntoBytes for BatchQuery {
 
 impl Frame {
     /// **Note:** This function should be used internally for building query request frames.
-    pub fn new_req_batch(query: BodyReqBatch, flags: Vec<Flag>) -> Frame {
+    pub fn new_req_batch(
+        query: BodyReqBatch,
+        flags: Vec<Flag>,
+        protocol_version: ProtocolVersion,
+    ) -> Frame {
         let version = Version::Request;
         let stream = rand::random::<u16>();
         let opcode = Opcode::Batch;
 
         Frame {
             version: version,
+            protocol_version,
             flags: flags,
             stream: stream,
             opcode: opcode,
-            body: query.into_cbytes(),
+            body: query.into_cbytes_with_version(protocol_version),
             // for request frames it's always None
             tracing_id: None,
             warnings: vec![],
+            custom_payload: CustomPayload::new(),
         }
+        .with_custom_payload(&query.custom_payload)
     }
 }
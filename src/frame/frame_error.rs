@@ -19,7 +19,8 @@ pub type Result = result::Result<Frame, CDRSError>;
 /// CDRS error which could be returned by Cassandra server as a response. As it goes
 /// from the specification it contains an error code and an error message. Apart of those
 /// depending of type of error it could contain an additional information about an error.
-/// This additional information is represented by `additional_info` property which is `ErrorKind`.
+/// This additional information is represented by `additional_info` property which is
+/// `AdditionalErrorInfo`.
 #[derive(Debug)]
 pub struct CDRSError {
     /// `i32` that points to a type of error.
@@ -44,6 +45,120 @@ impl FromCursor for CDRSError {
     }
 }
 
+impl CDRSError {
+    /// Returns a kind of the error which is defined by its code.
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::from_code(self.error_code)
+    }
+}
+
+/// Kind of an error returned by a server. Every kind corresponds to an
+/// [error code](https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v5.spec#L1202)
+/// of the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Something unexpected happened on a server side.
+    Server,
+    /// A client message triggered a protocol violation.
+    Protocol,
+    /// Authentication was required and failed.
+    Authentication,
+    /// Not enough replicas were alive to achieve a requested consistency level.
+    Unavailable,
+    /// A coordinator is overloaded.
+    Overloaded,
+    /// A coordinator is bootstrapping.
+    IsBootstrapping,
+    /// Error during a truncation operation.
+    Truncate,
+    /// Timeout exception during a write request.
+    WriteTimeout,
+    /// Timeout exception during a read request.
+    ReadTimeout,
+    /// A non-timeout exception during a read request.
+    ReadFailure,
+    /// A user defined function failed during execution.
+    FunctionFailure,
+    /// A non-timeout exception during a write request.
+    WriteFailure,
+    /// An exception occurred due to contended CDC space. Protocol v5 only.
+    CdcWriteFailure,
+    /// An exception occurred due to a contended Compare And Set write.
+    /// Protocol v5 only.
+    CasWriteUnknown,
+    /// A query has a syntax error.
+    Syntax,
+    /// A logged user doesn't have the right to perform a query.
+    Unauthorized,
+    /// A query is syntactically correct but invalid.
+    Invalid,
+    /// A query is invalid because of some configuration issue.
+    Config,
+    /// A query attempted to create a keyspace or a table that was already existing.
+    AlreadyExists,
+    /// Can be thrown while a prepared statement tries to be executed if the provided
+    /// prepared statement ID is not known by a coordinator.
+    Unprepared,
+    /// Error code which is not defined by the protocol.
+    Unknown(CInt),
+}
+
+impl ErrorKind {
+    /// Returns a kind of an error with a given code.
+    pub fn from_code(error_code: CInt) -> ErrorKind {
+        match error_code {
+            0x0000 => ErrorKind::Server,
+            0x000A => ErrorKind::Protocol,
+            0x0100 => ErrorKind::Authentication,
+            0x1000 => ErrorKind::Unavailable,
+            0x1001 => ErrorKind::Overloaded,
+            0x1002 => ErrorKind::IsBootstrapping,
+            0x1003 => ErrorKind::Truncate,
+            0x1100 => ErrorKind::WriteTimeout,
+            0x1200 => ErrorKind::ReadTimeout,
+            0x1300 => ErrorKind::ReadFailure,
+            0x1400 => ErrorKind::FunctionFailure,
+            0x1500 => ErrorKind::WriteFailure,
+            0x1600 => ErrorKind::CdcWriteFailure,
+            0x1700 => ErrorKind::CasWriteUnknown,
+            0x2000 => ErrorKind::Syntax,
+            0x2100 => ErrorKind::Unauthorized,
+            0x2200 => ErrorKind::Invalid,
+            0x2300 => ErrorKind::Config,
+            0x2400 => ErrorKind::AlreadyExists,
+            0x2500 => ErrorKind::Unprepared,
+            _ => ErrorKind::Unknown(error_code),
+        }
+    }
+
+    /// Returns a code of an error of this kind.
+    pub fn code(&self) -> CInt {
+        match *self {
+            ErrorKind::Server => 0x0000,
+            ErrorKind::Protocol => 0x000A,
+            ErrorKind::Authentication => 0x0100,
+            ErrorKind::Unavailable => 0x1000,
+            ErrorKind::Overloaded => 0x1001,
+            ErrorKind::IsBootstrapping => 0x1002,
+            ErrorKind::Truncate => 0x1003,
+            ErrorKind::WriteTimeout => 0x1100,
+            ErrorKind::ReadTimeout => 0x1200,
+            ErrorKind::ReadFailure => 0x1300,
+            ErrorKind::FunctionFailure => 0x1400,
+            ErrorKind::WriteFailure => 0x1500,
+            ErrorKind::CdcWriteFailure => 0x1600,
+            ErrorKind::CasWriteUnknown => 0x1700,
+            ErrorKind::Syntax => 0x2000,
+            ErrorKind::Unauthorized => 0x2100,
+            ErrorKind::Invalid => 0x2200,
+            ErrorKind::Config => 0x2300,
+            ErrorKind::AlreadyExists => 0x2400,
+            ErrorKind::Unprepared => 0x2500,
+            ErrorKind::Unknown(error_code) => error_code,
+        }
+    }
+}
+
 /// Additional error info in accordance to
 /// [Cassandra protocol v4]
 /// (https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L1011).
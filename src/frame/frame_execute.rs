@@ -9,6 +9,8 @@ use crate::types::*;
 pub struct BodyReqExecute<'a> {
     /// Id of prepared query
     id: &'a CBytesShort,
+    /// Id of result metadata of prepared query. Protocol v5 only.
+    result_metadata_id: Option<&'a CBytesShort>,
     /// Query paramaters which have the same meaning as one for `query`
     /// TODO: clarify if it is QueryParams or its shortened variant
     query_parameters: &'a QueryParams,
@@ -19,26 +21,70 @@ impl<'a> BodyReqExecute<'a> {
     pub fn new<'b>(id: &'b CBytesShort, query_parameters: &'b QueryParams) -> BodyReqExecute<'b> {
         BodyReqExecute {
             id: id,
+            result_metadata_id: None,
             query_parameters: query_parameters,
         }
     }
+
+    /// Sets an id of result metadata which is sent in protocol v5.
+    pub fn with_result_metadata_id(mut self, result_metadata_id: Option<&'a CBytesShort>) -> Self {
+        self.result_metadata_id = result_metadata_id;
+        self
+    }
 }
 
-impl<'a> IntoBytes for BodyReqExecute<'a> {
-    fn into_cbytes(&self) -> Vec<u8> {
+impl<'a> BodyReqExecute<'a> {
+    /// Serializes the body in accordance to a given protocol version.
+    pub fn into_cbytes_with_version(&self, protocol_version: ProtocolVersion) -> Vec<u8> {
         let mut v: Vec<u8> = vec![];
         v.extend_from_slice(self.id.into_cbytes().as_slice());
-        v.extend_from_slice(self.query_parameters.into_cbytes().as_slice());
+        if protocol_version >= ProtocolVersion::V5 {
+            let result_metadata_id = self
+                .result_metadata_id
+                .map(IntoBytes::into_cbytes)
+                .unwrap_or_else(|| CBytesShort::new(vec![]).into_cbytes());
+            v.extend_from_slice(result_metadata_id.as_slice());
+        }
+        v.extend_from_slice(
+            self.query_parameters
+                .into_cbytes_with_version(protocol_version)
+                .as_slice(),
+        );
         v
     }
 }
 
+impl<'a> IntoBytes for BodyReqExecute<'a> {
+    fn into_cbytes(&self) -> Vec<u8> {
+        self.into_cbytes_with_version(ProtocolVersion::default())
+    }
+}
+
 impl Frame {
     /// **Note:** This function should be used internally for building query request frames.
     pub fn new_req_execute(
         id: &CBytesShort,
         query_parameters: &QueryParams,
         flags: Vec<Flag>,
+        protocol_version: ProtocolVersion,
+    ) -> Frame {
+        Frame::new_req_execute_with_result_metadata_id(
+            id,
+            None,
+            query_parameters,
+            flags,
+            protocol_version,
+        )
+    }
+
+    /// **Note:** This function should be used internally for building query request frames.
+    /// `result_metadata_id` is only sent in protocol v5.
+    pub fn new_req_execute_with_result_metadata_id(
+        id: &CBytesShort,
+        result_metadata_id: Option<&CBytesShort>,
+        query_parameters: &QueryParams,
+        flags: Vec<Flag>,
+        protocol_version: ProtocolVersion,
     ) -> Frame {
         let version = Version::Request;
         let stream = rand::random::<u16>();
@@ -47,17 +93,51 @@ impl Frame {
             "prepared statement id{:?} getting executed with parameters {:?}",
             id, query_parameters
         );
-        let body = BodyReqExecute::new(id, query_parameters);
+        let body =
+            BodyReqExecute::new(id, query_parameters).with_result_metadata_id(result_metadata_id);
 
         Frame {
             version: version,
+            protocol_version,
             flags: flags,
             stream: stream,
             opcode: opcode,
-            body: body.into_cbytes(),
+            body: body.into_cbytes_with_version(protocol_version),
             // for request frames it's always None
             tracing_id: None,
             warnings: vec![],
+            custom_payload: CustomPayload::new(),
         }
+        .with_custom_payload(&query_parameters.custom_payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_req_execute_into_cbytes() {
+        let id = CBytesShort::new(vec![1, 2]);
+        let result_metadata_id = CBytesShort::new(vec![3]);
+        let params = QueryParams::default();
+        let body =
+            BodyReqExecute::new(&id, &params).with_result_metadata_id(Some(&result_metadata_id));
+
+        let mut expected = vec![0, 2, 1, 2];
+        expected.extend(params.into_cbytes_with_version(ProtocolVersion::V4));
+        assert_eq!(body.into_cbytes_with_version(ProtocolVersion::V4), expected);
+
+        let mut expected = vec![0, 2, 1, 2, 0, 1, 3];
+        expected.extend(params.into_cbytes_with_version(ProtocolVersion::V5));
+        assert_eq!(body.into_cbytes_with_version(ProtocolVersion::V5), expected);
+    }
+
+    #[test]
+    fn body_req_execute_without_result_metadata_id() {
+        let id = CBytesShort::new(vec![1]);
+        let params = QueryParams::default();
+        let body = BodyReqExecute::new(&id, &params).into_cbytes_with_version(ProtocolVersion::V5);
+        assert_eq!(&body[..5], &[0, 1, 1, 0, 0]);
     }
 }
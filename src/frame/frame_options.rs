@@ -16,7 +16,7 @@ impl IntoBytes for BodyReqOptions {
 
 impl Frame {
     /// Creates new frame of type `options`.
-    pub fn new_req_options() -> Frame {
+    pub fn new_req_options(protocol_version: ProtocolVersion) -> Frame {
         let version = Version::Request;
         let flag = Flag::Ignore;
         let stream = rand::random::<u16>();
@@ -25,6 +25,7 @@ impl Frame {
 
         Frame {
             version: version,
+            protocol_version,
             flags: vec![flag],
             stream: stream,
             opcode: opcode,
@@ -32,6 +33,7 @@ impl Frame {
             // for request frames it's always None
             tracing_id: None,
             warnings: vec![],
+            custom_payload: CustomPayload::new(),
         }
     }
 }
@@ -42,8 +44,9 @@ mod tests {
 
     #[test]
     fn test_frame_options() {
-        let frame = Frame::new_req_options();
+        let frame = Frame::new_req_options(ProtocolVersion::V4);
         assert_eq!(frame.version, Version::Request);
+        assert_eq!(frame.protocol_version, ProtocolVersion::V4);
         assert_eq!(frame.opcode, Opcode::Options);
         assert_eq!(frame.body, vec![]);
     }
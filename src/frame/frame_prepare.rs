@@ -18,15 +18,31 @@ impl BodyReqPrepare {
     }
 }
 
+impl BodyReqPrepare {
+    /// Serializes the body in accordance to a given protocol version.
+    pub fn into_cbytes_with_version(&self, protocol_version: ProtocolVersion) -> Vec<u8> {
+        let mut v = self.query.into_cbytes();
+        if protocol_version >= ProtocolVersion::V5 {
+            // no flags, i.e. a query is prepared in the current keyspace
+            v.extend_from_slice(to_int(0).as_slice());
+        }
+        v
+    }
+}
+
 impl IntoBytes for BodyReqPrepare {
     fn into_cbytes(&self) -> Vec<u8> {
-        self.query.into_cbytes()
+        self.into_cbytes_with_version(ProtocolVersion::default())
     }
 }
 
 impl Frame {
     /// **Note:** This function should be used internally for building query request frames.
-    pub fn new_req_prepare(query: String, flags: Vec<Flag>) -> Frame {
+    pub fn new_req_prepare(
+        query: String,
+        flags: Vec<Flag>,
+        protocol_version: ProtocolVersion,
+    ) -> Frame {
         let version = Version::Request;
         let stream = rand::random::<u16>();
         let opcode = Opcode::Prepare;
@@ -34,13 +50,15 @@ impl Frame {
 
         Frame {
             version: version,
+            protocol_version,
             flags: flags,
             stream: stream,
             opcode: opcode,
-            body: body.into_cbytes(),
+            body: body.into_cbytes_with_version(protocol_version),
             // for request frames it's always None
             tracing_id: None,
             warnings: vec![],
+            custom_payload: CustomPayload::new(),
         }
     }
 }
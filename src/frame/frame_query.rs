@@ -60,20 +60,36 @@ impl BodyReqQuery {
                 paging_state,
                 serial_consistency,
                 timestamp,
+                keyspace: None,
+                now_in_seconds: None,
+                is_idempotent: false,
+                custom_payload: CustomPayload::new(),
+                timeout: None,
             },
         }
     }
 }
 
-impl IntoBytes for BodyReqQuery {
-    fn into_cbytes(&self) -> Vec<u8> {
+impl BodyReqQuery {
+    /// Serializes the body in accordance to a given protocol version.
+    pub fn into_cbytes_with_version(&self, protocol_version: ProtocolVersion) -> Vec<u8> {
         let mut v: Vec<u8> = vec![];
         v.extend_from_slice(self.query.clone().into_cbytes().as_slice());
-        v.extend_from_slice(self.query_params.into_cbytes().as_slice());
+        v.extend_from_slice(
+            self.query_params
+                .into_cbytes_with_version(protocol_version)
+                .as_slice(),
+        );
         v
     }
 }
 
+impl IntoBytes for BodyReqQuery {
+    fn into_cbytes(&self) -> Vec<u8> {
+        self.into_cbytes_with_version(ProtocolVersion::default())
+    }
+}
+
 // Frame implementation related to BodyReqStartup
 
 impl Frame {
@@ -88,10 +104,8 @@ impl Frame {
         serial_consistency: Option<Consistency>,
         timestamp: Option<i64>,
         flags: Vec<Flag>,
+        protocol_version: ProtocolVersion,
     ) -> Frame {
-        let version = Version::Request;
-        let stream = rand::random::<u16>();
-        let opcode = Opcode::Query;
         let body = BodyReqQuery::new(
             query,
             consistency,
@@ -103,30 +117,40 @@ impl Frame {
             timestamp,
         );
 
+        Frame::new_req_query_body(body, flags, protocol_version)
+    }
+
+    /// **Note:** This function should be used internally for building query request frames.
+    pub fn new_query(query: Query, flags: Vec<Flag>, protocol_version: ProtocolVersion) -> Frame {
+        let body = BodyReqQuery {
+            query: CStringLong::new(query.query),
+            query_params: query.params,
+        };
+
+        Frame::new_req_query_body(body, flags, protocol_version)
+    }
+
+    fn new_req_query_body(
+        body: BodyReqQuery,
+        flags: Vec<Flag>,
+        protocol_version: ProtocolVersion,
+    ) -> Frame {
+        let version = Version::Request;
+        let stream = rand::random::<u16>();
+        let opcode = Opcode::Query;
+
         Frame {
             version: version,
+            protocol_version,
             flags: flags,
             stream: stream,
             opcode: opcode,
-            body: body.into_cbytes(),
+            body: body.into_cbytes_with_version(protocol_version),
             // for request frames it's always None
             tracing_id: None,
             warnings: vec![],
+            custom_payload: CustomPayload::new(),
         }
-    }
-
-    /// **Note:** This function should be used internally for building query request frames.
-    pub fn new_query(query: Query, flags: Vec<Flag>) -> Frame {
-        Frame::new_req_query(
-            query.query,
-            query.params.consistency,
-            query.params.values,
-            query.params.with_names,
-            query.params.page_size,
-            query.params.paging_state,
-            query.params.serial_consistency,
-            query.params.timestamp,
-            flags,
-        )
+        .with_custom_payload(&body.query_params.custom_payload)
     }
 }
@@ -26,7 +26,10 @@ impl IntoBytes for BodyReqRegister {
 
 impl Frame {
     /// Creates new frame of type `REGISTER`.
-    pub fn new_req_register(events: Vec<SimpleServerEvent>) -> Frame {
+    pub fn new_req_register(
+        events: Vec<SimpleServerEvent>,
+        protocol_version: ProtocolVersion,
+    ) -> Frame {
         let version = Version::Request;
         let flag = Flag::Ignore;
         let stream = rand::random::<u16>();
@@ -35,6 +38,7 @@ impl Frame {
 
         Frame {
             version: version,
+            protocol_version,
             flags: vec![flag],
             stream: stream,
             opcode: opcode,
@@ -42,6 +46,7 @@ impl Frame {
             // for request frames it's always None
             tracing_id: None,
             warnings: vec![],
+            custom_payload: CustomPayload::new(),
         }
     }
 }
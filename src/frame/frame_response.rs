@@ -12,7 +12,7 @@ use crate::frame::frame_result::{
 };
 use crate::frame::frame_supported::*;
 use crate::frame::FromCursor;
-use crate::frame::Opcode;
+use crate::frame::{Opcode, ProtocolVersion};
 use crate::types::rows::Row;
 
 #[derive(Debug)]
@@ -36,18 +36,35 @@ pub enum ResponseBody {
 }
 
 impl ResponseBody {
-    pub fn from(bytes: &[u8], response_type: &Opcode) -> error::Result<ResponseBody> {
+    pub fn from(
+        bytes: &[u8],
+        response_type: &Opcode,
+        protocol_version: ProtocolVersion,
+    ) -> error::Result<ResponseBody> {
+        ResponseBody::decode(bytes, response_type, protocol_version).map_err(error::decoding_error)
+    }
+
+    fn decode(
+        bytes: &[u8],
+        response_type: &Opcode,
+        protocol_version: ProtocolVersion,
+    ) -> error::Result<ResponseBody> {
         let mut cursor: Cursor<&[u8]> = Cursor::new(bytes);
         Ok(match *response_type {
             // request frames
-            Opcode::Startup => unreachable!(),
-            Opcode::Options => unreachable!(),
-            Opcode::Query => unreachable!(),
-            Opcode::Prepare => unreachable!(),
-            Opcode::Execute => unreachable!(),
-            Opcode::Register => unreachable!(),
-            Opcode::Batch => unreachable!(),
-            Opcode::AuthResponse => unreachable!(),
+            Opcode::Startup
+            | Opcode::Options
+            | Opcode::Query
+            | Opcode::Prepare
+            | Opcode::Execute
+            | Opcode::Register
+            | Opcode::Batch
+            | Opcode::AuthResponse => {
+                return Err(error::Error::InvalidFrame(format!(
+                    "Unexpected opcode of response frame {:?}",
+                    response_type
+                )))
+            }
 
             // response frames
             Opcode::Error => ResponseBody::Error(CDRSError::from_cursor(&mut cursor)?),
@@ -58,7 +75,10 @@ impl ResponseBody {
             Opcode::Supported => {
                 ResponseBody::Supported(BodyResSupported::from_cursor(&mut cursor)?)
             }
-            Opcode::Result => ResponseBody::Result(ResResultBody::from_cursor(&mut cursor)?),
+            Opcode::Result => ResponseBody::Result(ResResultBody::from_cursor_with_version(
+                &mut cursor,
+                protocol_version,
+            )?),
             Opcode::Event => ResponseBody::Event(BodyResEvent::from_cursor(&mut cursor)?),
             Opcode::AuthChallenge => {
                 ResponseBody::AuthChallenge(BodyResAuthChallenge::from_cursor(&mut cursor)?)
@@ -127,3 +147,85 @@ impl ResponseBody {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{corrupted, response_bodies, seeded_rng};
+    use rand::Rng;
+
+    const PROTOCOL_VERSIONS: [ProtocolVersion; 3] = [
+        ProtocolVersion::V3,
+        ProtocolVersion::V4,
+        ProtocolVersion::V5,
+    ];
+
+    #[test]
+    fn decodes_sample_bodies() {
+        for (opcode, body) in response_bodies() {
+            assert!(
+                ResponseBody::from(&body, &opcode, ProtocolVersion::V4).is_ok(),
+                "{:?} {:?}",
+                opcode,
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_request_opcodes() {
+        for opcode in vec![
+            Opcode::Startup,
+            Opcode::Options,
+            Opcode::Query,
+            Opcode::Prepare,
+            Opcode::Execute,
+            Opcode::Register,
+            Opcode::Batch,
+            Opcode::AuthResponse,
+        ] {
+            match ResponseBody::from(&[], &opcode, ProtocolVersion::V4) {
+                Err(error::Error::InvalidFrame(_)) => {}
+                result => panic!("{:?} is decoded as {:?}", opcode, result),
+            }
+        }
+    }
+
+    #[test]
+    fn does_not_panic_on_truncated_bodies() {
+        for (opcode, body) in response_bodies() {
+            for protocol_version in PROTOCOL_VERSIONS.iter() {
+                for len in 0..body.len() {
+                    let _ = ResponseBody::from(&body[..len], &opcode, *protocol_version);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn does_not_panic_on_corrupted_bodies() {
+        let mut rng = seeded_rng();
+        for (opcode, body) in response_bodies() {
+            for protocol_version in PROTOCOL_VERSIONS.iter() {
+                for _ in 0..500 {
+                    let body = corrupted(&body, &mut rng);
+                    let _ = ResponseBody::from(&body, &opcode, *protocol_version);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn does_not_panic_on_random_bodies() {
+        let mut rng = seeded_rng();
+        for (opcode, _) in response_bodies() {
+            for protocol_version in PROTOCOL_VERSIONS.iter() {
+                for _ in 0..500 {
+                    let len = rng.gen_range(0, 64);
+                    let body: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+                    let _ = ResponseBody::from(&body, &opcode, *protocol_version);
+                }
+            }
+        }
+    }
+}
@@ -2,7 +2,7 @@ use std::io::Cursor;
 
 use crate::error;
 use crate::frame::events::SchemaChange;
-use crate::frame::{FromBytes, FromCursor, IntoBytes};
+use crate::frame::{FromBytes, FromCursor, IntoBytes, ProtocolVersion};
 use crate::types::rows::Row;
 use crate::types::*;
 
@@ -78,16 +78,20 @@ impl ResResultBody {
     fn parse_body_from_cursor(
         mut cursor: &mut Cursor<&[u8]>,
         result_kind: ResultKind,
+        protocol_version: ProtocolVersion,
     ) -> error::Result<ResResultBody> {
         Ok(match result_kind {
             ResultKind::Void => ResResultBody::Void(BodyResResultVoid::from_cursor(&mut cursor)?),
-            ResultKind::Rows => ResResultBody::Rows(BodyResResultRows::from_cursor(&mut cursor)?),
+            ResultKind::Rows => ResResultBody::Rows(BodyResResultRows::from_cursor_with_version(
+                cursor,
+                protocol_version,
+            )?),
             ResultKind::SetKeyspace => {
                 ResResultBody::SetKeyspace(BodyResResultSetKeyspace::from_cursor(&mut cursor)?)
             }
-            ResultKind::Prepared => {
-                ResResultBody::Prepared(BodyResResultPrepared::from_cursor(&mut cursor)?)
-            }
+            ResultKind::Prepared => ResResultBody::Prepared(
+                BodyResResultPrepared::from_cursor_with_version(cursor, protocol_version)?,
+            ),
             ResultKind::SchemaChange => {
                 ResResultBody::SchemaChange(SchemaChange::from_cursor(&mut cursor)?)
             }
@@ -129,11 +133,21 @@ impl ResResultBody {
     }
 }
 
-impl FromCursor for ResResultBody {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<ResResultBody> {
+impl ResResultBody {
+    /// Parses a result body encoded in accordance to a given protocol version.
+    pub fn from_cursor_with_version(
+        mut cursor: &mut Cursor<&[u8]>,
+        protocol_version: ProtocolVersion,
+    ) -> error::Result<ResResultBody> {
         let result_kind = ResultKind::from_cursor(&mut cursor)?;
 
-        ResResultBody::parse_body_from_cursor(&mut cursor, result_kind)
+        ResResultBody::parse_body_from_cursor(cursor, result_kind, protocol_version)
+    }
+}
+
+impl FromCursor for ResResultBody {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<ResResultBody> {
+        ResResultBody::from_cursor_with_version(cursor, ProtocolVersion::default())
     }
 }
 
@@ -192,27 +206,30 @@ pub struct BodyResResultRows {
 impl BodyResResultRows {
     /// It retrieves rows content having knowledge about number of rows and columns.
     fn get_rows_content(
-        mut cursor: &mut Cursor<&[u8]>,
+        cursor: &mut Cursor<&[u8]>,
         rows_count: i32,
         columns_count: i32,
-    ) -> Vec<Vec<CBytes>> {
+    ) -> error::Result<Vec<Vec<CBytes>>> {
         (0..rows_count)
             .map(|_| {
                 (0..columns_count)
-                    // XXX unwrap()
-                    .map(|_| CBytes::from_cursor(&mut cursor).unwrap() as CBytes)
+                    .map(|_| CBytes::from_cursor(cursor))
                     .collect()
             })
             .collect()
     }
 }
 
-impl FromCursor for BodyResResultRows {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResResultRows> {
-        let metadata = RowsMetadata::from_cursor(&mut cursor)?;
+impl BodyResResultRows {
+    /// Parses rows encoded in accordance to a given protocol version.
+    pub fn from_cursor_with_version(
+        mut cursor: &mut Cursor<&[u8]>,
+        protocol_version: ProtocolVersion,
+    ) -> error::Result<BodyResResultRows> {
+        let metadata = RowsMetadata::from_cursor_with_version(cursor, protocol_version)?;
         let rows_count = CInt::from_cursor(&mut cursor)?;
         let rows_content: Vec<Vec<CBytes>> =
-            BodyResResultRows::get_rows_content(&mut cursor, rows_count, metadata.columns_count);
+            BodyResResultRows::get_rows_content(cursor, rows_count, metadata.columns_count)?;
 
         Ok(BodyResResultRows {
             metadata: metadata,
@@ -222,6 +239,12 @@ impl FromCursor for BodyResResultRows {
     }
 }
 
+impl FromCursor for BodyResResultRows {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResResultRows> {
+        BodyResResultRows::from_cursor_with_version(cursor, ProtocolVersion::default())
+    }
+}
+
 /// Rows metadata.
 #[derive(Debug, Clone)]
 pub struct RowsMetadata {
@@ -233,6 +256,9 @@ pub struct RowsMetadata {
     pub columns_count: i32,
     /// Paging state.
     pub paging_state: Option<CBytes>,
+    /// New id of result metadata if it has changed since a query was prepared.
+    /// Protocol v5 only.
+    pub new_metadata_id: Option<CBytesShort>,
     // In fact by specification Vec should have only two elements representing the
     // (unique) keyspace name and table name the columns belong to
     /// `Option` that may contain global table space.
@@ -241,8 +267,12 @@ pub struct RowsMetadata {
     pub col_specs: Vec<ColSpec>,
 }
 
-impl FromCursor for RowsMetadata {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<RowsMetadata> {
+impl RowsMetadata {
+    /// Parses rows metadata encoded in accordance to a given protocol version.
+    pub fn from_cursor_with_version(
+        mut cursor: &mut Cursor<&[u8]>,
+        protocol_version: ProtocolVersion,
+    ) -> error::Result<RowsMetadata> {
         let flags = CInt::from_cursor(&mut cursor)?;
         let columns_count = CInt::from_cursor(&mut cursor)?;
 
@@ -251,6 +281,12 @@ impl FromCursor for RowsMetadata {
             paging_state = Some(CBytes::from_cursor(&mut cursor)?)
         }
 
+        let mut new_metadata_id: Option<CBytesShort> = None;
+        if protocol_version >= ProtocolVersion::V5 && RowsMetadataFlag::has_metadata_changed(flags)
+        {
+            new_metadata_id = Some(CBytesShort::from_cursor(cursor)?)
+        }
+
         let mut global_table_space: Option<Vec<CString>> = None;
         let has_global_table_space = RowsMetadataFlag::has_global_table_space(flags);
         if has_global_table_space {
@@ -259,27 +295,38 @@ impl FromCursor for RowsMetadata {
             global_table_space = Some(vec![keyspace, tablename])
         }
 
-        let col_specs = ColSpec::parse_colspecs(&mut cursor, columns_count, has_global_table_space);
+        let col_specs =
+            ColSpec::parse_colspecs(cursor, columns_count, has_global_table_space)?;
 
         Ok(RowsMetadata {
             flags: flags,
             columns_count: columns_count,
             paging_state: paging_state,
+            new_metadata_id,
             global_table_space: global_table_space,
             col_specs: col_specs,
         })
     }
 }
 
+impl FromCursor for RowsMetadata {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<RowsMetadata> {
+        RowsMetadata::from_cursor_with_version(cursor, ProtocolVersion::default())
+    }
+}
+
 const GLOBAL_TABLE_SPACE: i32 = 0x0001;
 const HAS_MORE_PAGES: i32 = 0x0002;
 const NO_METADATA: i32 = 0x0004;
+const METADATA_CHANGED: i32 = 0x0008;
 
 /// Enum that represent a set of possible row metadata flags that could be set.
 pub enum RowsMetadataFlag {
     GlobalTableSpace,
     HasMorePages,
     NoMetadata,
+    /// Protocol v5 only.
+    MetadataChanged,
 }
 
 impl RowsMetadataFlag {
@@ -312,6 +359,16 @@ impl RowsMetadataFlag {
     pub fn set_no_metadata(flag: i32) -> i32 {
         flag | NO_METADATA
     }
+
+    /// Shows if provided flag contains MetadataChanged rows metadata flag
+    pub fn has_metadata_changed(flag: i32) -> bool {
+        (flag & METADATA_CHANGED) != 0
+    }
+
+    /// Sets MetadataChanged rows metadata flag
+    pub fn set_metadata_changed(flag: i32) -> i32 {
+        flag | METADATA_CHANGED
+    }
 }
 
 impl IntoBytes for RowsMetadataFlag {
@@ -320,6 +377,7 @@ impl IntoBytes for RowsMetadataFlag {
             RowsMetadataFlag::GlobalTableSpace => to_int(GLOBAL_TABLE_SPACE),
             RowsMetadataFlag::HasMorePages => to_int(HAS_MORE_PAGES),
             RowsMetadataFlag::NoMetadata => to_int(NO_METADATA),
+            RowsMetadataFlag::MetadataChanged => to_int(METADATA_CHANGED),
         }
     }
 }
@@ -332,6 +390,7 @@ impl FromBytes for RowsMetadataFlag {
                 GLOBAL_TABLE_SPACE => Ok(RowsMetadataFlag::GlobalTableSpace),
                 HAS_MORE_PAGES => Ok(RowsMetadataFlag::HasMorePages),
                 NO_METADATA => Ok(RowsMetadataFlag::NoMetadata),
+                METADATA_CHANGED => Ok(RowsMetadataFlag::MetadataChanged),
                 _ => Err("Unexpected rows metadata flag".into()),
             })
     }
@@ -357,34 +416,33 @@ impl ColSpec {
     /// number of columns (column_count) and flags that indicates
     /// if Global_tables_spec is specified. It returns column_count of ColSpecs.
     pub fn parse_colspecs(
-        mut cursor: &mut Cursor<&[u8]>,
+        cursor: &mut Cursor<&[u8]>,
         column_count: i32,
         with_globale_table_spec: bool,
-    ) -> Vec<ColSpec> {
+    ) -> error::Result<Vec<ColSpec>> {
         (0..column_count)
             .map(|_| {
                 let ksname: Option<CString> = if !with_globale_table_spec {
-                    Some(CString::from_cursor(&mut cursor).unwrap())
+                    Some(CString::from_cursor(cursor)?)
                 } else {
                     None
                 };
 
                 let tablename = if !with_globale_table_spec {
-                    Some(CString::from_cursor(&mut cursor).unwrap())
+                    Some(CString::from_cursor(cursor)?)
                 } else {
                     None
                 };
 
-                // XXX unwrap
-                let name = CString::from_cursor(&mut cursor).unwrap();
-                let col_type = ColTypeOption::from_cursor(&mut cursor).unwrap();
+                let name = CString::from_cursor(cursor)?;
+                let col_type = ColTypeOption::from_cursor(cursor)?;
 
-                ColSpec {
+                Ok(ColSpec {
                     ksname: ksname,
                     tablename: tablename,
                     name: name,
                     col_type: col_type,
-                }
+                })
             })
             .collect()
     }
@@ -413,6 +471,7 @@ pub enum ColType {
     Time,
     Smallint,
     Tinyint,
+    Duration,
     List,
     Map,
     Set,
@@ -446,6 +505,7 @@ impl FromBytes for ColType {
                 0x0012 => Ok(ColType::Time),
                 0x0013 => Ok(ColType::Smallint),
                 0x0014 => Ok(ColType::Tinyint),
+                0x0015 => Ok(ColType::Duration),
                 0x0020 => Ok(ColType::List),
                 0x0021 => Ok(ColType::Map),
                 0x0022 => Ok(ColType::Set),
@@ -582,6 +642,9 @@ impl FromCursor for CTuple {
 pub struct BodyResResultPrepared {
     /// id of prepared request
     pub id: CBytesShort,
+    /// id of result metadata which should be sent with executions of a query.
+    /// Protocol v5 only.
+    pub result_metadata_id: Option<CBytesShort>,
     /// metadata
     pub metadata: PreparedMetadata,
     /// It is defined exactly the same as <metadata> in the Rows
@@ -589,20 +652,36 @@ pub struct BodyResResultPrepared {
     pub result_metadata: RowsMetadata,
 }
 
-impl FromCursor for BodyResResultPrepared {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResResultPrepared> {
+impl BodyResResultPrepared {
+    /// Parses a prepared result encoded in accordance to a given protocol version.
+    pub fn from_cursor_with_version(
+        mut cursor: &mut Cursor<&[u8]>,
+        protocol_version: ProtocolVersion,
+    ) -> error::Result<BodyResResultPrepared> {
         let id = CBytesShort::from_cursor(&mut cursor)?;
-        let metadata = PreparedMetadata::from_cursor(&mut cursor)?;
-        let result_metadata = RowsMetadata::from_cursor(&mut cursor)?;
+        let result_metadata_id = if protocol_version >= ProtocolVersion::V5 {
+            Some(CBytesShort::from_cursor(cursor)?)
+        } else {
+            None
+        };
+        let metadata = PreparedMetadata::from_cursor_with_version(cursor, protocol_version)?;
+        let result_metadata = RowsMetadata::from_cursor_with_version(cursor, protocol_version)?;
 
         Ok(BodyResResultPrepared {
             id: id,
+            result_metadata_id,
             metadata: metadata,
             result_metadata: result_metadata,
         })
     }
 }
 
+impl FromCursor for BodyResResultPrepared {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<BodyResResultPrepared> {
+        BodyResResultPrepared::from_cursor_with_version(cursor, ProtocolVersion::default())
+    }
+}
+
 /// The structure that represents metadata of prepared response.
 #[derive(Debug)]
 pub struct PreparedMetadata {
@@ -614,33 +693,23 @@ pub struct PreparedMetadata {
     pub col_specs: Vec<ColSpec>,
 }
 
-impl FromCursor for PreparedMetadata {
-    fn from_cursor(mut cursor: &mut Cursor<&[u8]>) -> error::Result<PreparedMetadata> {
+impl PreparedMetadata {
+    /// Parses prepared metadata encoded in accordance to a given protocol version.
+    pub fn from_cursor_with_version(
+        mut cursor: &mut Cursor<&[u8]>,
+        protocol_version: ProtocolVersion,
+    ) -> error::Result<PreparedMetadata> {
         let flags = CInt::from_cursor(&mut cursor)?;
         let columns_count = CInt::from_cursor(&mut cursor)?;
-        let pk_count = if cfg!(feature = "v3") {
+        let pk_count = if protocol_version == ProtocolVersion::V3 {
             0
         } else {
             // v4 or v5
             CInt::from_cursor(&mut cursor)?
         };
-        let pk_index_results: Vec<Option<i16>> = (0..pk_count)
-            .map(|_| {
-                cursor_next_value(&mut cursor, SHORT_LEN as u64)
-                    .ok()
-                    .and_then(|b| try_i16_from_bytes(b.as_slice()).ok())
-            })
-            .collect();
-
-        let pk_indexes: Vec<i16> = if pk_index_results.iter().any(Option::is_none) {
-            return Err("pk indexes error".into());
-        } else {
-            pk_index_results
-                .iter()
-                .cloned()
-                .map(|r| r.unwrap())
-                .collect()
-        };
+        let pk_indexes: Vec<i16> = (0..pk_count)
+            .map(|_| CIntShort::from_cursor(cursor))
+            .collect::<error::Result<_>>()?;
         let mut global_table_space: Option<(CString, CString)> = None;
         let has_global_table_space = RowsMetadataFlag::has_global_table_space(flags);
         if has_global_table_space {
@@ -648,7 +717,8 @@ impl FromCursor for PreparedMetadata {
             let tablename = CString::from_cursor(&mut cursor)?;
             global_table_space = Some((keyspace, tablename))
         }
-        let col_specs = ColSpec::parse_colspecs(&mut cursor, columns_count, has_global_table_space);
+        let col_specs =
+            ColSpec::parse_colspecs(cursor, columns_count, has_global_table_space)?;
 
         Ok(PreparedMetadata {
             flags: flags,
@@ -660,3 +730,85 @@ impl FromCursor for PreparedMetadata {
         })
     }
 }
+
+impl FromCursor for PreparedMetadata {
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> error::Result<PreparedMetadata> {
+        PreparedMetadata::from_cursor_with_version(cursor, ProtocolVersion::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepared_bytes(protocol_version: ProtocolVersion) -> Vec<u8> {
+        let mut bytes = vec![0, 1, 7];
+        if protocol_version >= ProtocolVersion::V5 {
+            // result metadata id
+            bytes.extend_from_slice(&[0, 1, 8]);
+        }
+        // prepared metadata with one partition key column
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        if protocol_version >= ProtocolVersion::V4 {
+            bytes.extend_from_slice(&[0, 0, 0, 1, 0, 0]);
+        }
+        // result metadata without columns
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn body_res_result_prepared_from_cursor() {
+        for protocol_version in vec![
+            ProtocolVersion::V3,
+            ProtocolVersion::V4,
+            ProtocolVersion::V5,
+        ] {
+            let bytes = prepared_bytes(protocol_version);
+            let prepared = BodyResResultPrepared::from_cursor_with_version(
+                &mut Cursor::new(&bytes),
+                protocol_version,
+            )
+            .unwrap();
+            assert_eq!(prepared.id.into_plain(), Some(vec![7]));
+            if protocol_version == ProtocolVersion::V5 {
+                assert_eq!(
+                    prepared.result_metadata_id.unwrap().into_plain(),
+                    Some(vec![8])
+                );
+            } else {
+                assert!(prepared.result_metadata_id.is_none());
+            }
+            if protocol_version == ProtocolVersion::V3 {
+                assert!(prepared.metadata.pk_indexes.is_empty());
+            } else {
+                assert_eq!(prepared.metadata.pk_indexes, vec![0]);
+            }
+        }
+    }
+
+    #[test]
+    fn rows_metadata_with_new_metadata_id() {
+        let bytes = vec![0, 0, 0, 0x08, 0, 0, 0, 0, 0, 2, 1, 2];
+        let metadata =
+            RowsMetadata::from_cursor_with_version(&mut Cursor::new(&bytes), ProtocolVersion::V5)
+                .unwrap();
+        assert_eq!(
+            metadata.new_metadata_id.unwrap().into_plain(),
+            Some(vec![1, 2])
+        );
+
+        let metadata =
+            RowsMetadata::from_cursor_with_version(&mut Cursor::new(&bytes), ProtocolVersion::V4)
+                .unwrap();
+        assert!(metadata.new_metadata_id.is_none());
+    }
+
+    #[test]
+    fn duration_col_type() {
+        assert!(matches!(
+            ColType::from_bytes(&[0, 0x15]).unwrap(),
+            ColType::Duration
+        ));
+    }
+}
@@ -36,7 +36,10 @@ impl<'a> IntoBytes for BodyReqStartup<'a> {
         let mut v = vec![];
         // push number of key-value pairs
         v.extend_from_slice(&self.num().as_slice());
-        for (key, val) in self.map.iter() {
+        // keep an order of options stable
+        let mut options: Vec<_> = self.map.iter().collect();
+        options.sort();
+        for (key, val) in options {
             // push key len
             v.extend_from_slice(to_short(key.len() as i16).as_slice());
             // push key itself
@@ -54,7 +57,7 @@ impl<'a> IntoBytes for BodyReqStartup<'a> {
 
 impl Frame {
     /// Creates new frame of type `startup`.
-    pub fn new_req_startup(compression: Option<&str>) -> Frame {
+    pub fn new_req_startup(compression: Option<&str>, protocol_version: ProtocolVersion) -> Frame {
         let version = Version::Request;
         let flag = Flag::Ignore;
         let stream = rand::random::<u16>();
@@ -63,6 +66,7 @@ impl Frame {
 
         Frame {
             version: version,
+            protocol_version,
             flags: vec![flag],
             stream: stream,
             opcode: opcode,
@@ -70,6 +74,7 @@ impl Frame {
             // for request frames it's always None
             tracing_id: None,
             warnings: vec![],
+            custom_payload: CustomPayload::new(),
         }
     }
 }
@@ -77,7 +82,7 @@ impl Frame {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::frame::{Flag, Frame, Opcode, Version};
+    use crate::frame::{Flag, Frame, Opcode, ProtocolVersion, Version};
 
     #[test]
     fn new_body_req_startup_some_compression() {
@@ -98,7 +103,7 @@ mod test {
     #[test]
     fn new_req_startup() {
         let compression = Some("test_compression");
-        let frame = Frame::new_req_startup(compression);
+        let frame = Frame::new_req_startup(compression, ProtocolVersion::V4);
         assert_eq!(frame.version, Version::Request);
         assert_eq!(frame.flags, vec![Flag::Ignore]);
         assert_eq!(frame.opcode, Opcode::Startup);
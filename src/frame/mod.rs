@@ -1,8 +1,10 @@
 //! `frame` module contains general Frame functionality.
 use crate::compression::Compression;
+use crate::frame::frame_error::{CDRSError, ErrorKind};
 use crate::frame::frame_response::ResponseBody;
 pub use crate::frame::traits::*;
-use crate::types::to_n_bytes;
+use crate::types::{to_n_bytes, CBytesMap};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 /// Number of stream bytes in accordance to protocol.
@@ -29,24 +31,35 @@ pub mod frame_result;
 pub mod frame_startup;
 pub mod frame_supported;
 pub mod parser;
+pub mod segment;
 pub mod traits;
 
+use std::convert::TryFrom;
+
 use crate::error;
 
+/// Custom payload of a frame - a map of arbitrary bytes which is passed to and from
+/// custom query handlers of a server. Supported since protocol v4.
+pub type CustomPayload = HashMap<String, Vec<u8>>;
+
 #[derive(Debug)]
 pub struct Frame {
     pub version: Version,
+    pub protocol_version: ProtocolVersion,
     pub flags: Vec<Flag>,
     pub opcode: Opcode,
     pub stream: u16,
     pub body: Vec<u8>,
     pub tracing_id: Option<Uuid>,
     pub warnings: Vec<String>,
+    /// Custom payload of a response. Payloads of request frames are a part of their
+    /// bodies, so it is always empty for them.
+    pub custom_payload: CustomPayload,
 }
 
 impl Frame {
     pub fn get_body(&self) -> error::Result<ResponseBody> {
-        ResponseBody::from(self.body.as_slice(), &self.opcode)
+        ResponseBody::from(self.body.as_slice(), &self.opcode, self.protocol_version)
     }
 
     pub fn tracing_id(&self) -> &Option<Uuid> {
@@ -57,10 +70,40 @@ impl Frame {
         &self.warnings
     }
 
-    pub fn encode_with(self, compressor: Compression) -> error::Result<Vec<u8>> {
+    pub fn custom_payload(&self) -> &CustomPayload {
+        &self.custom_payload
+    }
+
+    /// Prepends a custom payload to a body of a request frame and sets
+    /// `CustomPayload` flag. Empty payloads are not sent, as well as any payload
+    /// in protocol v3 which does not support them.
+    pub(crate) fn with_custom_payload(mut self, custom_payload: &CustomPayload) -> Frame {
+        if custom_payload.is_empty() || self.protocol_version < ProtocolVersion::V4 {
+            return self;
+        }
+
+        let mut body = CBytesMap::new(custom_payload.clone()).into_cbytes();
+        body.append(&mut self.body);
+        self.body = body;
+        self.flags.push(Flag::CustomPayload);
+        self
+    }
+
+    /// Encodes a frame and compresses its body with a given compressor. `Compression`
+    /// flag is set if the body gets compressed. `STARTUP` frames are never compressed.
+    pub fn encode_with(mut self, compressor: Compression) -> error::Result<Vec<u8>> {
         let mut v = vec![];
 
-        let version_bytes = self.version.as_byte();
+        let compressor = if self.opcode == Opcode::Startup {
+            Compression::None
+        } else {
+            compressor
+        };
+        if compressor != Compression::None && !self.flags.contains(&Flag::Compression) {
+            self.flags.push(Flag::Compression);
+        }
+
+        let version_bytes = self.version.as_byte_with(self.protocol_version);
         let flag_bytes = Flag::many_to_cbytes(&self.flags);
         let opcode_bytes = self.opcode.as_byte();
         let encoded_body = compressor.encode(self.body)?;
@@ -75,13 +118,25 @@ impl Frame {
 
         Ok(v)
     }
+
+    /// Encodes a frame which is sent over a connection that has been already started up.
+    /// In protocol v5 the frame is wrapped into segments and `compressor` is applied
+    /// to the segments, otherwise it is the same as `encode_with`.
+    pub fn encode_framed(self, compressor: Compression) -> error::Result<Vec<u8>> {
+        if self.protocol_version.is_segmented() {
+            let frame = self.encode_with(Compression::None)?;
+            segment::encode_segments(frame.as_slice(), compressor)
+        } else {
+            self.encode_with(compressor)
+        }
+    }
 }
 
 impl<'a> IntoBytes for Frame {
     fn into_cbytes(&self) -> Vec<u8> {
         let mut v = vec![];
 
-        let version_bytes = self.version.as_byte();
+        let version_bytes = self.version.as_byte_with(self.protocol_version);
         let flag_bytes = Flag::many_to_cbytes(&self.flags);
         let opcode_bytes = self.opcode.as_byte();
         let body_len = self.body.len();
@@ -97,6 +152,109 @@ impl<'a> IntoBytes for Frame {
     }
 }
 
+/// Version of native protocol. A driver attempts `ProtocolVersion::HIGHEST` first
+/// and goes down to lower ones if a server does not support it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProtocolVersion {
+    V3,
+    V4,
+    V5,
+}
+
+impl ProtocolVersion {
+    /// The highest protocol version supported by the driver, v5 is only attempted if
+    /// `v5` feature is enabled. Connections are started up with it unless a server
+    /// rejects it.
+    pub const HIGHEST: ProtocolVersion = if cfg!(feature = "v5") {
+        ProtocolVersion::V5
+    } else {
+        ProtocolVersion::V4
+    };
+
+    /// Returns a protocol version encoded in a version byte of a frame header
+    /// or `None` if the version is not supported.
+    pub fn from_byte(byte: u8) -> Option<ProtocolVersion> {
+        match byte & !RESPONSE_VERSION_BIT {
+            0x03 => Some(ProtocolVersion::V3),
+            0x04 => Some(ProtocolVersion::V4),
+            0x05 => Some(ProtocolVersion::V5),
+            _ => None,
+        }
+    }
+
+    /// Returns the next lower protocol version or `None` if it is the lowest one.
+    pub fn lower(self) -> Option<ProtocolVersion> {
+        match self {
+            ProtocolVersion::V3 => None,
+            ProtocolVersion::V4 => Some(ProtocolVersion::V3),
+            ProtocolVersion::V5 => Some(ProtocolVersion::V4),
+        }
+    }
+
+    /// Returns a lower protocol version which should be attempted if a server has
+    /// rejected this one with a given error. `None` is returned if the error is not
+    /// caused by an unsupported protocol version or there is no lower version to try.
+    pub fn downgrade(self, error: &CDRSError) -> Option<ProtocolVersion> {
+        let message = error.message.as_str().to_lowercase();
+        if error.kind() != ErrorKind::Protocol
+            || !(message.contains("protocol version")
+                || message.contains("version of the protocol"))
+        {
+            return None;
+        }
+
+        // e.g. "Invalid or unsupported protocol version (5); supported versions are
+        // (3/v3, 4/v4, 5/v5-beta)", beta versions require a flag the driver does not set
+        let supported = match message.split("supported versions are").nth(1) {
+            Some(supported) => supported,
+            None => return self.lower(),
+        };
+        supported
+            .trim()
+            .trim_start_matches('(')
+            .split(&[',', ')'][..])
+            .map(str::trim)
+            .filter(|version| !version.ends_with("-beta"))
+            .filter_map(|version| version.split('/').next())
+            .filter_map(|version| version.parse::<u8>().ok())
+            .filter_map(ProtocolVersion::from_byte)
+            .filter(|version| *version < self)
+            .max()
+    }
+
+    /// Shows if frames are wrapped into segments once a connection is started up.
+    pub fn is_segmented(self) -> bool {
+        self >= ProtocolVersion::V5
+    }
+}
+
+impl Default for ProtocolVersion {
+    /// Protocol version selected by features - one of `v3`, `v4` or `v5`. It is used
+    /// by frames which are built without an explicit version, connections negotiate
+    /// theirs starting from `ProtocolVersion::HIGHEST`.
+    fn default() -> Self {
+        if cfg!(feature = "v5") {
+            ProtocolVersion::V5
+        } else if cfg!(feature = "v3") {
+            ProtocolVersion::V3
+        } else {
+            ProtocolVersion::V4
+        }
+    }
+}
+
+impl AsByte for ProtocolVersion {
+    fn as_byte(&self) -> u8 {
+        match self {
+            ProtocolVersion::V3 => 0x03,
+            ProtocolVersion::V4 => 0x04,
+            ProtocolVersion::V5 => 0x05,
+        }
+    }
+}
+
+const RESPONSE_VERSION_BIT: u8 = 0x80;
+
 /// Frame's version
 #[derive(Debug, PartialEq)]
 pub enum Version {
@@ -108,79 +266,45 @@ impl Version {
     /// Number of bytes that represent Cassandra frame's version.
     pub const BYTE_LENGTH: usize = 1;
 
-    /// It returns an actual Cassandra request frame version that CDRS can work with.
-    /// This version is based on selected feature - on of `v3`, `v4` or `v5`.
-    fn request_version() -> u8 {
-        if cfg!(feature = "v3") {
-            0x03
-        } else if cfg!(feature = "v4") || cfg!(feature = "v5") {
-            0x04
-        } else {
-            panic!(
-                "{}",
-                "Protocol version is not supported. CDRS should be run with protocol feature \
-                 set to v3, v4 or v5"
-            );
-        }
-    }
-
-    /// It returns an actual Cassandra response frame version that CDRS can work with.
-    /// This version is based on selected feature - on of `v3`, `v4` or `v5`.
-    fn response_version() -> u8 {
-        if cfg!(feature = "v3") {
-            0x83
-        } else if cfg!(feature = "v4") || cfg!(feature = "v5") {
-            0x84
-        } else {
-            panic!(
-                "{}",
-                "Protocol version is not supported. CDRS should be run with protocol feature \
-                 set to v3, v4 or v5"
-            );
+    /// Returns a version byte of a frame which is encoded with a given protocol version.
+    pub fn as_byte_with(&self, protocol_version: ProtocolVersion) -> u8 {
+        match self {
+            Version::Request => protocol_version.as_byte(),
+            Version::Response => protocol_version.as_byte() | RESPONSE_VERSION_BIT,
         }
     }
 }
 
 impl AsByte for Version {
     fn as_byte(&self) -> u8 {
-        match self {
-            &Version::Request => Version::request_version(),
-            &Version::Response => Version::response_version(),
-        }
+        self.as_byte_with(ProtocolVersion::default())
     }
 }
 
-impl From<Vec<u8>> for Version {
-    fn from(v: Vec<u8>) -> Version {
+impl TryFrom<Vec<u8>> for Version {
+    type Error = error::Error;
+
+    fn try_from(v: Vec<u8>) -> error::Result<Version> {
         if v.len() != Self::BYTE_LENGTH {
-            error!(
-                "Unexpected Cassandra verion. Should has {} byte(-s), got {:?}",
+            return Err(error::Error::InvalidFrame(format!(
+                "Unexpected Cassandra version. Should has {} byte(-s), got {:?}",
                 Self::BYTE_LENGTH,
                 v
-            );
-            panic!(
-                "Unexpected Cassandra verion. Should has {} byte(-s), got {:?}",
-                Self::BYTE_LENGTH,
-                v
-            );
+            )));
         }
         let version = v[0];
-        let req = Version::request_version();
-        let res = Version::response_version();
 
-        if version == req {
-            Version::Request
-        } else if version == res {
-            Version::Response
+        if ProtocolVersion::from_byte(version).is_none() {
+            return Err(error::Error::InvalidFrame(format!(
+                "Unsupported Cassandra version {:#04x}",
+                version
+            )));
+        }
+
+        if version & RESPONSE_VERSION_BIT == 0 {
+            Ok(Version::Request)
         } else {
-            error!(
-                "Unexpected Cassandra version {:?}, either {:?} or {:?} is expected",
-                version, req, res
-            );
-            panic!(
-                "Unexpected Cassandra version {:?}, either {:?} or {:?} is expected",
-                version, req, res
-            );
+            Ok(Version::Response)
         }
     }
 }
@@ -198,7 +322,7 @@ pub enum Flag {
 
 impl Flag {
     /// Number of flag bytes in accordance to protocol.
-    const BYTE_LENGTH: usize = 1;
+    pub const BYTE_LENGTH: usize = 1;
 
     /// It returns selected flags collection.
     pub fn get_collection(flags: u8) -> Vec<Flag> {
@@ -324,9 +448,11 @@ impl AsByte for Opcode {
     }
 }
 
-impl From<u8> for Opcode {
-    fn from(b: u8) -> Opcode {
-        match b {
+impl TryFrom<u8> for Opcode {
+    type Error = error::Error;
+
+    fn try_from(b: u8) -> error::Result<Opcode> {
+        let opcode = match b {
             0x00 => Opcode::Error,
             0x01 => Opcode::Startup,
             0x02 => Opcode::Ready,
@@ -343,8 +469,15 @@ impl From<u8> for Opcode {
             0x0E => Opcode::AuthChallenge,
             0x0F => Opcode::AuthResponse,
             0x10 => Opcode::AuthSuccess,
-            _ => unreachable!(),
-        }
+            _ => {
+                return Err(error::Error::InvalidFrame(format!(
+                    "Unknown opcode {:#04x}",
+                    b
+                )))
+            }
+        };
+
+        Ok(opcode)
     }
 }
 
@@ -354,7 +487,7 @@ mod tests {
     use crate::frame::traits::AsByte;
 
     #[test]
-    #[cfg(not(feature = "v3"))]
+    #[cfg(not(any(feature = "v3", feature = "v5")))]
     fn test_frame_version_as_byte() {
         let request_version = Version::Request;
         assert_eq!(request_version.as_byte(), 0x04);
@@ -363,7 +496,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "v3")]
+    #[cfg(all(feature = "v3", not(feature = "v5")))]
     fn test_frame_version_as_byte_v3() {
         let request_version = Version::Request;
         assert_eq!(request_version.as_byte(), 0x03);
@@ -372,21 +505,118 @@ mod tests {
     }
 
     #[test]
-    #[cfg(not(feature = "v3"))]
+    #[cfg(feature = "v5")]
+    fn test_frame_version_as_byte_v5() {
+        let request_version = Version::Request;
+        assert_eq!(request_version.as_byte(), 0x05);
+        let response_version = Version::Response;
+        assert_eq!(response_version.as_byte(), 0x85);
+    }
+
+    #[test]
+    #[cfg(not(any(feature = "v3", feature = "v5")))]
     fn test_frame_version_from() {
         let request: Vec<u8> = vec![0x04];
-        assert_eq!(Version::from(request), Version::Request);
+        assert_eq!(Version::try_from(request).unwrap(), Version::Request);
         let response: Vec<u8> = vec![0x84];
-        assert_eq!(Version::from(response), Version::Response);
+        assert_eq!(Version::try_from(response).unwrap(), Version::Response);
     }
 
     #[test]
-    #[cfg(feature = "v3")]
+    #[cfg(all(feature = "v3", not(feature = "v5")))]
     fn test_frame_version_from_v3() {
         let request: Vec<u8> = vec![0x03];
-        assert_eq!(Version::from(request), Version::Request);
+        assert_eq!(Version::try_from(request).unwrap(), Version::Request);
         let response: Vec<u8> = vec![0x83];
-        assert_eq!(Version::from(response), Version::Response);
+        assert_eq!(Version::try_from(response).unwrap(), Version::Response);
+    }
+
+    #[test]
+    #[cfg(feature = "v5")]
+    fn test_frame_version_from_v5() {
+        let request: Vec<u8> = vec![0x05];
+        assert_eq!(Version::try_from(request).unwrap(), Version::Request);
+        let response: Vec<u8> = vec![0x85];
+        assert_eq!(Version::try_from(response).unwrap(), Version::Response);
+    }
+
+    #[test]
+    fn highest_version_requires_v5_feature() {
+        let expected = if cfg!(feature = "v5") {
+            ProtocolVersion::V5
+        } else {
+            ProtocolVersion::V4
+        };
+        assert_eq!(ProtocolVersion::HIGHEST, expected);
+    }
+
+    #[test]
+    fn test_frame_version_try_from_invalid() {
+        assert!(Version::try_from(vec![0x02]).is_err());
+        assert!(Version::try_from(vec![0x86]).is_err());
+        assert!(Version::try_from(vec![]).is_err());
+        assert!(Version::try_from(vec![0x04, 0x04]).is_err());
+    }
+
+    #[test]
+    fn test_protocol_version_from_byte() {
+        assert_eq!(ProtocolVersion::from_byte(0x03), Some(ProtocolVersion::V3));
+        assert_eq!(ProtocolVersion::from_byte(0x84), Some(ProtocolVersion::V4));
+        assert_eq!(ProtocolVersion::from_byte(0x85), Some(ProtocolVersion::V5));
+        assert_eq!(ProtocolVersion::from_byte(0x02), None);
+        assert_eq!(ProtocolVersion::from_byte(0x86), None);
+    }
+
+    #[test]
+    fn test_frame_version_as_byte_with() {
+        assert_eq!(Version::Request.as_byte_with(ProtocolVersion::V3), 0x03);
+        assert_eq!(Version::Response.as_byte_with(ProtocolVersion::V4), 0x84);
+        assert_eq!(Version::Response.as_byte_with(ProtocolVersion::V5), 0x85);
+    }
+
+    #[test]
+    fn test_protocol_version_downgrade() {
+        let error = |code: i32, message: &str| CDRSError {
+            error_code: code,
+            message: crate::types::CString::new(message.to_string()),
+            additional_info: frame_error::AdditionalErrorInfo::Protocol(
+                frame_error::SimpleError {},
+            ),
+        };
+
+        // verbatim messages of Cassandra 3.11 and 2.2
+        let supported = error(
+            0x000A,
+            "Invalid or unsupported protocol version (5); supported versions are (3/v3, 4/v4, 5/v5-beta)",
+        );
+        assert_eq!(
+            ProtocolVersion::V5.downgrade(&supported),
+            Some(ProtocolVersion::V4)
+        );
+        let supported_with_beta = error(
+            0x000A,
+            "Invalid or unsupported protocol version (5); supported versions are (1/v1, 2/v2, 3/v3, 4/v4-beta)",
+        );
+        assert_eq!(
+            ProtocolVersion::V5.downgrade(&supported_with_beta),
+            Some(ProtocolVersion::V3)
+        );
+        assert_eq!(ProtocolVersion::V3.downgrade(&supported), None);
+
+        let beta = error(
+            0x000A,
+            "Beta version of the protocol used (5/v5-beta), but USE_BETA flag is unset",
+        );
+        assert_eq!(
+            ProtocolVersion::V5.downgrade(&beta),
+            Some(ProtocolVersion::V4)
+        );
+        assert_eq!(ProtocolVersion::V3.downgrade(&beta), None);
+
+        let other = error(0x000A, "Unknown opcode");
+        assert_eq!(ProtocolVersion::V5.downgrade(&other), None);
+        let not_protocol = error(0x2200, "Invalid protocol version");
+        assert_eq!(ProtocolVersion::V5.downgrade(&not_protocol), None);
     }
 
     #[test]
@@ -475,21 +705,120 @@ mod tests {
 
     #[test]
     fn test_opcode_from() {
-        assert_eq!(Opcode::from(0x00), Opcode::Error);
-        assert_eq!(Opcode::from(0x01), Opcode::Startup);
-        assert_eq!(Opcode::from(0x02), Opcode::Ready);
-        assert_eq!(Opcode::from(0x03), Opcode::Authenticate);
-        assert_eq!(Opcode::from(0x05), Opcode::Options);
-        assert_eq!(Opcode::from(0x06), Opcode::Supported);
-        assert_eq!(Opcode::from(0x07), Opcode::Query);
-        assert_eq!(Opcode::from(0x08), Opcode::Result);
-        assert_eq!(Opcode::from(0x09), Opcode::Prepare);
-        assert_eq!(Opcode::from(0x0A), Opcode::Execute);
-        assert_eq!(Opcode::from(0x0B), Opcode::Register);
-        assert_eq!(Opcode::from(0x0C), Opcode::Event);
-        assert_eq!(Opcode::from(0x0D), Opcode::Batch);
-        assert_eq!(Opcode::from(0x0E), Opcode::AuthChallenge);
-        assert_eq!(Opcode::from(0x0F), Opcode::AuthResponse);
-        assert_eq!(Opcode::from(0x10), Opcode::AuthSuccess);
+        assert_eq!(Opcode::try_from(0x00).unwrap(), Opcode::Error);
+        assert_eq!(Opcode::try_from(0x01).unwrap(), Opcode::Startup);
+        assert_eq!(Opcode::try_from(0x02).unwrap(), Opcode::Ready);
+        assert_eq!(Opcode::try_from(0x03).unwrap(), Opcode::Authenticate);
+        assert_eq!(Opcode::try_from(0x05).unwrap(), Opcode::Options);
+        assert_eq!(Opcode::try_from(0x06).unwrap(), Opcode::Supported);
+        assert_eq!(Opcode::try_from(0x07).unwrap(), Opcode::Query);
+        assert_eq!(Opcode::try_from(0x08).unwrap(), Opcode::Result);
+        assert_eq!(Opcode::try_from(0x09).unwrap(), Opcode::Prepare);
+        assert_eq!(Opcode::try_from(0x0A).unwrap(), Opcode::Execute);
+        assert_eq!(Opcode::try_from(0x0B).unwrap(), Opcode::Register);
+        assert_eq!(Opcode::try_from(0x0C).unwrap(), Opcode::Event);
+        assert_eq!(Opcode::try_from(0x0D).unwrap(), Opcode::Batch);
+        assert_eq!(Opcode::try_from(0x0E).unwrap(), Opcode::AuthChallenge);
+        assert_eq!(Opcode::try_from(0x0F).unwrap(), Opcode::AuthResponse);
+        assert_eq!(Opcode::try_from(0x10).unwrap(), Opcode::AuthSuccess);
+    }
+
+    #[test]
+    fn test_opcode_try_from_unknown() {
+        assert!(Opcode::try_from(0x04).is_err());
+        assert!(Opcode::try_from(0xFF).is_err());
+    }
+
+    fn query_frame(protocol_version: ProtocolVersion) -> Frame {
+        let query = crate::query::Query {
+            query: "SELECT * FROM system.local".to_string(),
+            params: Default::default(),
+        };
+        let mut frame = Frame::new_query(query, vec![], protocol_version);
+        frame.stream = 0;
+        frame
+    }
+
+    #[test]
+    fn test_frame_encode_with_compression() {
+        let fixtures = vec![
+            (Compression::None, "frame/query/none"),
+            (Compression::Lz4, "frame/query/lz4"),
+            (Compression::Snappy, "frame/query/snappy"),
+        ];
+
+        for (compression, fixture) in fixtures {
+            let encoded = query_frame(ProtocolVersion::V4)
+                .encode_with(compression)
+                .unwrap();
+            assert_eq!(encoded, fixture!(fixture).unwrap(), "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn test_frame_encode_with_compression_round_trip() {
+        for compression in vec![Compression::None, Compression::Lz4, Compression::Snappy] {
+            let encoded = query_frame(ProtocolVersion::V4)
+                .encode_with(compression)
+                .unwrap();
+            let cursor = std::io::Cursor::new(encoded);
+            let decoded =
+                parser::parse_frame(&std::cell::RefCell::new(cursor), &compression).unwrap();
+            assert_eq!(decoded.body, query_frame(ProtocolVersion::V4).body);
+            assert_eq!(
+                decoded.flags.contains(&Flag::Compression),
+                compression != Compression::None
+            );
+        }
+    }
+
+    #[test]
+    fn test_frame_encode_framed_round_trip() {
+        let versions = vec![
+            ProtocolVersion::V3,
+            ProtocolVersion::V4,
+            ProtocolVersion::V5,
+        ];
+        for protocol_version in versions {
+            for compression in vec![Compression::None, Compression::Lz4] {
+                let encoded = query_frame(protocol_version)
+                    .encode_framed(compression)
+                    .unwrap();
+                let cursor = std::io::Cursor::new(encoded);
+                let decoded = parser::parse_framed(
+                    &std::cell::RefCell::new(cursor),
+                    &compression,
+                    protocol_version,
+                )
+                .unwrap();
+                assert_eq!(decoded.opcode, Opcode::Query);
+                assert_eq!(decoded.protocol_version, protocol_version);
+                assert_eq!(decoded.body, query_frame(protocol_version).body);
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_encode_framed_v5() {
+        let frame = query_frame(ProtocolVersion::V5)
+            .encode_with(Compression::None)
+            .unwrap();
+        let encoded = query_frame(ProtocolVersion::V5)
+            .encode_framed(Compression::Lz4)
+            .unwrap();
+        let segment =
+            segment::Segment::read(&mut std::io::Cursor::new(encoded), Compression::Lz4).unwrap();
+        // frames are not compressed, segments are
+        assert_eq!(segment.payload, frame);
+        assert!(segment.is_self_contained);
+    }
+
+    #[test]
+    fn test_frame_startup_is_never_compressed() {
+        let frame = Frame::new_req_startup(Compression::Lz4.as_str(), ProtocolVersion::V4);
+        let body = frame.body.clone();
+        let encoded = frame.encode_with(Compression::Lz4).unwrap();
+        assert_eq!(encoded[1], 0);
+        assert_eq!(&encoded[9..], body.as_slice());
     }
 }
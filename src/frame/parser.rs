@@ -1,16 +1,39 @@
 use r2d2;
 use std::cell::RefCell;
-use std::io::{Cursor, Read};
+use std::convert::TryFrom;
+use std::io::{self, Cursor, Read};
 use std::ops::Deref;
 
 use super::*;
 use crate::compression::Compression;
 use crate::error;
 use crate::frame::frame_response::ResponseBody;
+use crate::frame::segment::SegmentDecoder;
 use crate::frame::FromCursor;
 use crate::transport::CDRSTransport;
 use crate::types::data_serialization_types::decode_timeuuid;
-use crate::types::{from_bytes, from_u16_bytes, CStringList, UUID_LEN};
+use crate::types::{from_bytes, from_u16_bytes, CBytesMap, CStringList, UUID_LEN};
+
+const STREAM_OFFSET: usize = Version::BYTE_LENGTH + Flag::BYTE_LENGTH;
+const LENGTH_OFFSET: usize = STREAM_OFFSET + STREAM_LEN + Opcode::BYTE_LENGTH;
+
+/// Number of bytes in a frame header.
+pub const HEADER_LEN: usize = LENGTH_OFFSET + LENGTH_LEN;
+
+/// Returns a stream id and a body length of a frame which header is given.
+/// `header` should contain at least `HEADER_LEN` bytes.
+pub(crate) fn parse_header(header: &[u8]) -> (u16, usize) {
+    let stream = from_u16_bytes(&header[STREAM_OFFSET..STREAM_OFFSET + STREAM_LEN]);
+    let length = from_bytes(&header[LENGTH_OFFSET..HEADER_LEN]) as usize;
+
+    (stream, length)
+}
+
+/// Replaces a stream id in a frame which header is given.
+/// `header` should contain at least `HEADER_LEN` bytes.
+pub(crate) fn set_header_stream(header: &mut [u8], stream: u16) {
+    header[STREAM_OFFSET..STREAM_OFFSET + STREAM_LEN].copy_from_slice(&stream.to_be_bytes());
+}
 
 pub fn from_connection<M, T>(
     conn: &r2d2::PooledConnection<M>,
@@ -23,7 +46,23 @@ where
     parse_frame(conn.deref(), compressor)
 }
 
-pub fn parse_frame(cursor_cell: &RefCell<dyn Read>, compressor: &Compression) -> error::Result<Frame> {
+/// Reads a frame which is received over a connection that has been already started up
+/// with a given protocol version. In protocol v5 the frame is unwrapped from segments,
+/// otherwise it is the same as `parse_frame`.
+pub fn parse_framed(
+    cursor_cell: &RefCell<dyn Read + '_>,
+    compressor: &Compression,
+    protocol_version: ProtocolVersion,
+) -> error::Result<Frame> {
+    if protocol_version.is_segmented() {
+        let frame = SegmentDecoder::new(*compressor).read_frame(&mut *cursor_cell.borrow_mut())?;
+        parse_frame(&RefCell::new(Cursor::new(frame)), &Compression::None)
+    } else {
+        parse_frame(cursor_cell, compressor)
+    }
+}
+
+pub fn parse_frame(cursor_cell: &RefCell<dyn Read + '_>, compressor: &Compression) -> error::Result<Frame> {
     let mut version_bytes = [0; Version::BYTE_LENGTH];
     let mut flag_bytes = [0; Flag::BYTE_LENGTH];
     let mut opcode_bytes = [0; Opcode::BYTE_LENGTH];
@@ -38,19 +77,36 @@ pub fn parse_frame(cursor_cell: &RefCell<dyn Read>, compressor: &Compression) ->
     cursor.read_exact(&mut opcode_bytes)?;
     cursor.read_exact(&mut length_bytes)?;
 
-    let version = Version::from(version_bytes.to_vec());
+    let protocol_version = ProtocolVersion::from_byte(version_bytes[0]).ok_or_else(|| {
+        error::Error::InvalidFrame(format!(
+            "Unsupported protocol version {:#04x}",
+            version_bytes[0]
+        ))
+    })?;
+    let version = Version::try_from(version_bytes.to_vec())?;
     let flags = Flag::get_collection(flag_bytes[0]);
     let stream = from_u16_bytes(&stream_bytes);
-    let opcode = Opcode::from(opcode_bytes[0]);
+    let opcode = Opcode::try_from(opcode_bytes[0])?;
     let length = from_bytes(&length_bytes) as usize;
 
-    let mut body_bytes = Vec::with_capacity(length);
-    unsafe {
-        body_bytes.set_len(length);
+    // a body is read gradually, so a corrupted length does not make us
+    // allocate gigabytes before the stream runs out of bytes
+    let mut body_bytes = Vec::new();
+    (&mut *cursor)
+        .take(length as u64)
+        .read_to_end(&mut body_bytes)?;
+    if body_bytes.len() != length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "Frame body is truncated: {} bytes, {} expected",
+                body_bytes.len(),
+                length
+            ),
+        )
+        .into());
     }
 
-    cursor.read_exact(&mut body_bytes)?;
-
     let full_body = if flags.iter().any(|flag| flag == &Flag::Compression) {
         compressor.decode(body_bytes)?
     } else {
@@ -61,35 +117,46 @@ pub fn parse_frame(cursor_cell: &RefCell<dyn Read>, compressor: &Compression) ->
     let mut body_cursor = Cursor::new(full_body.as_slice());
 
     let tracing_id = if flags.iter().any(|flag| flag == &Flag::Tracing) {
-        let mut tracing_bytes = Vec::with_capacity(UUID_LEN);
-        unsafe {
-            tracing_bytes.set_len(UUID_LEN);
-        }
-        body_cursor.read_exact(&mut tracing_bytes)?;
+        let mut tracing_bytes = [0; UUID_LEN];
+        body_cursor
+            .read_exact(&mut tracing_bytes)
+            .map_err(|err| error::decoding_error(err.into()))?;
 
-        decode_timeuuid(tracing_bytes.as_slice()).ok()
+        decode_timeuuid(&tracing_bytes).ok()
     } else {
         None
     };
 
     let warnings = if flags.iter().any(|flag| flag == &Flag::Warning) {
-        CStringList::from_cursor(&mut body_cursor)?.into_plain()
+        CStringList::from_cursor(&mut body_cursor)
+            .map_err(error::decoding_error)?
+            .into_plain()
     } else {
         vec![]
     };
 
+    let custom_payload = if flags.iter().any(|flag| flag == &Flag::CustomPayload) {
+        CBytesMap::from_cursor(&mut body_cursor)
+            .map_err(error::decoding_error)?
+            .into_plain()
+    } else {
+        CustomPayload::new()
+    };
+
     let mut body = vec![];
 
     body_cursor.read_to_end(&mut body)?;
 
     let frame = Frame {
         version: version,
+        protocol_version,
         flags: flags,
         opcode: opcode,
         stream: stream,
         body: body,
         tracing_id: tracing_id,
         warnings: warnings,
+        custom_payload,
     };
 
     convert_frame_into_result(frame)
@@ -99,8 +166,130 @@ fn convert_frame_into_result(frame: Frame) -> error::Result<Frame> {
     match frame.opcode {
         Opcode::Error => frame.get_body().and_then(|err| match err {
             ResponseBody::Error(err) => Err(error::Error::Server(err)),
-            _ => unreachable!(),
+            body => Err(error::Error::InvalidFrame(format!(
+                "Unexpected body of error frame {:?}",
+                body
+            ))),
         }),
         _ => Ok(frame),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{corrupted, response_bodies, seeded_rng, versioned_response_frame};
+    use rand::Rng;
+
+    const COMPRESSIONS: [Compression; 3] =
+        [Compression::None, Compression::Lz4, Compression::Snappy];
+
+    fn parse(bytes: Vec<u8>, compression: Compression) -> error::Result<Frame> {
+        parse_frame(&RefCell::new(Cursor::new(bytes)), &compression)
+    }
+
+    /// Parses a frame and decodes its body, so both stages are exercised.
+    fn parse_and_decode(bytes: Vec<u8>, compression: Compression) {
+        if let Ok(frame) = parse(bytes, compression) {
+            let _ = frame.get_body();
+        }
+    }
+
+    fn response_frames() -> Vec<Vec<u8>> {
+        let mut frames = vec![];
+        for protocol_version in vec![
+            ProtocolVersion::V3,
+            ProtocolVersion::V4,
+            ProtocolVersion::V5,
+        ] {
+            for (opcode, body) in response_bodies() {
+                frames.push(versioned_response_frame(opcode, body, protocol_version));
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn parses_response_frames() {
+        for frame in response_frames() {
+            match parse(frame.clone(), Compression::None) {
+                Ok(_) => {}
+                Err(error::Error::Server(_)) if frame[4] == Opcode::Error.as_byte() => {}
+                result => panic!("{:?} is parsed as {:?}", frame, result),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_protocol_version() {
+        let mut frame = versioned_response_frame(Opcode::Ready, vec![], ProtocolVersion::V4);
+        frame[0] = 0x82;
+        match parse(frame, Compression::None) {
+            Err(error::Error::InvalidFrame(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_opcode() {
+        let mut frame = versioned_response_frame(Opcode::Ready, vec![], ProtocolVersion::V4);
+        frame[4] = 0x04;
+        match parse(frame, Compression::None) {
+            Err(error::Error::InvalidFrame(_)) => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_body() {
+        let mut frame = versioned_response_frame(Opcode::Ready, vec![], ProtocolVersion::V4);
+        // body length is far larger than the actual body
+        frame[5] = 0x7F;
+        match parse(frame, Compression::None) {
+            Err(error::Error::Io(ref err)) if err.kind() == io::ErrorKind::UnexpectedEof => {}
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn does_not_panic_on_truncated_frames() {
+        for frame in response_frames() {
+            for len in 0..frame.len() {
+                assert!(parse(frame[..len].to_vec(), Compression::None).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn does_not_panic_on_corrupted_frames() {
+        let mut rng = seeded_rng();
+        for frame in response_frames() {
+            for compression in COMPRESSIONS.iter() {
+                for _ in 0..100 {
+                    parse_and_decode(corrupted(&frame, &mut rng), *compression);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn does_not_panic_on_random_frames() {
+        let mut rng = seeded_rng();
+        for compression in COMPRESSIONS.iter() {
+            for _ in 0..2000 {
+                let len = rng.gen_range(0, 64);
+                let mut body: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+                let mut frame = vec![
+                    *rng.choose(&[0x83, 0x84, 0x85]).unwrap(),
+                    rng.gen_range(0, 0x10),
+                    0,
+                    0,
+                    rng.gen_range(0, 0x11),
+                ];
+                frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+                frame.append(&mut body);
+                parse_and_decode(frame, *compression);
+            }
+        }
+    }
+}
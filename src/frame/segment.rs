@@ -0,0 +1,499 @@
+//! Segments are the framing layer of protocol v5. Once a connection is started up
+//! (i.e. a server responded to `STARTUP` request), frames are not sent as is anymore,
+//! instead they are wrapped into segments as it is described in [Apache Cassandra protocol](
+//! https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v5.spec#L213).
+//!
+//! A self-contained segment contains one or more complete frames, while a frame which
+//! does not fit into a single segment is split into several non-self-contained ones.
+//! Integrity of both segment headers and payloads is protected with checksums.
+//! If compression was negotiated then payloads of segments are compressed rather
+//! than bodies of frames. Only LZ4 compression is supported by protocol v5.
+use std::io::Read;
+
+use lz4_compress as lz4;
+
+use crate::compression::{Compression, CompressionError};
+use crate::error;
+use crate::frame::parser::{parse_header, HEADER_LEN};
+
+/// Maximum number of payload bytes a single segment can carry.
+pub const MAX_PAYLOAD_LEN: usize = (1 << 17) - 1;
+
+const CRC24_LEN: usize = 3;
+const CRC32_LEN: usize = 4;
+const UNCOMPRESSED_HEADER_LEN: usize = 3;
+const COMPRESSED_HEADER_LEN: usize = 5;
+
+const CRC24_INIT: u32 = 0x0087_5060;
+const CRC24_POLY: u32 = 0x0197_4F0B;
+const CRC32_INITIAL_BYTES: [u8; 4] = [0xFA, 0x2D, 0x55, 0xCA];
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+/// Computes CRC24 checksum of a segment header.
+fn crc24(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(CRC24_INIT, |mut crc, byte| {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+        crc
+    })
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Computes CRC32 checksum of a segment payload. The checksum is seeded with
+/// a few constant bytes, so empty payloads have non-zero checksums.
+fn crc32(bytes: &[u8]) -> u32 {
+    !CRC32_INITIAL_BYTES
+        .iter()
+        .chain(bytes.iter())
+        .fold(!0, |crc, byte| {
+            CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+        })
+}
+
+fn to_le_bytes(value: u64, len: usize) -> Vec<u8> {
+    value.to_le_bytes()[..len].to_vec()
+}
+
+fn from_le_bytes(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+fn check_compression(compression: Compression) -> error::Result<()> {
+    match compression {
+        Compression::Snappy => Err(error::Error::from(
+            "Snappy compression is not supported by protocol v5",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Single segment of protocol v5 framing layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// Uncompressed payload of a segment.
+    pub payload: Vec<u8>,
+    /// Whether the payload consists of complete frames.
+    pub is_self_contained: bool,
+}
+
+impl Segment {
+    /// Encodes a segment compressing its payload if `compression` is not `None`.
+    /// The payload is sent uncompressed if compression does not make it shorter.
+    pub fn encode(&self, compression: Compression) -> error::Result<Vec<u8>> {
+        check_compression(compression)?;
+        if self.payload.len() > MAX_PAYLOAD_LEN {
+            return Err(error::Error::General(format!(
+                "Segment payload is too long: {} bytes",
+                self.payload.len()
+            )));
+        }
+
+        let self_contained = self.is_self_contained as u64;
+        let (header, payload) = if compression == Compression::None {
+            let header = self.payload.len() as u64 | self_contained << 17;
+            (
+                to_le_bytes(header, UNCOMPRESSED_HEADER_LEN),
+                self.payload.clone(),
+            )
+        } else {
+            let compressed = lz4::compress(self.payload.as_slice());
+            let (payload, uncompressed_len) = if compressed.len() < self.payload.len() {
+                (compressed, self.payload.len())
+            } else {
+                (self.payload.clone(), 0)
+            };
+            let header =
+                payload.len() as u64 | (uncompressed_len as u64) << 17 | self_contained << 34;
+            (to_le_bytes(header, COMPRESSED_HEADER_LEN), payload)
+        };
+
+        let mut v = Vec::with_capacity(header.len() + CRC24_LEN + payload.len() + CRC32_LEN);
+        v.extend_from_slice(header.as_slice());
+        v.extend_from_slice(to_le_bytes(crc24(&header) as u64, CRC24_LEN).as_slice());
+        v.extend_from_slice(payload.as_slice());
+        v.extend_from_slice(to_le_bytes(crc32(&payload) as u64, CRC32_LEN).as_slice());
+
+        Ok(v)
+    }
+
+    /// Reads a single segment. Both checksums are verified and the payload
+    /// is decompressed if `compression` is not `None`.
+    pub fn read(reader: &mut dyn Read, compression: Compression) -> error::Result<Segment> {
+        check_compression(compression)?;
+        let header_len = header_len(compression);
+
+        let mut header = vec![0; header_len + CRC24_LEN];
+        reader.read_exact(&mut header)?;
+        let (payload_len, _, _) = parse_segment_header(&header, compression)?;
+
+        let mut rest = vec![0; payload_len + CRC32_LEN];
+        reader.read_exact(&mut rest)?;
+        header.extend(rest);
+
+        Segment::decode(&header, compression)
+    }
+
+    /// Decodes a segment from given bytes which should contain exactly one segment.
+    fn decode(bytes: &[u8], compression: Compression) -> error::Result<Segment> {
+        let (payload_len, uncompressed_len, is_self_contained) =
+            parse_segment_header(bytes, compression)?;
+        let payload_offset = header_len(compression) + CRC24_LEN;
+        let payload = &bytes[payload_offset..payload_offset + payload_len];
+
+        let expected_crc = from_le_bytes(&bytes[payload_offset + payload_len..]) as u32;
+        if crc32(payload) != expected_crc {
+            return Err(error::Error::InvalidFrame(
+                "Segment payload checksum mismatch".into(),
+            ));
+        }
+
+        let payload = if uncompressed_len > 0 {
+            let decompressed = lz4::decompress(payload).map_err(CompressionError::Lz4)?;
+            if decompressed.len() != uncompressed_len {
+                return Err(error::Error::InvalidFrame(format!(
+                    "Unexpected length of decompressed segment payload: {} bytes, {} expected",
+                    decompressed.len(),
+                    uncompressed_len
+                )));
+            }
+            decompressed
+        } else {
+            payload.to_vec()
+        };
+
+        Ok(Segment {
+            payload,
+            is_self_contained,
+        })
+    }
+}
+
+fn header_len(compression: Compression) -> usize {
+    if compression == Compression::None {
+        UNCOMPRESSED_HEADER_LEN
+    } else {
+        COMPRESSED_HEADER_LEN
+    }
+}
+
+/// Verifies a header checksum and returns a payload length as it is sent, a length
+/// of uncompressed payload (zero if the payload is not compressed) and whether
+/// a segment is self-contained.
+fn parse_segment_header(
+    bytes: &[u8],
+    compression: Compression,
+) -> error::Result<(usize, usize, bool)> {
+    let header_len = header_len(compression);
+    let header = &bytes[..header_len];
+    let expected_crc = from_le_bytes(&bytes[header_len..header_len + CRC24_LEN]) as u32;
+    if crc24(header) != expected_crc {
+        return Err(error::Error::InvalidFrame(
+            "Segment header checksum mismatch".into(),
+        ));
+    }
+
+    let header = from_le_bytes(header);
+    let payload_len = (header & MAX_PAYLOAD_LEN as u64) as usize;
+    if compression == Compression::None {
+        Ok((payload_len, 0, header >> 17 & 1 == 1))
+    } else {
+        let uncompressed_len = (header >> 17 & MAX_PAYLOAD_LEN as u64) as usize;
+        Ok((payload_len, uncompressed_len, header >> 34 & 1 == 1))
+    }
+}
+
+/// Wraps an encoded frame into segments. A frame which does not fit into
+/// a single segment is split into several non-self-contained ones.
+pub fn encode_segments(frame: &[u8], compression: Compression) -> error::Result<Vec<u8>> {
+    if frame.len() <= MAX_PAYLOAD_LEN {
+        return Segment {
+            payload: frame.to_vec(),
+            is_self_contained: true,
+        }
+        .encode(compression);
+    }
+
+    let mut v = vec![];
+    for chunk in frame.chunks(MAX_PAYLOAD_LEN) {
+        let segment = Segment {
+            payload: chunk.to_vec(),
+            is_self_contained: false,
+        };
+        v.extend(segment.encode(compression)?);
+    }
+
+    Ok(v)
+}
+
+/// Collects frames out of received segments.
+#[derive(Debug)]
+pub struct SegmentDecoder {
+    compression: Compression,
+    /// Payloads of received segments which do not form a complete frame yet.
+    payload: Vec<u8>,
+}
+
+impl SegmentDecoder {
+    /// Creates a decoder of segments which payloads are compressed with `compression`.
+    pub fn new(compression: Compression) -> Self {
+        SegmentDecoder {
+            compression,
+            payload: vec![],
+        }
+    }
+
+    /// Reads segments until a complete frame is received and returns bytes of the frame.
+    /// Frames which were received together with it are returned by following calls.
+    pub fn read_frame(&mut self, reader: &mut dyn Read) -> error::Result<Vec<u8>> {
+        loop {
+            if let Some(frame) = self.next_frame() {
+                return Ok(frame);
+            }
+
+            let segment = Segment::read(reader, self.compression)?;
+            self.push(segment)?;
+        }
+    }
+
+    /// Decodes all complete segments at the beginning of `buffer` and removes
+    /// them from it. Frames they contain are available via `next_frame`.
+    pub fn decode(&mut self, buffer: &mut Vec<u8>) -> error::Result<()> {
+        check_compression(self.compression)?;
+        let header_len = header_len(self.compression) + CRC24_LEN;
+
+        while buffer.len() >= header_len {
+            let (payload_len, _, _) = parse_segment_header(buffer, self.compression)?;
+            let segment_len = header_len + payload_len + CRC32_LEN;
+            if buffer.len() < segment_len {
+                break;
+            }
+
+            let rest = buffer.split_off(segment_len);
+            let segment = Segment::decode(buffer, self.compression)?;
+            *buffer = rest;
+            self.push(segment)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns bytes of a next complete frame if there is one.
+    pub fn next_frame(&mut self) -> Option<Vec<u8>> {
+        if self.payload.len() < HEADER_LEN {
+            return None;
+        }
+
+        let (_, length) = parse_header(&self.payload);
+        if self.payload.len() < HEADER_LEN + length {
+            return None;
+        }
+
+        let rest = self.payload.split_off(HEADER_LEN + length);
+        Some(std::mem::replace(&mut self.payload, rest))
+    }
+
+    fn push(&mut self, segment: Segment) -> error::Result<()> {
+        if segment.is_self_contained && self.has_incomplete_frame() {
+            return Err(error::Error::InvalidFrame(
+                "Self-contained segment was received while a frame was incomplete".into(),
+            ));
+        }
+
+        self.payload.extend(segment.payload);
+        Ok(())
+    }
+
+    fn has_incomplete_frame(&self) -> bool {
+        let mut offset = 0;
+        while self.payload.len() >= offset + HEADER_LEN {
+            let (_, length) = parse_header(&self.payload[offset..]);
+            offset += HEADER_LEN + length;
+        }
+        offset != self.payload.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn frame(body_len: usize) -> Vec<u8> {
+        let mut frame = vec![0x85, 0, 0, 1, 0x08];
+        frame.extend_from_slice(&(body_len as u32).to_be_bytes());
+        frame.extend((0..body_len).map(|i| (i % 7) as u8));
+        frame
+    }
+
+    #[test]
+    fn checksums() {
+        // the same as zlib CRC32 of the seed bytes followed by a payload
+        assert_eq!(crc32(&[]), 0x4477_7ED3);
+        assert_eq!(crc32(b"123456789"), 0xE2A2_61A7);
+        assert_eq!(crc24(&[0, 0, 0]), 0x007D_E777);
+        assert_eq!(crc24(&[3, 0, 2]), 0x007C_9642);
+    }
+
+    #[test]
+    fn uncompressed_segment_layout() {
+        let segment = Segment {
+            payload: vec![1, 2, 3],
+            is_self_contained: true,
+        };
+        let encoded = segment.encode(Compression::None).unwrap();
+        assert_eq!(&encoded[..3], &[3, 0, 2]);
+        assert_eq!(encoded.len(), 3 + 3 + 3 + 4);
+        assert_eq!(&encoded[6..9], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn segment_round_trip() {
+        for compression in vec![Compression::None, Compression::Lz4] {
+            for is_self_contained in vec![true, false] {
+                let segment = Segment {
+                    payload: frame(1000),
+                    is_self_contained,
+                };
+                let encoded = segment.encode(compression).unwrap();
+                let decoded = Segment::read(&mut Cursor::new(encoded), compression).unwrap();
+                assert_eq!(decoded, segment, "{:?}", compression);
+            }
+        }
+    }
+
+    #[test]
+    fn incompressible_payload_is_sent_as_is() {
+        let segment = Segment {
+            payload: vec![1],
+            is_self_contained: true,
+        };
+        let encoded = segment.encode(Compression::Lz4).unwrap();
+        // uncompressed length is zero
+        assert_eq!(&encoded[..5], &[1, 0, 0, 0, 4]);
+        let decoded = Segment::read(&mut Cursor::new(encoded), Compression::Lz4).unwrap();
+        assert_eq!(decoded, segment);
+    }
+
+    #[test]
+    fn corrupted_segment() {
+        let segment = Segment {
+            payload: vec![1, 2, 3],
+            is_self_contained: true,
+        };
+        let encoded = segment.encode(Compression::None).unwrap();
+
+        let mut header_corrupted = encoded.clone();
+        header_corrupted[0] = 4;
+        assert!(Segment::read(&mut Cursor::new(header_corrupted), Compression::None).is_err());
+
+        let mut payload_corrupted = encoded;
+        payload_corrupted[7] = 0;
+        assert!(Segment::read(&mut Cursor::new(payload_corrupted), Compression::None).is_err());
+    }
+
+    #[test]
+    fn snappy_is_not_supported() {
+        let segment = Segment {
+            payload: vec![1],
+            is_self_contained: true,
+        };
+        assert!(segment.encode(Compression::Snappy).is_err());
+    }
+
+    #[test]
+    fn large_frame_round_trip() {
+        let large = frame(MAX_PAYLOAD_LEN * 2);
+        for compression in vec![Compression::None, Compression::Lz4] {
+            let encoded = encode_segments(&large, compression).unwrap();
+            let mut cursor = Cursor::new(encoded);
+
+            let first = Segment::read(&mut cursor, compression).unwrap();
+            assert!(!first.is_self_contained);
+            assert_eq!(first.payload.len(), MAX_PAYLOAD_LEN);
+
+            cursor.set_position(0);
+            let mut decoder = SegmentDecoder::new(compression);
+            assert_eq!(decoder.read_frame(&mut cursor).unwrap(), large);
+        }
+    }
+
+    #[test]
+    fn several_frames_in_one_segment() {
+        let mut payload = frame(10);
+        payload.extend(frame(20));
+        let encoded = Segment {
+            payload,
+            is_self_contained: true,
+        }
+        .encode(Compression::None)
+        .unwrap();
+
+        let mut decoder = SegmentDecoder::new(Compression::None);
+        let mut cursor = Cursor::new(encoded);
+        assert_eq!(decoder.read_frame(&mut cursor).unwrap(), frame(10));
+        assert_eq!(decoder.read_frame(&mut cursor).unwrap(), frame(20));
+        assert!(decoder.read_frame(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn self_contained_segment_after_incomplete_frame() {
+        let large = frame(MAX_PAYLOAD_LEN);
+        let mut encoded = Segment {
+            payload: large[..MAX_PAYLOAD_LEN].to_vec(),
+            is_self_contained: false,
+        }
+        .encode(Compression::None)
+        .unwrap();
+        encoded.extend(encode_segments(&frame(10), Compression::None).unwrap());
+
+        let mut decoder = SegmentDecoder::new(Compression::None);
+        assert!(decoder.decode(&mut encoded).is_err());
+    }
+
+    #[test]
+    fn decode_buffer_by_parts() {
+        let mut encoded = encode_segments(&frame(10), Compression::Lz4).unwrap();
+        encoded.extend(encode_segments(&frame(MAX_PAYLOAD_LEN), Compression::Lz4).unwrap());
+
+        let mut decoder = SegmentDecoder::new(Compression::Lz4);
+        let mut buffer = vec![];
+        let mut frames = vec![];
+        for chunk in encoded.chunks(1000) {
+            buffer.extend_from_slice(chunk);
+            decoder.decode(&mut buffer).unwrap();
+            while let Some(frame) = decoder.next_frame() {
+                frames.push(frame);
+            }
+        }
+
+        assert!(buffer.is_empty());
+        assert_eq!(frames, vec![frame(10), frame(MAX_PAYLOAD_LEN)]);
+    }
+}
@@ -4,6 +4,9 @@ extern crate byteorder;
 extern crate snap;
 #[macro_use]
 pub mod macros;
+#[cfg(test)]
+#[macro_use]
+mod test;
 
 #[macro_use]
 extern crate log;
@@ -21,6 +24,9 @@ pub mod cluster;
 pub mod frame;
 pub mod load_balancing;
 pub mod query;
+pub mod retry;
+pub mod routing;
+pub mod speculative_execution;
 pub mod types;
 
 pub mod authenticators;
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+use super::{GetAddr, LoadBalancingStrategy, RoutingInfo};
+use crate::cluster::metadata::ClusterMetadata;
+
+/// Round robin load balancer which prefers nodes of a local datacenter. Datacenters of
+/// nodes are learnt from cluster metadata, nodes which datacenter is not known yet
+/// are considered to be remote. Until metadata is received all nodes are considered
+/// to be local, so a session is able to fetch the metadata over any of them.
+///
+/// Optionally it falls back to a limited number of nodes from every remote datacenter
+/// if there are no local nodes. Such fallback is never used for requests with
+/// datacenter local consistency levels, i.e. `LOCAL_ONE`, `LOCAL_QUORUM`
+/// and `LOCAL_SERIAL`.
+pub struct DcAwareRoundRobin<N> {
+    local_dc: String,
+    used_hosts_per_remote_dc: usize,
+    cluster: Vec<N>,
+    /// Datacenters of nodes, `None` until cluster metadata is received.
+    datacenters: Option<HashMap<IpAddr, String>>,
+    /// Indexes of local nodes in `cluster`.
+    local: Vec<usize>,
+    /// Indexes of remote nodes in `cluster` which could be used as a fallback.
+    remote: Vec<usize>,
+    prev_local_idx: Mutex<usize>,
+    prev_remote_idx: Mutex<usize>,
+}
+
+impl<N> DcAwareRoundRobin<N> {
+    /// Creates new load balancer which sends requests only to nodes of `local_dc`.
+    pub fn new<S: ToString>(local_dc: S) -> Self {
+        DcAwareRoundRobin {
+            local_dc: local_dc.to_string(),
+            used_hosts_per_remote_dc: 0,
+            cluster: vec![],
+            datacenters: None,
+            local: vec![],
+            remote: vec![],
+            prev_local_idx: Mutex::new(0),
+            prev_remote_idx: Mutex::new(0),
+        }
+    }
+
+    /// Sets a number of nodes per remote datacenter that could be used
+    /// if no local nodes are available.
+    pub fn used_hosts_per_remote_dc(mut self, used_hosts_per_remote_dc: usize) -> Self {
+        self.used_hosts_per_remote_dc = used_hosts_per_remote_dc;
+        self
+    }
+
+    fn next_local(&self) -> Option<&N> {
+        self.next_of(&self.local, &self.prev_local_idx)
+    }
+
+    fn next_remote(&self) -> Option<&N> {
+        self.next_of(&self.remote, &self.prev_remote_idx)
+    }
+
+    fn next_of(&self, candidates: &[usize], prev_idx: &Mutex<usize>) -> Option<&N> {
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let mut prev_idx = prev_idx.lock().ok()?;
+        *prev_idx = (*prev_idx + 1) % candidates.len();
+        self.cluster.get(candidates[*prev_idx])
+    }
+}
+
+impl<N: GetAddr> DcAwareRoundRobin<N> {
+    fn split_by_datacenter(&mut self) {
+        self.local.clear();
+        self.remote.clear();
+
+        let datacenters = match self.datacenters {
+            Some(ref datacenters) => datacenters,
+            None => {
+                self.local.extend(0..self.cluster.len());
+                return;
+            }
+        };
+
+        // nodes which datacenter is not known are counted as one more remote datacenter
+        let mut used_per_remote_dc: HashMap<Option<&str>, usize> = HashMap::new();
+        for (i, node) in self.cluster.iter().enumerate() {
+            match datacenters.get(&node.get_addr().ip()) {
+                Some(dc) if dc == &self.local_dc => self.local.push(i),
+                dc => {
+                    let used = used_per_remote_dc
+                        .entry(dc.map(String::as_str))
+                        .or_insert(0);
+                    if *used < self.used_hosts_per_remote_dc {
+                        *used += 1;
+                        self.remote.push(i);
+                    }
+                }
+            }
+        }
+
+        if self.local.is_empty() && !self.cluster.is_empty() {
+            warn!(
+                "No nodes of local datacenter {} are known, only {} remote nodes can be used",
+                self.local_dc,
+                self.remote.len()
+            );
+        }
+    }
+}
+
+impl<N: GetAddr> LoadBalancingStrategy<N> for DcAwareRoundRobin<N> {
+    fn init(&mut self, cluster: Vec<N>) {
+        self.cluster = cluster;
+        self.split_by_datacenter();
+    }
+
+    /// Returns next local node or a remote one if there are no local nodes.
+    fn next(&self) -> Option<&N> {
+        self.next_local().or_else(|| self.next_remote())
+    }
+
+    fn next_for(&self, routing: &RoutingInfo) -> Option<&N> {
+        match routing.consistency {
+            Some(consistency) if consistency.is_dc_local() => self.next_local(),
+            _ => self.next(),
+        }
+    }
+
+    /// Accepts local nodes and, if there are none, remote nodes that could be used
+    /// as a fallback for a request.
+    fn accepts(&self, node: &N, routing: &RoutingInfo) -> bool {
+        let addr = node.get_addr();
+        let is_node = |i: &usize| self.cluster[*i].get_addr() == addr;
+        let dc_local =
+            matches!(routing.consistency, Some(consistency) if consistency.is_dc_local());
+
+        self.local.iter().any(is_node)
+            || (self.local.is_empty() && !dc_local && self.remote.iter().any(is_node))
+    }
+
+    fn add_node(&mut self, node: N) {
+        self.cluster.push(node);
+        self.split_by_datacenter();
+    }
+
+    fn remove_node<F>(&mut self, filter: F)
+    where
+        F: FnMut(&N) -> bool,
+    {
+        if let Some(i) = self.cluster.iter().position(filter) {
+            self.cluster.remove(i);
+            self.split_by_datacenter();
+        }
+    }
+
+    fn update_metadata(&mut self, metadata: &ClusterMetadata) {
+        self.datacenters = Some(
+            metadata
+                .nodes
+                .iter()
+                .filter_map(|node| Some((node.addr, node.datacenter.clone()?)))
+                .collect(),
+        );
+        self.split_by_datacenter();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::metadata::NodeMetadata;
+    use crate::consistency::Consistency;
+    use std::net::SocketAddr;
+
+    fn addr(ip: &str) -> SocketAddr {
+        format!("{}:9042", ip).parse().unwrap()
+    }
+
+    fn metadata(nodes: &[(&str, &str)]) -> ClusterMetadata {
+        ClusterMetadata {
+            partitioner: None,
+            nodes: nodes
+                .iter()
+                .map(|(ip, dc)| NodeMetadata {
+                    addr: ip.parse().unwrap(),
+                    port: None,
+                    datacenter: Some(dc.to_string()),
+                    rack: Some("rack1".to_string()),
+                    tokens: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    fn load_balancer(used_hosts_per_remote_dc: usize) -> DcAwareRoundRobin<SocketAddr> {
+        let mut load_balancer =
+            DcAwareRoundRobin::new("dc1").used_hosts_per_remote_dc(used_hosts_per_remote_dc);
+        load_balancer.init(vec![
+            addr("127.0.0.1"),
+            addr("127.0.0.2"),
+            addr("127.0.0.3"),
+            addr("127.0.0.4"),
+        ]);
+        load_balancer.update_metadata(&metadata(&[
+            ("127.0.0.1", "dc1"),
+            ("127.0.0.2", "dc2"),
+            ("127.0.0.3", "dc1"),
+            ("127.0.0.4", "dc2"),
+        ]));
+        load_balancer
+    }
+
+    #[test]
+    fn prefers_local_dc() {
+        let load_balancer = load_balancer(1);
+        for _ in 0..3 {
+            assert_eq!(load_balancer.next(), Some(&addr("127.0.0.3")));
+            assert_eq!(load_balancer.next(), Some(&addr("127.0.0.1")));
+        }
+    }
+
+    #[test]
+    fn falls_back_to_remote_dc() {
+        let mut load_balancer = load_balancer(1);
+        load_balancer.remove_node(|n| n == &addr("127.0.0.1"));
+        load_balancer.remove_node(|n| n == &addr("127.0.0.3"));
+
+        assert_eq!(load_balancer.next(), Some(&addr("127.0.0.2")));
+        assert_eq!(load_balancer.next(), Some(&addr("127.0.0.2")));
+
+        let routing = RoutingInfo {
+            consistency: Some(Consistency::LocalQuorum),
+            ..Default::default()
+        };
+        assert_eq!(load_balancer.next_for(&routing), None);
+
+        let routing = RoutingInfo {
+            consistency: Some(Consistency::Quorum),
+            ..Default::default()
+        };
+        assert_eq!(load_balancer.next_for(&routing), Some(&addr("127.0.0.2")));
+    }
+
+    #[test]
+    fn does_not_fall_back_by_default() {
+        let mut load_balancer = load_balancer(0);
+        load_balancer.remove_node(|n| n == &addr("127.0.0.1"));
+        load_balancer.remove_node(|n| n == &addr("127.0.0.3"));
+
+        assert_eq!(load_balancer.next(), None);
+    }
+
+    #[test]
+    fn accepts_nodes_it_would_pick() {
+        let mut load_balancer = load_balancer(1);
+        let routing = RoutingInfo::default();
+        assert!(load_balancer.accepts(&addr("127.0.0.1"), &routing));
+        assert!(!load_balancer.accepts(&addr("127.0.0.2"), &routing));
+
+        load_balancer.remove_node(|n| n == &addr("127.0.0.1"));
+        load_balancer.remove_node(|n| n == &addr("127.0.0.3"));
+        assert!(load_balancer.accepts(&addr("127.0.0.2"), &routing));
+        assert!(!load_balancer.accepts(&addr("127.0.0.4"), &routing));
+
+        let routing = RoutingInfo {
+            consistency: Some(Consistency::LocalQuorum),
+            ..Default::default()
+        };
+        assert!(!load_balancer.accepts(&addr("127.0.0.2"), &routing));
+    }
+
+    #[test]
+    fn uses_all_nodes_until_metadata_is_received() {
+        let mut load_balancer = DcAwareRoundRobin::new("dc1");
+        load_balancer.init(vec![addr("127.0.0.1"), addr("127.0.0.2")]);
+
+        assert_eq!(load_balancer.next(), Some(&addr("127.0.0.2")));
+        assert_eq!(load_balancer.next(), Some(&addr("127.0.0.1")));
+        let routing = RoutingInfo {
+            consistency: Some(Consistency::LocalOne),
+            ..Default::default()
+        };
+        assert_eq!(load_balancer.next_for(&routing), Some(&addr("127.0.0.2")));
+    }
+
+    #[test]
+    fn nodes_of_unknown_dc_are_remote() {
+        let mut load_balancer = DcAwareRoundRobin::new("dc1");
+        load_balancer.init(vec![addr("127.0.0.1"), addr("127.0.0.2")]);
+        load_balancer.update_metadata(&metadata(&[("127.0.0.3", "dc1")]));
+        assert_eq!(load_balancer.next(), None);
+
+        let mut load_balancer = DcAwareRoundRobin::new("dc1").used_hosts_per_remote_dc(1);
+        load_balancer.init(vec![addr("127.0.0.1"), addr("127.0.0.2")]);
+        load_balancer.update_metadata(&metadata(&[("127.0.0.3", "dc1")]));
+        assert_eq!(load_balancer.next(), Some(&addr("127.0.0.1")));
+        assert_eq!(load_balancer.next(), Some(&addr("127.0.0.1")));
+
+        let routing = RoutingInfo {
+            consistency: Some(Consistency::LocalOne),
+            ..Default::default()
+        };
+        assert_eq!(load_balancer.next_for(&routing), None);
+    }
+
+    #[test]
+    fn rotates_local_and_remote_nodes_separately() {
+        let mut load_balancer = load_balancer(2);
+        load_balancer.remove_node(|n| n == &addr("127.0.0.3"));
+        let routing = RoutingInfo {
+            consistency: Some(Consistency::Quorum),
+            ..Default::default()
+        };
+
+        assert_eq!(load_balancer.next_remote(), Some(&addr("127.0.0.4")));
+        assert_eq!(load_balancer.next_for(&routing), Some(&addr("127.0.0.1")));
+        assert_eq!(load_balancer.next_remote(), Some(&addr("127.0.0.2")));
+        assert_eq!(load_balancer.next_remote(), Some(&addr("127.0.0.4")));
+    }
+}
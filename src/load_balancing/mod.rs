@@ -1,20 +1,77 @@
+mod dc_aware_round_robin;
 mod random;
 mod round_robin;
 mod round_robin_sync;
 mod single_node;
+mod token_aware;
 
+pub use crate::load_balancing::dc_aware_round_robin::DcAwareRoundRobin;
 pub use crate::load_balancing::random::Random;
 pub use crate::load_balancing::round_robin::RoundRobin;
 pub use crate::load_balancing::round_robin_sync::RoundRobinSync;
 pub use crate::load_balancing::single_node::SingleNode;
+pub use crate::load_balancing::token_aware::TokenAware;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::cluster::metadata::ClusterMetadata;
+use crate::consistency::Consistency;
+
+/// Information about a request which load balancers may use to pick a node
+/// that should handle it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoutingInfo {
+    /// Serialized partition key of a request if it is known.
+    pub routing_key: Option<Vec<u8>>,
+    /// Consistency level of a request.
+    pub consistency: Option<Consistency>,
+}
 
 pub trait LoadBalancingStrategy<N>: Sized {
     fn init(&mut self, cluster: Vec<N>);
     fn next(&self) -> Option<&N>;
+    /// Returns next node which should handle a request with given routing information.
+    /// By default routing information is ignored.
+    fn next_for(&self, _routing: &RoutingInfo) -> Option<&N> {
+        self.next()
+    }
+    /// Tells whether a node could be picked for a request with given routing information,
+    /// e.g. whether it is in a local datacenter. Load balancers which wrap other ones
+    /// use it to not send requests to nodes an inner load balancer avoids.
+    /// By default all nodes are accepted.
+    fn accepts(&self, _node: &N, _routing: &RoutingInfo) -> bool {
+        true
+    }
+    /// Adds a node which joined a cluster or became available again.
+    fn add_node(&mut self, _node: N) {
+        // default implementation does nothing
+    }
     fn remove_node<F>(&mut self, _filter: F)
     where
         F: FnMut(&N) -> bool,
     {
         // default implementation does nothing
     }
+    /// Notifies a load balancer about actual cluster topology.
+    fn update_metadata(&mut self, _metadata: &ClusterMetadata) {
+        // default implementation does nothing
+    }
+}
+
+/// Nodes which know an address of a Cassandra server they are connected to.
+pub trait GetAddr {
+    fn get_addr(&self) -> SocketAddr;
+}
+
+impl GetAddr for SocketAddr {
+    fn get_addr(&self) -> SocketAddr {
+        *self
+    }
+}
+
+impl<N: GetAddr> GetAddr for Arc<N> {
+    fn get_addr(&self) -> SocketAddr {
+        self.as_ref().get_addr()
+    }
 }
@@ -40,6 +40,10 @@ impl<N> LoadBalancingStrategy<N> for Random<N> {
         self.cluster.get(Self::rnd_idx((0, len)))
     }
 
+    fn add_node(&mut self, node: N) {
+        self.cluster.push(node);
+    }
+
     fn remove_node<F>(&mut self, filter: F)
     where
         F: FnMut(&N) -> bool,
@@ -32,12 +32,20 @@ impl<N> LoadBalancingStrategy<N> for RoundRobin<N> {
 
     /// Returns next node from a cluster
     fn next(&self) -> Option<&N> {
+        if self.cluster.is_empty() {
+            return None;
+        }
+
         let prev_idx = *self.prev_idx.borrow();
         let next_idx = (prev_idx + 1) % self.cluster.len();
         self.prev_idx.replace(next_idx);
         self.cluster.get(next_idx)
     }
 
+    fn add_node(&mut self, node: N) {
+        self.cluster.push(node);
+    }
+
     fn remove_node<F>(&mut self, filter: F)
     where
         F: FnMut(&N) -> bool,
@@ -70,5 +78,8 @@ mod tests {
 
         load_balancer.remove_node(|n| n == &"a");
         assert_eq!(&"b", load_balancer.next().unwrap());
+
+        load_balancer.remove_node(|n| n == &"b");
+        assert_eq!(None, load_balancer.next());
     }
 }
@@ -32,6 +32,10 @@ impl<N> LoadBalancingStrategy<N> for RoundRobinSync<N> {
 
     /// Returns next node from a cluster
     fn next(&self) -> Option<&N> {
+        if self.cluster.is_empty() {
+            return None;
+        }
+
         let mut prev_idx = self.prev_idx.lock();
         if let Ok(ref mut mtx) = prev_idx {
             let next_idx = (**mtx + 1) % self.cluster.len();
@@ -42,6 +46,10 @@ impl<N> LoadBalancingStrategy<N> for RoundRobinSync<N> {
         }
     }
 
+    fn add_node(&mut self, node: N) {
+        self.cluster.push(node);
+    }
+
     fn remove_node<F>(&mut self, filter: F)
     where
         F: FnMut(&N) -> bool,
@@ -74,5 +82,16 @@ mod tests {
 
         load_balancer.remove_node(|n| n == &"a");
         assert_eq!(&"b", load_balancer.next().unwrap());
+
+        load_balancer.remove_node(|n| n == &"b");
+        assert_eq!(None, load_balancer.next());
+    }
+
+    #[test]
+    fn add_to_round_robin() {
+        let mut load_balancer = RoundRobinSync::from(vec!["a"]);
+        load_balancer.add_node("b");
+        assert_eq!(&"b", load_balancer.next().unwrap());
+        assert_eq!(&"a", load_balancer.next().unwrap());
     }
 }
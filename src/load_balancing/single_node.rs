@@ -25,6 +25,19 @@ impl<N> LoadBalancingStrategy<N> for SingleNode<N> {
     fn next(&self) -> Option<&N> {
         self.cluster.get(0)
     }
+
+    fn add_node(&mut self, node: N) {
+        self.cluster.push(node);
+    }
+
+    fn remove_node<F>(&mut self, filter: F)
+    where
+        F: FnMut(&N) -> bool,
+    {
+        if let Some(i) = self.cluster.iter().position(filter) {
+            self.cluster.remove(i);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -40,4 +53,11 @@ mod tests {
         // and one more time to check
         assert_eq!(&nodes_c[0], load_balancer.next().unwrap());
     }
+
+    #[test]
+    fn remove_node() {
+        let mut load_balancer = SingleNode::from(vec!["a", "b"]);
+        load_balancer.remove_node(|n| n == &"a");
+        assert_eq!(&"b", load_balancer.next().unwrap());
+    }
 }
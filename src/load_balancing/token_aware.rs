@@ -0,0 +1,318 @@
+use super::{GetAddr, LoadBalancingStrategy, RoutingInfo};
+use crate::cluster::metadata::ClusterMetadata;
+use crate::routing::{Partitioner, Token, TokenRing};
+
+/// Load balancer which sends requests with known partition key directly to a replica
+/// which owns it and delegates everything else to an inner load balancer.
+///
+/// A replica is the first node the inner load balancer accepts going clockwise around
+/// the token ring from a token of a partition key. E.g. with `DcAwareRoundRobin` it is
+/// the first node of a local datacenter, which is a replica of the key if a keyspace
+/// is replicated with `NetworkTopologyStrategy`. Requests are balanced by the inner
+/// load balancer if it accepts none of the nodes.
+///
+/// The token ring is built from cluster metadata, so until it is received, as well
+/// as for clusters with partitioners other than `Murmur3Partitioner` and `RandomPartitioner`,
+/// all requests are balanced by the inner strategy.
+pub struct TokenAware<LB, N> {
+    inner: LB,
+    cluster: Vec<N>,
+    metadata: ClusterMetadata,
+    partitioner: Option<Partitioner>,
+    /// Ring of tokens where a node is represented by its index in `cluster`. Tokens
+    /// of nodes that are not in `cluster` are kept, so their keys are routed to
+    /// next replicas rather than to neighbours of the remaining tokens.
+    ring: TokenRing<Token, Option<usize>>,
+}
+
+impl<LB, N> TokenAware<LB, N> {
+    pub fn new(inner: LB) -> Self {
+        TokenAware {
+            inner,
+            cluster: vec![],
+            metadata: ClusterMetadata::default(),
+            partitioner: None,
+            ring: TokenRing::new(),
+        }
+    }
+}
+
+impl<LB, N> TokenAware<LB, N>
+where
+    LB: LoadBalancingStrategy<N>,
+{
+    /// Returns a first node going from a partition key of a request around the ring
+    /// which the inner load balancer accepts.
+    fn replica_for(&self, routing: &RoutingInfo) -> Option<&N> {
+        let token = self.partitioner?.token(routing.routing_key.as_ref()?);
+        self.ring
+            .iter_from(&token)
+            .filter_map(|index| self.cluster.get((*index)?))
+            .find(|node| self.inner.accepts(node, routing))
+    }
+}
+
+impl<LB, N: GetAddr> TokenAware<LB, N> {
+    fn rebuild_ring(&mut self) {
+        self.ring = TokenRing::new();
+
+        self.partitioner = self
+            .metadata
+            .partitioner
+            .as_ref()
+            .and_then(|name| Partitioner::from_class_name(name));
+        let partitioner = match self.partitioner {
+            Some(partitioner) => partitioner,
+            None => return,
+        };
+
+        for node in &self.metadata.nodes {
+            let index = self
+                .cluster
+                .iter()
+                .position(|n| n.get_addr().ip() == node.addr);
+            for token in &node.tokens {
+                match partitioner.parse_token(token) {
+                    Some(token) => self.ring.insert(token, index),
+                    None => warn!("Invalid token {} of node {}", token, node.addr),
+                }
+            }
+        }
+    }
+}
+
+impl<LB, N> LoadBalancingStrategy<N> for TokenAware<LB, N>
+where
+    LB: LoadBalancingStrategy<N>,
+    N: GetAddr + Clone,
+{
+    fn init(&mut self, cluster: Vec<N>) {
+        self.cluster = cluster.clone();
+        self.inner.init(cluster);
+        self.rebuild_ring();
+    }
+
+    fn next(&self) -> Option<&N> {
+        self.inner.next()
+    }
+
+    /// Returns a replica of a partition key of a request if it is known.
+    fn next_for(&self, routing: &RoutingInfo) -> Option<&N> {
+        self.replica_for(routing)
+            .or_else(|| self.inner.next_for(routing))
+    }
+
+    fn accepts(&self, node: &N, routing: &RoutingInfo) -> bool {
+        self.inner.accepts(node, routing)
+    }
+
+    fn add_node(&mut self, node: N) {
+        self.cluster.push(node.clone());
+        self.inner.add_node(node);
+        self.rebuild_ring();
+    }
+
+    fn remove_node<F>(&mut self, mut filter: F)
+    where
+        F: FnMut(&N) -> bool,
+    {
+        if let Some(i) = self.cluster.iter().position(&mut filter) {
+            self.cluster.remove(i);
+        }
+        self.inner.remove_node(filter);
+        self.rebuild_ring();
+    }
+
+    fn update_metadata(&mut self, metadata: &ClusterMetadata) {
+        self.metadata = metadata.clone();
+        self.inner.update_metadata(metadata);
+        self.rebuild_ring();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::metadata::NodeMetadata;
+    use crate::consistency::Consistency;
+    use crate::load_balancing::{DcAwareRoundRobin, RoundRobinSync};
+    use std::net::SocketAddr;
+
+    fn addr(ip: &str) -> SocketAddr {
+        format!("{}:9042", ip).parse().unwrap()
+    }
+
+    fn node(ip: &str, tokens: &[i64]) -> NodeMetadata {
+        NodeMetadata {
+            addr: ip.parse().unwrap(),
+            port: None,
+            datacenter: None,
+            rack: None,
+            tokens: tokens.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn dc_node(ip: &str, datacenter: &str, tokens: &[i64]) -> NodeMetadata {
+        NodeMetadata {
+            datacenter: Some(datacenter.to_string()),
+            ..node(ip, tokens)
+        }
+    }
+
+    fn metadata(partitioner: &str) -> ClusterMetadata {
+        ClusterMetadata {
+            partitioner: Some(partitioner.to_string()),
+            nodes: vec![
+                node("127.0.0.1", &[-5000000000000000000]),
+                node("127.0.0.2", &[0]),
+                node("127.0.0.3", &[5000000000000000000]),
+            ],
+        }
+    }
+
+    fn routing(key: i32) -> RoutingInfo {
+        RoutingInfo {
+            routing_key: Some(key.to_be_bytes().to_vec()),
+            ..Default::default()
+        }
+    }
+
+    fn load_balancer() -> TokenAware<RoundRobinSync<SocketAddr>, SocketAddr> {
+        let mut load_balancer = TokenAware::new(RoundRobinSync::new());
+        load_balancer.init(vec![
+            addr("127.0.0.1"),
+            addr("127.0.0.2"),
+            addr("127.0.0.3"),
+        ]);
+        load_balancer
+    }
+
+    #[test]
+    fn routes_to_token_owner() {
+        let mut load_balancer = load_balancer();
+        load_balancer.update_metadata(&metadata(Partitioner::Murmur3.as_str()));
+
+        // murmur3 tokens of 1, 2 and 3 ints are -4069959284402364209,
+        // -3248873570005575792 and 9010454139840013625 respectively
+        for _ in 0..3 {
+            assert_eq!(
+                load_balancer.next_for(&routing(1)),
+                Some(&addr("127.0.0.2"))
+            );
+            assert_eq!(
+                load_balancer.next_for(&routing(2)),
+                Some(&addr("127.0.0.2"))
+            );
+            assert_eq!(
+                load_balancer.next_for(&routing(3)),
+                Some(&addr("127.0.0.1"))
+            );
+        }
+    }
+
+    #[test]
+    fn falls_back_to_inner_load_balancer() {
+        let mut load_balancer = load_balancer();
+        assert_eq!(
+            load_balancer.next_for(&routing(1)),
+            Some(&addr("127.0.0.2"))
+        );
+        assert_eq!(
+            load_balancer.next_for(&routing(1)),
+            Some(&addr("127.0.0.3"))
+        );
+
+        load_balancer.update_metadata(&metadata("org.apache.cassandra.dht.ByteOrderedPartitioner"));
+        assert_eq!(
+            load_balancer.next_for(&routing(1)),
+            Some(&addr("127.0.0.1"))
+        );
+
+        load_balancer.update_metadata(&metadata(Partitioner::Murmur3.as_str()));
+        assert_eq!(
+            load_balancer.next_for(&RoutingInfo::default()),
+            Some(&addr("127.0.0.2"))
+        );
+    }
+
+    #[test]
+    fn does_not_route_to_removed_node() {
+        let mut load_balancer = load_balancer();
+        load_balancer.update_metadata(&metadata(Partitioner::Murmur3.as_str()));
+        load_balancer.remove_node(|n| n == &addr("127.0.0.2"));
+
+        assert_ne!(
+            load_balancer.next_for(&routing(1)),
+            Some(&addr("127.0.0.2"))
+        );
+        assert_eq!(
+            load_balancer.next_for(&routing(3)),
+            Some(&addr("127.0.0.1"))
+        );
+    }
+
+    fn multi_dc_load_balancer(
+        used_hosts_per_remote_dc: usize,
+    ) -> TokenAware<DcAwareRoundRobin<SocketAddr>, SocketAddr> {
+        let inner =
+            DcAwareRoundRobin::new("dc1").used_hosts_per_remote_dc(used_hosts_per_remote_dc);
+        let mut load_balancer = TokenAware::new(inner);
+        load_balancer.init(vec![
+            addr("127.0.0.1"),
+            addr("127.0.0.2"),
+            addr("127.0.0.3"),
+            addr("127.0.0.4"),
+        ]);
+        load_balancer.update_metadata(&ClusterMetadata {
+            partitioner: Some(Partitioner::Murmur3.as_str().to_string()),
+            nodes: vec![
+                dc_node("127.0.0.1", "dc1", &[-5000000000000000000]),
+                dc_node("127.0.0.2", "dc2", &[0]),
+                dc_node("127.0.0.3", "dc2", &[3000000000000000000]),
+                dc_node("127.0.0.4", "dc1", &[7000000000000000000]),
+            ],
+        });
+        load_balancer
+    }
+
+    #[test]
+    fn routes_to_replica_of_local_dc() {
+        let load_balancer = multi_dc_load_balancer(0);
+
+        // token of 1 is owned by 127.0.0.2, the next nodes of the ring are
+        // 127.0.0.3 of the remote datacenter and then 127.0.0.4
+        for _ in 0..3 {
+            assert_eq!(
+                load_balancer.next_for(&routing(1)),
+                Some(&addr("127.0.0.4"))
+            );
+            assert_eq!(
+                load_balancer.next_for(&routing(3)),
+                Some(&addr("127.0.0.1"))
+            );
+        }
+    }
+
+    #[test]
+    fn routes_to_remote_replica_if_inner_load_balancer_falls_back() {
+        let mut load_balancer = multi_dc_load_balancer(1);
+        load_balancer.remove_node(|n| n == &addr("127.0.0.1"));
+        load_balancer.remove_node(|n| n == &addr("127.0.0.4"));
+
+        // only 127.0.0.2 is used as a fallback of the remote datacenter
+        assert_eq!(
+            load_balancer.next_for(&routing(1)),
+            Some(&addr("127.0.0.2"))
+        );
+        assert_eq!(
+            load_balancer.next_for(&routing(3)),
+            Some(&addr("127.0.0.2"))
+        );
+
+        let routing = RoutingInfo {
+            consistency: Some(Consistency::LocalOne),
+            ..routing(1)
+        };
+        assert_eq!(load_balancer.next_for(&routing), None);
+    }
+}
@@ -163,10 +163,10 @@ macro_rules! as_res_opt {
 macro_rules! as_rust_type {
     ($data_type_option:ident, $data_value:ident, Blob) => {
         match $data_type_option.id {
-            ColType::Blob => as_res_opt!($data_value, decode_blob),
+            ColType::Blob | ColType::Custom => as_res_opt!($data_value, decode_blob),
             _ => Err(Error::General(format!(
                 "Invalid conversion. \
-                 Cannot convert {:?} into Vec<u8> (valid types: Blob).",
+                 Cannot convert {:?} into Vec<u8> (valid types: Blob, Custom).",
                 $data_type_option.id
             ))),
         }
@@ -381,4 +381,24 @@ macro_rules! as_rust_type {
             ))),
         }
     };
+    ($data_type_option:ident, $data_value:ident, Varint) => {
+        match $data_type_option.id {
+            ColType::Varint => as_res_opt!($data_value, decode_big_varint),
+            _ => Err(Error::General(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into Varint (valid types: Varint).",
+                $data_type_option.id
+            ))),
+        }
+    };
+    ($data_type_option:ident, $data_value:ident, Duration) => {
+        match $data_type_option.id {
+            ColType::Duration => as_res_opt!($data_value, decode_duration),
+            _ => Err(Error::General(format!(
+                "Invalid conversion. \
+                 Cannot convert {:?} into Duration (valid types: Duration).",
+                $data_type_option.id
+            ))),
+        }
+    };
 }
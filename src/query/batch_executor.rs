@@ -1,19 +1,28 @@
 use r2d2;
 use std::cell::RefCell;
+use std::convert::TryFrom;
 
-use crate::cluster::{GetCompressor, GetConnection};
+use crate::cluster::{
+    GetCompressor, GetConnection, GetRequestTimeout, GetRetryPolicy, GetSpeculativeExecutionPolicy,
+};
 use crate::error;
-use crate::frame::traits::IntoBytes;
 use crate::frame::Frame;
+use crate::load_balancing::RoutingInfo;
 use crate::query::batch_query_builder::QueryBatch;
+use crate::query::QueryResponse;
 use crate::transport::CDRSTransport;
 
-use super::utils::{prepare_flags, send_frame};
+use super::utils::{prepare_flags, send_retried_frame};
 
 pub trait BatchExecutor<
     T: CDRSTransport + 'static,
     M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
->: GetConnection<T, M> + GetCompressor<'static>
+>:
+    GetConnection<T, M>
+    + GetCompressor<'static>
+    + GetRetryPolicy
+    + GetSpeculativeExecutionPolicy
+    + GetRequestTimeout
 {
     fn batch_with_params_tw(
         &self,
@@ -24,11 +33,26 @@ pub trait BatchExecutor<
     where
         Self: Sized,
     {
-        let flags = prepare_flags(with_tracing, with_warnings);
+        let routing = RoutingInfo {
+            consistency: Some(batch.consistency),
+            ..Default::default()
+        };
 
-        let query_frame = Frame::new_req_batch(batch, flags).into_cbytes();
+        send_retried_frame(
+            self,
+            &routing,
+            batch.is_idempotent,
+            None,
+            |consistency, protocol_version| {
+                let batch = QueryBatch {
+                    consistency,
+                    ..batch.clone()
+                };
+                let flags = prepare_flags(with_tracing, with_warnings);
 
-        send_frame(self, query_frame)
+                Frame::new_req_batch(batch, flags, protocol_version)
+            },
+        )
     }
 
     fn batch_with_params(&self, batch: QueryBatch) -> error::Result<Frame>
@@ -37,4 +61,28 @@ pub trait BatchExecutor<
     {
         self.batch_with_params_tw(batch, false, false)
     }
+
+    /// Executes a batch with ability to trace it and see warnings. Unlike
+    /// `batch_with_params_tw` it returns a decoded response together with
+    /// a tracing id, warnings and a custom payload sent by a server.
+    fn batch_response_tw(
+        &self,
+        batch: QueryBatch,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<QueryResponse>
+    where
+        Self: Sized,
+    {
+        self.batch_with_params_tw(batch, with_tracing, with_warnings)
+            .and_then(QueryResponse::try_from)
+    }
+
+    /// Executes a batch without warnings and tracing and returns a decoded response.
+    fn batch_response(&self, batch: QueryBatch) -> error::Result<QueryResponse>
+    where
+        Self: Sized,
+    {
+        self.batch_response_tw(batch, false, false)
+    }
 }
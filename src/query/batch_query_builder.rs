@@ -1,6 +1,7 @@
 use crate::consistency::Consistency;
 use crate::error::{Error as CError, Result as CResult};
 use crate::frame::frame_batch::{BatchQuery, BatchQuerySubj, BatchType, BodyReqBatch};
+use crate::frame::CustomPayload;
 use crate::query::{QueryFlags, QueryValues, PreparedQuery};
 use crate::types::CStringLong;
 
@@ -13,6 +14,10 @@ pub struct BatchQueryBuilder {
     consistency: Consistency,
     serial_consistency: Option<Consistency>,
     timestamp: Option<i64>,
+    keyspace: Option<String>,
+    now_in_seconds: Option<i32>,
+    is_idempotent: bool,
+    custom_payload: CustomPayload,
 }
 
 impl BatchQueryBuilder {
@@ -23,6 +28,10 @@ impl BatchQueryBuilder {
             consistency: Consistency::One,
             serial_consistency: None,
             timestamp: None,
+            keyspace: None,
+            now_in_seconds: None,
+            is_idempotent: false,
+            custom_payload: CustomPayload::new(),
         }
     }
 
@@ -71,6 +80,31 @@ impl BatchQueryBuilder {
         self
     }
 
+    /// Sets keyspace queries should be executed against. Protocol v5 only.
+    pub fn keyspace(mut self, keyspace: Option<String>) -> Self {
+        self.keyspace = keyspace;
+        self
+    }
+
+    /// Sets current time in seconds a server should use for queries. Protocol v5 only.
+    pub fn now_in_seconds(mut self, now_in_seconds: Option<i32>) -> Self {
+        self.now_in_seconds = now_in_seconds;
+        self
+    }
+
+    /// Sets whether a batch is idempotent. Idempotent batches can be executed
+    /// speculatively and retried with other nodes.
+    pub fn idempotent(mut self, is_idempotent: bool) -> Self {
+        self.is_idempotent = is_idempotent;
+        self
+    }
+
+    /// Sets custom payload sent along with a batch. Protocol v4 and higher.
+    pub fn custom_payload(mut self, custom_payload: CustomPayload) -> Self {
+        self.custom_payload = custom_payload;
+        self
+    }
+
     pub fn finalize(self) -> CResult<BodyReqBatch> {
         let mut flags = vec![];
 
@@ -99,6 +133,14 @@ impl BatchQueryBuilder {
             flags.push(QueryFlags::WithNamesForValues);
         }
 
+        if self.keyspace.is_some() {
+            flags.push(QueryFlags::WithKeyspace);
+        }
+
+        if self.now_in_seconds.is_some() {
+            flags.push(QueryFlags::WithNowInSeconds);
+        }
+
         Ok(BodyReqBatch {
             batch_type: self.batch_type,
             queries: self.queries,
@@ -106,6 +148,10 @@ impl BatchQueryBuilder {
             consistency: self.consistency,
             serial_consistency: self.serial_consistency,
             timestamp: self.timestamp,
+            keyspace: self.keyspace,
+            now_in_seconds: self.now_in_seconds,
+            is_idempotent: self.is_idempotent,
+            custom_payload: self.custom_payload,
         })
     }
 }
@@ -1,19 +1,31 @@
 use r2d2;
 use std::cell::RefCell;
+use std::convert::TryFrom;
 
-use crate::cluster::{GetCompressor, GetConnection};
+use crate::cluster::{
+    GetCompressor, GetConnection, GetRequestTimeout, GetRetryPolicy, GetSpeculativeExecutionPolicy,
+};
 use crate::error;
-use crate::frame::{Frame, IntoBytes};
-use crate::query::{QueryParams, QueryParamsBuilder, QueryValues, PreparedQuery, PrepareExecutor};
+use crate::frame::Frame;
+use crate::load_balancing::RoutingInfo;
+use crate::query::{
+    PrepareExecutor, PreparedQuery, QueryParams, QueryParamsBuilder, QueryResponse, QueryValues,
+};
 use crate::transport::CDRSTransport;
 
-use super::utils::{prepare_flags, send_frame};
-use crate::error::Error;
+use super::utils::{prepare_flags, send_retried_frame};
+use crate::error::ErrorKind;
 
 pub trait ExecExecutor<
     T: CDRSTransport + 'static,
     M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
->: GetConnection<T, M> + GetCompressor<'static> + PrepareExecutor<T, M>
+>:
+    GetConnection<T, M>
+    + GetCompressor<'static>
+    + GetRetryPolicy
+    + GetSpeculativeExecutionPolicy
+    + GetRequestTimeout
+    + PrepareExecutor<T, M>
 {
     fn exec_with_params_tw(
         &self,
@@ -25,18 +37,48 @@ pub trait ExecExecutor<
     where
         Self: Sized,
     {
-        let flags = prepare_flags(with_tracing, with_warnings);
-        let options_frame = Frame::new_req_execute(&prepared.id.borrow(), &query_parameters, flags).into_cbytes();
+        let routing = RoutingInfo {
+            routing_key: query_parameters
+                .values
+                .as_ref()
+                .and_then(|values| prepared.routing_key(values)),
+            consistency: Some(query_parameters.consistency),
+        };
 
-        let mut result = send_frame(self, options_frame);
-        if let Err(Error::Server(error)) = &result {
+        let is_idempotent = prepared.is_idempotent() || query_parameters.is_idempotent;
+        let timeout = query_parameters.timeout;
+        let send_execute = || {
+            send_retried_frame(
+                self,
+                &routing,
+                is_idempotent,
+                timeout,
+                |consistency, protocol_version| {
+                    let query_parameters = QueryParams {
+                        consistency,
+                        ..query_parameters.clone()
+                    };
+                    let flags = prepare_flags(with_tracing, with_warnings);
+
+                    Frame::new_req_execute_with_result_metadata_id(
+                        &prepared.get_id(),
+                        prepared.get_result_metadata_id().as_ref(),
+                        &query_parameters,
+                        flags,
+                        protocol_version,
+                    )
+                },
+            )
+        };
+
+        let mut result = send_execute();
+        if let Err(ref error) = result {
             // if query is unprepared
-            if error.error_code == 0x2500 {
+            if error.kind() == Some(ErrorKind::Unprepared) {
                 if let Ok(new) = self.prepare_raw(&prepared.query) {
-                    prepared.id.replace(new.id);
-                    let flags = prepare_flags(with_tracing, with_warnings);
-                    let options_frame = Frame::new_req_execute(&prepared.id.borrow(), &query_parameters, flags).into_cbytes();
-                    result = send_frame(self, options_frame);
+                    prepared.set_id(new.id);
+                    prepared.set_result_metadata_id(new.result_metadata_id);
+                    result = send_execute();
                 }
             }
         }
@@ -99,4 +141,34 @@ pub trait ExecExecutor<
     {
         self.exec_tw(prepared, false, false)
     }
+
+    /// Executes a prepared query with query params and ability to trace it and see
+    /// warnings. Unlike `exec_with_params_tw` it returns a decoded response together
+    /// with a tracing id, warnings and a custom payload sent by a server.
+    fn exec_response_tw(
+        &self,
+        prepared: &PreparedQuery,
+        query_parameters: QueryParams,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<QueryResponse>
+    where
+        Self: Sized,
+    {
+        self.exec_with_params_tw(prepared, query_parameters, with_tracing, with_warnings)
+            .and_then(QueryResponse::try_from)
+    }
+
+    /// Executes a prepared query with query params without warnings and tracing
+    /// and returns a decoded response.
+    fn exec_response(
+        &self,
+        prepared: &PreparedQuery,
+        query_parameters: QueryParams,
+    ) -> error::Result<QueryResponse>
+    where
+        Self: Sized,
+    {
+        self.exec_response_tw(prepared, query_parameters, false, false)
+    }
 }
@@ -8,8 +8,9 @@ mod query_executor;
 mod query_flags;
 mod query_params;
 mod query_params_builder;
+mod query_response;
 mod query_values;
-mod utils;
+pub(crate) mod utils;
 
 pub use crate::query::batch_executor::BatchExecutor;
 pub use crate::query::batch_query_builder::{BatchQueryBuilder, QueryBatch};
@@ -21,4 +22,5 @@ pub use crate::query::query_executor::QueryExecutor;
 pub use crate::query::query_flags::QueryFlags;
 pub use crate::query::query_params::QueryParams;
 pub use crate::query::query_params_builder::QueryParamsBuilder;
+pub use crate::query::query_response::QueryResponse;
 pub use crate::query::query_values::QueryValues;
@@ -1,10 +1,10 @@
 use r2d2;
 use std::cell::RefCell;
 
-use crate::cluster::{GetCompressor, GetConnection};
+use crate::cluster::{GetCompressor, GetConnection, GetRequestTimeout};
 use crate::error;
 use crate::frame::frame_result::BodyResResultPrepared;
-use crate::frame::{Frame, IntoBytes};
+use crate::frame::Frame;
 use crate::transport::CDRSTransport;
 
 use super::utils::{prepare_flags, send_frame};
@@ -13,7 +13,7 @@ use crate::query::PreparedQuery;
 pub trait PrepareExecutor<
     T: CDRSTransport + 'static,
     M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
->: GetConnection<T, M> + GetCompressor<'static>
+>: GetConnection<T, M> + GetCompressor<'static> + GetRequestTimeout
 {
     /// It prepares a query for execution, along with query itself the
     /// method takes `with_tracing` and `with_warnings` flags to get
@@ -30,15 +30,15 @@ pub trait PrepareExecutor<
     {
         let flags = prepare_flags(with_tracing, with_warnings);
 
-        let query_frame = Frame::new_req_prepare(query.to_string(), flags).into_cbytes();
-
-        send_frame(self, query_frame)
-            .and_then(|response| response.get_body())
-            .and_then(|body| {
-                Ok(body
-                    .into_prepared()
-                    .expect("CDRS BUG: cannot convert frame into prepared"))
+        send_frame(self, |protocol_version| {
+            Frame::new_req_prepare(query.to_string(), flags, protocol_version)
+        })
+        .and_then(|response| response.get_body())
+        .and_then(|body| {
+            body.into_prepared().ok_or_else(|| {
+                error::Error::InvalidFrame("Expected prepared result in response".to_string())
             })
+        })
     }
 
     /// It prepares query without additional tracing information and warnings.
@@ -65,7 +65,7 @@ pub trait PrepareExecutor<
     {
         let str = query.to_string();
         self.prepare_raw_tw(query, with_tracing, with_warnings)
-            .map(|x| PreparedQuery { id: RefCell::new(x.id), query: str })
+            .map(|x| PreparedQuery::from_prepared(x, str))
     }
 
     /// It prepares query without additional tracing information and warnings.
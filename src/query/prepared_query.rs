@@ -1,8 +1,164 @@
-use std::cell::RefCell;
+use std::sync::RwLock;
+
+use crate::frame::frame_result::BodyResResultPrepared;
+use crate::query::QueryValues;
+use crate::routing::serialize_values;
+use crate::types::value::Value;
 use crate::types::CBytesShort;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct PreparedQuery {
-	pub(crate) id: RefCell<CBytesShort>,
+	pub(crate) id: RwLock<CBytesShort>,
+	/// Id of result metadata which is sent with executions in protocol v5.
+	pub(crate) result_metadata_id: RwLock<Option<CBytesShort>>,
 	pub(crate) query: String,
+	/// Indexes and names of bound values which make up a partition key.
+	pub(crate) partition_key: Vec<(usize, String)>,
+	pub(crate) is_idempotent: bool,
+}
+
+impl PreparedQuery {
+	pub(crate) fn new(id: CBytesShort, query: String) -> Self {
+		PreparedQuery {
+			id: RwLock::new(id),
+			result_metadata_id: RwLock::new(None),
+			query,
+			partition_key: vec![],
+			is_idempotent: false,
+		}
+	}
+
+	/// Marks prepared query as idempotent, so its executions could be speculative.
+	pub fn idempotent(mut self, is_idempotent: bool) -> Self {
+		self.is_idempotent = is_idempotent;
+		self
+	}
+
+	/// Shows if prepared query is idempotent.
+	pub fn is_idempotent(&self) -> bool {
+		self.is_idempotent
+	}
+
+	/// Creates prepared query from a server response, including information
+	/// about partition key columns.
+	pub(crate) fn from_prepared(prepared: BodyResResultPrepared, query: String) -> Self {
+		let metadata = prepared.metadata;
+		let mut prepared_query = PreparedQuery::new(prepared.id, query);
+		prepared_query.set_result_metadata_id(prepared.result_metadata_id);
+		prepared_query.partition_key = metadata
+			.pk_indexes
+			.iter()
+			.filter_map(|index| {
+				let index = *index as usize;
+				metadata
+					.col_specs
+					.get(index)
+					.map(|spec| (index, spec.name.as_plain()))
+			})
+			.collect();
+
+		prepared_query
+	}
+
+	/// Returns a copy of current id of prepared query.
+	pub(crate) fn get_id(&self) -> CBytesShort {
+		match self.id.read() {
+			Ok(id) => id.clone(),
+			Err(poisoned) => poisoned.into_inner().clone(),
+		}
+	}
+
+	/// Replaces an id of prepared query, e.g. when a query was re-prepared.
+	pub(crate) fn set_id(&self, id: CBytesShort) {
+		match self.id.write() {
+			Ok(mut current) => *current = id,
+			Err(poisoned) => *poisoned.into_inner() = id,
+		}
+	}
+
+	/// Returns a copy of current id of result metadata of prepared query.
+	pub(crate) fn get_result_metadata_id(&self) -> Option<CBytesShort> {
+		match self.result_metadata_id.read() {
+			Ok(id) => id.clone(),
+			Err(poisoned) => poisoned.into_inner().clone(),
+		}
+	}
+
+	/// Replaces an id of result metadata, e.g. when a query was re-prepared.
+	pub(crate) fn set_result_metadata_id(&self, id: Option<CBytesShort>) {
+		match self.result_metadata_id.write() {
+			Ok(mut current) => *current = id,
+			Err(poisoned) => *poisoned.into_inner() = id,
+		}
+	}
+
+	/// Returns serialized partition key made of bound values. `None` is returned
+	/// if partition key is unknown or some of its components are not bound.
+	pub(crate) fn routing_key(&self, values: &QueryValues) -> Option<Vec<u8>> {
+		let components = self
+			.partition_key
+			.iter()
+			.map(|(index, name)| match values {
+				QueryValues::SimpleValues(values) => values.get(*index),
+				QueryValues::NamedValues(values) => values.get(name),
+			})
+			.collect::<Option<Vec<&Value>>>()?;
+
+		serialize_values(components)
+	}
+}
+
+impl Clone for PreparedQuery {
+	fn clone(&self) -> Self {
+		PreparedQuery {
+			id: RwLock::new(self.get_id()),
+			result_metadata_id: RwLock::new(self.get_result_metadata_id()),
+			query: self.query.clone(),
+			partition_key: self.partition_key.clone(),
+			is_idempotent: self.is_idempotent,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+
+	fn prepared_query() -> PreparedQuery {
+		let mut query = PreparedQuery::new(CBytesShort::new(vec![1]), "".into());
+		query.partition_key = vec![(1, "a".into()), (0, "b".into())];
+		query
+	}
+
+	#[test]
+	fn routing_key_of_simple_values() {
+		let values = QueryValues::SimpleValues(vec![Value::from(1i32), Value::from(2i8)]);
+		assert_eq!(
+			prepared_query().routing_key(&values),
+			Some(vec![0, 1, 2, 0, 0, 4, 0, 0, 0, 1, 0])
+		);
+	}
+
+	#[test]
+	fn routing_key_of_named_values() {
+		let mut values = HashMap::new();
+		values.insert("a", Value::from(2i8));
+		values.insert("b", Value::from(1i32));
+		assert_eq!(
+			prepared_query().routing_key(&values.into()),
+			Some(vec![0, 1, 2, 0, 0, 4, 0, 0, 0, 1, 0])
+		);
+	}
+
+	#[test]
+	fn routing_key_of_unbound_values() {
+		let values = QueryValues::SimpleValues(vec![Value::new_null(), Value::from(2i8)]);
+		assert_eq!(prepared_query().routing_key(&values), None);
+		assert_eq!(
+			PreparedQuery::new(CBytesShort::new(vec![1]), "".into())
+				.routing_key(&QueryValues::SimpleValues(vec![Value::from(1i32)])),
+			None
+		);
+	}
 }
@@ -1,18 +1,27 @@
 use r2d2;
 use std::cell::RefCell;
+use std::convert::TryFrom;
 
-use crate::cluster::{GetCompressor, GetConnection};
+use crate::cluster::{
+    GetCompressor, GetConnection, GetRequestTimeout, GetRetryPolicy, GetSpeculativeExecutionPolicy,
+};
 use crate::error;
-use crate::frame::{Frame, IntoBytes};
-use crate::query::{Query, QueryParams, QueryParamsBuilder, QueryValues};
+use crate::frame::Frame;
+use crate::load_balancing::RoutingInfo;
+use crate::query::{Query, QueryParams, QueryParamsBuilder, QueryResponse, QueryValues};
 use crate::transport::CDRSTransport;
 
-use super::utils::{prepare_flags, send_frame};
+use super::utils::{prepare_flags, send_retried_frame};
 
 pub trait QueryExecutor<
     T: CDRSTransport + 'static,
     M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
->: GetConnection<T, M> + GetCompressor<'static>
+>:
+    GetConnection<T, M>
+    + GetCompressor<'static>
+    + GetRetryPolicy
+    + GetSpeculativeExecutionPolicy
+    + GetRequestTimeout
 {
     fn query_with_params_tw<Q: ToString>(
         &self,
@@ -24,16 +33,33 @@ pub trait QueryExecutor<
     where
         Self: Sized,
     {
-        let query = Query {
-            query: query.to_string(),
-            params: query_params,
+        let routing = RoutingInfo {
+            consistency: Some(query_params.consistency),
+            ..Default::default()
         };
+        let query = query.to_string();
+        let is_idempotent = query_params.is_idempotent;
+        let timeout = query_params.timeout;
 
-        let flags = prepare_flags(with_tracing, with_warnings);
+        send_retried_frame(
+            self,
+            &routing,
+            is_idempotent,
+            timeout,
+            |consistency, protocol_version| {
+                let query = Query {
+                    query: query.clone(),
+                    params: QueryParams {
+                        consistency,
+                        ..query_params.clone()
+                    },
+                };
 
-        let query_frame = Frame::new_query(query, flags).into_cbytes();
+                let flags = prepare_flags(with_tracing, with_warnings);
 
-        send_frame(self, query_frame)
+                Frame::new_query(query, flags, protocol_version)
+            },
+        )
     }
 
     /// Executes a query with default parameters:
@@ -100,4 +126,34 @@ pub trait QueryExecutor<
     {
         self.query_with_params_tw(query, query_params, false, false)
     }
+
+    /// Executes a query with query params and ability to trace it and see warnings.
+    /// Unlike `query_with_params_tw` it returns a decoded response together with
+    /// a tracing id, warnings and a custom payload sent by a server.
+    fn query_response_tw<Q: ToString>(
+        &self,
+        query: Q,
+        query_params: QueryParams,
+        with_tracing: bool,
+        with_warnings: bool,
+    ) -> error::Result<QueryResponse>
+    where
+        Self: Sized,
+    {
+        self.query_with_params_tw(query, query_params, with_tracing, with_warnings)
+            .and_then(QueryResponse::try_from)
+    }
+
+    /// Executes a query with query params without warnings and tracing
+    /// and returns a decoded response.
+    fn query_response<Q: ToString>(
+        &self,
+        query: Q,
+        query_params: QueryParams,
+    ) -> error::Result<QueryResponse>
+    where
+        Self: Sized,
+    {
+        self.query_response_tw(query, query_params, false, false)
+    }
 }
@@ -7,6 +7,8 @@ const WITH_PAGING_STATE: u8 = 0x08;
 const WITH_SERIAL_CONSISTENCY: u8 = 0x10;
 const WITH_DEFAULT_TIMESTAMP: u8 = 0x20;
 const WITH_NAME_FOR_VALUES: u8 = 0x40;
+const WITH_KEYSPACE: i32 = 0x80;
+const WITH_NOW_IN_SECONDS: i32 = 0x100;
 
 /// Cassandra Query Flags.
 #[derive(Clone, Debug)]
@@ -25,6 +27,11 @@ pub enum QueryFlags {
     WithDefaultTimestamp,
     /// If set indicates that Query Params values are named ones.
     WithNamesForValues,
+    /// If set indicates that Query Params contains keyspace. Protocol v5 only.
+    WithKeyspace,
+    /// If set indicates that Query Params contains current time in seconds.
+    /// Protocol v5 only.
+    WithNowInSeconds,
 }
 
 impl QueryFlags {
@@ -97,9 +104,40 @@ impl QueryFlags {
     pub fn set_with_names_for_values(byte: u8) -> u8 {
         byte | WITH_NAME_FOR_VALUES
     }
+
+    #[doc(hidden)]
+    pub fn has_with_keyspace(flags: i32) -> bool {
+        (flags & WITH_KEYSPACE) != 0
+    }
+
+    #[doc(hidden)]
+    pub fn set_with_keyspace(flags: i32) -> i32 {
+        flags | WITH_KEYSPACE
+    }
+
+    #[doc(hidden)]
+    pub fn has_with_now_in_seconds(flags: i32) -> bool {
+        (flags & WITH_NOW_IN_SECONDS) != 0
+    }
+
+    #[doc(hidden)]
+    pub fn set_with_now_in_seconds(flags: i32) -> i32 {
+        flags | WITH_NOW_IN_SECONDS
+    }
+
+    /// Returns a flag as an int. Protocol v5 sends query flags as an int
+    /// while previous versions send them as a single byte.
+    pub fn as_int(&self) -> i32 {
+        match *self {
+            QueryFlags::WithKeyspace => WITH_KEYSPACE,
+            QueryFlags::WithNowInSeconds => WITH_NOW_IN_SECONDS,
+            _ => self.as_byte() as i32,
+        }
+    }
 }
 
 impl AsByte for QueryFlags {
+    /// Flags which are specific to protocol v5 are represented as zero.
     fn as_byte(&self) -> u8 {
         match *self {
             QueryFlags::Value => FLAGS_VALUE,
@@ -109,6 +147,7 @@ impl AsByte for QueryFlags {
             QueryFlags::WithSerialConsistency => WITH_SERIAL_CONSISTENCY,
             QueryFlags::WithDefaultTimestamp => WITH_DEFAULT_TIMESTAMP,
             QueryFlags::WithNamesForValues => WITH_NAME_FOR_VALUES,
+            QueryFlags::WithKeyspace | QueryFlags::WithNowInSeconds => 0,
         }
     }
 }
@@ -264,6 +303,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn with_keyspace_test() {
+        assert!(QueryFlags::has_with_keyspace(WITH_KEYSPACE | 0x10));
+        assert!(!QueryFlags::has_with_keyspace(WITH_NOW_IN_SECONDS));
+        assert_eq!(QueryFlags::set_with_keyspace(0), WITH_KEYSPACE);
+    }
+
+    #[test]
+    fn with_now_in_seconds_test() {
+        assert!(QueryFlags::has_with_now_in_seconds(
+            WITH_NOW_IN_SECONDS | 0x10
+        ));
+        assert!(!QueryFlags::has_with_now_in_seconds(WITH_KEYSPACE));
+        assert_eq!(QueryFlags::set_with_now_in_seconds(0), WITH_NOW_IN_SECONDS);
+    }
+
+    #[test]
+    fn as_int_test() {
+        assert_eq!(QueryFlags::Value.as_int(), FLAGS_VALUE as i32);
+        assert_eq!(QueryFlags::WithKeyspace.as_int(), WITH_KEYSPACE);
+        assert_eq!(QueryFlags::WithNowInSeconds.as_int(), WITH_NOW_IN_SECONDS);
+        assert_eq!(QueryFlags::WithKeyspace.as_byte(), 0);
+        assert_eq!(QueryFlags::WithNowInSeconds.as_byte(), 0);
+    }
+
     #[test]
     fn as_byte_test() {
         assert_eq!(
@@ -1,9 +1,11 @@
+use std::time::Duration;
+
 use crate::consistency::Consistency;
 use crate::frame::AsByte;
-use crate::frame::IntoBytes;
+use crate::frame::{CustomPayload, IntoBytes, ProtocolVersion};
 use crate::query::query_flags::QueryFlags;
 use crate::query::query_values::QueryValues;
-use crate::types::{to_bigint, to_int, to_short, CBytes};
+use crate::types::{to_bigint, to_int, to_short, CBytes, CString};
 
 /// Parameters of Query for query operation.
 #[derive(Debug, Default, Clone)]
@@ -24,6 +26,20 @@ pub struct QueryParams {
     pub serial_consistency: Option<Consistency>,
     /// Timestamp.
     pub timestamp: Option<i64>,
+    /// Keyspace a query should be executed against. Protocol v5 only.
+    pub keyspace: Option<String>,
+    /// Current time in seconds a server should use for a query, e.g. to check
+    /// TTLs. Protocol v5 only.
+    pub now_in_seconds: Option<i32>,
+    /// Is a query idempotent, i.e. can it be applied multiple times without changing
+    /// the result. Only idempotent queries are executed speculatively or retried with
+    /// other nodes. It is not sent to a server.
+    pub is_idempotent: bool,
+    /// Custom payload sent along with a query. It is not sent in protocol v3.
+    pub custom_payload: CustomPayload,
+    /// Time to wait for a response, overrides a request timeout of a session.
+    /// It is not sent to a server.
+    pub timeout: Option<Duration>,
 }
 
 impl QueryParams {
@@ -37,6 +53,10 @@ impl QueryParams {
         self.flags.iter().fold(0, |acc, flag| acc | flag.as_byte())
     }
 
+    fn flags_as_int(&self) -> i32 {
+        self.flags.iter().fold(0, |acc, flag| acc | flag.as_int())
+    }
+
     #[allow(dead_code)]
     fn parse_query_flags(byte: u8) -> Vec<QueryFlags> {
         let mut flags: Vec<QueryFlags> = vec![];
@@ -67,12 +87,17 @@ impl QueryParams {
     }
 }
 
-impl IntoBytes for QueryParams {
-    fn into_cbytes(&self) -> Vec<u8> {
+impl QueryParams {
+    /// Serializes query parameters in accordance to a given protocol version.
+    pub fn into_cbytes_with_version(&self, protocol_version: ProtocolVersion) -> Vec<u8> {
         let mut v: Vec<u8> = vec![];
 
         v.extend_from_slice(self.consistency.into_cbytes().as_slice());
-        v.push(self.flags_as_byte());
+        if protocol_version >= ProtocolVersion::V5 {
+            v.extend_from_slice(to_int(self.flags_as_int()).as_slice());
+        } else {
+            v.push(self.flags_as_byte());
+        }
         if QueryFlags::has_value(self.flags_as_byte()) {
             if let Some(ref values) = self.values {
                 v.extend_from_slice(to_short(values.len() as i16).as_slice());
@@ -123,7 +148,55 @@ impl IntoBytes for QueryParams {
             // unwrap is safe as we've checked that self.timestamp.is_some()
             v.extend_from_slice(to_bigint(self.timestamp.unwrap()).as_slice());
         }
+        if protocol_version >= ProtocolVersion::V5 {
+            if let Some(ref keyspace) = self.keyspace {
+                if QueryFlags::has_with_keyspace(self.flags_as_int()) {
+                    v.extend_from_slice(CString::new(keyspace.clone()).into_cbytes().as_slice());
+                }
+            }
+            if let Some(now_in_seconds) = self.now_in_seconds {
+                if QueryFlags::has_with_now_in_seconds(self.flags_as_int()) {
+                    v.extend_from_slice(to_int(now_in_seconds).as_slice());
+                }
+            }
+        }
 
         v
     }
 }
+
+impl IntoBytes for QueryParams {
+    fn into_cbytes(&self) -> Vec<u8> {
+        self.into_cbytes_with_version(ProtocolVersion::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryParamsBuilder;
+
+    #[test]
+    fn keyspace_is_not_sent_before_v5() {
+        let params = QueryParamsBuilder::new()
+            .keyspace("ks")
+            .now_in_seconds(1)
+            .finalize();
+        assert_eq!(
+            params.into_cbytes_with_version(ProtocolVersion::V4),
+            vec![0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn keyspace_and_now_in_seconds() {
+        let params = QueryParamsBuilder::new()
+            .keyspace("ks")
+            .now_in_seconds(1)
+            .finalize();
+        assert_eq!(
+            params.into_cbytes_with_version(ProtocolVersion::V5),
+            vec![0, 1, 0, 0, 1, 0x80, 0, 2, b'k', b's', 0, 0, 0, 1]
+        );
+    }
+}
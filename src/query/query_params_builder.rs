@@ -1,5 +1,8 @@
+use std::time::Duration;
+
 use super::{QueryFlags, QueryParams, QueryValues};
 use crate::consistency::Consistency;
+use crate::frame::CustomPayload;
 use crate::types::CBytes;
 
 #[derive(Debug, Default)]
@@ -12,6 +15,11 @@ pub struct QueryParamsBuilder {
     paging_state: Option<CBytes>,
     serial_consistency: Option<Consistency>,
     timestamp: Option<i64>,
+    keyspace: Option<String>,
+    now_in_seconds: Option<i32>,
+    is_idempotent: bool,
+    custom_payload: CustomPayload,
+    timeout: Option<Duration>,
 }
 
 impl QueryParamsBuilder {
@@ -81,6 +89,51 @@ impl QueryParamsBuilder {
     /// Sets new timestamp value.
     builder_opt_field!(timestamp, i64);
 
+    /// Sets keyspace a query should be executed against instead of the current
+    /// keyspace of a connection. Protocol v5 only.
+    pub fn keyspace<K: Into<String>>(mut self, keyspace: K) -> Self {
+        self.keyspace = Some(keyspace.into());
+        self.flags = self.flags.or(Some(vec![])).map(|mut flags| {
+            flags.push(QueryFlags::WithKeyspace);
+            flags
+        });
+
+        self
+    }
+
+    /// Sets current time in seconds a server should use for a query. Protocol v5 only.
+    pub fn now_in_seconds(mut self, now_in_seconds: i32) -> Self {
+        self.now_in_seconds = Some(now_in_seconds);
+        self.flags = self.flags.or(Some(vec![])).map(|mut flags| {
+            flags.push(QueryFlags::WithNowInSeconds);
+            flags
+        });
+
+        self
+    }
+
+    /// Marks query as idempotent, so it could be executed speculatively and retried
+    /// with other nodes.
+    pub fn idempotent(mut self, is_idempotent: bool) -> Self {
+        self.is_idempotent = is_idempotent;
+
+        self
+    }
+
+    /// Sets custom payload sent along with a query. Protocol v4 and higher.
+    pub fn custom_payload(mut self, custom_payload: CustomPayload) -> Self {
+        self.custom_payload = custom_payload;
+
+        self
+    }
+
+    /// Sets time to wait for a response instead of a request timeout of a session.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+
+        self
+    }
+
     /// Finalizes query building process and returns query itself
     pub fn finalize(self) -> QueryParams {
         QueryParams {
@@ -92,6 +145,11 @@ impl QueryParamsBuilder {
             paging_state: self.paging_state,
             serial_consistency: self.serial_consistency,
             timestamp: self.timestamp,
+            keyspace: self.keyspace,
+            now_in_seconds: self.now_in_seconds,
+            is_idempotent: self.is_idempotent,
+            custom_payload: self.custom_payload,
+            timeout: self.timeout,
         }
     }
 }
@@ -0,0 +1,109 @@
+use std::convert::TryFrom;
+
+use uuid::Uuid;
+
+use crate::error;
+use crate::frame::frame_response::ResponseBody;
+use crate::frame::{CustomPayload, Frame};
+use crate::types::rows::Row;
+
+/// Decoded response to a request like QUERY, EXECUTE or BATCH together with
+/// information a server has sent along with its body.
+#[derive(Debug)]
+pub struct QueryResponse {
+    pub body: ResponseBody,
+    /// Id of a trace if a request has been sent with tracing enabled.
+    pub tracing_id: Option<Uuid>,
+    /// Warnings a server has produced while executing a request.
+    pub warnings: Vec<String>,
+    /// Custom payload returned by a custom query handler of a server.
+    pub custom_payload: CustomPayload,
+}
+
+impl QueryResponse {
+    /// Returns rows if the response is a result of `Rows` kind.
+    pub fn into_rows(self) -> Option<Vec<Row>> {
+        self.body.into_rows()
+    }
+}
+
+impl TryFrom<Frame> for QueryResponse {
+    type Error = error::Error;
+
+    fn try_from(frame: Frame) -> error::Result<QueryResponse> {
+        Ok(QueryResponse {
+            body: frame.get_body()?,
+            tracing_id: frame.tracing_id,
+            warnings: frame.warnings,
+            custom_payload: frame.custom_payload,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::Compression;
+    use crate::frame::parser::parse_frame;
+    use crate::frame::{Flag, IntoBytes, Opcode, ProtocolVersion};
+    use crate::query::{Query, QueryParamsBuilder};
+    use crate::test::versioned_response_frame;
+    use crate::types::{CBytesMap, CString, CStringList};
+    use std::cell::RefCell;
+    use std::io::Cursor;
+
+    fn payload() -> CustomPayload {
+        let mut payload = CustomPayload::new();
+        payload.insert("handler".to_string(), vec![1, 2, 3]);
+        payload
+    }
+
+    #[test]
+    fn response_with_tracing_warnings_and_payload() {
+        let tracing_id = Uuid::parse_str("5e0c6a40-1d3b-11eb-8b6f-0242ac110002").unwrap();
+        let mut body = tracing_id.as_bytes().to_vec();
+        let warnings = CStringList {
+            list: vec![CString::new("Aggregation query used".to_string())],
+        };
+        body.extend(warnings.into_cbytes());
+        body.extend(CBytesMap::new(payload()).into_cbytes());
+        // void result
+        body.extend_from_slice(&[0, 0, 0, 1]);
+
+        let mut bytes = versioned_response_frame(Opcode::Result, body, ProtocolVersion::V4);
+        bytes[1] = Flag::many_to_cbytes(&vec![Flag::Tracing, Flag::Warning, Flag::CustomPayload]);
+        let frame = parse_frame(&RefCell::new(Cursor::new(bytes)), &Compression::None).unwrap();
+        let response = QueryResponse::try_from(frame).unwrap();
+
+        assert_eq!(response.tracing_id, Some(tracing_id));
+        assert_eq!(
+            response.warnings,
+            vec!["Aggregation query used".to_string()]
+        );
+        assert_eq!(response.custom_payload, payload());
+        assert!(matches!(response.body, ResponseBody::Result(_)));
+    }
+
+    #[test]
+    fn request_with_payload() {
+        let query = |protocol_version| {
+            let query = Query {
+                query: "SELECT 1".to_string(),
+                params: QueryParamsBuilder::new()
+                    .custom_payload(payload())
+                    .finalize(),
+            };
+            Frame::new_query(query, vec![], protocol_version)
+        };
+
+        let frame = query(ProtocolVersion::V4);
+        assert_eq!(frame.flags, vec![Flag::CustomPayload]);
+        let payload_bytes = CBytesMap::new(payload()).into_cbytes();
+        assert!(frame.body.starts_with(&payload_bytes));
+
+        // protocol v3 does not support custom payloads
+        let frame = query(ProtocolVersion::V3);
+        assert!(frame.flags.is_empty());
+        assert!(!frame.body.starts_with(&payload_bytes));
+    }
+}
@@ -1,9 +1,23 @@
 use std::cell::RefCell;
+use std::io::{self, Read, Write};
+use std::net;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::cluster::{GetCompressor, GetConnection};
+use crate::cluster::{
+    GetCompressor, GetConnection, GetRequestTimeout, GetRetryPolicy, GetSpeculativeExecutionPolicy,
+};
+use crate::compression::Compression;
+use crate::consistency::Consistency;
 use crate::error;
-use crate::frame::parser::from_connection;
-use crate::frame::{Flag, Frame};
+use crate::frame::parser::parse_framed;
+use crate::frame::{Flag, Frame, ProtocolVersion};
+use crate::load_balancing::RoutingInfo;
+use crate::retry::{RetryDecision, RetryInfo};
+use crate::speculative_execution::{
+    SpeculativeExecutionPolicy, DEFAULT_SPECULATIVE_EXECUTION_TIMEOUT,
+};
 use crate::transport::CDRSTransport;
 
 pub fn prepare_flags(with_tracing: bool, with_warnings: bool) -> Vec<Flag> {
@@ -20,28 +34,334 @@ pub fn prepare_flags(with_tracing: bool, with_warnings: bool) -> Vec<Flag> {
     flags
 }
 
-pub fn send_frame<S, T, M>(sender: &S, frame_bytes: Vec<u8>) -> error::Result<Frame>
+/// Builds a request frame for a protocol version of a picked connection, encodes it
+/// with sender's compression, sends it and waits for a response within sender's
+/// request timeout.
+pub fn send_frame<S, T, M, F>(sender: &S, build_frame: F) -> error::Result<Frame>
 where
-    S: GetConnection<T, M> + GetCompressor<'static> + Sized,
+    S: GetConnection<T, M> + GetCompressor<'static> + GetRequestTimeout + Sized,
     T: CDRSTransport + 'static,
     M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
+    F: FnOnce(ProtocolVersion) -> Frame,
+{
+    send_routed_frame(sender, build_frame, &RoutingInfo::default())
+}
+
+/// Does the same as `send_frame` but picks a connection basing on routing information.
+pub fn send_routed_frame<S, T, M, F>(
+    sender: &S,
+    build_frame: F,
+    routing: &RoutingInfo,
+) -> error::Result<Frame>
+where
+    S: GetConnection<T, M> + GetCompressor<'static> + GetRequestTimeout + Sized,
+    T: CDRSTransport + 'static,
+    M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
+    F: FnOnce(ProtocolVersion) -> Frame,
 {
-    let ref compression = sender.get_compressor();
     let transport_cell = sender
-        .get_connection()
-        .ok_or(error::Error::from("Unable to get transport"))?;
+        .get_connection_for(routing)
+        .ok_or_else(|| error::Error::Pool("Unable to get transport".to_string()))?;
+    let frame = build_frame(transport_cell.borrow().protocol_version());
+
+    send_frame_over(
+        &transport_cell,
+        frame,
+        sender.get_compressor(),
+        sender.get_request_timeout(),
+    )
+}
+
+/// Sends a request frame built for a consistency level from routing information
+/// and a protocol version of a picked connection and resends it as long as sender's
+/// retry policy decides to retry server errors.
+/// Idempotent requests are executed speculatively if sender has such policy.
+/// Every attempt is given `timeout`, or sender's request timeout if it is not specified.
+/// Speculative executions are given `DEFAULT_SPECULATIVE_EXECUTION_TIMEOUT` if there
+/// is neither.
+pub fn send_retried_frame<S, T, M, F>(
+    sender: &S,
+    routing: &RoutingInfo,
+    is_idempotent: bool,
+    timeout: Option<Duration>,
+    build_frame: F,
+) -> error::Result<Frame>
+where
+    S: GetConnection<T, M>
+        + GetCompressor<'static>
+        + GetRetryPolicy
+        + GetSpeculativeExecutionPolicy
+        + GetRequestTimeout
+        + Sized,
+    T: CDRSTransport + 'static,
+    M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
+    F: Fn(Consistency, ProtocolVersion) -> Frame,
+{
+    let speculative_execution_policy = sender
+        .get_speculative_execution_policy()
+        .filter(|_| is_idempotent);
+    let timeout = timeout.or_else(|| sender.get_request_timeout());
+    let mut consistency = routing.consistency.unwrap_or_default();
+    let mut retry_count = 0;
+    let mut transport_cell = sender
+        .get_connection_for(routing)
+        .ok_or_else(|| error::Error::Pool("Unable to get transport".to_string()))?;
+
+    loop {
+        let result = match speculative_execution_policy {
+            Some(policy) => {
+                let (winner, result) = send_speculative_frame(
+                    sender,
+                    transport_cell,
+                    consistency,
+                    policy,
+                    timeout.unwrap_or(DEFAULT_SPECULATIVE_EXECUTION_TIMEOUT),
+                    |version| build_frame(consistency, version),
+                )?;
+                transport_cell = winner;
+                result
+            }
+            None => {
+                let protocol_version = transport_cell.borrow().protocol_version();
+                send_frame_over(
+                    &transport_cell,
+                    build_frame(consistency, protocol_version),
+                    sender.get_compressor(),
+                    timeout,
+                )
+            }
+        };
+        let decision = match result.as_ref().map_err(error::Error::server_error) {
+            Err(Some(error)) => sender.get_retry_policy().decide(&RetryInfo {
+                error,
+                consistency,
+                retry_count,
+                is_idempotent,
+            }),
+            _ => RetryDecision::DontRetry,
+        };
+
+        match decision {
+            RetryDecision::RetrySameNode(new_consistency) => {
+                consistency = new_consistency.unwrap_or(consistency);
+            }
+            RetryDecision::RetryNextNode(new_consistency) => {
+                consistency = new_consistency.unwrap_or(consistency);
+                // routing key is omitted, otherwise the same replica could be picked again
+                let next_routing = RoutingInfo {
+                    routing_key: None,
+                    consistency: Some(consistency),
+                };
+                transport_cell = sender
+                    .get_connection_for(&next_routing)
+                    .ok_or_else(|| error::Error::Pool("Unable to get transport".to_string()))?;
+            }
+            RetryDecision::DontRetry => return result,
+        }
+
+        retry_count += 1;
+    }
+}
+
+/// Sends a request frame over given transport and then over connections to next nodes
+/// every time speculative execution policy decides so until a first successful response
+/// is received. Responses to other executions are ignored. Returns the first successful
+/// response or the last error if all executions failed, together with a connection
+/// it was received with.
+///
+/// Executions which have lost cannot be interrupted and keep their connections until
+/// they are finished, so every execution is limited by `timeout`.
+fn send_speculative_frame<S, T, M, F>(
+    sender: &S,
+    transport_cell: r2d2::PooledConnection<M>,
+    consistency: Consistency,
+    policy: &dyn SpeculativeExecutionPolicy,
+    timeout: Duration,
+    build_frame: F,
+) -> error::Result<(r2d2::PooledConnection<M>, error::Result<Frame>)>
+where
+    S: GetConnection<T, M> + GetCompressor<'static> + Sized,
+    T: CDRSTransport + 'static,
+    M: r2d2::ManageConnection<Connection = RefCell<T>, Error = error::Error> + Sized,
+    F: Fn(ProtocolVersion) -> Frame,
+{
+    let compression = sender.get_compressor();
+    let (tx, rx) = mpsc::channel();
+    let execute = |transport_cell: r2d2::PooledConnection<M>| {
+        let tx = tx.clone();
+        let frame = build_frame(transport_cell.borrow().protocol_version());
+        thread::spawn(move || {
+            let result = send_frame_over(&transport_cell, frame, compression, Some(timeout));
+            // receiver is dropped once other execution has succeeded
+            let _ = tx.send((transport_cell, result));
+        });
+    };
+    // routing key is omitted, otherwise the same replica could be picked again
+    let next_routing = RoutingInfo {
+        routing_key: None,
+        consistency: Some(consistency),
+    };
+
+    execute(transport_cell);
+    let mut running_executions = 1;
+    let mut pending_executions = 1;
+    // a delay is counted from a start of the last execution, so failed responses
+    // which are received in the meantime do not postpone a next one
+    let mut next_execution_at = policy
+        .next_execution(running_executions)
+        .map(|delay| Instant::now() + delay);
 
-    transport_cell
+    loop {
+        let received = match next_execution_at {
+            Some(at) => rx.recv_timeout(at.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(mpsc::RecvTimeoutError::from),
+        };
+
+        match received {
+            Ok((transport_cell, result)) => {
+                pending_executions -= 1;
+                if result.is_ok() || pending_executions == 0 {
+                    return Ok((transport_cell, result));
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                next_execution_at = match sender.get_connection_for(&next_routing) {
+                    Some(transport_cell) => {
+                        execute(transport_cell);
+                        running_executions += 1;
+                        pending_executions += 1;
+                        policy
+                            .next_execution(running_executions)
+                            .map(|delay| Instant::now() + delay)
+                    }
+                    None => None,
+                };
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(error::Error::General(
+                    "Speculative executions finished without a response".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// Sends a request frame over given transport and waits for a response. The frame
+/// should be built for a protocol version negotiated for the transport.
+/// Returned errors carry an address of a node the transport is connected to.
+///
+/// `timeout` limits the whole exchange: writes to and reads from the transport get
+/// a timeout which is left until a deadline. If it expires `Error::Timeout` is returned
+/// and the transport is closed, as a late response would be read by a next request
+/// otherwise, so a pool discards the connection.
+pub fn send_frame_over<T: CDRSTransport + 'static>(
+    transport: &RefCell<T>,
+    frame: Frame,
+    compression: Compression,
+    timeout: Option<Duration>,
+) -> error::Result<Frame> {
+    let protocol_version = transport.borrow().protocol_version();
+    let addr = transport.borrow().addr();
+    let frame_bytes = frame.encode_framed(compression)?;
+
+    // transports are reused by requests, so a timeout of a previous one is replaced
+    transport
         .borrow_mut()
-        .write_all(frame_bytes.as_slice())
-        .map_err(error::Error::from)?;
+        .set_timeout(timeout)
+        .map_err(|err| error::Error::from(err).with_node(addr))?;
+
+    let result = {
+        let mut transport = transport.borrow_mut();
+        let deadline_transport = RefCell::new(DeadlineTransport {
+            transport: &mut *transport,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+        });
+        let written = deadline_transport
+            .borrow_mut()
+            .write_all(frame_bytes.as_slice());
+        match written {
+            Ok(_) => parse_framed(&deadline_transport, &compression, protocol_version),
+            Err(err) => Err(err.into()),
+        }
+    };
+
+    result.map_err(|err| {
+        match (err, timeout) {
+            (error::Error::Io(ref io_err), Some(timeout)) if is_expired(io_err) => {
+                if let Err(err) = transport.borrow_mut().close(net::Shutdown::Both) {
+                    warn!("Unable to close connection to {}: {}", addr, err);
+                }
+                error::Error::Timeout(timeout)
+            }
+            (err, _) => err,
+        }
+        .with_node(addr)
+    })
+}
 
-    from_connection(&transport_cell, compression)
+/// Transport which shrinks a timeout before every read and write, so all of them
+/// together do not take longer than until a deadline.
+struct DeadlineTransport<'a, T: CDRSTransport> {
+    transport: &'a mut T,
+    deadline: Option<Instant>,
+}
+
+impl<'a, T: CDRSTransport> DeadlineTransport<'a, T> {
+    fn set_remaining_timeout(&mut self) -> io::Result<()> {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return Ok(()),
+        };
+        // a zero timeout is not allowed and means the deadline has passed anyway
+        match deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if remaining > Duration::from_secs(0) => {
+                self.transport.set_timeout(Some(remaining))
+            }
+            _ => Err(io::ErrorKind::TimedOut.into()),
+        }
+    }
+}
+
+impl<'a, T: CDRSTransport> Read for DeadlineTransport<'a, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.set_remaining_timeout()?;
+        self.transport.read(buf)
+    }
+}
+
+impl<'a, T: CDRSTransport> Write for DeadlineTransport<'a, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.set_remaining_timeout()?;
+        self.transport.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.set_remaining_timeout()?;
+        self.transport.flush()
+    }
+}
+
+/// Sockets report expired read and write timeouts either as `TimedOut` or
+/// as `WouldBlock` depending on a platform.
+fn is_expired(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::TimedOut || err.kind() == io::ErrorKind::WouldBlock
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::frame::Opcode;
+    use crate::test::{versioned_response_frame, ScriptedTransport, SCRIPTED_TRANSPORT_ADDR};
+
+    fn transport(to_read: Vec<u8>) -> RefCell<ScriptedTransport> {
+        let mut transport = ScriptedTransport::new(to_read);
+        transport.set_protocol_version(ProtocolVersion::V4);
+        RefCell::new(transport)
+    }
+
+    fn options() -> Frame {
+        Frame::new_req_options(ProtocolVersion::V4)
+    }
 
     #[test]
     fn prepare_flags_test() {
@@ -52,4 +372,58 @@ mod test {
             vec![Flag::Tracing, Flag::Warning]
         );
     }
+
+    #[test]
+    fn send_frame_over_sets_timeout() {
+        let ready = versioned_response_frame(Opcode::Ready, vec![], ProtocolVersion::V4);
+        let transport = transport(ready);
+        let timeout = Some(Duration::from_secs(1));
+
+        let response = send_frame_over(&transport, options(), Compression::None, timeout);
+        assert_eq!(response.unwrap().opcode, Opcode::Ready);
+        assert!(transport.borrow().timeout.is_some());
+        assert!(transport.borrow().timeout <= timeout);
+        assert!(transport.borrow().is_alive());
+    }
+
+    #[test]
+    fn send_frame_over_limits_all_reads_by_timeout() {
+        let ready = versioned_response_frame(Opcode::Ready, vec![], ProtocolVersion::V4);
+        let transport = transport(ready);
+        // every read alone fits into the timeout, while all of them do not
+        transport.borrow_mut().read_delay = Duration::from_millis(20);
+        let timeout = Duration::from_millis(50);
+
+        let err =
+            send_frame_over(&transport, options(), Compression::None, Some(timeout)).unwrap_err();
+        assert!(matches!(err.inner(), error::Error::Timeout(t) if *t == timeout));
+        assert!(!transport.borrow().is_alive());
+    }
+
+    #[test]
+    fn send_frame_over_times_out() {
+        let transport = transport(vec![]);
+        let timeout = Duration::from_secs(1);
+
+        let err =
+            send_frame_over(&transport, options(), Compression::None, Some(timeout)).unwrap_err();
+        assert!(matches!(err.inner(), error::Error::Timeout(t) if *t == timeout));
+        assert!(err.is_timeout());
+        assert_eq!(err.node_addr(), SCRIPTED_TRANSPORT_ADDR.parse().ok());
+        // a late response would desynchronize the connection
+        assert!(!transport.borrow().is_alive());
+    }
+
+    #[test]
+    fn send_frame_over_resets_timeout() {
+        let ready = versioned_response_frame(Opcode::Ready, vec![], ProtocolVersion::V4);
+        let transport = transport(ready);
+        transport
+            .borrow_mut()
+            .set_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+
+        assert!(send_frame_over(&transport, options(), Compression::None, None).is_ok());
+        assert_eq!(transport.borrow().timeout, None);
+    }
 }
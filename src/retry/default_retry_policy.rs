@@ -0,0 +1,121 @@
+use super::{RetryDecision, RetryInfo, RetryPolicy};
+use crate::frame::frame_error::{AdditionalErrorInfo, WriteType};
+
+/// Retry policy which retries a request at most once and only when it is safe
+/// and is likely to succeed:
+/// * on read timeout - if enough replicas responded but data was not retrieved;
+/// * on write timeout - if it happened while writing a batch log;
+/// * on unavailable error, overloaded or bootstrapping coordinator - with a next node
+///   if the request is idempotent.
+///
+/// Consistency level of a request is never changed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRetryPolicy;
+
+impl DefaultRetryPolicy {
+    pub fn new() -> Self {
+        DefaultRetryPolicy
+    }
+}
+
+impl RetryPolicy for DefaultRetryPolicy {
+    fn decide(&self, info: &RetryInfo) -> RetryDecision {
+        if info.retry_count > 0 {
+            return RetryDecision::DontRetry;
+        }
+
+        match info.error.additional_info {
+            AdditionalErrorInfo::ReadTimeout(ref error)
+                if error.received >= error.blockfor && !error.replica_has_responded() =>
+            {
+                RetryDecision::RetrySameNode(None)
+            }
+            AdditionalErrorInfo::WriteTimeout(ref error)
+                if matches!(error.write_type, WriteType::BatchLog) =>
+            {
+                RetryDecision::RetrySameNode(None)
+            }
+            AdditionalErrorInfo::Unavailable(_)
+            | AdditionalErrorInfo::Overloaded(_)
+            | AdditionalErrorInfo::IsBootstrapping(_)
+                if info.is_idempotent =>
+            {
+                RetryDecision::RetryNextNode(None)
+            }
+            _ => RetryDecision::DontRetry,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consistency::Consistency;
+    use crate::retry::test_utils::*;
+
+    fn decide(error: &crate::frame::frame_error::CDRSError, retry_count: usize) -> RetryDecision {
+        decide_idempotent(error, retry_count, true)
+    }
+
+    fn decide_idempotent(
+        error: &crate::frame::frame_error::CDRSError,
+        retry_count: usize,
+        is_idempotent: bool,
+    ) -> RetryDecision {
+        DefaultRetryPolicy::new().decide(&RetryInfo {
+            error,
+            consistency: Consistency::Quorum,
+            retry_count,
+            is_idempotent,
+        })
+    }
+
+    #[test]
+    fn retries_read_timeout_without_data() {
+        assert_eq!(
+            decide(&read_timeout(3, false), 0),
+            RetryDecision::RetrySameNode(None)
+        );
+        assert_eq!(decide(&read_timeout(3, true), 0), RetryDecision::DontRetry);
+        assert_eq!(decide(&read_timeout(2, false), 0), RetryDecision::DontRetry);
+        assert_eq!(decide(&read_timeout(3, false), 1), RetryDecision::DontRetry);
+    }
+
+    #[test]
+    fn retries_batch_log_write_timeout() {
+        assert_eq!(
+            decide(&write_timeout(1, WriteType::BatchLog), 0),
+            RetryDecision::RetrySameNode(None)
+        );
+        assert_eq!(
+            decide(&write_timeout(1, WriteType::Simple), 0),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn retries_unavailable_on_next_node() {
+        assert_eq!(
+            decide(&unavailable(1), 0),
+            RetryDecision::RetryNextNode(None)
+        );
+        assert_eq!(decide(&overloaded(), 0), RetryDecision::RetryNextNode(None));
+        assert_eq!(decide(&unavailable(1), 1), RetryDecision::DontRetry);
+    }
+
+    #[test]
+    fn does_not_retry_non_idempotent_requests_on_next_node() {
+        assert_eq!(
+            decide_idempotent(&unavailable(1), 0, false),
+            RetryDecision::DontRetry
+        );
+        assert_eq!(
+            decide_idempotent(&overloaded(), 0, false),
+            RetryDecision::DontRetry
+        );
+        assert_eq!(
+            decide_idempotent(&read_timeout(3, false), 0, false),
+            RetryDecision::RetrySameNode(None)
+        );
+    }
+}
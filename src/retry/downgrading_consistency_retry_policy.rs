@@ -0,0 +1,125 @@
+use super::{DefaultRetryPolicy, RetryDecision, RetryInfo, RetryPolicy};
+use crate::consistency::Consistency;
+use crate::frame::frame_error::{AdditionalErrorInfo, WriteType};
+use crate::types::CInt;
+
+/// Retry policy which retries a request at most once with a lower consistency level
+/// if not enough replicas are alive or responded in time. The new consistency level
+/// is the highest one which is likely to be achieved with replicas that responded.
+///
+/// Writes are not retried with lower consistency levels except unlogged batches.
+/// Other errors are handled the same way as `DefaultRetryPolicy` does.
+///
+/// It is only safe to use this policy if reads and writes with weaker consistency
+/// than requested are acceptable for an application.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DowngradingConsistencyRetryPolicy;
+
+impl DowngradingConsistencyRetryPolicy {
+    pub fn new() -> Self {
+        DowngradingConsistencyRetryPolicy
+    }
+}
+
+impl RetryPolicy for DowngradingConsistencyRetryPolicy {
+    fn decide(&self, info: &RetryInfo) -> RetryDecision {
+        if info.retry_count > 0 {
+            return RetryDecision::DontRetry;
+        }
+
+        match info.error.additional_info {
+            AdditionalErrorInfo::ReadTimeout(ref error) if error.received < error.blockfor => {
+                downgrade(info.consistency, error.received)
+            }
+            AdditionalErrorInfo::WriteTimeout(ref error)
+                if matches!(error.write_type, WriteType::UnloggedBatch) =>
+            {
+                downgrade(info.consistency, error.received)
+            }
+            AdditionalErrorInfo::Unavailable(ref error) => downgrade(info.consistency, error.alive),
+            _ => DefaultRetryPolicy.decide(info),
+        }
+    }
+}
+
+/// Picks the highest consistency level which could be achieved with given number
+/// of replicas.
+fn downgrade(consistency: Consistency, replicas: CInt) -> RetryDecision {
+    // serial consistency levels cannot be downgraded
+    if matches!(consistency, Consistency::Serial | Consistency::LocalSerial) {
+        return RetryDecision::DontRetry;
+    }
+
+    let downgraded = match replicas {
+        replicas if replicas >= 3 => Consistency::Three,
+        2 => Consistency::Two,
+        1 if consistency == Consistency::EachQuorum => Consistency::LocalOne,
+        1 => Consistency::One,
+        _ => return RetryDecision::DontRetry,
+    };
+
+    RetryDecision::RetrySameNode(Some(downgraded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_error::CDRSError;
+    use crate::retry::test_utils::*;
+
+    fn decide(error: &CDRSError, consistency: Consistency) -> RetryDecision {
+        DowngradingConsistencyRetryPolicy::new().decide(&RetryInfo {
+            error,
+            consistency,
+            retry_count: 0,
+            is_idempotent: true,
+        })
+    }
+
+    #[test]
+    fn downgrades_to_responded_replicas() {
+        assert_eq!(
+            decide(&read_timeout(2, false), Consistency::Quorum),
+            RetryDecision::RetrySameNode(Some(Consistency::Two))
+        );
+        assert_eq!(
+            decide(&unavailable(1), Consistency::All),
+            RetryDecision::RetrySameNode(Some(Consistency::One))
+        );
+        assert_eq!(
+            decide(
+                &write_timeout(1, WriteType::UnloggedBatch),
+                Consistency::Quorum
+            ),
+            RetryDecision::RetrySameNode(Some(Consistency::One))
+        );
+        assert_eq!(
+            decide(&unavailable(0), Consistency::Quorum),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn does_not_downgrade_writes_and_serial() {
+        assert_eq!(
+            decide(&write_timeout(1, WriteType::Simple), Consistency::Quorum),
+            RetryDecision::DontRetry
+        );
+        assert_eq!(
+            decide(&unavailable(1), Consistency::Serial),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn falls_back_to_default_policy() {
+        assert_eq!(
+            decide(&read_timeout(3, false), Consistency::Quorum),
+            RetryDecision::RetrySameNode(None)
+        );
+        assert_eq!(
+            decide(&overloaded(), Consistency::Quorum),
+            RetryDecision::RetryNextNode(None)
+        );
+    }
+}
@@ -0,0 +1,17 @@
+use super::{RetryDecision, RetryInfo, RetryPolicy};
+
+/// Retry policy which never retries requests, so every error is returned to a caller.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FallthroughRetryPolicy;
+
+impl FallthroughRetryPolicy {
+    pub fn new() -> Self {
+        FallthroughRetryPolicy
+    }
+}
+
+impl RetryPolicy for FallthroughRetryPolicy {
+    fn decide(&self, _info: &RetryInfo) -> RetryDecision {
+        RetryDecision::DontRetry
+    }
+}
@@ -0,0 +1,106 @@
+//! Retry policies decide what to do with requests that failed with server errors,
+//! e.g. timeouts or unavailable replicas. A policy is used by a session for
+//! every query, execution of prepared statement and batch it sends.
+mod default_retry_policy;
+mod downgrading_consistency_retry_policy;
+mod fallthrough_retry_policy;
+
+pub use crate::retry::default_retry_policy::DefaultRetryPolicy;
+pub use crate::retry::downgrading_consistency_retry_policy::DowngradingConsistencyRetryPolicy;
+pub use crate::retry::fallthrough_retry_policy::FallthroughRetryPolicy;
+
+use std::fmt;
+
+use crate::consistency::Consistency;
+use crate::frame::frame_error::CDRSError;
+
+/// Information about a failed request which retry policies decide on.
+#[derive(Debug)]
+pub struct RetryInfo<'a> {
+    /// Error returned by a server.
+    pub error: &'a CDRSError,
+    /// Consistency level the request was sent with.
+    pub consistency: Consistency,
+    /// Number of retries of the request that have been already made.
+    pub retry_count: usize,
+    /// Whether the request can be applied multiple times without changing the result.
+    pub is_idempotent: bool,
+}
+
+/// Decision made by a retry policy about a failed request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryDecision {
+    /// Send the request to the same node again. If consistency level is provided
+    /// the request is sent with it instead of the original one.
+    RetrySameNode(Option<Consistency>),
+    /// Send the request to a next node picked by a load balancer. If consistency
+    /// level is provided the request is sent with it instead of the original one.
+    RetryNextNode(Option<Consistency>),
+    /// Return the error to a caller.
+    DontRetry,
+}
+
+pub trait RetryPolicy: Send + Sync {
+    /// Decides whether a request which failed with a server error should be retried.
+    fn decide(&self, info: &RetryInfo) -> RetryDecision;
+}
+
+impl fmt::Debug for dyn RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RetryPolicy")
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use std::io::Cursor;
+
+    use crate::frame::frame_error::{
+        AdditionalErrorInfo, CDRSError, SimpleError, UnavailableError, WriteTimeoutError, WriteType,
+    };
+    use crate::frame::traits::FromCursor;
+    use crate::types::CString;
+
+    fn server_error(error_code: i32, additional_info: AdditionalErrorInfo) -> CDRSError {
+        CDRSError {
+            error_code,
+            message: CString::new("".into()),
+            additional_info,
+        }
+    }
+
+    pub fn unavailable(alive: i32) -> CDRSError {
+        server_error(
+            0x1000,
+            AdditionalErrorInfo::Unavailable(UnavailableError {
+                cl: super::Consistency::Quorum,
+                required: 3,
+                alive,
+            }),
+        )
+    }
+
+    pub fn overloaded() -> CDRSError {
+        server_error(0x1001, AdditionalErrorInfo::Overloaded(SimpleError {}))
+    }
+
+    pub fn write_timeout(received: i32, write_type: WriteType) -> CDRSError {
+        server_error(
+            0x1100,
+            AdditionalErrorInfo::WriteTimeout(WriteTimeoutError {
+                cl: super::Consistency::Quorum,
+                received,
+                blockfor: 3,
+                write_type,
+            }),
+        )
+    }
+
+    pub fn read_timeout(received: i32, data_present: bool) -> CDRSError {
+        let mut bytes = vec![0, 0, 0x12, 0, 0, 0, 0, 4];
+        bytes.extend_from_slice(&[0, 0, 0, received as u8, 0, 0, 0, 3]);
+        bytes.push(data_present as u8);
+
+        CDRSError::from_cursor(&mut Cursor::new(bytes.as_slice())).unwrap()
+    }
+}
@@ -0,0 +1,267 @@
+//! Tools for routing requests directly to nodes that own the data, i.e. partitioner
+//! specific token computation and a token ring. They do not require a connection
+//! to a cluster, so they could be used for offline data placement analysis as well.
+//!
+//! ```
+//! use cdrs::routing::{serialize_values, Partitioner, Token, TokenRing};
+//! use cdrs::types::value::Value;
+//!
+//! let key = serialize_values(&[Value::from(1i32)]).unwrap();
+//! let token = Partitioner::Murmur3.token(&key);
+//! assert_eq!(token, Token::Murmur3(-4069959284402364209));
+//!
+//! let mut ring = TokenRing::new();
+//! ring.insert(Token::Murmur3(-5000000000000000000), "127.0.0.1");
+//! ring.insert(Token::Murmur3(0), "127.0.0.2");
+//! assert_eq!(ring.get(&token), Some(&"127.0.0.2"));
+//! ```
+mod md5;
+mod murmur3;
+mod partitioner;
+mod token_ring;
+
+pub use crate::routing::murmur3::murmur3_token;
+pub use crate::routing::partitioner::{random_token, Partitioner, Token};
+pub use crate::routing::token_ring::{TokenRange, TokenRing};
+
+use crate::types::value::{Value, ValueType};
+
+/// Serializes a partition key in the same way Cassandra does before computing its token.
+/// A single component key is used as is, while every component of a composite key
+/// is written as `<short length><component bytes><0x00>`.
+pub fn serialize_partition_key(components: &[&[u8]]) -> Vec<u8> {
+    if let [component] = components {
+        return component.to_vec();
+    }
+
+    let mut key = Vec::with_capacity(components.iter().map(|c| c.len() + 3).sum());
+    for component in components {
+        key.extend_from_slice(&(component.len() as u16).to_be_bytes());
+        key.extend_from_slice(component);
+        key.push(0);
+    }
+    key
+}
+
+/// Serializes a partition key made of values of its columns in the order they are
+/// declared in a table's partition key. `None` is returned if some of values are
+/// either null or not set as such keys cannot exist.
+pub fn serialize_values<'a, I>(values: I) -> Option<Vec<u8>>
+where
+    I: IntoIterator<Item = &'a Value>,
+{
+    let components = values
+        .into_iter()
+        .map(|value| match value.value_type {
+            ValueType::Normal(_) => Some(value.body.as_slice()),
+            _ => None,
+        })
+        .collect::<Option<Vec<&[u8]>>>()?;
+
+    if components.is_empty() {
+        return None;
+    }
+
+    Some(serialize_partition_key(&components))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::ColType;
+    use crate::types::blob::Blob;
+    use crate::types::decimal::Decimal;
+    use crate::types::value::Bytes;
+    use std::collections::HashMap;
+    use std::net::IpAddr;
+    use time::{date, time};
+    use uuid::Uuid;
+
+    #[test]
+    fn serialize_single_component() {
+        assert_eq!(serialize_partition_key(&[&[1, 2, 3]]), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn serialize_composite_key() {
+        assert_eq!(
+            serialize_partition_key(&[&[1, 2], &[3]]),
+            vec![0, 2, 1, 2, 0, 0, 1, 3, 0]
+        );
+    }
+
+    #[test]
+    fn serialize_unbound_values() {
+        assert_eq!(serialize_values(&[]), None);
+        assert_eq!(serialize_values(&[Value::new_null()]), None);
+        assert_eq!(
+            serialize_values(&[Value::from(1i32), Value::new_not_set()]),
+            None
+        );
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Keys of every column type which could be a part of a partition key along with
+    /// their serialized form and Murmur3 token.
+    fn keys() -> Vec<(ColType, Value, &'static str, i64)> {
+        let mut map = HashMap::new();
+        map.insert(1i32, "a");
+
+        vec![
+            (
+                ColType::Custom,
+                Bytes::new(vec![0xca, 0xfe]).into(),
+                "cafe",
+                -7875094454306029168,
+            ),
+            (
+                ColType::Ascii,
+                "cdrs".into(),
+                "63647273",
+                -2594954185012613640,
+            ),
+            (
+                ColType::Bigint,
+                1i64.into(),
+                "0000000000000001",
+                6292367497774912474,
+            ),
+            (
+                ColType::Blob,
+                Blob::new(vec![0xde, 0xad]).into(),
+                "dead",
+                1756899954679350844,
+            ),
+            (ColType::Boolean, true.into(), "01", 8849112093580131862),
+            (
+                ColType::Decimal,
+                Decimal::new(129, 1).into(),
+                "000000010081",
+                -2086345422587511072,
+            ),
+            (
+                ColType::Double,
+                1.5f64.into(),
+                "3ff8000000000000",
+                -2904970586342177944,
+            ),
+            (
+                ColType::Float,
+                1.5f32.into(),
+                "3fc00000",
+                731613433076368549,
+            ),
+            (ColType::Int, 1i32.into(), "00000001", -4069959284402364209),
+            (
+                ColType::Timestamp,
+                date!(2020 - 01 - 01).with_time(time!(0:00)).into(),
+                "0000016f5e66e800",
+                -4896792074347871559,
+            ),
+            (
+                ColType::Uuid,
+                Uuid::parse_str("d0a1f9c4-9f5a-4c33-8f5e-6f7d1a2b3c4d")
+                    .unwrap()
+                    .into(),
+                "d0a1f9c49f5a4c338f5e6f7d1a2b3c4d",
+                7776271864644293574,
+            ),
+            (
+                ColType::Varchar,
+                "123".into(),
+                "313233",
+                -7468325962851647638,
+            ),
+            (
+                ColType::Varint,
+                Bytes::new(vec![0x00, 0x81]).into(),
+                "0081",
+                9178657284466499138,
+            ),
+            (
+                ColType::Timeuuid,
+                Uuid::parse_str("8e14e760-7fa8-11eb-bc66-000000000001")
+                    .unwrap()
+                    .into(),
+                "8e14e7607fa811ebbc66000000000001",
+                -9205606702123799857,
+            ),
+            (
+                ColType::Inet,
+                "127.0.0.1".parse::<IpAddr>().unwrap().into(),
+                "7f000001",
+                3370802529007389742,
+            ),
+            (
+                ColType::Inet,
+                "::1".parse::<IpAddr>().unwrap().into(),
+                "00000000000000000000000000000001",
+                2589554819249504804,
+            ),
+            // days since epoch are shifted by 2^31
+            (
+                ColType::Date,
+                (1u32 << 31).into(),
+                "80000000",
+                -420533958509279465,
+            ),
+            // nanoseconds since midnight
+            (
+                ColType::Time,
+                3_600_000_000_000i64.into(),
+                "0000034630b8a000",
+                -3751414977639016099,
+            ),
+            (ColType::Smallint, 1i16.into(), "0001", 8985795910368437836),
+            (ColType::Tinyint, 1i8.into(), "01", 8849112093580131862),
+            (
+                ColType::List,
+                vec![1i32, 2].into(),
+                "0000000200000004000000010000000400000002",
+                -7321538233726735308,
+            ),
+            (
+                ColType::Map,
+                map.into(),
+                "0000000100000004000000010000000161",
+                -4372137471723067591,
+            ),
+            (
+                ColType::Set,
+                vec!["a"].into(),
+                "000000010000000161",
+                -2853098659702332167,
+            ),
+            // UDT and tuple values are serialized by users as a sequence of [bytes]
+            (
+                ColType::Udt,
+                Bytes::new(vec![0, 0, 0, 1, 7]).into(),
+                "0000000107",
+                -1519078934873971281,
+            ),
+            (
+                ColType::Tuple,
+                Bytes::new(vec![0, 0, 0, 1, 7, 0xff, 0xff, 0xff, 0xff]).into(),
+                "0000000107ffffffff",
+                -8346392021478143622,
+            ),
+        ]
+    }
+
+    #[test]
+    fn murmur3_tokens_of_every_col_type() {
+        for (col_type, value, serialized, token) in keys() {
+            let key = serialize_values(&[value]).unwrap();
+            assert_eq!(hex(&key), serialized, "{:?}", col_type);
+            assert_eq!(
+                Partitioner::Murmur3.token(&key),
+                Token::Murmur3(token),
+                "{:?}",
+                col_type
+            );
+        }
+    }
+}
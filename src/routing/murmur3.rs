@@ -0,0 +1,118 @@
+const C1: u64 = 0x87c3_7b91_1142_53d5;
+const C2: u64 = 0x4cf5_ad43_2745_937f;
+
+/// Computes a token of a serialized partition key as `Murmur3Partitioner` does.
+///
+/// Cassandra uses its own variant of x64 128-bit MurmurHash3 where bytes of a tail
+/// are sign extended, so the result differs from the reference implementation
+/// for keys which have bytes bigger than `0x7f` in their tails.
+pub fn murmur3_token(key: &[u8]) -> i64 {
+    let token = hash3_x64_128(key) as i64;
+    if token == i64::MIN {
+        i64::MAX
+    } else {
+        token
+    }
+}
+
+fn hash3_x64_128(key: &[u8]) -> u64 {
+    let mut h1: u64 = 0;
+    let mut h2: u64 = 0;
+
+    let mut blocks = key.chunks_exact(16);
+    for block in &mut blocks {
+        let (k1, k2) = block.split_at(8);
+        let k1 = u64::from_le_bytes([k1[0], k1[1], k1[2], k1[3], k1[4], k1[5], k1[6], k1[7]]);
+        let k2 = u64::from_le_bytes([k2[0], k2[1], k2[2], k2[3], k2[4], k2[5], k2[6], k2[7]]);
+
+        h1 ^= mix_k1(k1);
+        h1 = h1
+            .rotate_left(27)
+            .wrapping_add(h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dc_e729);
+
+        h2 ^= mix_k2(k2);
+        h2 = h2
+            .rotate_left(31)
+            .wrapping_add(h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x3849_5ab5);
+    }
+
+    let tail = blocks.remainder();
+    let mut k1: u64 = 0;
+    let mut k2: u64 = 0;
+    for (i, byte) in tail.iter().enumerate() {
+        // sign extension of tail bytes is a Cassandra specific behaviour
+        let byte = *byte as i8 as i64 as u64;
+        if i < 8 {
+            k1 ^= byte << (i * 8);
+        } else {
+            k2 ^= byte << ((i - 8) * 8);
+        }
+    }
+    if tail.len() > 8 {
+        h2 ^= mix_k2(k2);
+    }
+    if !tail.is_empty() {
+        h1 ^= mix_k1(k1);
+    }
+
+    let len = key.len() as u64;
+    h1 ^= len;
+    h2 ^= len;
+
+    h1 = h1.wrapping_add(h2);
+    h2 = h2.wrapping_add(h1);
+
+    h1 = fmix(h1);
+    h2 = fmix(h2);
+
+    h1.wrapping_add(h2)
+}
+
+fn mix_k1(k1: u64) -> u64 {
+    k1.wrapping_mul(C1).rotate_left(31).wrapping_mul(C2)
+}
+
+fn mix_k2(k2: u64) -> u64 {
+    k2.wrapping_mul(C2).rotate_left(33).wrapping_mul(C1)
+}
+
+fn fmix(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    k ^= k >> 33;
+    k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn murmur3_token_of_text() {
+        assert_eq!(murmur3_token(b"123"), -7468325962851647638);
+        assert_eq!(murmur3_token(b"9223372036854775807"), 7162290910810015547);
+    }
+
+    #[test]
+    fn murmur3_token_of_int() {
+        assert_eq!(murmur3_token(&1i32.to_be_bytes()), -4069959284402364209);
+        assert_eq!(murmur3_token(&2i32.to_be_bytes()), -3248873570005575792);
+        assert_eq!(murmur3_token(&3i32.to_be_bytes()), 9010454139840013625);
+    }
+
+    #[test]
+    fn murmur3_token_sign_extends_tail() {
+        assert_eq!(murmur3_token(&[0xfe; 8]), -8927430733708461935);
+        assert_eq!(murmur3_token(&[0x10; 8]), 1446172840243228796);
+        assert_eq!(
+            murmur3_token(&b"\x00\xff\x10\xfa\x99".repeat(10)),
+            5837342703291459765
+        );
+    }
+}
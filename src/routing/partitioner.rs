@@ -0,0 +1,117 @@
+use std::fmt;
+
+use crate::routing::md5::md5;
+use crate::routing::murmur3::murmur3_token;
+
+/// Token of a partition key. Tokens of different partitioners are never mixed
+/// in one ring, so they are compared only with tokens of the same kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Token {
+    Murmur3(i64),
+    Random(u128),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Murmur3(token) => write!(f, "{}", token),
+            Token::Random(token) => write!(f, "{}", token),
+        }
+    }
+}
+
+/// Partitioners which distribute data across a cluster basing on tokens of partition keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partitioner {
+    /// `org.apache.cassandra.dht.Murmur3Partitioner`, a default one since Cassandra 1.2.
+    Murmur3,
+    /// `org.apache.cassandra.dht.RandomPartitioner`.
+    Random,
+}
+
+impl Partitioner {
+    /// Returns a partitioner by either full or simple name of its class,
+    /// e.g. as it is stored in `system.local`.
+    pub fn from_class_name(name: &str) -> Option<Partitioner> {
+        match name.rsplit('.').next() {
+            Some("Murmur3Partitioner") => Some(Partitioner::Murmur3),
+            Some("RandomPartitioner") => Some(Partitioner::Random),
+            _ => None,
+        }
+    }
+
+    /// Returns full class name of a partitioner.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Partitioner::Murmur3 => "org.apache.cassandra.dht.Murmur3Partitioner",
+            Partitioner::Random => "org.apache.cassandra.dht.RandomPartitioner",
+        }
+    }
+
+    /// Computes a token of serialized partition key.
+    pub fn token(&self, key: &[u8]) -> Token {
+        match self {
+            Partitioner::Murmur3 => Token::Murmur3(murmur3_token(key)),
+            Partitioner::Random => Token::Random(random_token(key)),
+        }
+    }
+
+    /// Parses a token as it is stored in system tables.
+    pub fn parse_token(&self, token: &str) -> Option<Token> {
+        match self {
+            Partitioner::Murmur3 => token.parse().ok().map(Token::Murmur3),
+            Partitioner::Random => token.parse().ok().map(Token::Random),
+        }
+    }
+}
+
+/// Computes a token of a serialized partition key as `RandomPartitioner` does,
+/// i.e. as an absolute value of MD5 digest treated as a signed 128-bit integer.
+pub fn random_token(key: &[u8]) -> u128 {
+    i128::from_be_bytes(md5(key)).unsigned_abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partitioner_from_class_name() {
+        assert_eq!(
+            Partitioner::from_class_name("org.apache.cassandra.dht.Murmur3Partitioner"),
+            Some(Partitioner::Murmur3)
+        );
+        assert_eq!(
+            Partitioner::from_class_name("RandomPartitioner"),
+            Some(Partitioner::Random)
+        );
+        assert_eq!(
+            Partitioner::from_class_name("org.apache.cassandra.dht.ByteOrderedPartitioner"),
+            None
+        );
+    }
+
+    #[test]
+    fn random_token_is_absolute_digest() {
+        // md5 of an empty key is d41d8cd98f00b204e9800998ecf8427e which is negative
+        assert_eq!(random_token(b""), 0x2be2_7326_70ff_4dfb_167f_f667_1307_bd82);
+        // md5 of "a" is 0cc175b9c0f1b6a831c399e269772661 which is positive
+        assert_eq!(
+            random_token(b"a"),
+            0x0cc1_75b9_c0f1_b6a8_31c3_99e2_6977_2661
+        );
+    }
+
+    #[test]
+    fn parse_token() {
+        assert_eq!(
+            Partitioner::Murmur3.parse_token("-4069959284402364209"),
+            Some(Token::Murmur3(-4069959284402364209))
+        );
+        assert_eq!(
+            Partitioner::Random.parse_token("170141183460469231731687303715884105727"),
+            Some(Token::Random(i128::MAX as u128))
+        );
+        assert_eq!(Partitioner::Random.parse_token("-1"), None);
+    }
+}
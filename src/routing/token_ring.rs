@@ -0,0 +1,164 @@
+/// Range of tokens from `start` exclusively to `end` inclusively. The range wraps around
+/// if `start` is not less than `end`, e.g. a ring with a single token owns the whole ring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenRange<T> {
+    pub start: T,
+    pub end: T,
+}
+
+/// Ring of tokens where every token is owned by some node. A token of a partition key
+/// belongs to the node which owns the first ring token that is bigger than or equal to it.
+/// Tokens bigger than the last one in the ring wrap around to the first node.
+#[derive(Debug, Clone)]
+pub struct TokenRing<T, N> {
+    ring: Vec<(T, N)>,
+}
+
+impl<T: Ord, N> TokenRing<T, N> {
+    pub fn new() -> Self {
+        TokenRing { ring: vec![] }
+    }
+
+    /// Adds a token owned by a node. If the token is already in the ring its owner
+    /// gets replaced.
+    pub fn insert(&mut self, token: T, node: N) {
+        match self.ring.binary_search_by(|(t, _)| t.cmp(&token)) {
+            Ok(i) => self.ring[i].1 = node,
+            Err(i) => self.ring.insert(i, (token, node)),
+        }
+    }
+
+    /// Returns a node which owns given token.
+    pub fn get(&self, token: &T) -> Option<&N> {
+        let i = match self.ring.binary_search_by(|(t, _)| t.cmp(token)) {
+            Ok(i) => i,
+            Err(i) if i == self.ring.len() => 0,
+            Err(i) => i,
+        };
+        self.ring.get(i).map(|(_, node)| node)
+    }
+
+    /// Returns a range of tokens which given token falls into. Every range ends
+    /// with a token of the ring, so all tokens of a range are owned by the same node.
+    pub fn get_range(&self, token: &T) -> Option<TokenRange<T>>
+    where
+        T: Clone,
+    {
+        if self.ring.is_empty() {
+            return None;
+        }
+
+        let end = match self.ring.binary_search_by(|(t, _)| t.cmp(token)) {
+            Ok(i) => i,
+            Err(i) if i == self.ring.len() => 0,
+            Err(i) => i,
+        };
+        let start = if end == 0 { self.ring.len() } else { end } - 1;
+
+        Some(TokenRange {
+            start: self.ring.get(start)?.0.clone(),
+            end: self.ring.get(end)?.0.clone(),
+        })
+    }
+
+    /// Returns owners of ring tokens starting with the owner of given token and going
+    /// clockwise around the ring once. A node is returned once per every token it owns.
+    pub fn iter_from(&self, token: &T) -> impl Iterator<Item = &N> {
+        let start = match self.ring.binary_search_by(|(t, _)| t.cmp(token)) {
+            Ok(i) => i,
+            Err(i) if i == self.ring.len() => 0,
+            Err(i) => i,
+        };
+        self.ring[start..]
+            .iter()
+            .chain(self.ring[..start].iter())
+            .map(|(_, node)| node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ring.is_empty()
+    }
+}
+
+impl<T: Ord, N> Default for TokenRing<T, N> {
+    fn default() -> Self {
+        TokenRing::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_owner_of_token() {
+        let mut ring = TokenRing::new();
+        ring.insert(100, "b");
+        ring.insert(-100, "a");
+        ring.insert(200, "c");
+
+        assert_eq!(ring.get(&-1000), Some(&"a"));
+        assert_eq!(ring.get(&-100), Some(&"a"));
+        assert_eq!(ring.get(&0), Some(&"b"));
+        assert_eq!(ring.get(&150), Some(&"c"));
+        assert_eq!(ring.get(&201), Some(&"a"));
+    }
+
+    #[test]
+    fn get_from_empty_ring() {
+        let ring: TokenRing<i64, &str> = TokenRing::new();
+        assert_eq!(ring.get(&0), None);
+        assert_eq!(ring.get_range(&0), None);
+        assert_eq!(ring.iter_from(&0).next(), None);
+    }
+
+    #[test]
+    fn iterate_from_owner_of_token() {
+        let mut ring = TokenRing::new();
+        ring.insert(-100, "a");
+        ring.insert(100, "b");
+        ring.insert(200, "c");
+
+        assert_eq!(
+            ring.iter_from(&0).collect::<Vec<_>>(),
+            vec![&"b", &"c", &"a"]
+        );
+        assert_eq!(
+            ring.iter_from(&201).collect::<Vec<_>>(),
+            vec![&"a", &"b", &"c"]
+        );
+    }
+
+    #[test]
+    fn get_range_of_token() {
+        let mut ring = TokenRing::new();
+        ring.insert(-100, "a");
+        ring.insert(100, "b");
+
+        assert_eq!(
+            ring.get_range(&0),
+            Some(TokenRange {
+                start: -100,
+                end: 100
+            })
+        );
+        assert_eq!(
+            ring.get_range(&-100),
+            Some(TokenRange {
+                start: 100,
+                end: -100
+            })
+        );
+        assert_eq!(
+            ring.get_range(&101),
+            Some(TokenRange {
+                start: 100,
+                end: -100
+            })
+        );
+    }
+}
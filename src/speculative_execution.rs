@@ -0,0 +1,71 @@
+//! Speculative execution policies decide when a request which has not been answered
+//! yet should be sent to one more node. The first successful response is returned
+//! to a caller while responses of other executions are ignored.
+//!
+//! Only requests marked as idempotent are executed speculatively, see
+//! `QueryParamsBuilder::idempotent` and `PreparedQuery::idempotent`.
+use std::fmt;
+use std::time::Duration;
+
+/// Timeout which blocking sessions give to speculatively executed requests if neither
+/// requests nor a session have their own one, as executions which have lost cannot
+/// be interrupted and keep their connections until they are finished.
+pub const DEFAULT_SPECULATIVE_EXECUTION_TIMEOUT: Duration = Duration::from_secs(12);
+
+pub trait SpeculativeExecutionPolicy: Send + Sync {
+    /// Returns a delay after which a next execution of a request should be started
+    /// or `None` if there should be no more executions. `running_executions` is a number
+    /// of already started executions including the initial one.
+    fn next_execution(&self, running_executions: usize) -> Option<Duration>;
+}
+
+impl fmt::Debug for dyn SpeculativeExecutionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SpeculativeExecutionPolicy")
+    }
+}
+
+/// Speculative execution policy which starts up to given number of speculative
+/// executions with a constant delay between them.
+#[derive(Debug, Clone, Copy)]
+pub struct ConstantSpeculativeExecutionPolicy {
+    max_speculative_executions: usize,
+    delay: Duration,
+}
+
+impl ConstantSpeculativeExecutionPolicy {
+    pub fn new(max_speculative_executions: usize, delay: Duration) -> Self {
+        ConstantSpeculativeExecutionPolicy {
+            max_speculative_executions,
+            delay,
+        }
+    }
+}
+
+impl SpeculativeExecutionPolicy for ConstantSpeculativeExecutionPolicy {
+    fn next_execution(&self, running_executions: usize) -> Option<Duration> {
+        if running_executions <= self.max_speculative_executions {
+            Some(self.delay)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_policy() {
+        let delay = Duration::from_millis(100);
+        let policy = ConstantSpeculativeExecutionPolicy::new(2, delay);
+        assert_eq!(policy.next_execution(1), Some(delay));
+        assert_eq!(policy.next_execution(2), Some(delay));
+        assert_eq!(policy.next_execution(3), None);
+        assert_eq!(
+            ConstantSpeculativeExecutionPolicy::new(0, delay).next_execution(1),
+            None
+        );
+    }
+}
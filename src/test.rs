@@ -13,3 +13,571 @@ macro_rules! fixture {
         }
     }
 }
+
+use std::cell::RefCell;
+#[cfg(feature = "async")]
+use std::convert::TryFrom;
+use std::io::{self, Cursor, Read, Write};
+use std::net;
+use std::thread;
+use std::time::Duration;
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+#[cfg(feature = "async")]
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::authenticators::{Authenticator, SaslAuthenticator};
+#[cfg(feature = "async")]
+use crate::cluster::async_connection::{read_raw_frame, read_segmented_frame};
+#[cfg(feature = "async")]
+use crate::compression::Compression;
+use crate::error;
+use crate::frame::frame_result::{
+    BodyResResultRows, ColSpec, ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata,
+};
+#[cfg(feature = "async")]
+use crate::frame::parser::parse_header;
+#[cfg(feature = "async")]
+use crate::frame::segment::{encode_segments, SegmentDecoder};
+use crate::frame::{AsByte, CustomPayload, Frame, IntoBytes, Opcode, ProtocolVersion, Version};
+use crate::transport::CDRSTransport;
+use crate::types::rows::Row;
+use crate::types::{CBytes, CString};
+
+/// Address of a node which `ScriptedTransport` pretends to be connected to.
+pub const SCRIPTED_TRANSPORT_ADDR: &str = "127.0.0.1:9042";
+
+/// Transport that replies with prerecorded bytes and keeps everything written to it.
+/// Once the bytes are exhausted reads time out if a timeout is set, like reads of
+/// a socket of a stalled node do.
+pub struct ScriptedTransport {
+    pub written: Vec<u8>,
+    pub timeout: Option<Duration>,
+    pub closed: bool,
+    /// Time every read takes, like reads of a socket of a slow node do.
+    pub read_delay: Duration,
+    to_read: Cursor<Vec<u8>>,
+    protocol_version: ProtocolVersion,
+}
+
+impl ScriptedTransport {
+    pub fn new(to_read: Vec<u8>) -> Self {
+        ScriptedTransport {
+            written: vec![],
+            timeout: None,
+            closed: false,
+            read_delay: Duration::from_secs(0),
+            to_read: Cursor::new(to_read),
+            protocol_version: ProtocolVersion::default(),
+        }
+    }
+}
+
+impl Read for ScriptedTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        thread::sleep(self.read_delay);
+        match self.to_read.read(buf)? {
+            0 if self.timeout.is_some() && !buf.is_empty() => {
+                Err(io::Error::from(io::ErrorKind::WouldBlock))
+            }
+            read => Ok(read),
+        }
+    }
+}
+
+impl Write for ScriptedTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CDRSTransport for ScriptedTransport {
+    fn try_clone(&self) -> io::Result<Self> {
+        Err(io::Error::other("not supported"))
+    }
+
+    fn close(&mut self, _close: net::Shutdown) -> io::Result<()> {
+        self.closed = true;
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, dur: Option<Duration>) -> io::Result<()> {
+        self.timeout = dur;
+        Ok(())
+    }
+
+    fn is_alive(&self) -> bool {
+        !self.closed
+    }
+
+    fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    fn set_protocol_version(&mut self, protocol_version: ProtocolVersion) {
+        self.protocol_version = protocol_version;
+    }
+
+    fn addr(&self) -> net::SocketAddr {
+        SCRIPTED_TRANSPORT_ADDR.parse().unwrap()
+    }
+}
+
+/// `r2d2` connection manager of `ScriptedTransport`s. Every connection replies
+/// with the same prerecorded bytes, speaks a given protocol version and reads
+/// with a given delay.
+pub struct ScriptedConnectionsManager {
+    pub to_read: Vec<u8>,
+    pub protocol_version: ProtocolVersion,
+    pub read_delay: Duration,
+}
+
+impl r2d2::ManageConnection for ScriptedConnectionsManager {
+    type Connection = RefCell<ScriptedTransport>;
+    type Error = error::Error;
+
+    fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        let mut transport = ScriptedTransport::new(self.to_read.clone());
+        transport.set_protocol_version(self.protocol_version);
+        transport.read_delay = self.read_delay;
+        Ok(RefCell::new(transport))
+    }
+
+    fn is_valid(&self, _conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        !conn.borrow().is_alive()
+    }
+}
+
+/// Server side of an asynchronous connection which supports given protocol version
+/// and wraps frames into segments after startup in protocol v5.
+#[cfg(feature = "async")]
+pub struct TestServer {
+    socket: TcpStream,
+    protocol_version: ProtocolVersion,
+    /// Protocol version of the last received request.
+    pub request_version: Option<ProtocolVersion>,
+    started: bool,
+    segments: SegmentDecoder,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "async")]
+impl TestServer {
+    pub async fn accept(listener: &TcpListener) -> TestServer {
+        TestServer::accept_with_version(listener, ProtocolVersion::HIGHEST).await
+    }
+
+    pub async fn accept_with_version(
+        listener: &TcpListener,
+        protocol_version: ProtocolVersion,
+    ) -> TestServer {
+        let (socket, _) = listener.accept().await.unwrap();
+        TestServer {
+            socket,
+            protocol_version,
+            request_version: None,
+            started: false,
+            segments: SegmentDecoder::new(Compression::None),
+            buffer: vec![],
+        }
+    }
+
+    pub async fn read_request(&mut self) -> (u16, Opcode) {
+        let bytes = if self.started && self.protocol_version.is_segmented() {
+            read_segmented_frame(&mut self.socket, &mut self.segments, &mut self.buffer).await
+        } else {
+            read_raw_frame(&mut self.socket).await
+        }
+        .unwrap();
+        let (stream, _) = parse_header(&bytes);
+        self.request_version = ProtocolVersion::from_byte(bytes[0]);
+
+        (stream, Opcode::try_from(bytes[4]).unwrap())
+    }
+
+    pub async fn respond(&mut self, stream: u16, opcode: Opcode, body: Vec<u8>) {
+        let mut bytes = versioned_response_frame(opcode, body, self.protocol_version);
+        bytes[2..4].copy_from_slice(&stream.to_be_bytes());
+        if self.started && self.protocol_version.is_segmented() {
+            bytes = encode_segments(&bytes, Compression::None).unwrap();
+        }
+        self.started = true;
+        // `AsyncWriteExt` is not imported as it conflicts with `Write` of `ScriptedTransport`
+        tokio::io::AsyncWriteExt::write_all(&mut self.socket, &bytes)
+            .await
+            .unwrap();
+    }
+}
+
+/// Returns bytes of a response frame with zero stream id.
+pub fn response_frame(opcode: Opcode, body: Vec<u8>) -> Vec<u8> {
+    versioned_response_frame(opcode, body, ProtocolVersion::default())
+}
+
+/// Returns bytes of a response frame of a given protocol version with zero stream id.
+pub fn versioned_response_frame(
+    opcode: Opcode,
+    body: Vec<u8>,
+    protocol_version: ProtocolVersion,
+) -> Vec<u8> {
+    Frame {
+        version: Version::Response,
+        protocol_version,
+        flags: vec![],
+        opcode,
+        stream: 0,
+        body,
+        tracing_id: None,
+        warnings: vec![],
+        custom_payload: CustomPayload::new(),
+    }
+    .into_cbytes()
+}
+
+/// Returns a body of an error which a server responds with to a request of
+/// unsupported protocol version.
+pub fn unsupported_version_error(
+    requested: ProtocolVersion,
+    highest_supported: ProtocolVersion,
+) -> Vec<u8> {
+    let mut supported = vec![];
+    let mut version = Some(highest_supported);
+    while let Some(v) = version {
+        supported.insert(0, format!("{0}/v{0}", v.as_byte()));
+        version = v.lower();
+    }
+    let message = format!(
+        "Invalid or unsupported protocol version ({}); supported versions are ({})",
+        requested.as_byte(),
+        supported.join(", ")
+    );
+    let mut body = vec![0, 0, 0, 0x0A];
+    body.extend_from_slice(&(message.len() as u16).to_be_bytes());
+    body.extend_from_slice(message.as_bytes());
+    body
+}
+
+/// Authenticator of a SASL mechanism in which a server challenges a client once
+/// with `CHALLENGE` and finishes authentication with `SERVER_FINAL` token.
+#[derive(Clone)]
+pub struct ChallengedAuthenticator;
+
+impl ChallengedAuthenticator {
+    pub const NAME: &'static str = "org.example.ChallengedAuthenticator";
+    pub const CHALLENGE: &'static [u8] = b"server-first";
+    pub const SERVER_FINAL: &'static [u8] = b"server-final";
+
+    /// Returns bodies of responses with which a server authenticates a client:
+    /// `AUTHENTICATE`, `AUTH_CHALLENGE` and `AUTH_SUCCESS` with a given final token.
+    pub fn server_responses(server_final: &[u8]) -> Vec<(Opcode, Vec<u8>)> {
+        vec![
+            (Opcode::Authenticate, string(ChallengedAuthenticator::NAME)),
+            (
+                Opcode::AuthChallenge,
+                CBytes::new(ChallengedAuthenticator::CHALLENGE.to_vec()).into_cbytes(),
+            ),
+            (
+                Opcode::AuthSuccess,
+                CBytes::new(server_final.to_vec()).into_cbytes(),
+            ),
+        ]
+    }
+}
+
+impl Authenticator for ChallengedAuthenticator {
+    fn get_auth_token(&self) -> CBytes {
+        CBytes::new(b"client-first".to_vec())
+    }
+
+    fn get_cassandra_name(&self) -> Option<&str> {
+        Some(ChallengedAuthenticator::NAME)
+    }
+
+    fn new_sasl_authenticator(&self) -> Box<dyn SaslAuthenticator> {
+        Box::new(ChallengedSaslAuthenticator { challenged: false })
+    }
+}
+
+struct ChallengedSaslAuthenticator {
+    challenged: bool,
+}
+
+impl SaslAuthenticator for ChallengedSaslAuthenticator {
+    fn initial_response(&mut self) -> CBytes {
+        CBytes::new(b"client-first".to_vec())
+    }
+
+    fn evaluate_challenge(&mut self, challenge: &[u8]) -> CBytes {
+        self.challenged = challenge == ChallengedAuthenticator::CHALLENGE;
+        CBytes::new(b"client-final".to_vec())
+    }
+
+    fn on_auth_success(&mut self, token: &[u8]) -> error::Result<()> {
+        if self.challenged && token == ChallengedAuthenticator::SERVER_FINAL {
+            Ok(())
+        } else {
+            Err(error::Error::from("Server has not proved its identity"))
+        }
+    }
+}
+
+fn string(value: &str) -> Vec<u8> {
+    let mut bytes = (value.len() as u16).to_be_bytes().to_vec();
+    bytes.extend_from_slice(value.as_bytes());
+    bytes
+}
+
+/// Returns well-formed bodies of every kind of response a server may send
+/// in protocol v4. They are used as seeds for tests of malformed responses.
+pub fn response_bodies() -> Vec<(Opcode, Vec<u8>)> {
+    let mut rows = vec![0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 5];
+    rows.extend(string("ks"));
+    rows.extend(string("table"));
+    rows.extend(string("id"));
+    rows.extend_from_slice(&[0, 0x09]);
+    rows.extend(string("tags"));
+    rows.extend_from_slice(&[0, 0x20, 0, 0x0D]);
+    rows.extend(string("attrs"));
+    rows.extend_from_slice(&[0, 0x21, 0, 0x0D, 0, 0x09]);
+    rows.extend(string("point"));
+    rows.extend_from_slice(&[0, 0x30]);
+    rows.extend(string("ks"));
+    rows.extend(string("point"));
+    rows.extend_from_slice(&[0, 2]);
+    rows.extend(string("x"));
+    rows.extend_from_slice(&[0, 0x07]);
+    rows.extend(string("y"));
+    rows.extend_from_slice(&[0, 0x07]);
+    rows.extend(string("pair"));
+    rows.extend_from_slice(&[0, 0x31, 0, 2, 0, 0x09, 0, 0x0D]);
+    // one row: an int, a list with a single string and three nulls
+    rows.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 4, 0, 0, 0, 1]);
+    rows.extend_from_slice(&[0, 0, 0, 9, 0, 0, 0, 1, 0, 0, 0, 1, b'a']);
+    rows.extend_from_slice(&[0xFF; 12]);
+
+    let mut prepared = vec![
+        0, 0, 0, 4, 0, 1, 7, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0,
+    ];
+    prepared.extend(string("ks"));
+    prepared.extend(string("table"));
+    prepared.extend(string("id"));
+    // result metadata without columns
+    prepared.extend_from_slice(&[0, 0x09, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    let mut set_keyspace = vec![0, 0, 0, 3];
+    set_keyspace.extend(string("ks"));
+
+    let mut schema_change = vec![0, 0, 0, 5];
+    schema_change.extend(string("CREATED"));
+    schema_change.extend(string("TABLE"));
+    schema_change.extend(string("ks"));
+    schema_change.extend(string("table"));
+
+    let mut unavailable = vec![0, 0, 0x10, 0];
+    unavailable.extend(string("Cannot achieve consistency level QUORUM"));
+    unavailable.extend_from_slice(&[0, 4, 0, 0, 0, 3, 0, 0, 0, 1]);
+
+    let mut supported = vec![0, 1];
+    supported.extend(string("COMPRESSION"));
+    supported.extend_from_slice(&[0, 2]);
+    supported.extend(string("lz4"));
+    supported.extend(string("snappy"));
+
+    let mut event = string("STATUS_CHANGE");
+    event.extend(string("UP"));
+    event.extend_from_slice(&[4, 127, 0, 0, 1, 0, 0, 0x23, 0x52]);
+
+    vec![
+        (Opcode::Ready, vec![]),
+        (
+            Opcode::Authenticate,
+            string("org.apache.cassandra.auth.PasswordAuthenticator"),
+        ),
+        (Opcode::Supported, supported),
+        (Opcode::Result, vec![0, 0, 0, 1]),
+        (Opcode::Result, rows),
+        (Opcode::Result, set_keyspace),
+        (Opcode::Result, prepared),
+        (Opcode::Result, schema_change),
+        (Opcode::Error, unavailable),
+        (
+            Opcode::Error,
+            unsupported_version_error(ProtocolVersion::V5, ProtocolVersion::V4),
+        ),
+        (Opcode::Event, event),
+        (Opcode::AuthChallenge, vec![0, 0, 0, 2, 1, 2]),
+        (Opcode::AuthSuccess, vec![0xFF, 0xFF, 0xFF, 0xFF]),
+    ]
+}
+
+/// Returns a copy of `bytes` with a few bytes replaced by random ones.
+pub fn corrupted<R: Rng>(bytes: &[u8], rng: &mut R) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    if bytes.is_empty() {
+        return bytes;
+    }
+
+    for _ in 0..rng.gen_range(1, 4) {
+        let position = rng.gen_range(0, bytes.len());
+        bytes[position] = rng.gen();
+    }
+    bytes
+}
+
+/// Returns a random generator that produces the same values in every run,
+/// so a failure of a test which uses it could be reproduced.
+pub fn seeded_rng() -> XorShiftRng {
+    XorShiftRng::from_seed([0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb])
+}
+
+/// Replaces stream id of every frame in `bytes` with zero, so frames with random
+/// stream ids could be compared with fixtures.
+pub fn reset_stream_ids(mut bytes: Vec<u8>) -> Vec<u8> {
+    use crate::frame::parser::{parse_header, HEADER_LEN};
+
+    let mut offset = 0;
+    while offset + HEADER_LEN <= bytes.len() {
+        let (_, length) = parse_header(&bytes[offset..]);
+        bytes[offset + 2] = 0;
+        bytes[offset + 3] = 0;
+        offset += HEADER_LEN + length;
+    }
+    bytes
+}
+
+/// Class name of custom type columns built by `rows`.
+pub const CUSTOM_TYPE: &str = "com.example.PointType";
+
+fn col_spec(name: &str, id: ColType) -> ColSpec {
+    let varchar = || {
+        Box::new(ColTypeOption {
+            id: ColType::Varchar,
+            value: None,
+        })
+    };
+    let value = match id {
+        ColType::Set => Some(ColTypeOptionValue::CSet(varchar())),
+        ColType::Map => Some(ColTypeOptionValue::CMap((varchar(), varchar()))),
+        ColType::Custom => Some(ColTypeOptionValue::CString(CString::new(
+            CUSTOM_TYPE.to_string(),
+        ))),
+        _ => None,
+    };
+
+    ColSpec {
+        ksname: None,
+        tablename: None,
+        name: CString::new(name.to_string()),
+        col_type: ColTypeOption { id, value },
+    }
+}
+
+/// Builds rows of given columns from serialized values. Sets and maps consist
+/// of varchar elements, custom types have `CUSTOM_TYPE` class name.
+pub fn rows(columns: &[(&str, ColType)], rows: Vec<Vec<Option<Vec<u8>>>>) -> Vec<Row> {
+    let col_specs: Vec<ColSpec> = columns
+        .iter()
+        .map(|(name, id)| col_spec(name, id.clone()))
+        .collect();
+
+    Row::from_frame_body(BodyResResultRows {
+        metadata: RowsMetadata {
+            flags: 0,
+            columns_count: col_specs.len() as i32,
+            new_metadata_id: None,
+            paging_state: None,
+            global_table_space: None,
+            col_specs,
+        },
+        rows_count: rows.len() as i32,
+        rows_content: rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|value| value.map(CBytes::new).unwrap_or_else(CBytes::new_empty))
+                    .collect()
+            })
+            .collect(),
+    })
+}
+
+/// Returns bytes of a `RESULT` response frame which contains rows of given columns.
+/// Values and column types are the same as ones of `rows`, except that lists,
+/// UDTs and tuples are not supported.
+pub fn rows_response(
+    columns: &[(&str, ColType)],
+    rows: Vec<Vec<Option<Vec<u8>>>>,
+    protocol_version: ProtocolVersion,
+) -> Vec<u8> {
+    let string = |value: &str| {
+        let mut bytes = (value.len() as u16).to_be_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    };
+
+    // rows kind, global table spec flag and columns count
+    let mut body = 2i32.to_be_bytes().to_vec();
+    body.extend_from_slice(&1i32.to_be_bytes());
+    body.extend_from_slice(&(columns.len() as i32).to_be_bytes());
+    body.extend(string("system"));
+    body.extend(string("table"));
+    for (name, id) in columns {
+        body.extend(string(name));
+        match id {
+            ColType::Custom => {
+                body.extend_from_slice(&[0, 0]);
+                body.extend(string(CUSTOM_TYPE));
+            }
+            ColType::Set => body.extend_from_slice(&[0, 0x22, 0, 0x0D]),
+            ColType::Map => body.extend_from_slice(&[0, 0x21, 0, 0x0D, 0, 0x0D]),
+            id => body.extend_from_slice(&col_type_id(id).to_be_bytes()),
+        }
+    }
+
+    body.extend_from_slice(&(rows.len() as i32).to_be_bytes());
+    for value in rows.into_iter().flatten() {
+        match value {
+            Some(value) => {
+                body.extend_from_slice(&(value.len() as i32).to_be_bytes());
+                body.extend(value);
+            }
+            None => body.extend_from_slice(&(-1i32).to_be_bytes()),
+        }
+    }
+
+    versioned_response_frame(Opcode::Result, body, protocol_version)
+}
+
+fn col_type_id(id: &ColType) -> u16 {
+    match id {
+        ColType::Ascii => 0x0001,
+        ColType::Bigint => 0x0002,
+        ColType::Blob => 0x0003,
+        ColType::Boolean => 0x0004,
+        ColType::Counter => 0x0005,
+        ColType::Decimal => 0x0006,
+        ColType::Double => 0x0007,
+        ColType::Float => 0x0008,
+        ColType::Int => 0x0009,
+        ColType::Timestamp => 0x000B,
+        ColType::Uuid => 0x000C,
+        ColType::Varchar => 0x000D,
+        ColType::Varint => 0x000E,
+        ColType::Timeuuid => 0x000F,
+        ColType::Inet => 0x0010,
+        ColType::Date => 0x0011,
+        ColType::Time => 0x0012,
+        ColType::Smallint => 0x0013,
+        ColType::Tinyint => 0x0014,
+        ColType::Duration => 0x0015,
+        id => panic!("{:?} columns are not supported", id),
+    }
+}
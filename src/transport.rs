@@ -20,6 +20,8 @@ use std::net::TcpStream;
 use std::time::Duration;
 use std::sync::Arc;
 
+use crate::frame::ProtocolVersion;
+
 // TODO [v 2.x.x]: CDRSTransport: ... + BufReader + ButWriter + ...
 ///General CDRS transport trait. Both [`TranportTcp`][transportTcp]
 ///and [`TransportTls`][transportTls] has their own implementations of this trait. Generaly
@@ -42,14 +44,34 @@ pub trait CDRSTransport: Sized + Read + Write + Send + Sync {
     /// It is an error to pass the zero Duration to this method.
     fn set_timeout(&mut self, dur: Option<Duration>) -> io::Result<()>;
 
-    /// Method that checks that transport is alive
+    /// Method that checks that transport is alive. A transport which has been
+    /// closed is not alive.
     fn is_alive(&self) -> bool;
+
+    /// Returns a protocol version which has been negotiated with a server for this
+    /// connection. Transports which do not store it use the default protocol version.
+    fn protocol_version(&self) -> ProtocolVersion {
+        ProtocolVersion::default()
+    }
+
+    /// Stores a protocol version which has been negotiated with a server for this
+    /// connection. It is ignored by default.
+    fn set_protocol_version(&mut self, _protocol_version: ProtocolVersion) {}
+
+    /// Returns an address of a node this transport is connected to. By default it is
+    /// an unspecified address, so errors do not point to a particular node.
+    fn addr(&self) -> net::SocketAddr {
+        net::SocketAddr::from(([0, 0, 0, 0], 0))
+    }
 }
 
 /// Default Tcp transport.
 pub struct TransportTcp {
     tcp: TcpStream,
     addr: String,
+    peer_addr: net::SocketAddr,
+    protocol_version: ProtocolVersion,
+    closed: bool,
 }
 
 impl TransportTcp {
@@ -63,9 +85,13 @@ impl TransportTcp {
     /// let tcp_transport = TransportTcp::new(addr).unwrap();
     /// ```
     pub fn new(addr: &str) -> io::Result<TransportTcp> {
-        TcpStream::connect(addr).map(|socket| TransportTcp {
+        let socket = TcpStream::connect(addr)?;
+        Ok(TransportTcp {
+            peer_addr: socket.peer_addr()?,
             tcp: socket,
             addr: addr.to_string(),
+            protocol_version: ProtocolVersion::default(),
+            closed: false,
         })
     }
 }
@@ -88,13 +114,13 @@ impl Write for TransportTcp {
 
 impl CDRSTransport for TransportTcp {
     fn try_clone(&self) -> io::Result<TransportTcp> {
-        TcpStream::connect(self.addr.as_str()).map(|socket| TransportTcp {
-            tcp: socket,
-            addr: self.addr.clone(),
-        })
+        let mut transport = TransportTcp::new(self.addr.as_str())?;
+        transport.protocol_version = self.protocol_version;
+        Ok(transport)
     }
 
     fn close(&mut self, close: net::Shutdown) -> io::Result<()> {
+        self.closed = true;
         self.tcp.shutdown(close)
     }
 
@@ -105,7 +131,19 @@ impl CDRSTransport for TransportTcp {
     }
 
     fn is_alive(&self) -> bool {
-        self.tcp.peer_addr().is_ok()
+        !self.closed && self.tcp.peer_addr().is_ok()
+    }
+
+    fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    fn set_protocol_version(&mut self, protocol_version: ProtocolVersion) {
+        self.protocol_version = protocol_version;
+    }
+
+    fn addr(&self) -> net::SocketAddr {
+        self.peer_addr
     }
 }
 
@@ -115,6 +153,8 @@ pub struct TransportRustls {
     config: Arc<rustls::ClientConfig>,
     addr: net::SocketAddr,
     dns_name: webpki::DNSName,
+    protocol_version: ProtocolVersion,
+    closed: bool,
 }
 
 #[cfg(feature = "rust-tls")]
@@ -129,6 +169,8 @@ impl TransportRustls {
             config,
             addr,
             dns_name,
+            protocol_version: ProtocolVersion::default(),
+            closed: false,
         })
     }
 }
@@ -158,10 +200,13 @@ impl Write for TransportRustls {
 impl CDRSTransport for TransportRustls {
     #[inline]
     fn try_clone(&self) -> io::Result<Self> {
-        Self::new(self.addr, self.dns_name.clone(), self.config.clone())
+        let mut transport = Self::new(self.addr, self.dns_name.clone(), self.config.clone())?;
+        transport.protocol_version = self.protocol_version;
+        Ok(transport)
     }
 
     fn close(&mut self, close: net::Shutdown) -> io::Result<()> {
+        self.closed = true;
         self.inner.get_mut().shutdown(close)
     }
 
@@ -171,7 +216,19 @@ impl CDRSTransport for TransportRustls {
     }
 
     fn is_alive(&self) -> bool {
-        self.inner.get_ref().peer_addr().is_ok()
+        !self.closed && self.inner.get_ref().peer_addr().is_ok()
+    }
+
+    fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    fn set_protocol_version(&mut self, protocol_version: ProtocolVersion) {
+        self.protocol_version = protocol_version;
+    }
+
+    fn addr(&self) -> net::SocketAddr {
+        self.addr
     }
 }
 
@@ -181,19 +238,26 @@ pub struct TransportTls {
     ssl: SslStream<TcpStream>,
     connector: SslConnector,
     addr: String,
+    peer_addr: net::SocketAddr,
+    protocol_version: ProtocolVersion,
+    closed: bool,
 }
 #[cfg(feature = "ssl")]
 impl TransportTls {
     pub fn new(addr: &str, connector: &SslConnector) -> io::Result<TransportTls> {
         let a: Vec<&str> = addr.split(':').collect();
-        let res = net::TcpStream::connect(addr).map(|socket| {
-            connector
+        let res = net::TcpStream::connect(addr).and_then(|socket| {
+            let peer_addr = socket.peer_addr()?;
+            Ok(connector
                 .connect(a[0], socket)
                 .map(|sslsocket| TransportTls {
                     ssl: sslsocket,
                     connector: connector.clone(),
                     addr: addr.to_string(),
-                })
+                    peer_addr,
+                    protocol_version: ProtocolVersion::default(),
+                    closed: false,
+                }))
         });
 
         res.and_then(|res| {
@@ -236,14 +300,19 @@ impl CDRSTransport for TransportTls {
             }
         };
 
-        let res = net::TcpStream::connect(self.addr.as_str()).map(|socket| {
-            self.connector
+        let res = net::TcpStream::connect(self.addr.as_str()).and_then(|socket| {
+            let peer_addr = socket.peer_addr()?;
+            Ok(self
+                .connector
                 .connect(ip, socket)
                 .map(|sslsocket| TransportTls {
                     ssl: sslsocket,
                     connector: self.connector.clone(),
                     addr: self.addr.clone(),
-                })
+                    peer_addr,
+                    protocol_version: self.protocol_version,
+                    closed: false,
+                }))
         });
 
         res.and_then(|res| {
@@ -253,6 +322,7 @@ impl CDRSTransport for TransportTls {
     }
 
     fn close(&mut self, _close: net::Shutdown) -> io::Result<()> {
+        self.closed = true;
         self.ssl
             .shutdown()
             .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
@@ -267,6 +337,18 @@ impl CDRSTransport for TransportTls {
     }
 
     fn is_alive(&self) -> bool {
-        self.ssl.get_ref().peer_addr().is_ok()
+        !self.closed && self.ssl.get_ref().peer_addr().is_ok()
+    }
+
+    fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    fn set_protocol_version(&mut self, protocol_version: ProtocolVersion) {
+        self.protocol_version = protocol_version;
+    }
+
+    fn addr(&self) -> net::SocketAddr {
+        self.peer_addr
     }
 }
@@ -0,0 +1,409 @@
+use std::net::IpAddr;
+
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
+use crate::frame::IntoBytes;
+use crate::types::blob::Blob;
+use crate::types::data_serialization_types::*;
+use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
+use crate::types::value::{Bytes, Value, ValueType};
+use crate::types::varint::Varint;
+use crate::types::{to_int, CBytes};
+
+/// Value of any CQL type. Unlike `as_rust_type!` conversions it is decoded basing
+/// on a type of a column only, so rows of tables which schema is not known ahead
+/// of time can be read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CqlValue {
+    Ascii(String),
+    Bigint(i64),
+    Blob(Blob),
+    Boolean(bool),
+    Counter(i64),
+    Decimal(Decimal),
+    Double(f64),
+    Float(f32),
+    Int(i32),
+    /// Milliseconds since the Unix epoch.
+    Timestamp(i64),
+    Uuid(Uuid),
+    Text(String),
+    Varint(Varint),
+    Timeuuid(Uuid),
+    Inet(IpAddr),
+    /// Days since the Unix epoch which is represented by 2^31.
+    Date(u32),
+    /// Nanoseconds since midnight.
+    Time(i64),
+    Smallint(i16),
+    Tinyint(i8),
+    Duration(Duration),
+    List(Vec<CqlValue>),
+    Set(Vec<CqlValue>),
+    /// Entries of a map in the order they have been received.
+    Map(Vec<(CqlValue, CqlValue)>),
+    /// Fields of a user defined type together with their names.
+    Udt(Vec<(String, CqlValue)>),
+    Tuple(Vec<CqlValue>),
+    /// Raw bytes of a custom type value, see `CustomTypeRegistry` to decode them.
+    Custom(Blob),
+    /// Value of a type other than text or blob which has been written as empty bytes.
+    Empty,
+    Null,
+}
+
+impl CqlValue {
+    /// Decodes a value of a given type. Absent bytes are decoded as `Null`.
+    pub fn decode(col_type: &ColTypeOption, bytes: Option<&[u8]>) -> Result<CqlValue> {
+        let bytes = match bytes {
+            Some(bytes) => bytes,
+            None => return Ok(CqlValue::Null),
+        };
+        if bytes.is_empty() && !may_be_empty(&col_type.id) {
+            return Ok(CqlValue::Empty);
+        }
+
+        let value = match col_type.id {
+            ColType::Custom => CqlValue::Custom(Blob::new(bytes.to_vec())),
+            ColType::Ascii => CqlValue::Ascii(decode_ascii(bytes)?),
+            ColType::Bigint => CqlValue::Bigint(decode_bigint(bytes)?),
+            ColType::Blob => CqlValue::Blob(Blob::new(bytes.to_vec())),
+            ColType::Boolean => CqlValue::Boolean(decode_boolean(bytes)?),
+            ColType::Counter => CqlValue::Counter(decode_bigint(bytes)?),
+            ColType::Decimal => CqlValue::Decimal(decode_decimal(bytes)?),
+            ColType::Double => CqlValue::Double(decode_double(bytes)?),
+            ColType::Float => CqlValue::Float(decode_float(bytes)?),
+            ColType::Int => CqlValue::Int(decode_int(bytes)?),
+            ColType::Timestamp => CqlValue::Timestamp(decode_timestamp(bytes)?),
+            ColType::Uuid => CqlValue::Uuid(decode_timeuuid(bytes)?),
+            ColType::Varchar => CqlValue::Text(decode_varchar(bytes)?),
+            ColType::Varint => CqlValue::Varint(decode_big_varint(bytes)?),
+            ColType::Timeuuid => CqlValue::Timeuuid(decode_timeuuid(bytes)?),
+            ColType::Inet => CqlValue::Inet(decode_inet(bytes)?),
+            ColType::Date => CqlValue::Date(decode_date(bytes)? as u32),
+            ColType::Time => CqlValue::Time(decode_time(bytes)?),
+            ColType::Smallint => CqlValue::Smallint(decode_smallint(bytes)?),
+            ColType::Tinyint => CqlValue::Tinyint(decode_tinyint(bytes)?),
+            ColType::Duration => CqlValue::Duration(decode_duration(bytes)?),
+            ColType::List => match col_type.value {
+                Some(ColTypeOptionValue::CList(ref item_type)) => {
+                    CqlValue::List(decode_items(item_type, &decode_list(bytes)?)?)
+                }
+                _ => return Err(invalid_metadata(col_type)),
+            },
+            ColType::Set => match col_type.value {
+                Some(ColTypeOptionValue::CSet(ref item_type)) => {
+                    CqlValue::Set(decode_items(item_type, &decode_set(bytes)?)?)
+                }
+                _ => return Err(invalid_metadata(col_type)),
+            },
+            ColType::Map => match col_type.value {
+                Some(ColTypeOptionValue::CMap((ref key_type, ref value_type))) => CqlValue::Map(
+                    decode_map(bytes)?
+                        .iter()
+                        .map(|(key, value)| {
+                            Ok((
+                                CqlValue::decode(key_type, key.as_slice())?,
+                                CqlValue::decode(value_type, value.as_slice())?,
+                            ))
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                _ => return Err(invalid_metadata(col_type)),
+            },
+            ColType::Udt => match col_type.value {
+                Some(ColTypeOptionValue::UdtType(ref udt)) => CqlValue::Udt(
+                    udt.descriptions
+                        .iter()
+                        .zip(decode_udt(bytes, udt.descriptions.len())?.iter())
+                        .map(|((name, field_type), field)| {
+                            Ok((
+                                name.as_str().to_string(),
+                                CqlValue::decode(field_type, field.as_slice())?,
+                            ))
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                _ => return Err(invalid_metadata(col_type)),
+            },
+            ColType::Tuple => match col_type.value {
+                Some(ColTypeOptionValue::TupleType(ref tuple)) => CqlValue::Tuple(
+                    tuple
+                        .types
+                        .iter()
+                        .zip(decode_tuple(bytes, tuple.types.len())?.iter())
+                        .map(|(item_type, item)| CqlValue::decode(item_type, item.as_slice()))
+                        .collect::<Result<_>>()?,
+                ),
+                _ => return Err(invalid_metadata(col_type)),
+            },
+            ColType::Null => CqlValue::Null,
+        };
+
+        Ok(value)
+    }
+
+    /// Decodes a value which is going to be bound to a query.
+    /// Not set values cannot be decoded.
+    pub fn from_value(value: &Value, col_type: &ColTypeOption) -> Result<CqlValue> {
+        match value.value_type {
+            ValueType::Normal(_) => CqlValue::decode(col_type, Some(value.body.as_slice())),
+            ValueType::Null => Ok(CqlValue::Null),
+            ValueType::NotSet => Err(Error::General(
+                "Not set value cannot be converted into CqlValue".to_string(),
+            )),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == CqlValue::Null
+    }
+}
+
+impl From<CqlValue> for Value {
+    fn from(value: CqlValue) -> Value {
+        match value {
+            CqlValue::Ascii(s) | CqlValue::Text(s) => Value::new_normal(s),
+            CqlValue::Bigint(i)
+            | CqlValue::Counter(i)
+            | CqlValue::Timestamp(i)
+            | CqlValue::Time(i) => Value::new_normal(i),
+            CqlValue::Blob(blob) | CqlValue::Custom(blob) => Value::new_normal(blob),
+            CqlValue::Boolean(b) => Value::new_normal(b),
+            CqlValue::Decimal(decimal) => Value::new_normal(decimal),
+            CqlValue::Double(f) => Value::new_normal(f),
+            CqlValue::Float(f) => Value::new_normal(f),
+            CqlValue::Int(i) => Value::new_normal(i),
+            CqlValue::Uuid(uuid) | CqlValue::Timeuuid(uuid) => Value::new_normal(uuid),
+            CqlValue::Varint(varint) => Value::new_normal(varint),
+            CqlValue::Inet(addr) => Value::new_normal(addr),
+            CqlValue::Date(date) => Value::new_normal(date),
+            CqlValue::Smallint(i) => Value::new_normal(i),
+            CqlValue::Tinyint(i) => Value::new_normal(i),
+            CqlValue::Duration(duration) => Value::new_normal(duration),
+            CqlValue::List(items) | CqlValue::Set(items) => {
+                let mut bytes = to_int(items.len() as i32);
+                encode_items(&mut bytes, items);
+                Value::new_normal(Bytes::new(bytes))
+            }
+            CqlValue::Map(entries) => {
+                let mut bytes = to_int(entries.len() as i32);
+                for (key, value) in entries {
+                    encode_items(&mut bytes, vec![key, value]);
+                }
+                Value::new_normal(Bytes::new(bytes))
+            }
+            CqlValue::Udt(fields) => {
+                let mut bytes = vec![];
+                encode_items(&mut bytes, fields.into_iter().map(|(_, field)| field));
+                Value::new_normal(Bytes::new(bytes))
+            }
+            CqlValue::Tuple(items) => {
+                let mut bytes = vec![];
+                encode_items(&mut bytes, items);
+                Value::new_normal(Bytes::new(bytes))
+            }
+            CqlValue::Empty => Value::new_normal(Bytes::new(vec![])),
+            CqlValue::Null => Value::new_null(),
+        }
+    }
+}
+
+/// Only values of these types are meaningful when they consist of empty bytes.
+fn may_be_empty(col_type: &ColType) -> bool {
+    matches!(
+        *col_type,
+        ColType::Ascii | ColType::Varchar | ColType::Blob | ColType::Custom
+    )
+}
+
+pub(crate) fn decode_items(item_type: &ColTypeOption, items: &[CBytes]) -> Result<Vec<CqlValue>> {
+    items
+        .iter()
+        .map(|item| CqlValue::decode(item_type, item.as_slice()))
+        .collect()
+}
+
+fn encode_items<I: IntoIterator<Item = CqlValue>>(bytes: &mut Vec<u8>, items: I) {
+    for item in items {
+        bytes.extend(Value::from(item).into_cbytes());
+    }
+}
+
+pub(crate) fn invalid_metadata(col_type: &ColTypeOption) -> Error {
+    Error::InvalidFrame(format!("Invalid metadata of {:?} type", col_type.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::{CTuple, CUdt};
+    use crate::test::rows;
+    use crate::types::CString;
+
+    fn col_type(id: ColType, value: Option<ColTypeOptionValue>) -> ColTypeOption {
+        ColTypeOption { id, value }
+    }
+
+    fn simple(id: ColType) -> Box<ColTypeOption> {
+        Box::new(col_type(id, None))
+    }
+
+    fn round_trip(col_type: &ColTypeOption, value: CqlValue) {
+        let bound = Value::from(value.clone());
+        assert_eq!(CqlValue::from_value(&bound, col_type).unwrap(), value);
+    }
+
+    #[test]
+    fn decodes_simple_types() {
+        let int = col_type(ColType::Int, None);
+        assert_eq!(
+            CqlValue::decode(&int, Some(&[0, 0, 0, 7])).unwrap(),
+            CqlValue::Int(7)
+        );
+        assert_eq!(CqlValue::decode(&int, None).unwrap(), CqlValue::Null);
+        assert_eq!(CqlValue::decode(&int, Some(&[])).unwrap(), CqlValue::Empty);
+
+        let text = col_type(ColType::Varchar, None);
+        assert_eq!(
+            CqlValue::decode(&text, Some(&[])).unwrap(),
+            CqlValue::Text(String::new())
+        );
+
+        for (id, value) in vec![
+            (ColType::Ascii, CqlValue::Ascii("abc".to_string())),
+            (ColType::Bigint, CqlValue::Bigint(-1)),
+            (ColType::Blob, CqlValue::Blob(Blob::new(vec![1, 2]))),
+            (ColType::Boolean, CqlValue::Boolean(true)),
+            (ColType::Decimal, CqlValue::Decimal(Decimal::new(12001, 2))),
+            (ColType::Double, CqlValue::Double(1.5)),
+            (ColType::Float, CqlValue::Float(-1.5)),
+            (ColType::Timestamp, CqlValue::Timestamp(1_600_000_000_000)),
+            (ColType::Uuid, CqlValue::Uuid(Uuid::nil())),
+            (ColType::Varint, CqlValue::Varint(Varint::from(u128::MAX))),
+            (ColType::Inet, CqlValue::Inet("127.0.0.1".parse().unwrap())),
+            (ColType::Date, CqlValue::Date(1 << 31)),
+            (ColType::Time, CqlValue::Time(1_000)),
+            (ColType::Smallint, CqlValue::Smallint(-2)),
+            (ColType::Tinyint, CqlValue::Tinyint(-3)),
+            (
+                ColType::Duration,
+                CqlValue::Duration(Duration::new(1, 2, 3)),
+            ),
+            (ColType::Custom, CqlValue::Custom(Blob::new(vec![3]))),
+        ] {
+            round_trip(&col_type(id, None), value);
+        }
+        round_trip(&int, CqlValue::Empty);
+        round_trip(&int, CqlValue::Null);
+    }
+
+    #[test]
+    fn decodes_collections() {
+        let list = col_type(
+            ColType::List,
+            Some(ColTypeOptionValue::CList(simple(ColType::Int))),
+        );
+        round_trip(
+            &list,
+            CqlValue::List(vec![CqlValue::Int(1), CqlValue::Null, CqlValue::Int(3)]),
+        );
+
+        let set = col_type(
+            ColType::Set,
+            Some(ColTypeOptionValue::CSet(simple(ColType::Varchar))),
+        );
+        round_trip(&set, CqlValue::Set(vec![CqlValue::Text("a".to_string())]));
+
+        let map = col_type(
+            ColType::Map,
+            Some(ColTypeOptionValue::CMap((
+                simple(ColType::Varchar),
+                Box::new(list),
+            ))),
+        );
+        round_trip(
+            &map,
+            CqlValue::Map(vec![(
+                CqlValue::Text("a".to_string()),
+                CqlValue::List(vec![CqlValue::Int(1)]),
+            )]),
+        );
+
+        assert!(CqlValue::decode(&col_type(ColType::List, None), Some(&[0, 0, 0, 0])).is_err());
+    }
+
+    #[test]
+    fn decodes_udts_and_tuples() {
+        let udt = col_type(
+            ColType::Udt,
+            Some(ColTypeOptionValue::UdtType(CUdt {
+                ks: CString::new("ks".to_string()),
+                udt_name: CString::new("address".to_string()),
+                descriptions: vec![
+                    (
+                        CString::new("street".to_string()),
+                        *simple(ColType::Varchar),
+                    ),
+                    (CString::new("number".to_string()), *simple(ColType::Int)),
+                ],
+            })),
+        );
+        round_trip(
+            &udt,
+            CqlValue::Udt(vec![
+                ("street".to_string(), CqlValue::Text("Main".to_string())),
+                ("number".to_string(), CqlValue::Int(1)),
+            ]),
+        );
+        // fields added to a type after a value has been written are absent
+        let old_value = Value::from(CqlValue::Udt(vec![(
+            "street".to_string(),
+            CqlValue::Text("Main".to_string()),
+        )]));
+        assert_eq!(
+            CqlValue::from_value(&old_value, &udt).unwrap(),
+            CqlValue::Udt(vec![
+                ("street".to_string(), CqlValue::Text("Main".to_string())),
+                ("number".to_string(), CqlValue::Null),
+            ])
+        );
+
+        let tuple = col_type(
+            ColType::Tuple,
+            Some(ColTypeOptionValue::TupleType(CTuple {
+                types: vec![*simple(ColType::Int), *simple(ColType::Boolean)],
+            })),
+        );
+        round_trip(
+            &tuple,
+            CqlValue::Tuple(vec![CqlValue::Int(1), CqlValue::Boolean(false)]),
+        );
+    }
+
+    #[test]
+    fn decodes_rows() {
+        let rows = rows(
+            &[("id", ColType::Int), ("tags", ColType::Set)],
+            vec![vec![Some(vec![0, 0, 0, 1]), None]],
+        );
+
+        assert_eq!(rows[0].column_names(), vec!["id", "tags"]);
+        assert_eq!(
+            rows[0].get_cql_value_by_name("id").unwrap(),
+            CqlValue::Int(1)
+        );
+        assert!(rows[0].get_cql_value_by_index(1).unwrap().is_null());
+        assert!(rows[0].get_cql_value_by_name("name").is_err());
+        assert!(rows[0].get_cql_value_by_index(2).is_err());
+    }
+
+    #[test]
+    fn not_set_values() {
+        let int = col_type(ColType::Int, None);
+        assert!(CqlValue::from_value(&Value::new_not_set(), &int).is_err());
+    }
+}
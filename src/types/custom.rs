@@ -0,0 +1,243 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::error::{Error, Result};
+use crate::types::blob::Blob;
+use crate::types::value::Bytes;
+
+/// Decoder and encoder of values of a custom type, i.e. a type which is identified
+/// by a Java class name of a server side implementation.
+pub trait CustomTypeCodec: Send + Sync {
+    /// Decodes bytes received from a server.
+    fn decode(&self, bytes: &[u8]) -> Result<Box<dyn Any + Send + Sync>>;
+
+    /// Encodes a value so it can be sent to a server. An error is returned if
+    /// the value has a type which is not supported by the codec.
+    fn encode(&self, value: &dyn Any) -> Result<Vec<u8>>;
+}
+
+/// Value of a custom type column.
+pub enum CustomValue {
+    /// Value decoded by a codec registered for a class name of the column.
+    Decoded(Box<dyn Any + Send + Sync>),
+    /// Raw bytes of a value which class name has no registered codec.
+    Raw(Blob),
+}
+
+impl CustomValue {
+    /// Returns a reference to a decoded value if it has a type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match *self {
+            CustomValue::Decoded(ref value) => value.downcast_ref(),
+            CustomValue::Raw(_) => None,
+        }
+    }
+
+    /// Returns a decoded value if it has a type `T`, otherwise the value is returned back.
+    pub fn downcast<T: Any>(self) -> ::std::result::Result<T, CustomValue> {
+        match self {
+            CustomValue::Decoded(value) => match value.downcast() {
+                Ok(value) => Ok(*value),
+                Err(value) => Err(CustomValue::Decoded(value)),
+            },
+            raw => Err(raw),
+        }
+    }
+
+    /// Returns raw bytes of a value which has not been decoded.
+    pub fn into_raw(self) -> Option<Blob> {
+        match self {
+            CustomValue::Raw(blob) => Some(blob),
+            CustomValue::Decoded(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for CustomValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CustomValue::Decoded(_) => f.write_str("Decoded(..)"),
+            CustomValue::Raw(ref blob) => f.debug_tuple("Raw").field(blob).finish(),
+        }
+    }
+}
+
+/// Registry of codecs of custom types by their Java class names, e.g.
+/// `org.apache.cassandra.db.marshal.DynamicCompositeType`. Values of custom types
+/// which do not have a registered codec are returned as raw bytes.
+#[derive(Default)]
+pub struct CustomTypeRegistry {
+    codecs: HashMap<String, Box<dyn CustomTypeCodec>>,
+}
+
+impl CustomTypeRegistry {
+    pub fn new() -> Self {
+        CustomTypeRegistry::default()
+    }
+
+    /// Registers a codec for a given class name. A codec which has been registered
+    /// for the same class name before is replaced.
+    pub fn register_codec<C: CustomTypeCodec + 'static>(&mut self, class_name: &str, codec: C) {
+        self.codecs.insert(class_name.to_string(), Box::new(codec));
+    }
+
+    /// Registers functions which decode values of a given class name into `T`
+    /// and encode them back.
+    pub fn register<T, D, E>(&mut self, class_name: &str, decode: D, encode: E)
+    where
+        T: Any + Send + Sync,
+        D: Fn(&[u8]) -> Result<T> + Send + Sync + 'static,
+        E: Fn(&T) -> Vec<u8> + Send + Sync + 'static,
+    {
+        self.register_codec(
+            class_name,
+            FnCodec {
+                decode,
+                encode,
+                value_type: PhantomData,
+            },
+        );
+    }
+
+    /// Indicates if a codec is registered for a given class name.
+    pub fn contains(&self, class_name: &str) -> bool {
+        self.codecs.contains_key(class_name)
+    }
+
+    /// Decodes bytes of a given class name with a registered codec,
+    /// or returns them as they are if there is no such codec.
+    pub fn decode(&self, class_name: &str, bytes: &[u8]) -> Result<CustomValue> {
+        match self.codecs.get(class_name) {
+            Some(codec) => codec.decode(bytes).map(CustomValue::Decoded),
+            None => Ok(CustomValue::Raw(Blob::new(bytes.to_vec()))),
+        }
+    }
+
+    /// Encodes a value of a given class name with a registered codec,
+    /// so it can be bound to a query.
+    pub fn encode(&self, class_name: &str, value: &dyn Any) -> Result<Bytes> {
+        match self.codecs.get(class_name) {
+            Some(codec) => codec.encode(value).map(Bytes::new),
+            None => Err(Error::General(format!(
+                "No codec is registered for custom type {}",
+                class_name
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for CustomTypeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.codecs.keys()).finish()
+    }
+}
+
+struct FnCodec<T, D, E> {
+    decode: D,
+    encode: E,
+    value_type: PhantomData<fn() -> T>,
+}
+
+impl<T, D, E> CustomTypeCodec for FnCodec<T, D, E>
+where
+    T: Any + Send + Sync,
+    D: Fn(&[u8]) -> Result<T> + Send + Sync,
+    E: Fn(&T) -> Vec<u8> + Send + Sync,
+{
+    fn decode(&self, bytes: &[u8]) -> Result<Box<dyn Any + Send + Sync>> {
+        (self.decode)(bytes).map(|value| Box::new(value) as Box<dyn Any + Send + Sync>)
+    }
+
+    fn encode(&self, value: &dyn Any) -> Result<Vec<u8>> {
+        match value.downcast_ref::<T>() {
+            Some(value) => Ok((self.encode)(value)),
+            None => Err(Error::General(
+                "Value has a type which is not supported by a custom type codec".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::ColType;
+    use crate::test::{rows, CUSTOM_TYPE as POINT};
+    use crate::types::value::Value;
+    use crate::types::IntoRustByName;
+
+    #[derive(Debug, PartialEq)]
+    struct Point(u8, u8);
+
+    fn registry() -> CustomTypeRegistry {
+        let mut registry = CustomTypeRegistry::new();
+        registry.register(
+            POINT,
+            |bytes: &[u8]| match *bytes {
+                [x, y] => Ok(Point(x, y)),
+                _ => Err(Error::General("Invalid point".to_string())),
+            },
+            |point: &Point| vec![point.0, point.1],
+        );
+        registry
+    }
+
+    #[test]
+    fn decodes_registered_types() {
+        let registry = registry();
+        assert!(registry.contains(POINT));
+
+        let value = registry.decode(POINT, &[1, 2]).unwrap();
+        assert_eq!(value.downcast_ref::<Point>(), Some(&Point(1, 2)));
+        assert!(value.downcast_ref::<String>().is_none());
+        assert_eq!(value.downcast::<Point>().unwrap(), Point(1, 2));
+        assert!(registry.decode(POINT, &[1]).is_err());
+    }
+
+    #[test]
+    fn falls_back_to_raw_bytes() {
+        let value = registry().decode("com.example.Other", &[1, 2, 3]).unwrap();
+        assert!(value.downcast_ref::<Point>().is_none());
+        assert_eq!(value.into_raw(), Some(Blob::new(vec![1, 2, 3])));
+    }
+
+    #[test]
+    fn decodes_rows() {
+        let rows = rows(
+            &[("point", ColType::Custom), ("id", ColType::Int)],
+            vec![
+                vec![Some(vec![5, 6]), Some(vec![0, 0, 0, 1])],
+                vec![None, None],
+            ],
+        );
+        let registry = registry();
+
+        let point = rows[0].get_custom_by_name("point", &registry).unwrap();
+        assert_eq!(point.unwrap().downcast::<Point>().unwrap(), Point(5, 6));
+        let point = rows[0].get_custom_by_index(0, &CustomTypeRegistry::new());
+        assert_eq!(
+            point.unwrap().unwrap().into_raw(),
+            Some(Blob::new(vec![5, 6]))
+        );
+        let raw: Option<Blob> = rows[0].get_by_name("point").unwrap();
+        assert_eq!(raw, Some(Blob::new(vec![5, 6])));
+
+        assert!(rows[1]
+            .get_custom_by_name("point", &registry)
+            .unwrap()
+            .is_none());
+        assert!(rows[0].get_custom_by_name("id", &registry).is_err());
+        assert!(rows[0].get_custom_by_index(2, &registry).is_err());
+    }
+
+    #[test]
+    fn encodes_registered_types() {
+        let registry = registry();
+        let value = Value::from(registry.encode(POINT, &Point(3, 4)).unwrap());
+        assert_eq!(value, Value::new_normal(Blob::new(vec![3, 4])));
+        assert!(registry.encode(POINT, &"3,4").is_err());
+        assert!(registry.encode("com.example.Other", &Point(3, 4)).is_err());
+    }
+}
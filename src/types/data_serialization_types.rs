@@ -4,9 +4,11 @@ use std::string::FromUtf8Error;
 
 use super::blob::Blob;
 use super::decimal::Decimal;
+use super::duration::Duration;
+use super::varint::Varint;
 use super::*;
 use crate::error;
-use crate::frame::FromCursor;
+use crate::frame::{FromBytes, FromCursor};
 use uuid;
 
 // https://github.com/apache/cassandra/blob/trunk/doc/native_protocol_v4.spec#L813
@@ -67,8 +69,8 @@ pub fn decode_date(bytes: &[u8]) -> Result<i32, io::Error> {
 pub fn decode_decimal(bytes: &[u8]) -> Result<Decimal, io::Error> {
     let lr = bytes.split_at(INT_LEN);
 
-    let scale = try_i_from_bytes(lr.0)? as u32;
-    let unscaled = try_i_from_bytes(lr.1)?;
+    let scale = try_i_from_bytes(lr.0)? as i32;
+    let unscaled = Varint::from_signed_bytes_be(lr.1);
 
     Ok(Decimal::new(unscaled, scale))
 }
@@ -180,9 +182,25 @@ pub fn decode_timeuuid(bytes: &[u8]) -> Result<uuid::Uuid, uuid::Error> {
     uuid::Uuid::from_slice(bytes)
 }
 
-// Decodes Cassandra `varint` data (bytes) into Rust's `Result<i64, io::Error>`
+// Decodes Cassandra `varint` data (bytes) into Rust's `Result<i64, io::Error>`,
+// values which do not fit into `i64` are reported as errors
 pub fn decode_varint(bytes: &[u8]) -> Result<i64, io::Error> {
-    try_i_from_bytes(bytes)
+    decode_big_varint(bytes)?.to_i64().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "varint value does not fit into i64",
+        )
+    })
+}
+
+// Decodes Cassandra `varint` data (bytes) of any size into Rust's `Result<Varint, io::Error>`
+pub fn decode_big_varint(bytes: &[u8]) -> Result<Varint, io::Error> {
+    Ok(Varint::from_signed_bytes_be(bytes))
+}
+
+// Decodes Cassandra `duration` data (bytes) into Rust's `Result<Duration, error::Error>`
+pub fn decode_duration(bytes: &[u8]) -> Result<Duration, error::Error> {
+    Duration::from_bytes(bytes)
 }
 
 // Decodes Cassandra `Udt` data (bytes) into Rust's `Result<Vec<CBytes>, io::Error>`
@@ -363,6 +381,11 @@ mod tests {
             decode_decimal(&[0, 0, 0, 1, 0xFF, 0x7F]).unwrap(),
             Decimal::new(-129, 1)
         );
+
+        assert_eq!(
+            decode_decimal(&[0xFF, 0xFF, 0xFF, 0xFE, 0x00, 0x81]).unwrap(),
+            Decimal::new(129, -2)
+        );
     }
 
     #[test]
@@ -397,6 +420,22 @@ mod tests {
         assert_eq!(decode_varint(&[0xFF, 0x7F]).unwrap(), -129);
     }
 
+    #[test]
+    fn decode_big_varint_test() {
+        let bytes = [0x01, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(decode_big_varint(&bytes).unwrap().to_i128(), Some(1 << 64));
+        assert!(decode_varint(&bytes).is_err());
+
+        let mut bytes = vec![0, 0, 0, 2];
+        bytes.extend_from_slice(&[0xFF; 12]);
+        bytes.push(0x7F);
+        assert_eq!(
+            decode_decimal(&bytes).unwrap(),
+            Decimal::new(Varint::from_signed_bytes_be(&bytes[4..]), 2)
+        );
+        assert_eq!(decode_decimal(&bytes).unwrap(), Decimal::new(-129, 2));
+    }
+
     #[test]
     fn decode_udt_test() {
         let udt = decode_udt(&[0, 0, 0, 2, 1, 2], 1).unwrap();
@@ -528,6 +567,40 @@ mod tests {
         assert!(as_rust_type!(wrong_type, data, i8).is_err());
     }
 
+    #[test]
+    fn as_rust_varint_test() {
+        let type_varint = DataType {
+            id: ColType::Varint,
+        };
+        let data = CBytes::new(vec![0x7F; 20]);
+        assert_eq!(
+            as_rust_type!(type_varint, data, Varint).unwrap().unwrap(),
+            Varint::from_signed_bytes_be(&[0x7F; 20])
+        );
+        assert!(as_rust_type!(type_varint, data, i64).is_err());
+        let wrong_type = DataType {
+            id: ColType::Bigint,
+        };
+        assert!(as_rust_type!(wrong_type, data, Varint).is_err());
+    }
+
+    #[test]
+    fn as_rust_duration_test() {
+        let type_duration = DataType {
+            id: ColType::Duration,
+        };
+        let duration = Duration::new(1, -2, 3_000_000_000);
+        let data = CBytes::new(duration.into_cbytes());
+        assert_eq!(
+            as_rust_type!(type_duration, data, Duration).unwrap().unwrap(),
+            duration
+        );
+        let wrong_type = DataType {
+            id: ColType::Bigint,
+        };
+        assert!(as_rust_type!(wrong_type, data, Duration).is_err());
+    }
+
     #[test]
     fn as_rust_f64_test() {
         let type_double = DataType {
@@ -1,29 +1,42 @@
-use super::{to_int, to_varint};
+#[cfg(feature = "bigdecimal")]
+use bigdecimal::BigDecimal;
+#[cfg(feature = "bigdecimal")]
+use std::convert::TryFrom;
+
+use super::to_int;
+use super::varint::Varint;
 use crate::frame::traits::IntoBytes;
 
-/// Cassandra Decimal type
+/// Cassandra Decimal type, i.e. `unscaled * 10^-scale`. A scale may be negative.
+///
+/// An unscaled value is a `Varint`, so decimals of any precision are kept losslessly.
+/// Prior to that it was `i64` and a scale was `u32`: code which reads the fields
+/// should use `Varint` conversions, e.g. `decimal.unscaled.to_i64()`.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Decimal {
-    pub unscaled: i64,
-    pub scale: u32,
+    pub unscaled: Varint,
+    pub scale: i32,
 }
 
 impl Decimal {
-    pub fn new(unscaled: i64, scale: u32) -> Self {
-        Decimal { unscaled, scale }
+    pub fn new<U: Into<Varint>>(unscaled: U, scale: i32) -> Self {
+        Decimal {
+            unscaled: unscaled.into(),
+            scale,
+        }
     }
 
     /// Method that returns plain `f64` value.
     pub fn as_plain(&self) -> f64 {
-        (self.unscaled as f64) / (10i64.pow(self.scale) as f64)
+        self.unscaled.to_f64() / 10f64.powi(self.scale)
     }
 }
 
 impl IntoBytes for Decimal {
     fn into_cbytes(&self) -> Vec<u8> {
         let mut bytes: Vec<u8> = vec![];
-        bytes.extend(to_int(self.scale as i32));
-        bytes.extend(to_varint(self.unscaled));
+        bytes.extend(to_int(self.scale));
+        bytes.extend(self.unscaled.into_cbytes());
 
         bytes
     }
@@ -33,10 +46,7 @@ macro_rules! impl_from_for_decimal {
     ($t:ty) => {
         impl From<$t> for Decimal {
             fn from(i: $t) -> Self {
-                Decimal {
-                    unscaled: i as i64,
-                    scale: 0,
-                }
+                Decimal::new(i, 0)
             }
         }
     };
@@ -46,15 +56,19 @@ impl_from_for_decimal!(i8);
 impl_from_for_decimal!(i16);
 impl_from_for_decimal!(i32);
 impl_from_for_decimal!(i64);
+impl_from_for_decimal!(i128);
 impl_from_for_decimal!(u8);
 impl_from_for_decimal!(u16);
+impl_from_for_decimal!(u32);
+impl_from_for_decimal!(u64);
+impl_from_for_decimal!(Varint);
 
 impl From<f32> for Decimal {
     fn from(f: f32) -> Decimal {
-        let mut scale: u32 = 0;
+        let mut scale: i32 = 0;
 
         loop {
-            let unscaled = f * (10i64.pow(scale) as f32);
+            let unscaled = f * (10i64.pow(scale as u32) as f32);
 
             if unscaled == unscaled.trunc() {
                 return Decimal::new(unscaled as i64, scale);
@@ -67,10 +81,10 @@ impl From<f32> for Decimal {
 
 impl From<f64> for Decimal {
     fn from(f: f64) -> Decimal {
-        let mut scale: u32 = 0;
+        let mut scale: i32 = 0;
 
         loop {
-            let unscaled = f * (10i64.pow(scale) as f64);
+            let unscaled = f * (10i64.pow(scale as u32) as f64);
 
             if unscaled == unscaled.trunc() {
                 return Decimal::new(unscaled as i64, scale);
@@ -81,6 +95,28 @@ impl From<f64> for Decimal {
     }
 }
 
+#[cfg(feature = "bigdecimal")]
+impl From<BigDecimal> for Decimal {
+    fn from(decimal: BigDecimal) -> Self {
+        let (unscaled, scale) = decimal.as_bigint_and_exponent();
+        match i32::try_from(scale) {
+            Ok(scale) => Decimal::new(unscaled, scale),
+            // a scale is a 32-bit integer in the protocol
+            Err(_) => {
+                let scale = scale.clamp(i32::MIN as i64, i32::MAX as i64);
+                Decimal::from(decimal.with_scale(scale))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl From<Decimal> for BigDecimal {
+    fn from(decimal: Decimal) -> Self {
+        BigDecimal::new(decimal.unscaled.into(), decimal.scale as i64)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -102,6 +138,35 @@ mod test {
 
         let expected: Vec<u8> = vec![0, 0, 0, 1, 0xFF, 0x7F];
         assert_eq!(Decimal::new(-129, 1).into_cbytes(), expected);
+
+        let expected: Vec<u8> = vec![0xFF, 0xFF, 0xFF, 0xFE, 0x00, 0x81];
+        assert_eq!(Decimal::new(129, -2).into_cbytes(), expected);
+    }
+
+    #[test]
+    fn into_cbytes_beyond_i64() {
+        let mut expected: Vec<u8> = vec![0, 0, 0, 2, 0x00];
+        expected.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert_eq!(Decimal::new(u64::MAX, 2).into_cbytes(), expected);
+        assert_eq!(
+            Decimal::new(u64::MAX, 2).as_plain(),
+            u64::MAX as f64 / 100.0
+        );
+    }
+
+    #[cfg(feature = "bigdecimal")]
+    #[test]
+    fn big_decimal() {
+        let big: BigDecimal = "-12345678901234567890.123456789".parse().unwrap();
+        let decimal = Decimal::from(big.clone());
+        assert_eq!(decimal.scale, 9);
+        assert_eq!(BigDecimal::from(decimal), big);
+
+        let big: BigDecimal = "1.5e3".parse().unwrap();
+        let decimal = Decimal::from(big.clone());
+        assert_eq!(decimal, Decimal::new(15, -2));
+        assert_eq!(decimal.as_plain(), 1500.0);
+        assert_eq!(BigDecimal::from(decimal), big);
     }
 
     #[test]
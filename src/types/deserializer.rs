@@ -0,0 +1,461 @@
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::error::{Error, Result};
+use crate::types::cql_value::CqlValue;
+use crate::types::list::List;
+use crate::types::map::Map;
+use crate::types::rows::Row;
+use crate::types::tuple::Tuple;
+use crate::types::udt::UDT;
+
+/// Deserializes a row into `T`. Columns are deserialized as fields of a struct or
+/// entries of a map by their names, or as items of a tuple in the order of the row.
+pub fn from_row<T: DeserializeOwned>(row: &Row) -> Result<T> {
+    T::deserialize(RowDeserializer { row })
+}
+
+/// Deserializes every row of a result set into `T`, see `from_row`.
+pub fn from_rows<T: DeserializeOwned>(rows: &[Row]) -> Result<Vec<T>> {
+    rows.iter().map(from_row).collect()
+}
+
+/// Deserializes a value of any CQL type into `T`. User defined types are
+/// deserialized like maps with field names as keys, lists, sets and tuples
+/// are deserialized like sequences.
+pub fn from_cql_value<T: DeserializeOwned>(value: CqlValue) -> Result<T> {
+    T::deserialize(value)
+}
+
+pub fn from_udt<T: DeserializeOwned>(udt: &UDT) -> Result<T> {
+    from_cql_value(udt.to_cql_value()?)
+}
+
+pub fn from_tuple<T: DeserializeOwned>(tuple: &Tuple) -> Result<T> {
+    from_cql_value(tuple.to_cql_value()?)
+}
+
+pub fn from_list<T: DeserializeOwned>(list: &List) -> Result<T> {
+    from_cql_value(list.to_cql_value()?)
+}
+
+pub fn from_map<T: DeserializeOwned>(map: &Map) -> Result<T> {
+    from_cql_value(map.to_cql_value()?)
+}
+
+struct RowDeserializer<'a> {
+    row: &'a Row,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for RowDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(Columns::new(self.row))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut columns = Columns::new(self.row);
+        let value = visitor.visit_seq(&mut columns)?;
+        match columns.names.len() - columns.index {
+            0 => Ok(value),
+            remaining => Err(Error::General(format!(
+                "{} columns of a row have not been deserialized",
+                remaining
+            ))),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf option unit unit_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Columns of a row which are accessed either by their names or in order.
+struct Columns<'a> {
+    row: &'a Row,
+    names: Vec<&'a str>,
+    index: usize,
+}
+
+impl<'a> Columns<'a> {
+    fn new(row: &'a Row) -> Self {
+        Columns {
+            row,
+            names: row.column_names(),
+            index: 0,
+        }
+    }
+
+    fn next_column<'de, T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value> {
+        let index = self.index;
+        self.index += 1;
+
+        self.row
+            .get_cql_value_by_index(index)
+            .and_then(|value| seed.deserialize(value))
+            .map_err(|err| {
+                Error::General(format!("Column '{}': {}", self.names[index], err.inner()))
+            })
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for Columns<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.names.get(self.index) {
+            Some(name) => seed.deserialize(name.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        self.next_column(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.names.len() - self.index)
+    }
+}
+
+impl<'de, 'a> SeqAccess<'de> for Columns<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.index < self.names.len() {
+            self.next_column(seed).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.names.len() - self.index)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for CqlValue {
+    type Deserializer = CqlValue;
+
+    fn into_deserializer(self) -> CqlValue {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for CqlValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            CqlValue::Ascii(s) | CqlValue::Text(s) => visitor.visit_string(s),
+            CqlValue::Bigint(i)
+            | CqlValue::Counter(i)
+            | CqlValue::Timestamp(i)
+            | CqlValue::Time(i) => visitor.visit_i64(i),
+            CqlValue::Blob(blob) | CqlValue::Custom(blob) => {
+                visitor.visit_byte_buf(blob.into_vec())
+            }
+            CqlValue::Boolean(b) => visitor.visit_bool(b),
+            CqlValue::Decimal(decimal) => visitor.visit_f64(decimal.as_plain()),
+            CqlValue::Double(f) => visitor.visit_f64(f),
+            CqlValue::Float(f) => visitor.visit_f32(f),
+            CqlValue::Int(i) => visitor.visit_i32(i),
+            CqlValue::Uuid(uuid) | CqlValue::Timeuuid(uuid) => {
+                visitor.visit_string(uuid.to_string())
+            }
+            CqlValue::Varint(varint) => match (varint.to_i64(), varint.to_i128()) {
+                (Some(i), _) => visitor.visit_i64(i),
+                (None, Some(i)) => visitor.visit_i128(i),
+                (None, None) => Err(de::Error::custom("Varint value does not fit into i128")),
+            },
+            CqlValue::Inet(addr) => visitor.visit_string(addr.to_string()),
+            CqlValue::Date(date) => visitor.visit_u32(date),
+            CqlValue::Smallint(i) => visitor.visit_i16(i),
+            CqlValue::Tinyint(i) => visitor.visit_i8(i),
+            CqlValue::Duration(duration) => visit_map(
+                visitor,
+                MapDeserializer::new(
+                    vec![
+                        ("months", duration.months as i64),
+                        ("days", duration.days as i64),
+                        ("nanoseconds", duration.nanoseconds),
+                    ]
+                    .into_iter(),
+                ),
+            ),
+            CqlValue::List(items) | CqlValue::Set(items) | CqlValue::Tuple(items) => {
+                visit_seq(visitor, SeqDeserializer::new(items.into_iter()))
+            }
+            CqlValue::Map(entries) => visit_map(visitor, MapDeserializer::new(entries.into_iter())),
+            CqlValue::Udt(fields) => visit_map(visitor, MapDeserializer::new(fields.into_iter())),
+            CqlValue::Empty | CqlValue::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            CqlValue::Empty | CqlValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            // e.g. `Vec<u8>` is deserialized from a sequence
+            CqlValue::Blob(blob) | CqlValue::Custom(blob) => {
+                visit_seq(visitor, SeqDeserializer::new(blob.into_vec().into_iter()))
+            }
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self {
+            // unit variants are stored as their names
+            CqlValue::Ascii(s) | CqlValue::Text(s) => visitor.visit_enum(s.into_deserializer()),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Visits a sequence and checks that all its items have been consumed.
+fn visit_seq<'de, V, I, T>(visitor: V, mut seq: SeqDeserializer<I, Error>) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+    I: Iterator<Item = T>,
+    T: IntoDeserializer<'de, Error>,
+{
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+/// Visits a map and checks that all its entries have been consumed.
+fn visit_map<'de, V, I, K, T>(
+    visitor: V,
+    mut map: MapDeserializer<'de, I, Error>,
+) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+    I: Iterator<Item = (K, T)>,
+    K: IntoDeserializer<'de, Error>,
+    T: IntoDeserializer<'de, Error>,
+{
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::frame_result::ColType;
+    use crate::test::rows;
+    use crate::types::blob::Blob;
+    use crate::types::duration::Duration;
+    use serde::de::IgnoredAny;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Status {
+        Active,
+        Banned,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User {
+        id: i32,
+        #[serde(rename = "user_name")]
+        name: String,
+        email: Option<String>,
+        tags: Vec<String>,
+        status: Status,
+    }
+
+    fn user_rows() -> Vec<Row> {
+        let text = |s: &str| Some(s.as_bytes().to_vec());
+        let tags = {
+            let mut bytes = 1i32.to_be_bytes().to_vec();
+            bytes.extend_from_slice(&1i32.to_be_bytes());
+            bytes.push(b'a');
+            bytes
+        };
+
+        rows(
+            &[
+                ("id", ColType::Int),
+                ("user_name", ColType::Varchar),
+                ("email", ColType::Varchar),
+                ("tags", ColType::Set),
+                ("status", ColType::Varchar),
+            ],
+            vec![
+                vec![
+                    Some(1i32.to_be_bytes().to_vec()),
+                    text("alice"),
+                    None,
+                    Some(tags),
+                    text("active"),
+                ],
+                vec![
+                    Some(2i32.to_be_bytes().to_vec()),
+                    text("bob"),
+                    text("bob@example.com"),
+                    Some(0i32.to_be_bytes().to_vec()),
+                    text("banned"),
+                ],
+            ],
+        )
+    }
+
+    #[test]
+    fn structs() {
+        let users: Vec<User> = from_rows(&user_rows()).unwrap();
+        assert_eq!(
+            users,
+            vec![
+                User {
+                    id: 1,
+                    name: "alice".to_string(),
+                    email: None,
+                    tags: vec!["a".to_string()],
+                    status: Status::Active,
+                },
+                User {
+                    id: 2,
+                    name: "bob".to_string(),
+                    email: Some("bob@example.com".to_string()),
+                    tags: vec![],
+                    status: Status::Banned,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tuples_and_maps() {
+        let rows = user_rows();
+        let row: (i32, String, Option<String>, Vec<String>, String) = from_row(&rows[1]).unwrap();
+        assert_eq!(row.0, 2);
+        assert_eq!(row.2, Some("bob@example.com".to_string()));
+        assert!(from_row::<(i32, String)>(&rows[0]).is_err());
+
+        let columns: HashMap<String, IgnoredAny> = from_row(&rows[1]).unwrap();
+        assert_eq!(columns.len(), 5);
+    }
+
+    #[test]
+    fn errors_name_columns() {
+        #[derive(Debug, Deserialize)]
+        struct Missing {
+            #[allow(dead_code)]
+            phone: String,
+        }
+        let err = from_row::<Missing>(&user_rows()[0]).unwrap_err();
+        assert!(err.to_string().contains("'phone' is missing"));
+
+        #[derive(Debug, Deserialize)]
+        struct Invalid {
+            #[allow(dead_code)]
+            email: String,
+        }
+        let err = from_row::<Invalid>(&user_rows()[0]).unwrap_err();
+        assert!(err.to_string().contains("Column 'email'"));
+    }
+
+    #[test]
+    fn values() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Address {
+            street: String,
+            number: Option<i32>,
+        }
+        let udt = CqlValue::Udt(vec![
+            ("street".to_string(), CqlValue::Text("Main".to_string())),
+            ("number".to_string(), CqlValue::Null),
+        ]);
+        assert_eq!(
+            from_cql_value::<Address>(udt).unwrap(),
+            Address {
+                street: "Main".to_string(),
+                number: None,
+            }
+        );
+
+        let map = CqlValue::Map(vec![(CqlValue::Text("a".to_string()), CqlValue::Int(1))]);
+        let map: HashMap<String, i64> = from_cql_value(map).unwrap();
+        assert_eq!(map.get("a"), Some(&1));
+
+        let tuple = CqlValue::Tuple(vec![CqlValue::Int(1), CqlValue::Boolean(true)]);
+        assert_eq!(
+            from_cql_value::<(i32, bool)>(tuple.clone()).unwrap(),
+            (1, true)
+        );
+        assert!(from_cql_value::<(i32,)>(tuple).is_err());
+
+        let blob = CqlValue::Blob(Blob::new(vec![1, 2]));
+        assert_eq!(from_cql_value::<Vec<u8>>(blob).unwrap(), vec![1, 2]);
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Period {
+            months: i32,
+            days: i32,
+            nanoseconds: i64,
+        }
+        let duration = CqlValue::Duration(Duration::new(1, 2, 3));
+        assert_eq!(
+            from_cql_value::<Period>(duration).unwrap(),
+            Period {
+                months: 1,
+                days: 2,
+                nanoseconds: 3,
+            }
+        );
+    }
+}
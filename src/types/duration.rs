@@ -0,0 +1,161 @@
+use crate::error::{Error, Result};
+use crate::frame::traits::{FromBytes, IntoBytes};
+
+/// Cassandra Duration type. Months, days and nanoseconds are stored separately
+/// because a number of days in a month as well as a number of nanoseconds in a day
+/// (due to daylight saving) may vary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Duration {
+    pub months: i32,
+    pub days: i32,
+    pub nanoseconds: i64,
+}
+
+impl Duration {
+    pub fn new(months: i32, days: i32, nanoseconds: i64) -> Self {
+        Duration {
+            months,
+            days,
+            nanoseconds,
+        }
+    }
+}
+
+impl IntoBytes for Duration {
+    fn into_cbytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        encode_vint(self.months as i64, &mut bytes);
+        encode_vint(self.days as i64, &mut bytes);
+        encode_vint(self.nanoseconds, &mut bytes);
+
+        bytes
+    }
+}
+
+impl FromBytes for Duration {
+    fn from_bytes(bytes: &[u8]) -> Result<Duration> {
+        let (months, bytes) = decode_vint(bytes)?;
+        let (days, bytes) = decode_vint(bytes)?;
+        let (nanoseconds, bytes) = decode_vint(bytes)?;
+
+        if !bytes.is_empty() {
+            return Err(Error::from("Unexpected bytes after duration"));
+        }
+
+        let to_i32 = |value: i64| {
+            if value < i32::MIN as i64 || value > i32::MAX as i64 {
+                Err(Error::from("Duration component is out of range"))
+            } else {
+                Ok(value as i32)
+            }
+        };
+
+        Ok(Duration::new(to_i32(months)?, to_i32(days)?, nanoseconds))
+    }
+}
+
+/// Encodes a signed integer as zigzag encoded variable length integer. First byte
+/// contains as many leading ones as there are extra bytes after it.
+fn encode_vint(value: i64, bytes: &mut Vec<u8>) {
+    let value = ((value << 1) ^ (value >> 63)) as u64;
+    let magnitude = (value | 1).leading_zeros() as usize;
+    let size = (639 - magnitude * 9) >> 6;
+
+    if size == 1 {
+        bytes.push(value as u8);
+        return;
+    }
+
+    let extra_bytes = size - 1;
+    let mut encoded = value.to_be_bytes()[8 - size.min(8)..].to_vec();
+    if size == 9 {
+        encoded.insert(0, 0xFF);
+    } else {
+        encoded[0] |= !(0xFFu8 >> extra_bytes);
+    }
+    bytes.extend(encoded);
+}
+
+/// Decodes a zigzag encoded variable length integer and returns it together
+/// with the rest of bytes.
+fn decode_vint(bytes: &[u8]) -> Result<(i64, &[u8])> {
+    let first = *bytes
+        .first()
+        .ok_or_else(|| Error::from("Not enough bytes for duration"))?;
+    let extra_bytes = first.leading_ones() as usize;
+    if bytes.len() < 1 + extra_bytes {
+        return Err(Error::from("Not enough bytes for duration"));
+    }
+
+    let first = if extra_bytes == 8 {
+        0
+    } else {
+        first & (0xFF >> extra_bytes)
+    };
+    let value = bytes[1..=extra_bytes]
+        .iter()
+        .fold(first as u64, |value, byte| (value << 8) | *byte as u64);
+    let value = ((value >> 1) as i64) ^ -((value & 1) as i64);
+
+    Ok((value, &bytes[1 + extra_bytes..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vint_encoding() {
+        let encode = |value| {
+            let mut bytes = vec![];
+            encode_vint(value, &mut bytes);
+            bytes
+        };
+
+        assert_eq!(encode(0), vec![0]);
+        assert_eq!(encode(-1), vec![1]);
+        assert_eq!(encode(1), vec![2]);
+        assert_eq!(encode(63), vec![0x7E]);
+        assert_eq!(encode(64), vec![0x80, 0x80]);
+        assert_eq!(encode(-65), vec![0x80, 0x81]);
+        assert_eq!(
+            encode(i64::MAX),
+            [vec![0xFF], vec![0xFF; 7], vec![0xFE]].concat()
+        );
+        assert_eq!(encode(i64::MIN), vec![0xFF; 9]);
+    }
+
+    #[test]
+    fn duration_round_trip() {
+        let durations = vec![
+            Duration::new(0, 0, 0),
+            Duration::new(1, 2, 3),
+            Duration::new(-14, -3, -1_000_000_000),
+            Duration::new(i32::MAX, i32::MIN, i64::MAX),
+            Duration::new(0, 0, i64::MIN),
+        ];
+
+        for duration in durations {
+            let bytes = duration.into_cbytes();
+            assert_eq!(Duration::from_bytes(&bytes).unwrap(), duration);
+        }
+    }
+
+    #[test]
+    fn duration_bytes() {
+        assert_eq!(Duration::new(1, 2, 3).into_cbytes(), vec![2, 4, 6]);
+        assert_eq!(
+            Duration::from_bytes(&[2, 4, 0x80, 0x80]).unwrap(),
+            Duration::new(1, 2, 64)
+        );
+    }
+
+    #[test]
+    fn invalid_duration_bytes() {
+        assert!(Duration::from_bytes(&[2, 4]).is_err());
+        assert!(Duration::from_bytes(&[2, 4, 0x80]).is_err());
+        assert!(Duration::from_bytes(&[2, 4, 6, 8]).is_err());
+        // months overflow i32
+        assert!(Duration::from_bytes(&[0xF1, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+}
@@ -5,10 +5,12 @@ use uuid::Uuid;
 use crate::error::Result as CDRSResult;
 use crate::types::blob::Blob;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
 use crate::types::udt::UDT;
+use crate::types::varint::Varint;
 use crate::types::{AsRustType, ByName, IntoRustByName};
 
 pub trait FromCDRS {
@@ -46,6 +48,8 @@ impl FromCDRS for UDT {}
 impl FromCDRS for Tuple {}
 impl FromCDRS for PrimitiveDateTime {}
 impl FromCDRS for Decimal {}
+impl FromCDRS for Varint {}
+impl FromCDRS for Duration {}
 
 pub trait FromCDRSByName {
     fn from_cdrs_by_name<T>(cdrs_type: &T, name: &str) -> CDRSResult<Option<Self>>
@@ -82,3 +86,5 @@ impl FromCDRSByName for UDT {}
 impl FromCDRSByName for Tuple {}
 impl FromCDRSByName for PrimitiveDateTime {}
 impl FromCDRSByName for Decimal {}
+impl FromCDRSByName for Varint {}
+impl FromCDRSByName for Duration {}
@@ -1,11 +1,14 @@
 use crate::error::{Error, Result};
 use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
 use crate::types::blob::Blob;
+use crate::types::cql_value::{decode_items, invalid_metadata, CqlValue};
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
 use crate::types::udt::UDT;
+use crate::types::varint::Varint;
 use crate::types::{AsRust, AsRustType, CBytes};
 use std::net::IpAddr;
 use uuid::Uuid;
@@ -27,6 +30,19 @@ impl List {
         }
     }
 
+    /// Decodes items of the list basing on their type.
+    pub fn to_cql_value(&self) -> Result<CqlValue> {
+        match self.metadata.value {
+            Some(ColTypeOptionValue::CList(ref item_type)) => {
+                decode_items(item_type, &self.data).map(CqlValue::List)
+            }
+            Some(ColTypeOptionValue::CSet(ref item_type)) => {
+                decode_items(item_type, &self.data).map(CqlValue::Set)
+            }
+            _ => Err(invalid_metadata(&self.metadata)),
+        }
+    }
+
     fn map<T, F>(&self, f: F) -> Vec<T>
     where
         F: FnMut(&CBytes) -> T,
@@ -53,3 +69,5 @@ list_as_rust!(Map);
 list_as_rust!(UDT);
 list_as_rust!(Tuple);
 list_as_rust!(Decimal);
+list_as_rust!(Varint);
+list_as_rust!(Duration);
@@ -6,11 +6,14 @@ use uuid::Uuid;
 use crate::error::{Error, Result};
 use crate::frame::frame_result::{ColType, ColTypeOption, ColTypeOptionValue};
 use crate::types::blob::Blob;
+use crate::types::cql_value::{invalid_metadata, CqlValue};
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::list::List;
 use crate::types::tuple::Tuple;
 use crate::types::udt::UDT;
+use crate::types::varint::Varint;
 use crate::types::{AsRust, AsRustType, CBytes};
 
 #[derive(Debug)]
@@ -27,6 +30,24 @@ impl Map {
             data: data,
         }
     }
+
+    /// Decodes entries of the map basing on types of their keys and values.
+    pub fn to_cql_value(&self) -> Result<CqlValue> {
+        match self.metadata.value {
+            Some(ColTypeOptionValue::CMap((ref key_type, ref value_type))) => self
+                .data
+                .iter()
+                .map(|(key, value)| {
+                    Ok((
+                        CqlValue::decode(key_type, key.as_slice())?,
+                        CqlValue::decode(value_type, value.as_slice())?,
+                    ))
+                })
+                .collect::<Result<_>>()
+                .map(CqlValue::Map),
+            _ => Err(invalid_metadata(&self.metadata)),
+        }
+    }
 }
 
 impl AsRust for Map {}
@@ -53,6 +74,8 @@ map_as_rust!({ Blob }, { Map });
 map_as_rust!({ Blob }, { UDT });
 map_as_rust!({ Blob }, { Tuple });
 map_as_rust!({ Blob }, { Decimal });
+map_as_rust!({ Blob }, { Varint });
+map_as_rust!({ Blob }, { Duration });
 
 map_as_rust!({ String }, { Blob });
 map_as_rust!({ String }, { String });
@@ -71,6 +94,8 @@ map_as_rust!({ String }, { Map });
 map_as_rust!({ String }, { UDT });
 map_as_rust!({ String }, { Tuple });
 map_as_rust!({ String }, { Decimal });
+map_as_rust!({ String }, { Varint });
+map_as_rust!({ String }, { Duration });
 
 map_as_rust!({ bool }, { Blob });
 map_as_rust!({ bool }, { String });
@@ -89,6 +114,8 @@ map_as_rust!({ bool }, { Map });
 map_as_rust!({ bool }, { UDT });
 map_as_rust!({ bool }, { Tuple });
 map_as_rust!({ bool }, { Decimal });
+map_as_rust!({ bool }, { Varint });
+map_as_rust!({ bool }, { Duration });
 
 map_as_rust!({ i64 }, { Blob });
 map_as_rust!({ i64 }, { String });
@@ -107,6 +134,8 @@ map_as_rust!({ i64 }, { Map });
 map_as_rust!({ i64 }, { UDT });
 map_as_rust!({ i64 }, { Tuple });
 map_as_rust!({ i64 }, { Decimal });
+map_as_rust!({ i64 }, { Varint });
+map_as_rust!({ i64 }, { Duration });
 
 map_as_rust!({ i32 }, { Blob });
 map_as_rust!({ i32 }, { String });
@@ -125,6 +154,8 @@ map_as_rust!({ i32 }, { Map });
 map_as_rust!({ i32 }, { UDT });
 map_as_rust!({ i32 }, { Tuple });
 map_as_rust!({ i32 }, { Decimal });
+map_as_rust!({ i32 }, { Varint });
+map_as_rust!({ i32 }, { Duration });
 
 map_as_rust!({ i16 }, { Blob });
 map_as_rust!({ i16 }, { String });
@@ -143,6 +174,8 @@ map_as_rust!({ i16 }, { Map });
 map_as_rust!({ i16 }, { UDT });
 map_as_rust!({ i16 }, { Tuple });
 map_as_rust!({ i16 }, { Decimal });
+map_as_rust!({ i16 }, { Varint });
+map_as_rust!({ i16 }, { Duration });
 
 map_as_rust!({ i8 }, { Blob });
 map_as_rust!({ i8 }, { String });
@@ -161,6 +194,8 @@ map_as_rust!({ i8 }, { Map });
 map_as_rust!({ i8 }, { UDT });
 map_as_rust!({ i8 }, { Tuple });
 map_as_rust!({ i8 }, { Decimal });
+map_as_rust!({ i8 }, { Varint });
+map_as_rust!({ i8 }, { Duration });
 
 map_as_rust!({ IpAddr }, { Blob });
 map_as_rust!({ IpAddr }, { String });
@@ -179,6 +214,8 @@ map_as_rust!({ IpAddr }, { Map });
 map_as_rust!({ IpAddr }, { UDT });
 map_as_rust!({ IpAddr }, { Tuple });
 map_as_rust!({ IpAddr }, { Decimal });
+map_as_rust!({ IpAddr }, { Varint });
+map_as_rust!({ IpAddr }, { Duration });
 
 map_as_rust!({ Uuid }, { Blob });
 map_as_rust!({ Uuid }, { String });
@@ -197,6 +234,8 @@ map_as_rust!({ Uuid }, { Map });
 map_as_rust!({ Uuid }, { UDT });
 map_as_rust!({ Uuid }, { Tuple });
 map_as_rust!({ Uuid }, { Decimal });
+map_as_rust!({ Uuid }, { Varint });
+map_as_rust!({ Uuid }, { Duration });
 
 map_as_rust!({ PrimitiveDateTime }, { Blob });
 map_as_rust!({ PrimitiveDateTime }, { String });
@@ -215,6 +254,8 @@ map_as_rust!({ PrimitiveDateTime }, { Map });
 map_as_rust!({ PrimitiveDateTime }, { UDT });
 map_as_rust!({ PrimitiveDateTime }, { Tuple });
 map_as_rust!({ PrimitiveDateTime }, { Decimal });
+map_as_rust!({ PrimitiveDateTime }, { Varint });
+map_as_rust!({ PrimitiveDateTime }, { Duration });
 
 map_as_rust!({ Tuple }, { Blob });
 map_as_rust!({ Tuple }, { String });
@@ -233,3 +274,5 @@ map_as_rust!({ Tuple }, { Map });
 map_as_rust!({ Tuple }, { UDT });
 map_as_rust!({ Tuple }, { Tuple });
 map_as_rust!({ Tuple }, { Decimal });
+map_as_rust!({ Tuple }, { Varint });
+map_as_rust!({ Tuple }, { Duration });
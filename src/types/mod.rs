@@ -1,4 +1,5 @@
 /// Cassandra types
+use std::collections::HashMap;
 use std::io;
 use std::io::{Cursor, Read};
 use std::net::SocketAddr;
@@ -15,8 +16,13 @@ pub const UUID_LEN: usize = 16;
 
 #[macro_use]
 pub mod blob;
+pub mod cql_value;
+pub mod custom;
 pub mod data_serialization_types;
 pub mod decimal;
+#[cfg(feature = "serde")]
+pub mod deserializer;
+pub mod duration;
 pub mod from_cdrs;
 pub mod list;
 pub mod map;
@@ -24,18 +30,22 @@ pub mod rows;
 pub mod tuple;
 pub mod udt;
 pub mod value;
+pub mod varint;
 
 pub mod prelude {
     pub use crate::error::{Error, Result};
     pub use crate::frame::{TryFromRow, TryFromUDT};
     pub use crate::types::blob::Blob;
+    pub use crate::types::cql_value::CqlValue;
     pub use crate::types::decimal::Decimal;
+    pub use crate::types::duration::Duration;
     pub use crate::types::list::List;
     pub use crate::types::map::Map;
     pub use crate::types::rows::Row;
     pub use crate::types::tuple::Tuple;
     pub use crate::types::udt::UDT;
     pub use crate::types::value::{Bytes, Value};
+    pub use crate::types::varint::Varint;
     pub use crate::types::AsRustType;
 }
 
@@ -511,6 +521,53 @@ impl FromCursor for CStringList {
     }
 }
 
+/// The structure that represents Cassandra [bytes map], e.g. a custom payload of a frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CBytesMap {
+    pub map: HashMap<String, Vec<u8>>,
+}
+
+impl CBytesMap {
+    pub fn new(map: HashMap<String, Vec<u8>>) -> CBytesMap {
+        CBytesMap { map }
+    }
+
+    pub fn into_plain(self) -> HashMap<String, Vec<u8>> {
+        self.map
+    }
+}
+
+impl IntoBytes for CBytesMap {
+    fn into_cbytes(&self) -> Vec<u8> {
+        let mut bytes = to_short(self.map.len() as i16);
+
+        for (key, value) in &self.map {
+            bytes.extend_from_slice(CString::new(key.clone()).into_cbytes().as_slice());
+            bytes.extend_from_slice(CBytes::new(value.clone()).into_cbytes().as_slice());
+        }
+
+        bytes
+    }
+}
+
+impl FromCursor for CBytesMap {
+    /// Null values are read as empty ones.
+    fn from_cursor(cursor: &mut Cursor<&[u8]>) -> CDRSResult<CBytesMap> {
+        let len_bytes = cursor_next_value(cursor, SHORT_LEN as u64)?;
+        let len: u64 = try_from_bytes(len_bytes.as_slice())?;
+        let mut map = HashMap::new();
+        for _ in 0..len {
+            let key = CString::from_cursor(cursor)?.into_plain();
+            let value = CBytes::from_cursor(cursor)?
+                .into_plain()
+                .unwrap_or_default();
+            map.insert(key, value);
+        }
+
+        Ok(CBytesMap { map })
+    }
+}
+
 //
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -678,14 +735,19 @@ impl FromCursor for CInet {
 }
 
 pub fn cursor_next_value(cursor: &mut Cursor<&[u8]>, len: u64) -> CDRSResult<Vec<u8>> {
-    let l = len as usize;
     let current_position = cursor.position();
-    let mut buff: Vec<u8> = Vec::with_capacity(l);
-    unsafe {
-        buff.set_len(l);
+    let available = (cursor.get_ref().len() as u64).saturating_sub(current_position);
+    // a length comes from a server, so it is checked before a buffer is allocated
+    if len > available {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("Expected {} bytes, only {} are available", len, available),
+        )
+        .into());
     }
+
+    let mut buff: Vec<u8> = vec![0; len as usize];
     cursor.read_exact(&mut buff)?;
-    cursor.set_position(current_position + len);
     Ok(buff)
 }
 
@@ -787,6 +849,26 @@ mod tests {
         }
     }
 
+    // CBytesMap
+    #[test]
+    fn test_cbytesmap() {
+        let a = &[
+            0, 2, 0, 1, 97, 0, 0, 0, 2, 1, 2, 0, 1, 98, 255, 255, 255, 255,
+        ];
+        let mut cursor: Cursor<&[u8]> = Cursor::new(a);
+        let map = CBytesMap::from_cursor(&mut cursor).unwrap().into_plain();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["a"], vec![1, 2]);
+        assert_eq!(map["b"], Vec::<u8>::new());
+
+        let mut single = HashMap::new();
+        single.insert("a".to_string(), vec![1, 2]);
+        assert_eq!(
+            CBytesMap::new(single).into_cbytes(),
+            vec![0, 1, 0, 1, 97, 0, 0, 0, 2, 1, 2]
+        );
+    }
+
     // CBytes
     #[test]
     fn test_cbytes_new() {
@@ -7,12 +7,16 @@ use crate::frame::frame_result::{
     BodyResResultRows, ColSpec, ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata,
 };
 use crate::types::blob::Blob;
+use crate::types::cql_value::CqlValue;
+use crate::types::custom::{CustomTypeRegistry, CustomValue};
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
 use crate::types::udt::UDT;
+use crate::types::varint::Varint;
 use crate::types::{ByIndex, ByName, CBytes, IntoRustByIndex, IntoRustByName};
 
 #[derive(Clone, Debug)]
@@ -49,6 +53,85 @@ impl Row {
         let values = self.row_content.iter();
         specs.zip(values).nth(index)
     }
+
+    /// Returns names of columns in the order of their values.
+    pub fn column_names(&self) -> Vec<&str> {
+        self.metadata
+            .col_specs
+            .iter()
+            .map(|spec| spec.name.as_str())
+            .collect()
+    }
+
+    /// Returns a value of a column with a given name decoded basing on a type
+    /// of the column.
+    pub fn get_cql_value_by_name(&self, name: &str) -> Result<CqlValue> {
+        match self.get_col_spec_by_name(name) {
+            Some((col_spec, data)) => CqlValue::decode(&col_spec.col_type, data.as_slice()),
+            None => Err(column_is_empty_err(name)),
+        }
+    }
+
+    /// Returns a value of a column with a given index decoded basing on a type
+    /// of the column.
+    pub fn get_cql_value_by_index(&self, index: usize) -> Result<CqlValue> {
+        match self.get_col_spec_by_index(index) {
+            Some((col_spec, data)) => CqlValue::decode(&col_spec.col_type, data.as_slice()),
+            None => Err(column_is_empty_err(index)),
+        }
+    }
+
+    /// Returns a value of a custom type column with a given name. It is decoded by
+    /// a codec registered for a class name of the column or returned as raw bytes
+    /// if there is no such codec.
+    pub fn get_custom_by_name(
+        &self,
+        name: &str,
+        registry: &CustomTypeRegistry,
+    ) -> Result<Option<CustomValue>> {
+        match self.get_col_spec_by_name(name) {
+            Some((col_spec, data)) => decode_custom_value(col_spec, data, registry),
+            None => Err(column_is_empty_err(name)),
+        }
+    }
+
+    /// Returns a value of a custom type column with a given index. It is decoded by
+    /// a codec registered for a class name of the column or returned as raw bytes
+    /// if there is no such codec.
+    pub fn get_custom_by_index(
+        &self,
+        index: usize,
+        registry: &CustomTypeRegistry,
+    ) -> Result<Option<CustomValue>> {
+        match self.get_col_spec_by_index(index) {
+            Some((col_spec, data)) => decode_custom_value(col_spec, data, registry),
+            None => Err(column_is_empty_err(index)),
+        }
+    }
+}
+
+fn decode_custom_value(
+    col_spec: &ColSpec,
+    data: &CBytes,
+    registry: &CustomTypeRegistry,
+) -> Result<Option<CustomValue>> {
+    let class_name = match col_spec.col_type {
+        ColTypeOption {
+            id: ColType::Custom,
+            value: Some(ColTypeOptionValue::CString(ref class_name)),
+        } => class_name.as_str(),
+        _ => {
+            return Err(Error::General(format!(
+                "Column {} is not of a custom type",
+                col_spec.name.as_str()
+            )))
+        }
+    };
+
+    match data.as_slice() {
+        Some(bytes) => registry.decode(class_name, bytes).map(Some),
+        None => Ok(None),
+    }
 }
 
 impl ByName for Row {}
@@ -70,6 +153,8 @@ into_rust_by_name!(Row, UDT);
 into_rust_by_name!(Row, Tuple);
 into_rust_by_name!(Row, PrimitiveDateTime);
 into_rust_by_name!(Row, Decimal);
+into_rust_by_name!(Row, Varint);
+into_rust_by_name!(Row, Duration);
 
 impl ByIndex for Row {}
 
@@ -90,3 +175,5 @@ into_rust_by_index!(Row, UDT);
 into_rust_by_index!(Row, Tuple);
 into_rust_by_index!(Row, PrimitiveDateTime);
 into_rust_by_index!(Row, Decimal);
+into_rust_by_index!(Row, Varint);
+into_rust_by_index!(Row, Duration);
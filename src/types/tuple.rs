@@ -5,11 +5,14 @@ use uuid::Uuid;
 use crate::error::{column_is_empty_err, Error, Result};
 use crate::frame::frame_result::{CTuple, ColType, ColTypeOption, ColTypeOptionValue};
 use crate::types::blob::Blob;
+use crate::types::cql_value::CqlValue;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::udt::UDT;
+use crate::types::varint::Varint;
 use crate::types::{ByIndex, CBytes, IntoRustByIndex};
 
 use std::hash::{Hash, Hasher};
@@ -56,6 +59,15 @@ impl Tuple {
 
         Tuple { data: d }
     }
+
+    /// Decodes items of the tuple basing on their types.
+    pub fn to_cql_value(&self) -> Result<CqlValue> {
+        self.data
+            .iter()
+            .map(|(item_type, item)| CqlValue::decode(item_type, item.as_slice()))
+            .collect::<Result<_>>()
+            .map(CqlValue::Tuple)
+    }
 }
 
 impl ByIndex for Tuple {}
@@ -77,3 +89,5 @@ into_rust_by_index!(Tuple, UDT);
 into_rust_by_index!(Tuple, Tuple);
 into_rust_by_index!(Tuple, PrimitiveDateTime);
 into_rust_by_index!(Tuple, Decimal);
+into_rust_by_index!(Tuple, Varint);
+into_rust_by_index!(Tuple, Duration);
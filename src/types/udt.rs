@@ -6,11 +6,14 @@ use uuid::Uuid;
 use crate::error::{column_is_empty_err, Error, Result};
 use crate::frame::frame_result::{CUdt, ColType, ColTypeOption, ColTypeOptionValue};
 use crate::types::blob::Blob;
+use crate::types::cql_value::CqlValue;
 use crate::types::data_serialization_types::*;
 use crate::types::decimal::Decimal;
+use crate::types::duration::Duration;
 use crate::types::list::List;
 use crate::types::map::Map;
 use crate::types::tuple::Tuple;
+use crate::types::varint::Varint;
 use crate::types::{ByName, CBytes, IntoRustByName};
 
 #[derive(Clone, Debug)]
@@ -34,6 +37,21 @@ impl UDT {
 
         UDT { data: d }
     }
+
+    /// Decodes fields of the value basing on their types. Fields are returned
+    /// in an arbitrary order.
+    pub fn to_cql_value(&self) -> Result<CqlValue> {
+        self.data
+            .iter()
+            .map(|(name, (field_type, field))| {
+                Ok((
+                    name.clone(),
+                    CqlValue::decode(field_type, field.as_slice())?,
+                ))
+            })
+            .collect::<Result<_>>()
+            .map(CqlValue::Udt)
+    }
 }
 
 impl ByName for UDT {}
@@ -55,3 +73,5 @@ into_rust_by_name!(UDT, UDT);
 into_rust_by_name!(UDT, Tuple);
 into_rust_by_name!(UDT, PrimitiveDateTime);
 into_rust_by_name!(UDT, Decimal);
+into_rust_by_name!(UDT, Varint);
+into_rust_by_name!(UDT, Duration);
@@ -5,12 +5,19 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::net::IpAddr;
 
+#[cfg(feature = "bigdecimal")]
+use bigdecimal::BigDecimal;
+#[cfg(feature = "num-bigint")]
+use num_bigint::BigInt;
+
 use crate::frame::IntoBytes;
 use crate::time::PrimitiveDateTime;
 use uuid::Uuid;
 
 use super::blob::Blob;
 use super::decimal::Decimal;
+use super::duration::Duration;
+use super::varint::Varint;
 use super::*;
 
 /// Types of Cassandra value: normal value (bits), null value and not-set value
@@ -218,6 +225,32 @@ impl Into<Bytes> for Decimal {
     }
 }
 
+impl From<Duration> for Bytes {
+    fn from(duration: Duration) -> Bytes {
+        Bytes(duration.into_cbytes())
+    }
+}
+
+impl From<Varint> for Bytes {
+    fn from(varint: Varint) -> Bytes {
+        Bytes(varint.into_cbytes())
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+impl From<BigInt> for Bytes {
+    fn from(i: BigInt) -> Bytes {
+        Varint::from(i).into()
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl From<BigDecimal> for Bytes {
+    fn from(decimal: BigDecimal) -> Bytes {
+        Decimal::from(decimal).into()
+    }
+}
+
 impl<T: Into<Bytes> + Clone + Debug> From<Vec<T>> for Bytes {
     fn from(vec: Vec<T>) -> Bytes {
         let mut bytes: Vec<u8> = vec![];
@@ -294,6 +327,9 @@ mod tests {
         let _ = Value::new_normal(1 as i32);
         let _ = Value::new_normal(1 as i64);
         let _ = Value::new_normal(true);
+        let _ = Value::new_normal(Decimal::new(1, 0));
+        let _ = Value::new_normal(Varint::from(1));
+        let _ = Value::new_normal(Duration::new(1, 2, 3));
     }
 
     #[test]
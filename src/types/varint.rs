@@ -0,0 +1,155 @@
+use std::convert::TryFrom;
+
+#[cfg(feature = "num-bigint")]
+use num_bigint::BigInt;
+
+use crate::frame::traits::IntoBytes;
+
+/// Cassandra Varint type, an integer of arbitrary precision. It keeps a minimal
+/// big-endian two's complement representation of a value, so values which do not
+/// fit into `i64` are not truncated.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Varint(Vec<u8>);
+
+impl Varint {
+    /// Creates a varint from big-endian two's complement bytes, e.g. ones received
+    /// from a server. Empty bytes represent zero.
+    pub fn from_signed_bytes_be(bytes: &[u8]) -> Self {
+        let sign = match bytes.first() {
+            Some(b) if b & 0x80 != 0 => 0xFF,
+            _ => 0x00,
+        };
+        // leading bytes which only repeat a sign are redundant
+        let redundant = bytes
+            .windows(2)
+            .take_while(|pair| pair[0] == sign && pair[1] & 0x80 == sign & 0x80)
+            .count();
+
+        match &bytes[redundant..] {
+            [] => Varint(vec![0]),
+            bytes => Varint(bytes.to_vec()),
+        }
+    }
+
+    /// Returns minimal big-endian two's complement bytes of the value.
+    pub fn as_signed_bytes_be(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0[0] & 0x80 != 0
+    }
+
+    /// Returns the value as `i64` if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        self.to_i128().and_then(|i| i64::try_from(i).ok())
+    }
+
+    /// Returns the value as `i128` if it fits.
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.0.len() > 16 {
+            return None;
+        }
+
+        let sign = if self.is_negative() { -1 } else { 0 };
+        Some(self.0.iter().fold(sign, |acc, b| acc << 8 | *b as i128))
+    }
+
+    /// Returns the value as `f64`, precision of large values is lost.
+    pub fn to_f64(&self) -> f64 {
+        let first = self.0[0] as i8 as f64;
+        self.0[1..]
+            .iter()
+            .fold(first, |acc, b| acc * 256.0 + *b as f64)
+    }
+}
+
+impl IntoBytes for Varint {
+    fn into_cbytes(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+}
+
+macro_rules! impl_from_for_varint {
+    ($t:ty) => {
+        impl From<$t> for Varint {
+            fn from(i: $t) -> Self {
+                Varint::from_signed_bytes_be(&(i as i128).to_be_bytes())
+            }
+        }
+    };
+}
+
+impl_from_for_varint!(i8);
+impl_from_for_varint!(i16);
+impl_from_for_varint!(i32);
+impl_from_for_varint!(i64);
+impl_from_for_varint!(i128);
+impl_from_for_varint!(u8);
+impl_from_for_varint!(u16);
+impl_from_for_varint!(u32);
+impl_from_for_varint!(u64);
+
+impl From<u128> for Varint {
+    fn from(i: u128) -> Self {
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(&i.to_be_bytes());
+        Varint::from_signed_bytes_be(&bytes)
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+impl From<BigInt> for Varint {
+    fn from(i: BigInt) -> Self {
+        Varint::from_signed_bytes_be(&i.to_signed_bytes_be())
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+impl From<Varint> for BigInt {
+    fn from(varint: Varint) -> Self {
+        BigInt::from_signed_bytes_be(&varint.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn minimal_bytes() {
+        assert_eq!(Varint::from(0).as_signed_bytes_be(), &[0x00]);
+        assert_eq!(Varint::from(127).as_signed_bytes_be(), &[0x7F]);
+        assert_eq!(Varint::from(128).as_signed_bytes_be(), &[0x00, 0x80]);
+        assert_eq!(Varint::from(-1).as_signed_bytes_be(), &[0xFF]);
+        assert_eq!(Varint::from(-129).as_signed_bytes_be(), &[0xFF, 0x7F]);
+        assert_eq!(Varint::from_signed_bytes_be(&[]), Varint::from(0));
+        assert_eq!(
+            Varint::from_signed_bytes_be(&[0xFF, 0xFF, 0x80]),
+            Varint::from(-128)
+        );
+    }
+
+    #[test]
+    fn beyond_i64() {
+        let max = Varint::from(u128::MAX);
+        assert_eq!(max.as_signed_bytes_be().len(), 17);
+        assert_eq!(max.to_i128(), None);
+        assert_eq!(max.to_f64(), u128::MAX as f64);
+
+        let min = Varint::from(i128::MIN);
+        assert_eq!(min.to_i128(), Some(i128::MIN));
+        assert_eq!(min.to_i64(), None);
+        assert!(min.is_negative());
+        assert_eq!(Varint::from(i64::MIN).to_i64(), Some(i64::MIN));
+    }
+
+    #[cfg(feature = "num-bigint")]
+    #[test]
+    fn big_int() {
+        let big: BigInt = "-123456789012345678901234567890".parse().unwrap();
+        let varint = Varint::from(big.clone());
+        assert_eq!(varint.as_signed_bytes_be(), &big.to_signed_bytes_be()[..]);
+        assert_eq!(BigInt::from(varint), big);
+    }
+}
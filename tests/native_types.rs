@@ -17,6 +17,8 @@ use cdrs::types::blob::Blob;
 #[cfg(feature = "e2e-tests")]
 use cdrs::types::decimal::Decimal;
 #[cfg(feature = "e2e-tests")]
+use cdrs::types::duration::Duration;
+#[cfg(feature = "e2e-tests")]
 use cdrs::types::map::Map;
 #[cfg(feature = "e2e-tests")]
 use cdrs::types::value::Bytes;
@@ -389,3 +391,34 @@ fn inet() {
         assert_eq!(my_inet_v6_row, my_inet_v6);
     }
 }
+
+#[test]
+#[cfg(feature = "e2e-tests")]
+fn duration() {
+    let cql = "CREATE TABLE IF NOT EXISTS cdrs_test.test_duration \
+               (my_key int PRIMARY KEY, my_duration duration)";
+    let session = setup(cql).expect("setup");
+
+    let my_duration = Duration::new(1, -2, 3_000_000_000);
+    let values = query_values!(1, my_duration);
+
+    let query = "INSERT INTO cdrs_test.test_duration (my_key, my_duration) VALUES (?, ?)";
+    session
+        .query_with_values(query, values)
+        .expect("insert duration error");
+
+    let query = "SELECT * FROM cdrs_test.test_duration";
+    let rows = session
+        .query(query)
+        .expect("query duration error")
+        .get_body()
+        .expect("get body with duration error")
+        .into_rows()
+        .expect("converting body with duration into rows error");
+
+    assert_eq!(rows.len(), 1);
+    for row in rows {
+        let my_duration_row: Duration = row.get_r_by_name("my_duration").expect("my_duration");
+        assert_eq!(my_duration_row, my_duration);
+    }
+}